@@ -15,4 +15,18 @@ fn main() {
             .unwrap_or_else(|_| panic!("Could not get the environment variable {}", name));
         println!("cargo:rustc-env={}={}", name, value);
     }
+
+    // `native-tls` is backed by OpenSSL (LibreSSL isn't supported by TLS controls)
+    println!("cargo:rustc-check-cfg=cfg(openssl_tls)");
+    println!("cargo:rustc-check-cfg=cfg(ossl300)");
+    if let Ok(version) = env::var("DEP_OPENSSL_VERSION_NUMBER") {
+        let version = u64::from_str_radix(&version, 16)
+            .unwrap_or_else(|_| panic!("Could not parse OpenSSL version {}", version));
+        if version >= 0x1010_0000 {
+            println!("cargo:rustc-cfg=openssl_tls");
+        }
+        if version >= 0x3000_0000 {
+            println!("cargo:rustc-cfg=ossl300");
+        }
+    }
 }
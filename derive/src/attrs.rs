@@ -0,0 +1,174 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Parsing of `#[mysql(...)]` attributes.
+
+use heck::{
+    AsKebabCase, AsLowerCamelCase, AsShoutyKebabCase, AsShoutySnakeCase, AsSnakeCase,
+    AsUpperCamelCase,
+};
+use syn::{punctuated::Punctuated, Attribute, LitStr, Path, Token, WherePredicate};
+
+/// Case conversion of field names (`#[mysql(rename_all = "...")]`).
+#[derive(Debug, Clone, Copy)]
+pub enum RenameAll {
+    Lowercase,
+    Uppercase,
+    PascalCase,
+    CamelCase,
+    SnakeCase,
+    KebabCase,
+    ScreamingSnakeCase,
+    ScreamingKebabCase,
+}
+
+impl RenameAll {
+    fn parse(value: &LitStr) -> syn::Result<Self> {
+        match value.value().as_str() {
+            "lowercase" => Ok(RenameAll::Lowercase),
+            "UPPERCASE" => Ok(RenameAll::Uppercase),
+            "PascalCase" => Ok(RenameAll::PascalCase),
+            "camelCase" => Ok(RenameAll::CamelCase),
+            "snake_case" => Ok(RenameAll::SnakeCase),
+            "kebab-case" => Ok(RenameAll::KebabCase),
+            "SCREAMING_SNAKE_CASE" => Ok(RenameAll::ScreamingSnakeCase),
+            "SCREAMING-KEBAB-CASE" => Ok(RenameAll::ScreamingKebabCase),
+            _ => Err(syn::Error::new_spanned(value, "unknown case convention")),
+        }
+    }
+
+    pub fn apply(self, name: &str) -> String {
+        match self {
+            RenameAll::Lowercase => name.to_lowercase(),
+            RenameAll::Uppercase => name.to_uppercase(),
+            RenameAll::PascalCase => AsUpperCamelCase(name).to_string(),
+            RenameAll::CamelCase => AsLowerCamelCase(name).to_string(),
+            RenameAll::SnakeCase => AsSnakeCase(name).to_string(),
+            RenameAll::KebabCase => AsKebabCase(name).to_string(),
+            RenameAll::ScreamingSnakeCase => AsShoutySnakeCase(name).to_string(),
+            RenameAll::ScreamingKebabCase => AsShoutyKebabCase(name).to_string(),
+        }
+    }
+}
+
+/// Attributes of a struct.
+#[derive(Default)]
+pub struct Container {
+    /// Path of the `mysql` crate (`#[mysql(crate_name = "...")]`).
+    pub crate_name: Option<Path>,
+    pub rename_all: Option<RenameAll>,
+    pub table_name: Option<LitStr>,
+    /// Missing columns default to `Default::default()` (`#[mysql(default)]`).
+    pub default: bool,
+    /// Additional where predicates (`#[mysql(bound = "T: FromValue")]`).
+    pub bound: Vec<WherePredicate>,
+}
+
+impl Container {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut this = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("mysql")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("crate_name") {
+                    this.crate_name = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                } else if meta.path.is_ident("rename_all") {
+                    this.rename_all = Some(RenameAll::parse(&meta.value()?.parse()?)?);
+                } else if meta.path.is_ident("table_name") {
+                    this.table_name = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    this.default = true;
+                } else if meta.path.is_ident("bound") {
+                    let bound = meta.value()?.parse::<LitStr>()?;
+                    let predicates = bound
+                        .parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+                    this.bound.extend(predicates);
+                } else {
+                    return Err(meta.error("unknown mysql attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(this)
+    }
+
+    /// Returns the column or parameter name of the field.
+    pub fn field_name(&self, field: &syn::Ident, attrs: &Field) -> LitStr {
+        let name = match (&attrs.rename, self.rename_all) {
+            (Some(rename), _) => rename.value(),
+            (None, Some(rename_all)) => rename_all.apply(&field.to_string()),
+            (None, None) => field.to_string(),
+        };
+        LitStr::new(&name, field.span())
+    }
+
+    /// Returns the default of the field, that applies if its column is missing.
+    pub fn field_default(&self, attrs: &Field) -> Option<FieldDefault> {
+        match attrs.default {
+            Some(ref default) => Some(default.clone()),
+            None if self.default => Some(FieldDefault::Trait),
+            None => None,
+        }
+    }
+
+    /// Returns the path of the `mysql` crate.
+    pub fn crate_path(&self) -> Path {
+        self.crate_name
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote!(::mysql))
+    }
+}
+
+/// Default value of a field.
+#[derive(Clone)]
+pub enum FieldDefault {
+    /// `Default::default()` (`#[mysql(default)]`).
+    Trait,
+    /// Result of the given function (`#[mysql(default = "path")]`).
+    Path(Path),
+}
+
+/// Attributes of a field.
+#[derive(Default)]
+pub struct Field {
+    pub rename: Option<LitStr>,
+    /// Value is a JSON document (`#[mysql(json)]`).
+    pub json: bool,
+    /// Missing column defaults to `Default::default()` (`#[mysql(default)]`)
+    /// or to the result of a function (`#[mysql(default = "path")]`).
+    pub default: Option<FieldDefault>,
+    /// Field isn't mapped to a column or a parameter (`#[mysql(skip)]`).
+    pub skip: bool,
+}
+
+impl Field {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut this = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("mysql")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    this.rename = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("json") {
+                    this.json = true;
+                } else if meta.path.is_ident("default") {
+                    this.default = if meta.input.peek(Token![=]) {
+                        let path = meta.value()?.parse::<LitStr>()?;
+                        Some(FieldDefault::Path(path.parse()?))
+                    } else {
+                        Some(FieldDefault::Trait)
+                    };
+                } else if meta.path.is_ident("skip") {
+                    this.skip = true;
+                } else {
+                    return Err(meta.error("unknown mysql attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(this)
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use heck::AsShoutySnakeCase;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields};
+
+use crate::attrs;
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "FromRow can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromRow can only be derived for structs",
+            ))
+        }
+    };
+
+    let container = attrs::Container::parse(&input.attrs)?;
+    let crat = container.crate_path();
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut generics = input.generics.clone();
+    let bounded = generics.make_where_clause();
+    bounded.predicates.extend(container.bound.iter().cloned());
+
+    let mut constants = Vec::new();
+    let mut takes = Vec::new();
+    let mut inits = Vec::new();
+    let mut rollbacks = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let attrs = attrs::Field::parse(&field.attrs)?;
+        let field_ident = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+
+        if attrs.skip {
+            inits.push(quote!(#field_ident: ::std::default::Default::default()));
+            continue;
+        }
+        let name = container.field_name(field_ident, &attrs);
+
+        let const_ident = format_ident!(
+            "{}_FIELD",
+            AsShoutySnakeCase(field_ident.to_string()).to_string()
+        );
+        constants.push(quote!(const #const_ident: &'static str = #name;));
+
+        let var = format_ident!("__field_{}", i);
+        let (ir, commit) = if attrs.json {
+            (
+                quote!(<#crat::Deserialized<#ty> as #crat::prelude::FromValue>::Intermediate),
+                quote!(::std::convert::Into::<#crat::Deserialized<#ty>>::into(ir).0),
+            )
+        } else {
+            (
+                quote!(<#ty as #crat::prelude::FromValue>::Intermediate),
+                quote!(::std::convert::Into::<#ty>::into(ir)),
+            )
+        };
+        // taken values of fields with defaults are optional
+        let default = container
+            .field_default(&attrs)
+            .map(|default| match default {
+                attrs::FieldDefault::Trait => quote!(::std::default::Default::default()),
+                attrs::FieldDefault::Path(path) => quote!(#path()),
+            });
+        let (taken, missing, init, rollback) = if let Some(default) = default {
+            (
+                quote!(::std::option::Option::Some((index, ir))),
+                quote!(::std::option::Option::None),
+                quote!(match #var {
+                    ::std::option::Option::Some((_, ir)) => #commit,
+                    ::std::option::Option::None => #default,
+                }),
+                quote!(if let ::std::option::Option::Some((index, ir)) = #var {
+                    row.place(index, ::std::convert::Into::<#crat::Value>::into(ir));
+                }),
+            )
+        } else {
+            (
+                quote!((index, ir)),
+                quote!({
+                    #(#rollbacks)*
+                    return ::std::result::Result::Err(#crat::FromRowError(row));
+                }),
+                quote!({
+                    let (_, ir) = #var;
+                    #commit
+                }),
+                quote!({
+                    let (index, ir) = #var;
+                    row.place(index, ::std::convert::Into::<#crat::Value>::into(ir));
+                }),
+            )
+        };
+
+        takes.push(quote!(
+            let #var = match index_of(#name) {
+                ::std::option::Option::Some(index) => match row.take_opt::<#crat::Value, usize>(index) {
+                    ::std::option::Option::Some(::std::result::Result::Ok(value)) => {
+                        match <#ir as ::std::convert::TryFrom<#crat::Value>>::try_from(value) {
+                            ::std::result::Result::Ok(ir) => #taken,
+                            ::std::result::Result::Err(err) => {
+                                row.place(index, err.0);
+                                #(#rollbacks)*
+                                return ::std::result::Result::Err(#crat::FromRowError(row));
+                            }
+                        }
+                    }
+                    _ => {
+                        #(#rollbacks)*
+                        return ::std::result::Result::Err(#crat::FromRowError(row));
+                    }
+                },
+                ::std::option::Option::None => #missing,
+            };
+        ));
+        inits.push(quote!(#field_ident: #init));
+        rollbacks.push(rollback);
+    }
+
+    let table_name = container
+        .table_name
+        .as_ref()
+        .map(|name| quote!(const TABLE_NAME: &'static str = #name;));
+
+    Ok(quote!(
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #table_name
+            #(#constants)*
+        }
+
+        impl #impl_generics #crat::prelude::FromRow for #ident #ty_generics #bounded {
+            fn from_row_opt(
+                mut row: #crat::Row,
+            ) -> ::std::result::Result<Self, #crat::FromRowError> {
+                let columns = row.columns();
+                let index_of = |name: &str| {
+                    columns
+                        .iter()
+                        .position(|column| column.name_ref() == name.as_bytes())
+                };
+                #(#takes)*
+                ::std::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+    ))
+}
@@ -0,0 +1,53 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Derive macros of the `mysql` crate (re-exported under `mysql::prelude`
+//! if the `derive` feature is enabled).
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod attrs;
+mod from_row;
+mod mysql_enum;
+mod to_params;
+
+/// Implements `FromRow` for a struct with named fields by matching field names to column names.
+///
+/// See the documentation of the `mysql` crate for the list of supported attributes.
+#[proc_macro_derive(FromRow, attributes(mysql))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_row::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `From<T>` and `From<&T>` for `Params` for a struct with named fields,
+/// so that fields become named parameters.
+///
+/// See the documentation of the `mysql` crate for the list of supported attributes.
+#[proc_macro_derive(ToParams, attributes(mysql))]
+pub fn derive_to_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_params::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `MysqlEnum` and conversions to and from `Value` for an enum with unit variants,
+/// so that it maps to values of an `ENUM` column (or members of a `SET` column).
+///
+/// See the documentation of the `mysql` crate for the list of supported attributes.
+#[proc_macro_derive(MysqlEnum, attributes(mysql))]
+pub fn derive_mysql_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    mysql_enum::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,101 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields};
+
+use crate::attrs;
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let variants = match input.data {
+        Data::Enum(ref data) => &data.variants,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "MysqlEnum can only be derived for enums",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "MysqlEnum can't be derived for generic enums",
+        ));
+    }
+
+    let container = attrs::Container::parse(&input.attrs)?;
+    let crat = container.crate_path();
+    let ident = &input.ident;
+
+    let mut names = Vec::new();
+    let mut from_index = Vec::new();
+    let mut index = Vec::new();
+    for (i, variant) in variants.iter().enumerate() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "MysqlEnum can only be derived for enums with unit variants",
+            ));
+        }
+        let attrs = attrs::Field::parse(&variant.attrs)?;
+        if attrs.json || attrs.default.is_some() || attrs.skip {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "only `rename` is supported for variants",
+            ));
+        }
+        let variant_ident = &variant.ident;
+        names.push(container.field_name(variant_ident, &attrs));
+        from_index.push(quote!(#i => ::std::option::Option::Some(Self::#variant_ident)));
+        index.push(quote!(Self::#variant_ident => #i));
+    }
+
+    Ok(quote!(
+        impl #crat::prelude::MysqlEnum for #ident {
+            const NAMES: &'static [&'static str] = &[#(#names),*];
+
+            fn from_index(index: usize) -> ::std::option::Option<Self> {
+                match index {
+                    #(#from_index,)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            fn index(&self) -> usize {
+                match *self {
+                    #(#index,)*
+                }
+            }
+        }
+
+        impl ::std::convert::TryFrom<#crat::Value> for #ident {
+            type Error = #crat::FromValueError;
+
+            fn try_from(value: #crat::Value) -> ::std::result::Result<Self, Self::Error> {
+                #crat::column_types::enum_from_value(value)
+            }
+        }
+
+        impl #crat::prelude::FromValue for #ident {
+            type Intermediate = Self;
+        }
+
+        impl ::std::convert::From<#ident> for #crat::Value {
+            fn from(value: #ident) -> Self {
+                ::std::convert::From::from(#crat::prelude::MysqlEnum::name(&value))
+            }
+        }
+
+        impl ::std::convert::From<&#ident> for #crat::Value {
+            fn from(value: &#ident) -> Self {
+                ::std::convert::From::from(#crat::prelude::MysqlEnum::name(value))
+            }
+        }
+    ))
+}
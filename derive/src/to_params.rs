@@ -0,0 +1,95 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Fields, GenericParam, Lifetime, LifetimeParam};
+
+use crate::attrs;
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "ToParams can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "ToParams can only be derived for structs",
+            ))
+        }
+    };
+
+    let container = attrs::Container::parse(&input.attrs)?;
+    let crat = container.crate_path();
+    let ident = &input.ident;
+
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(container.bound.iter().cloned());
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // `impl From<&'__a T> for Params`
+    let lifetime = Lifetime::new("'__a", proc_macro2::Span::call_site());
+    let mut ref_generics = generics.clone();
+    ref_generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())),
+    );
+    let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+
+    let mut names = Vec::new();
+    let mut owned = Vec::new();
+    let mut borrowed = Vec::new();
+    for field in fields {
+        let attrs = attrs::Field::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let field_ident = field.ident.as_ref().expect("named field");
+        names.push(container.field_name(field_ident, &attrs));
+        if attrs.json {
+            owned.push(quote!(#crat::Value::from(#crat::Serialized(this.#field_ident))));
+            borrowed.push(quote!(#crat::Value::from(#crat::Serialized(&this.#field_ident))));
+        } else {
+            owned.push(quote!(#crat::Value::from(this.#field_ident)));
+            borrowed.push(quote!(#crat::prelude::ToValue::to_value(&this.#field_ident)));
+        }
+    }
+    let len = names.len();
+    let to_params = |values: &[TokenStream]| {
+        quote!({
+            let mut params = ::std::collections::HashMap::with_capacity(#len);
+            #(params.insert(::std::vec::Vec::from(#names.as_bytes()), #values);)*
+            #crat::Params::Named(params)
+        })
+    };
+    let owned = to_params(&owned);
+    let borrowed = to_params(&borrowed);
+
+    let self_ty: syn::Type = parse_quote!(#ident #ty_generics);
+    Ok(quote!(
+        impl #impl_generics ::std::convert::From<#self_ty> for #crat::Params #where_clause {
+            fn from(this: #self_ty) -> Self #owned
+        }
+
+        impl #ref_impl_generics ::std::convert::From<&#lifetime #self_ty> for #crat::Params
+        #where_clause
+        {
+            fn from(this: &#lifetime #self_ty) -> Self #borrowed
+        }
+    ))
+}
@@ -0,0 +1,662 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Typed values of `BIT`, `ENUM`, `SET` and `VECTOR` columns.
+
+use bitvec::{order::Lsb0, vec::BitVec};
+use mysql_common::value::convert::{FromValue, FromValueError};
+
+use std::{convert::TryFrom, fmt, hash, iter::FromIterator, marker::PhantomData, str::FromStr};
+
+use crate::{schema::TableColumn, DriverError, Result, Value};
+
+/// Value of a `BIT(n)` column.
+///
+/// MySql sends `BIT` values as big-endian binary strings, so they can't be read as integers
+/// directly. Values are bound as unsigned integers.
+///
+/// ```
+/// # use mysql::column_types::Bits;
+/// # use mysql::{from_value, Value};
+/// let bits: Bits = from_value(Value::Bytes(vec![0b10, 0b0000_0101]));
+/// assert_eq!(bits.value(), 0b10_0000_0101);
+/// assert!(bits.get(0) && !bits.get(1) && bits.get(9));
+///
+/// let bits = bits.to_bitvec(10);
+/// assert_eq!(bits.iter_ones().collect::<Vec<_>>(), [0, 2, 9]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bits(pub u64);
+
+impl Bits {
+    /// Returns the value as an integer.
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the given bit (`0` is the least significant one).
+    pub fn get(self, bit: u32) -> bool {
+        bit < u64::BITS && self.0 & (1 << bit) != 0
+    }
+
+    /// Returns the lowest `width` bits (e.g. `n` of `BIT(n)`), where the index
+    /// of a bit is its position starting from the least significant one.
+    pub fn to_bitvec(self, width: usize) -> BitVec<u64, Lsb0> {
+        let mut bits = BitVec::from_element(self.0);
+        bits.resize(width, false);
+        bits
+    }
+}
+
+impl From<u64> for Bits {
+    fn from(value: u64) -> Self {
+        Bits(value)
+    }
+}
+
+impl TryFrom<Value> for Bits {
+    type Error = FromValueError;
+
+    fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+        match value {
+            Value::Bytes(bytes) if bytes.len() <= 8 => {
+                let value = bytes.iter().fold(0, |acc, byte| acc << 8 | *byte as u64);
+                Ok(Bits(value))
+            }
+            // e.g. `SELECT bit_column + 0`
+            Value::Int(value) if value >= 0 => Ok(Bits(value as u64)),
+            Value::UInt(value) => Ok(Bits(value)),
+            value => Err(FromValueError(value)),
+        }
+    }
+}
+
+impl FromValue for Bits {
+    type Intermediate = Bits;
+}
+
+impl From<Bits> for Value {
+    fn from(bits: Bits) -> Value {
+        Value::UInt(bits.0)
+    }
+}
+
+/// Rust enum that maps to values of an `ENUM` column (or members of a `SET` column).
+///
+/// Implement it using `#[derive(MysqlEnum)]` (requires the `derive` feature), that also
+/// implements conversions to and from [`Value`]. Variant names are used as is, unless renamed
+/// using `#[mysql(rename = "...")]` or `#[mysql(rename_all = "...")]`.
+///
+/// ```
+/// # use mysql::{from_value, from_value_opt, Value};
+/// use mysql::prelude::*;
+///
+/// #[derive(Debug, PartialEq, MysqlEnum)]
+/// #[mysql(rename_all = "lowercase")]
+/// enum Size {
+///     Small,
+///     Medium,
+///     #[mysql(rename = "x-large")]
+///     ExtraLarge,
+/// }
+///
+/// assert_eq!(Size::NAMES, ["small", "medium", "x-large"]);
+/// assert_eq!(from_value::<Size>(Value::from("x-large")), Size::ExtraLarge);
+/// assert_eq!(Value::from(Size::Medium), Value::from("medium"));
+/// assert!(from_value_opt::<Size>(Value::from("huge")).is_err());
+/// ```
+pub trait MysqlEnum: Sized {
+    /// Names of variants in the order of declaration.
+    const NAMES: &'static [&'static str];
+
+    /// Returns the variant with the given index in [`MysqlEnum::NAMES`].
+    fn from_index(index: usize) -> Option<Self>;
+
+    /// Returns the index of this variant in [`MysqlEnum::NAMES`].
+    fn index(&self) -> usize;
+
+    /// Returns the name of this variant.
+    fn name(&self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+
+    /// Returns the variant with the given name.
+    ///
+    /// Names are compared case-insensitively, if there is no exact match
+    /// (it's how the server compares `ENUM` values).
+    fn from_name(name: &str) -> Option<Self> {
+        let index = Self::NAMES
+            .iter()
+            .position(|known| *known == name)
+            .or_else(|| {
+                Self::NAMES
+                    .iter()
+                    .position(|known| known.eq_ignore_ascii_case(name))
+            })?;
+        Self::from_index(index)
+    }
+
+    /// Makes sure that every variant is a value of the given `ENUM` or `SET` column
+    /// (see [`crate::Conn::columns`]).
+    fn check_column(column: &TableColumn) -> Result<()> {
+        let values = column.values().unwrap_or_default();
+        let missing = Self::NAMES
+            .iter()
+            .filter(|name| !values.iter().any(|value| value.eq_ignore_ascii_case(name)))
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(DriverError::EnumColumnMismatch {
+                column: column.name().into(),
+                missing,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Converts a value of an `ENUM` column to `T` (used by `#[derive(MysqlEnum)]`).
+#[doc(hidden)]
+pub fn enum_from_value<T: MysqlEnum>(value: Value) -> std::result::Result<T, FromValueError> {
+    let variant = match value {
+        Value::Bytes(ref bytes) => std::str::from_utf8(bytes).ok().and_then(T::from_name),
+        // 1-based index of the value, e.g. `SELECT enum_column + 0`
+        Value::Int(index) if index > 0 => T::from_index(index as usize - 1),
+        Value::UInt(index) if index > 0 => T::from_index(index as usize - 1),
+        _ => None,
+    };
+    variant.ok_or(FromValueError(value))
+}
+
+/// Members of a `SET` column.
+///
+/// ```
+/// # use mysql::column_types::Set;
+/// # use mysql::{from_value, Value};
+/// use mysql::prelude::*;
+///
+/// #[derive(Debug, PartialEq, MysqlEnum)]
+/// #[mysql(rename_all = "lowercase")]
+/// enum Flag {
+///     Read,
+///     Write,
+///     Exec,
+/// }
+///
+/// let flags: Set<Flag> = from_value(Value::from("read,exec"));
+/// assert!(flags.contains(&Flag::Read) && !flags.contains(&Flag::Write));
+/// assert_eq!(flags.iter().collect::<Vec<_>>(), [Flag::Read, Flag::Exec]);
+///
+/// let flags = Set::new().with(Flag::Write).with(Flag::Read);
+/// assert_eq!(Value::from(flags), Value::from("read,write"));
+/// ```
+pub struct Set<T> {
+    /// Bit `i` is set if the set contains the variant with index `i`.
+    mask: u64,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: MysqlEnum> Set<T> {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::from_mask(0)
+    }
+
+    fn from_mask(mask: u64) -> Self {
+        Self {
+            mask,
+            marker: PhantomData,
+        }
+    }
+
+    fn bit(member: &T) -> u64 {
+        let index = member.index();
+        assert!(index < 64, "SET can't have more than 64 members");
+        1 << index
+    }
+
+    /// Adds the given member to this set.
+    pub fn with(mut self, member: T) -> Self {
+        self.insert(member);
+        self
+    }
+
+    /// Adds the given member and returns `true` if it wasn't in this set.
+    pub fn insert(&mut self, member: T) -> bool {
+        let bit = Self::bit(&member);
+        let inserted = self.mask & bit == 0;
+        self.mask |= bit;
+        inserted
+    }
+
+    /// Removes the given member and returns `true` if it was in this set.
+    pub fn remove(&mut self, member: &T) -> bool {
+        let bit = Self::bit(member);
+        let removed = self.mask & bit != 0;
+        self.mask &= !bit;
+        removed
+    }
+
+    /// Returns `true` if this set contains the given member.
+    pub fn contains(&self, member: &T) -> bool {
+        self.mask & Self::bit(member) != 0
+    }
+
+    /// Returns the number of members.
+    pub fn len(&self) -> usize {
+        self.mask.count_ones() as usize
+    }
+
+    /// Returns `true` if this set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    /// Returns the bitmask of this set, i.e. its numeric value as stored by the server
+    /// (given that members are declared in the order of the column definition).
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    /// Returns members in the order of declaration.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..64)
+            .filter(move |index| self.mask & (1 << index) != 0)
+            .filter_map(T::from_index)
+    }
+}
+
+impl<T: MysqlEnum> Default for Set<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Set<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Set<T> {}
+
+impl<T> PartialEq for Set<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.mask == other.mask
+    }
+}
+
+impl<T> Eq for Set<T> {}
+
+impl<T> hash::Hash for Set<T> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.mask.hash(state)
+    }
+}
+
+impl<T: MysqlEnum> fmt::Debug for Set<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(|member| member.name()))
+            .finish()
+    }
+}
+
+impl<T: MysqlEnum> FromIterator<T> for Set<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::new(), Self::with)
+    }
+}
+
+impl<T: MysqlEnum> TryFrom<Value> for Set<T> {
+    type Error = FromValueError;
+
+    fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+        let known = match T::NAMES.len() {
+            len if len >= 64 => u64::MAX,
+            len => (1 << len) - 1,
+        };
+        match value {
+            Value::Bytes(ref bytes) => {
+                let members = match std::str::from_utf8(bytes) {
+                    Ok("") => Some(Self::new()),
+                    Ok(members) => members.split(',').map(T::from_name).collect(),
+                    Err(_) => None,
+                };
+                members.ok_or(FromValueError(value))
+            }
+            // e.g. `SELECT set_column + 0`
+            Value::Int(mask) if mask >= 0 && mask as u64 & !known == 0 => {
+                Ok(Self::from_mask(mask as u64))
+            }
+            Value::UInt(mask) if mask & !known == 0 => Ok(Self::from_mask(mask)),
+            value => Err(FromValueError(value)),
+        }
+    }
+}
+
+impl<T: MysqlEnum> FromValue for Set<T> {
+    type Intermediate = Set<T>;
+}
+
+impl<T: MysqlEnum> From<Set<T>> for Value {
+    fn from(set: Set<T>) -> Value {
+        let members = set.iter().map(|member| member.name()).collect::<Vec<_>>();
+        Value::from(members.join(","))
+    }
+}
+
+/// Value of a `VECTOR(n)` column (MySql 9.0+).
+///
+/// MySql sends vectors as binary strings of little-endian `f32` values, and vectors are bound
+/// the same way. Use [`Vector::to_text`] along with `STRING_TO_VECTOR(?)`, if the server
+/// doesn't accept binary values for `VECTOR` columns. The text form (e.g. the result
+/// of `VECTOR_TO_STRING`) is accepted as well.
+///
+/// ```
+/// # use mysql::column_types::Vector;
+/// # use mysql::{from_value, Value};
+/// let vector = Vector(vec![1.0, -0.5]);
+/// assert_eq!(Value::from(vector.clone()), Value::Bytes(vec![0, 0, 128, 63, 0, 0, 0, 191]));
+/// assert_eq!(vector.to_string(), "[1,-0.5]");
+///
+/// let vector: Vector = from_value(Value::from("[1.00000e+00,-5.00000e-01]"));
+/// assert_eq!(vector.as_slice(), [1.0, -0.5]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Vector(pub Vec<f32>);
+
+impl Vector {
+    /// Returns the number of dimensions.
+    pub fn dimensions(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns the values of this vector.
+    pub fn as_slice(&self) -> &[f32] {
+        &self.0
+    }
+
+    /// Returns the text form of this vector, that could be bound to `STRING_TO_VECTOR(?)`.
+    pub fn to_text(&self) -> Value {
+        Value::from(self.to_string())
+    }
+
+    fn from_binary(bytes: &[u8]) -> Option<Self> {
+        let chunks = bytes.chunks_exact(4);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+        let values = chunks
+            .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+            .collect();
+        Some(Vector(values))
+    }
+}
+
+impl From<Vec<f32>> for Vector {
+    fn from(values: Vec<f32>) -> Self {
+        Vector(values)
+    }
+}
+
+impl From<Vector> for Vec<f32> {
+    fn from(vector: Vector) -> Self {
+        vector.0
+    }
+}
+
+impl fmt::Display for Vector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, value) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Error of parsing the text form of a [`Vector`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseVectorError(String);
+
+impl fmt::Display for ParseVectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid vector `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseVectorError {}
+
+impl FromStr for Vector {
+    type Err = ParseVectorError;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        let error = || ParseVectorError(text.into());
+        let values = text
+            .trim()
+            .strip_prefix('[')
+            .and_then(|x| x.strip_suffix(']'))
+            .ok_or_else(error)?
+            .trim();
+        if values.is_empty() {
+            return Ok(Vector::default());
+        }
+        values
+            .split(',')
+            .map(|x| x.trim().parse::<f32>().map_err(|_| error()))
+            .collect::<std::result::Result<_, _>>()
+            .map(Vector)
+    }
+}
+
+impl TryFrom<Value> for Vector {
+    type Error = FromValueError;
+
+    fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+        let bytes = match value {
+            Value::Bytes(ref bytes) => bytes,
+            value => return Err(FromValueError(value)),
+        };
+        // binary vectors practically never look like `[<number>,...]`
+        let text = match (bytes.first(), bytes.last()) {
+            (Some(b'['), Some(b']')) => std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|text| text.parse().ok()),
+            _ => None,
+        };
+        match text.or_else(|| Vector::from_binary(bytes)) {
+            Some(vector) => Ok(vector),
+            None => Err(FromValueError(value)),
+        }
+    }
+}
+
+impl FromValue for Vector {
+    type Intermediate = Vector;
+}
+
+impl From<Vector> for Value {
+    fn from(vector: Vector) -> Value {
+        let bytes = vector.0.iter().flat_map(|x| x.to_le_bytes()).collect();
+        Value::Bytes(bytes)
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod test {
+    use super::{Bits, MysqlEnum, Set, Vector};
+    use crate::{from_value, from_value_opt, Value};
+
+    #[derive(Debug, PartialEq, Eq, crate::prelude::MysqlEnum)]
+    #[mysql(rename_all = "SCREAMING_SNAKE_CASE")]
+    enum Color {
+        Red,
+        DarkGreen,
+        #[mysql(rename = "blue")]
+        Blue,
+    }
+
+    #[test]
+    fn should_convert_bits() {
+        assert_eq!(from_value::<Bits>(Value::Bytes(vec![])), Bits(0));
+        assert_eq!(from_value::<Bits>(Value::Bytes(vec![1, 0])), Bits(256));
+        assert_eq!(
+            from_value::<Bits>(Value::Bytes(vec![0xff; 8])),
+            Bits(u64::MAX)
+        );
+        assert_eq!(from_value::<Bits>(Value::Int(5)), Bits(5));
+        assert!(from_value_opt::<Bits>(Value::Bytes(vec![0; 9])).is_err());
+        assert!(from_value_opt::<Bits>(Value::Int(-1)).is_err());
+        assert_eq!(Value::from(Bits(3)), Value::UInt(3));
+
+        assert!(Bits(u64::MAX).get(63));
+        assert!(!Bits(u64::MAX).get(64));
+        assert_eq!(Bits(0b101).to_bitvec(2).len(), 2);
+        assert_eq!(
+            Bits(1 << 63).to_bitvec(70).iter_ones().collect::<Vec<_>>(),
+            [63]
+        );
+    }
+
+    #[test]
+    fn should_convert_enums() {
+        assert_eq!(Color::NAMES, ["RED", "DARK_GREEN", "blue"]);
+        assert_eq!(Color::DarkGreen.name(), "DARK_GREEN");
+        assert_eq!(Color::from_name("dark_green"), Some(Color::DarkGreen));
+        assert_eq!(from_value::<Color>(Value::from("blue")), Color::Blue);
+        assert_eq!(from_value::<Color>(Value::Int(1)), Color::Red);
+        assert!(from_value_opt::<Color>(Value::Int(0)).is_err());
+        assert!(from_value_opt::<Color>(Value::from("green")).is_err());
+        assert_eq!(Value::from(Color::Red), Value::from("RED"));
+    }
+
+    #[test]
+    fn should_convert_sets() {
+        let set: Set<Color> = from_value(Value::from("blue,RED"));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.mask(), 0b101);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [Color::Red, Color::Blue]);
+        assert_eq!(Value::from(set), Value::from("RED,blue"));
+        assert_eq!(format!("{:?}", set), r#"{"RED", "blue"}"#);
+
+        assert!(from_value::<Set<Color>>(Value::from("")).is_empty());
+        assert_eq!(from_value::<Set<Color>>(Value::UInt(0b10)).len(), 1);
+        assert!(from_value_opt::<Set<Color>>(Value::UInt(0b1000)).is_err());
+        assert!(from_value_opt::<Set<Color>>(Value::from("RED,green")).is_err());
+
+        let mut set = [Color::Blue].into_iter().collect::<Set<_>>();
+        assert!(set.insert(Color::Red));
+        assert!(!set.insert(Color::Red));
+        assert!(set.remove(&Color::Blue));
+        assert!(!set.contains(&Color::Blue));
+    }
+
+    #[test]
+    fn should_convert_vectors() {
+        let vector = Vector(vec![0.25, -3.0, 1e-3]);
+        assert_eq!(from_value::<Vector>(Value::from(vector.clone())), vector);
+        assert_eq!(vector.to_text(), Value::from("[0.25,-3,0.001]"));
+        assert_eq!(from_value::<Vector>(vector.to_text()), vector);
+        assert_eq!(from_value::<Vector>(Value::from("[ ]")), Vector::default());
+        assert_eq!(
+            from_value::<Vector>(Value::Bytes(vec![])),
+            Vector::default()
+        );
+        assert_eq!(
+            "[1.00000e+00, 2.5]".parse::<Vector>().unwrap().as_slice(),
+            [1.0, 2.5]
+        );
+
+        assert!(from_value_opt::<Vector>(Value::Bytes(vec![0; 5])).is_err());
+        assert!(from_value_opt::<Vector>(Value::Int(1)).is_err());
+        assert!("[1,x]".parse::<Vector>().is_err());
+        assert!("1,2".parse::<Vector>().is_err());
+    }
+
+    #[test]
+    fn should_read_and_write_vector_columns() {
+        use crate::{prelude::*, test_misc::get_opts, Conn, ServerFlavor};
+
+        let mut conn = Conn::new(get_opts()).unwrap();
+        if conn.server_flavor() != ServerFlavor::MySql || conn.server_version() < (9, 0, 0) {
+            return;
+        }
+        conn.query_drop("CREATE TEMPORARY TABLE mysql.vectors (v VECTOR(3))")
+            .unwrap();
+        let vector = Vector(vec![1.0, 0.5, -2.0]);
+        conn.exec_drop("INSERT INTO mysql.vectors (v) VALUES (?)", (&vector,))
+            .unwrap();
+        conn.exec_drop(
+            "INSERT INTO mysql.vectors (v) VALUES (STRING_TO_VECTOR(?))",
+            (vector.to_text(),),
+        )
+        .unwrap();
+
+        let vectors: Vec<Vector> = conn.query("SELECT v FROM mysql.vectors").unwrap();
+        assert_eq!(vectors, [vector.clone(), vector.clone()]);
+        let vectors: Vec<Vector> = conn
+            .exec("SELECT VECTOR_TO_STRING(v) FROM mysql.vectors", ())
+            .unwrap();
+        assert_eq!(vectors, [vector.clone(), vector]);
+    }
+
+    #[test]
+    fn should_read_and_write_bit_enum_and_set_columns() {
+        use crate::{prelude::*, test_misc::get_opts, Conn};
+
+        let mut conn = Conn::new(get_opts()).unwrap();
+        // temporary tables aren't visible in `information_schema`
+        conn.query_drop("DROP TABLE IF EXISTS mysql.column_types")
+            .unwrap();
+        conn.query_drop(
+            "CREATE TABLE mysql.column_types (\
+             bits BIT(10), color ENUM('RED', 'DARK_GREEN', 'blue'), \
+             colors SET('RED', 'DARK_GREEN', 'blue'), size ENUM('small', 'large'))",
+        )
+        .unwrap();
+        conn.exec_drop(
+            "INSERT INTO mysql.column_types (bits, color, colors) VALUES (?, ?, ?)",
+            (
+                Bits(0b10_0000_0001),
+                Color::DarkGreen,
+                Set::new().with(Color::Blue).with(Color::Red),
+            ),
+        )
+        .unwrap();
+
+        let row: (Bits, Color, Set<Color>) = conn
+            .query_first("SELECT bits, color, colors FROM mysql.column_types")
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.0, Bits(0b10_0000_0001));
+        assert_eq!(row.1, Color::DarkGreen);
+        assert_eq!(row.2.iter().collect::<Vec<_>>(), [Color::Red, Color::Blue]);
+
+        let row: (Bits, Color, Set<Color>) = conn
+            .exec_first(
+                "SELECT bits, color + 0, colors + 0 FROM mysql.column_types",
+                (),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.0.to_bitvec(10).count_ones(), 2);
+        assert_eq!(row.1, Color::DarkGreen);
+        assert_eq!(row.2.mask(), 0b101);
+
+        let columns = conn.columns("mysql", "column_types").unwrap();
+        conn.query_drop("DROP TABLE mysql.column_types").unwrap();
+        assert!(Color::check_column(&columns[1]).is_ok());
+        assert!(Color::check_column(&columns[2]).is_ok());
+        assert!(Color::check_column(&columns[3]).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{collections::HashMap, convert::TryFrom};
+
+use crate::{
+    conn::query_result::Text, consts::Command, from_row_opt, from_value_opt, prelude::*, Conn,
+    Opts, OptsBuilder, QueryResult, Result, Row, Value,
+};
+
+/// `MYSQL_OPTION_MULTI_STATEMENTS_OFF` option of `COM_SET_OPTION`.
+const MYSQL_OPTION_MULTI_STATEMENTS_OFF: u16 = 1;
+
+/// A row of the server's processlist (see [`AdminConn::processlist`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// Thread id (usable with [`AdminConn::kill_query`] and [`AdminConn::kill_connection`]).
+    pub id: u64,
+    pub user: String,
+    pub host: String,
+    pub db: Option<String>,
+    pub command: String,
+    /// Time in seconds the thread has been in its current state.
+    pub time: Option<i64>,
+    pub state: Option<String>,
+    /// Statement the thread is executing, if any.
+    pub info: Option<String>,
+}
+
+impl ProcessInfo {
+    fn from_row(mut row: Row) -> Result<Self> {
+        fn take<T: FromValue>(row: &mut Row, name: &str) -> Result<T> {
+            match row.take_opt(name) {
+                Some(value) => Ok(value?),
+                None => Ok(from_value_opt(Value::NULL)?),
+            }
+        }
+
+        Ok(ProcessInfo {
+            id: take(&mut row, "Id")?,
+            user: take::<Option<String>>(&mut row, "User")?.unwrap_or_default(),
+            host: take::<Option<String>>(&mut row, "Host")?.unwrap_or_default(),
+            db: take(&mut row, "db")?,
+            command: take::<Option<String>>(&mut row, "Command")?.unwrap_or_default(),
+            time: take(&mut row, "Time")?,
+            state: take(&mut row, "State")?,
+            info: take(&mut row, "Info")?,
+        })
+    }
+}
+
+/// A token that allows to abort a statement running on a connection from another thread
+/// (see [`Conn::cancel_handle`]).
+///
+/// It holds the connection id and options, so it's cheap to create and to send
+/// to another thread. Every call opens a short-lived [`AdminConn`] to issue the `KILL`.
+#[derive(Debug, Clone)]
+pub struct CancelHandle {
+    connection_id: u32,
+    opts: Opts,
+}
+
+impl CancelHandle {
+    /// Returns the id of the connection this handle refers to.
+    pub fn connection_id(&self) -> u32 {
+        self.connection_id
+    }
+
+    /// Executes `KILL QUERY` for the connection, i.e. terminates the statement
+    /// it's executing, leaving the connection intact.
+    pub fn kill_query(&self) -> Result<()> {
+        AdminConn::new(self.opts.clone())?.kill_query(u64::from(self.connection_id))
+    }
+
+    /// Executes `KILL CONNECTION` for the connection.
+    pub fn kill_connection(&self) -> Result<()> {
+        AdminConn::new(self.opts.clone())?.kill_connection(u64::from(self.connection_id))
+    }
+}
+
+impl Conn {
+    /// Returns a handle that allows to abort a statement running on this connection
+    /// from another thread.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # use std::{thread, time::Duration};
+    /// let mut conn = Conn::new(get_opts())?;
+    /// let handle = conn.cancel_handle();
+    /// let watchdog = thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(100));
+    ///     handle.kill_query()
+    /// });
+    /// // `SLEEP` returns 1 if interrupted
+    /// let interrupted: Option<u8> = conn.query_first("SELECT SLEEP(60)")?;
+    /// assert_eq!(interrupted, Some(1));
+    /// watchdog.join().unwrap()?;
+    /// # });
+    /// ```
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            connection_id: self.connection_id(),
+            opts: self.0.opts.clone(),
+        }
+    }
+}
+
+/// Connection for monitoring and administrative tasks.
+///
+/// It is designed to have predictable and low impact on the server:
+///
+/// * it always uses its own connection (it's never taken from a pool);
+/// * statement cache is disabled and no statements are prepared by helpers;
+/// * multi-statements are turned off for the session (via `COM_SET_OPTION`);
+/// * results are not buffered unless a helper returns a collection
+///   (use [`AdminConn::query_iter`] to stream rows).
+///
+/// If the connection is lost (e.g. killed by another administrator), then helpers
+/// will reconnect and retry once.
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// # use mysql::*;
+/// let mut admin = AdminConn::new(get_opts())?;
+/// let processlist = admin.processlist()?;
+/// assert!(processlist.iter().any(|process| process.id == u64::from(admin.connection_id())));
+///
+/// let status = admin.status()?;
+/// assert!(status.contains_key("Uptime"));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct AdminConn {
+    conn: Conn,
+}
+
+impl AdminConn {
+    /// Creates a new administrative connection.
+    pub fn new<T, E>(opts: T) -> Result<Self>
+    where
+        Opts: TryFrom<T, Error = E>,
+        crate::Error: From<E>,
+    {
+        let opts = OptsBuilder::from_opts(Opts::try_from(opts)?).stmt_cache_size(0);
+        Ok(AdminConn {
+            conn: Self::connect(opts.into())?,
+        })
+    }
+
+    fn connect(opts: Opts) -> Result<Conn> {
+        let mut conn = Conn::new(opts)?;
+        conn.write_command(
+            Command::COM_SET_OPTION,
+            &MYSQL_OPTION_MULTI_STATEMENTS_OFF.to_le_bytes(),
+        )?;
+        conn.drop_packet()?;
+        Ok(conn)
+    }
+
+    /// Runs `f` and retries it once on a new connection, if the connection was lost.
+    fn with_retry<T, F>(&mut self, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut Conn) -> Result<T>,
+    {
+        match f(&mut self.conn) {
+            Err(err) if err.is_connectivity_error() => {
+                self.conn = Self::connect(self.conn.0.opts.clone())?;
+                f(&mut self.conn)
+            }
+            result => result,
+        }
+    }
+
+    /// Returns the thread id of this connection.
+    pub fn connection_id(&self) -> u32 {
+        self.conn.connection_id()
+    }
+
+    /// Executes `KILL QUERY`, i.e. terminates the statement that the thread is executing,
+    /// leaving the connection intact.
+    pub fn kill_query(&mut self, thread_id: u64) -> Result<()> {
+        self.with_retry(|conn| conn.query_drop(format!("KILL QUERY {}", thread_id)))
+    }
+
+    /// Executes `KILL CONNECTION`, i.e. terminates the connection of the thread.
+    pub fn kill_connection(&mut self, thread_id: u64) -> Result<()> {
+        self.with_retry(|conn| conn.query_drop(format!("KILL CONNECTION {}", thread_id)))
+    }
+
+    /// Returns the server's processlist (`SHOW FULL PROCESSLIST`).
+    pub fn processlist(&mut self) -> Result<Vec<ProcessInfo>> {
+        self.with_retry(|conn| {
+            conn.query_iter("SHOW FULL PROCESSLIST")?
+                .map(|row| ProcessInfo::from_row(row?))
+                .collect()
+        })
+    }
+
+    /// Returns server status variables (`SHOW GLOBAL STATUS`).
+    pub fn status(&mut self) -> Result<HashMap<String, String>> {
+        self.with_retry(|conn| {
+            conn.query_iter("SHOW GLOBAL STATUS")?
+                .map(|row| {
+                    let (name, value): (String, Option<String>) = from_row_opt(row?)?;
+                    Ok((name, value.unwrap_or_default()))
+                })
+                .collect()
+        })
+    }
+
+    /// Executes a text query and returns unbuffered result.
+    ///
+    /// Note that this one isn't retried.
+    pub fn query_iter<T: AsRef<str>>(&mut self, query: T) -> Result<QueryResult<'_, '_, '_, Text>> {
+        self.conn.query_iter(query)
+    }
+
+    /// Executes `COM_PING`. Returns `true` on success.
+    pub fn ping(&mut self) -> bool {
+        self.conn.ping()
+    }
+
+    /// Returns the underlying connection.
+    pub fn into_inner(self) -> Conn {
+        self.conn
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::{AdminConn, CancelHandle};
+    use crate::{prelude::*, test_misc::get_opts, Conn};
+
+    #[test]
+    fn should_cancel_queries() {
+        fn assert_send<T: Send>() {}
+        assert_send::<CancelHandle>();
+
+        let mut conn = Conn::new(get_opts()).unwrap();
+        let handle = conn.cancel_handle();
+        assert_eq!(handle.connection_id(), conn.connection_id());
+
+        let started = Instant::now();
+        let watchdog = thread::spawn({
+            let handle = handle.clone();
+            move || {
+                thread::sleep(Duration::from_millis(200));
+                handle.kill_query()
+            }
+        });
+        let result: Option<u8> = conn.query_first("SELECT SLEEP(30)").unwrap();
+        watchdog.join().unwrap().unwrap();
+        assert_eq!(result, Some(1));
+        assert!(started.elapsed() < Duration::from_secs(30));
+
+        // the connection survives `KILL QUERY`, but not `KILL CONNECTION`
+        conn.query_drop("SELECT 1").unwrap();
+        handle.kill_connection().unwrap();
+        assert!(conn.query_drop("SELECT 1").is_err());
+    }
+
+    #[test]
+    fn should_kill_queries_and_connections() {
+        let mut admin = AdminConn::new(get_opts()).unwrap();
+        assert!(admin.conn.query_drop("SELECT 1; SELECT 2").is_err());
+
+        let mut conn = Conn::new(get_opts()).unwrap();
+        let id = u64::from(conn.connection_id());
+        let process = admin
+            .processlist()
+            .unwrap()
+            .into_iter()
+            .find(|process| process.id == id)
+            .unwrap();
+        assert_eq!(process.command, "Sleep");
+
+        admin.kill_query(id).unwrap();
+        admin.kill_connection(id).unwrap();
+        assert!(conn.query_drop("SELECT 1").is_err());
+
+        // admin connection survives being killed
+        let id = u64::from(admin.connection_id());
+        let mut other = AdminConn::new(get_opts()).unwrap();
+        other.kill_connection(id).unwrap();
+        assert!(!admin.status().unwrap().is_empty());
+        assert_ne!(u64::from(admin.connection_id()), id);
+    }
+}
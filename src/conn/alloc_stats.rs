@@ -0,0 +1,127 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::mem;
+
+use crate::{Row, Value};
+
+/// Memory allocated by a connection for queries (see [`crate::Conn::alloc_stats`]).
+///
+/// Accounts packet buffers (both sent and received) and decoded rows. Buffers are usually
+/// reused (see the `buffer-pool` feature), so these numbers describe the memory
+/// a connection needs rather than the pressure on the allocator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AllocStats {
+    queries: u64,
+    total_bytes: u64,
+    last_query_bytes: usize,
+    max_query_bytes: usize,
+    max_packet_bytes: usize,
+    max_row_bytes: usize,
+}
+
+impl AllocStats {
+    /// Number of queries and statement executions.
+    pub fn queries(&self) -> u64 {
+        self.queries
+    }
+
+    /// Bytes allocated by all queries.
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Bytes allocated by the last query (so far, if its result is still being read).
+    pub fn last_query_bytes(&self) -> usize {
+        self.last_query_bytes
+    }
+
+    /// Maximum number of bytes allocated by a single query.
+    pub fn max_query_bytes(&self) -> usize {
+        self.max_query_bytes
+    }
+
+    /// Size of the largest packet.
+    pub fn max_packet_bytes(&self) -> usize {
+        self.max_packet_bytes
+    }
+
+    /// Size of the largest decoded row.
+    pub fn max_row_bytes(&self) -> usize {
+        self.max_row_bytes
+    }
+
+    /// Starts accounting of a new query.
+    pub(crate) fn start_query(&mut self) {
+        self.queries += 1;
+        self.last_query_bytes = 0;
+    }
+
+    /// Accounts a packet buffer.
+    pub(crate) fn record_packet(&mut self, len: usize) {
+        self.max_packet_bytes = self.max_packet_bytes.max(len);
+        self.record(len);
+    }
+
+    /// Accounts a decoded row.
+    pub(crate) fn record_row(&mut self, row: &Row) {
+        let values = (0..row.len()).filter_map(|i| row.as_ref(i));
+        let len = values
+            .map(|value| match value {
+                Value::Bytes(bytes) => bytes.capacity(),
+                _ => 0,
+            })
+            .sum::<usize>()
+            + row.len() * mem::size_of::<Option<Value>>();
+        self.max_row_bytes = self.max_row_bytes.max(len);
+        self.record(len);
+    }
+
+    fn record(&mut self, len: usize) {
+        self.total_bytes += len as u64;
+        self.last_query_bytes += len;
+        self.max_query_bytes = self.max_query_bytes.max(self.last_query_bytes);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{constants::ColumnType, row::new_row};
+
+    use std::mem;
+
+    use super::AllocStats;
+    use crate::{Column, Value};
+
+    #[test]
+    fn should_track_maxima() {
+        let mut stats = AllocStats::default();
+        stats.start_query();
+        stats.record_packet(100);
+        stats.record_packet(20);
+        stats.start_query();
+        stats.record_packet(50);
+
+        assert_eq!(stats.queries(), 2);
+        assert_eq!(stats.total_bytes(), 170);
+        assert_eq!(stats.last_query_bytes(), 50);
+        assert_eq!(stats.max_query_bytes(), 120);
+        assert_eq!(stats.max_packet_bytes(), 100);
+
+        let columns = vec![
+            Column::new(ColumnType::MYSQL_TYPE_BLOB),
+            Column::new(ColumnType::MYSQL_TYPE_NULL),
+        ];
+        let row = new_row(vec![Value::Bytes(vec![0; 10]), Value::NULL], columns.into());
+        stats.record_row(&row);
+        assert_eq!(
+            stats.max_row_bytes(),
+            10 + 2 * mem::size_of::<Option<Value>>()
+        );
+    }
+}
@@ -0,0 +1,560 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::proto::MySerialize;
+
+use std::{cmp, mem};
+
+use crate::{
+    conn::{
+        export::quote_identifier, interpolate::literal, query_result::Binary,
+        server_flavor::MariadbCapabilities, ConnMut,
+    },
+    consts::{ColumnType, MAX_PAYLOAD_LEN},
+    from_row_opt,
+    prelude::*,
+    Conn, DriverError, Params, QueryResult, Result, Statement, Value,
+};
+
+/// MariaDB command that executes a prepared statement for many rows at once.
+const COM_STMT_BULK_EXECUTE: u8 = 0xfa;
+/// Server sends the generated id and the number of affected rows of every row.
+const STMT_BULK_FLAG_SEND_UNIT_RESULTS: u16 = 64;
+/// Types of parameters follow the flags.
+const STMT_BULK_FLAG_SEND_TYPES_TO_SERVER: u16 = 128;
+/// Indicator of a parameter value that follows.
+const INDICATOR_NONE: u8 = 0;
+/// Indicator of a `NULL` parameter value.
+const INDICATOR_NULL: u8 = 1;
+
+/// Multi-row `INSERT` statement split around the row tuple.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BulkTemplate {
+    /// Statement up to the row tuple (e.g. `INSERT INTO t (a, b) VALUES `).
+    prefix: String,
+    /// Row tuple split by placeholders (e.g. `["(", ", ", ")"]`).
+    row: Vec<String>,
+    /// Rest of the statement (e.g. ` ON DUPLICATE KEY UPDATE b = VALUES(b)`).
+    suffix: String,
+}
+
+impl BulkTemplate {
+    /// Parses an `INSERT` or `REPLACE` statement with a single row tuple of `?` placeholders.
+    ///
+    /// Returns `None` if `stmt` isn't such a statement.
+    fn parse(stmt: &str) -> Option<Self> {
+        let trimmed = stmt.trim();
+        let keyword = trimmed.split_whitespace().next()?;
+        if !keyword.eq_ignore_ascii_case("INSERT") && !keyword.eq_ignore_ascii_case("REPLACE") {
+            return None;
+        }
+
+        let values = find_keyword(trimmed, "VALUES").or_else(|| find_keyword(trimmed, "VALUE"))?;
+        let open = values + trimmed[values..].find('(')?;
+        let mut row = vec![String::new()];
+        let mut quote = None;
+        let mut depth = 0_usize;
+        for (i, c) in trimmed[open..].char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => (),
+                (None, '\'' | '"' | '`') => quote = Some(c),
+                (None, '(') => depth += 1,
+                (None, ')') => depth -= 1,
+                (None, '?') => {
+                    row.push(String::new());
+                    continue;
+                }
+                _ => (),
+            }
+            row.last_mut().expect("not empty").push(c);
+            if depth == 0 {
+                return Some(Self {
+                    prefix: format!("{} ", trimmed[..open].trim_end()),
+                    row,
+                    suffix: trimmed[open + i + 1..].to_owned(),
+                });
+            }
+        }
+        None
+    }
+
+    /// Creates `INSERT INTO table VALUES (?, ...)` with the given number of columns.
+    fn for_table(table: &str, columns: usize) -> Self {
+        let table = table
+            .split('.')
+            .map(quote_identifier)
+            .collect::<Vec<_>>()
+            .join(".");
+        let row = match columns {
+            0 => vec!["()".to_owned()],
+            _ => {
+                let mut row = vec!["(".to_owned()];
+                row.extend((1..columns).map(|_| ",".to_owned()));
+                row.push(")".to_owned());
+                row
+            }
+        };
+        Self {
+            prefix: format!("INSERT INTO {} VALUES ", table),
+            row,
+            suffix: String::new(),
+        }
+    }
+
+    fn num_params(&self) -> usize {
+        self.row.len() - 1
+    }
+
+    /// Renders the row tuple with the given values inlined as SQL literals
+    /// (see [`Conn::interpolate`]).
+    fn render_row(
+        &self,
+        values: &[Value],
+        no_backslash_escape: bool,
+        charset: &str,
+        out: &mut String,
+    ) -> Result<()> {
+        for (part, value) in self.row.iter().zip(values) {
+            out.push_str(part);
+            literal(value, no_backslash_escape, charset, out)?;
+        }
+        out.push_str(self.row.last().expect("not empty"));
+        Ok(())
+    }
+}
+
+fn is_word_byte(byte: Option<&u8>) -> bool {
+    matches!(byte, Some(x) if x.is_ascii_alphanumeric() || *x == b'_')
+}
+
+/// Returns the byte offset of the end of the given keyword (outside of quotes), if any.
+fn find_keyword(stmt: &str, keyword: &str) -> Option<usize> {
+    let bytes = stmt.as_bytes();
+    let mut quote = None;
+    for (i, &b) in bytes.iter().enumerate() {
+        match (quote, b) {
+            (Some(q), b) if b == q => quote = None,
+            (Some(_), _) => (),
+            (None, b'\'' | b'"' | b'`') => quote = Some(b),
+            (None, _) => {
+                let end = i + keyword.len();
+                if end <= bytes.len()
+                    && bytes[i..end].eq_ignore_ascii_case(keyword.as_bytes())
+                    && (i == 0 || !is_word_byte(bytes.get(i - 1)))
+                    && !is_word_byte(bytes.get(end))
+                {
+                    return Some(end);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Returns the binary protocol type and flags of a parameter value (`None` for `NULL`).
+fn param_type(value: &Value) -> Option<[u8; 2]> {
+    let (column_type, unsigned) = match value {
+        Value::NULL => return None,
+        Value::Bytes(_) => (ColumnType::MYSQL_TYPE_VAR_STRING, false),
+        Value::Int(_) => (ColumnType::MYSQL_TYPE_LONGLONG, false),
+        Value::UInt(_) => (ColumnType::MYSQL_TYPE_LONGLONG, true),
+        Value::Float(_) => (ColumnType::MYSQL_TYPE_FLOAT, false),
+        Value::Double(_) => (ColumnType::MYSQL_TYPE_DOUBLE, false),
+        Value::Date(..) => (ColumnType::MYSQL_TYPE_DATETIME, false),
+        Value::Time(..) => (ColumnType::MYSQL_TYPE_TIME, false),
+    };
+    Some([column_type as u8, if unsigned { 0x80 } else { 0 }])
+}
+
+/// Builds `COM_STMT_BULK_EXECUTE` packets for the given rows of positional parameters.
+/// Every packet is at most `max_len` bytes long.
+///
+/// Returns `None` if rows can't be sent this way, i.e. if values of a parameter
+/// have different types or if a row doesn't fit into a packet.
+fn bulk_execute_packets(
+    stmt_id: u32,
+    rows: &[Vec<Value>],
+    flags: u16,
+    max_len: usize,
+) -> Option<Vec<Vec<u8>>> {
+    let mut types = vec![None; rows.first()?.len()];
+    for row in rows {
+        for (known, value) in types.iter_mut().zip(row) {
+            match (*known, param_type(value)) {
+                (Some(known), Some(actual)) if known != actual => return None,
+                (None, actual) => *known = actual,
+                _ => (),
+            }
+        }
+    }
+
+    let mut header = vec![COM_STMT_BULK_EXECUTE];
+    header.extend_from_slice(&stmt_id.to_le_bytes());
+    header.extend_from_slice(&(flags | STMT_BULK_FLAG_SEND_TYPES_TO_SERVER).to_le_bytes());
+    for param_type in types {
+        header.extend_from_slice(&param_type.unwrap_or([ColumnType::MYSQL_TYPE_NULL as u8, 0]));
+    }
+
+    let mut packets = Vec::new();
+    let mut packet = header.clone();
+    let mut row_buf = Vec::new();
+    for row in rows {
+        row_buf.clear();
+        for value in row {
+            if *value == Value::NULL {
+                row_buf.push(INDICATOR_NULL);
+            } else {
+                row_buf.push(INDICATOR_NONE);
+                value.serialize(&mut row_buf);
+            }
+        }
+        if header.len() + row_buf.len() > max_len {
+            return None;
+        }
+        if packet.len() + row_buf.len() > max_len {
+            packets.push(mem::replace(&mut packet, header.clone()));
+        }
+        packet.extend_from_slice(&row_buf);
+    }
+    packets.push(packet);
+    Some(packets)
+}
+
+/// Converts parameters of a row to positional parameters of the statement.
+fn positional_params(stmt: &Statement, params: Params) -> Result<Vec<Value>> {
+    let values = match stmt.positional_params(params)? {
+        Params::Positional(values) => values,
+        _ => Vec::new(),
+    };
+    if values.len() != stmt.num_params() as usize {
+        return Err(DriverError::MismatchedStmtParams(stmt.num_params(), values.len()).into());
+    }
+    Ok(values)
+}
+
+impl Conn {
+    /// Returns `true` if the statement could be executed for many rows at once
+    /// using MariaDB's `COM_STMT_BULK_EXECUTE`.
+    fn supports_bulk_execute(&self, stmt: &Statement) -> bool {
+        let query = String::from_utf8_lossy(stmt.inner.query());
+        let keyword = query
+            .trim_start()
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        self.requested_mariadb_capabilities()
+            .contains(MariadbCapabilities::STMT_BULK_OPERATIONS)
+            && stmt.num_params() > 0
+            && stmt.num_columns() == 0
+            && matches!(&*keyword, "INSERT" | "REPLACE" | "UPDATE" | "DELETE")
+    }
+
+    fn write_bulk_execute(&mut self, packet: &[u8]) -> Result<()> {
+        self.check_fork()?;
+        self.reset_seq_id();
+        self.0.last_command = COM_STMT_BULK_EXECUTE;
+        #[cfg(feature = "alloc-stats")]
+        self.record_command_alloc(packet);
+        self.write_packet(&mut &*packet)
+    }
+
+    /// Executes the statement for every row of parameters, in bulk if possible.
+    ///
+    /// Returns generated ids of rows if `ids` is `true` (empty list otherwise).
+    pub(crate) fn _exec_batch<P, I>(
+        &mut self,
+        stmt: &Statement,
+        params: I,
+        ids: bool,
+    ) -> Result<Vec<Option<u64>>>
+    where
+        P: Into<Params>,
+        I: IntoIterator<Item = P>,
+    {
+        let mut bulk = self.supports_bulk_execute(stmt);
+        if ids {
+            bulk &= self
+                .requested_mariadb_capabilities()
+                .contains(MariadbCapabilities::BULK_UNIT_RESULTS);
+        }
+        if !bulk {
+            return self.exec_rows(stmt, params.into_iter().map(Into::into), ids);
+        }
+
+        let rows = params
+            .into_iter()
+            .map(|params| positional_params(stmt, params.into()))
+            .collect::<Result<Vec<_>>>()?;
+        let flags = if ids {
+            STMT_BULK_FLAG_SEND_UNIT_RESULTS
+        } else {
+            0
+        };
+        let max_len = cmp::min(
+            self.stream_ref().codec().max_allowed_packet,
+            MAX_PAYLOAD_LEN,
+        );
+        let packets = match bulk_execute_packets(stmt.id(), &rows, flags, max_len) {
+            Some(packets) => packets,
+            None => return self.exec_rows(stmt, rows.into_iter().map(Params::Positional), ids),
+        };
+
+        let mut generated = Vec::new();
+        for packet in packets {
+            self.write_bulk_execute(&packet)?;
+            let meta = self.handle_result_set()?;
+            // unit results are sent as a result set of `Id` and `Affected_rows` columns
+            for row in QueryResult::<Binary>::new(ConnMut::Mut(self), meta) {
+                let (id, _affected_rows) = from_row_opt::<(Option<u64>, u64)>(row?)?;
+                generated.push(id.filter(|id| *id != 0));
+            }
+        }
+        Ok(generated)
+    }
+
+    /// Executes the statement for every row of parameters one by one.
+    fn exec_rows<I>(&mut self, stmt: &Statement, rows: I, ids: bool) -> Result<Vec<Option<u64>>>
+    where
+        I: Iterator<Item = Params>,
+    {
+        let mut generated = Vec::new();
+        for params in rows {
+            let result = self.exec_result(stmt, params)?;
+            if ids {
+                generated.push(result.last_insert_id());
+            }
+        }
+        Ok(generated)
+    }
+
+    /// Same as [`Queryable::exec_batch`] but returns the generated id of every row
+    /// (`None` if the row didn't generate one).
+    ///
+    /// MariaDB 11.5+ reports ids of rows executed in bulk, otherwise the statement
+    /// is executed row by row.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop(
+    ///     "CREATE TEMPORARY TABLE mysql.tbl (id INT PRIMARY KEY AUTO_INCREMENT, name TEXT)",
+    /// )?;
+    ///
+    /// let names = vec![("foo",), ("bar",)];
+    /// let ids = conn.exec_batch_ids("INSERT INTO mysql.tbl (name) VALUES (?)", names)?;
+    /// assert_eq!(ids, [Some(1), Some(2)]);
+    /// # });
+    /// ```
+    pub fn exec_batch_ids<S, P, I>(&mut self, stmt: S, params: I) -> Result<Vec<Option<u64>>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+        I: IntoIterator<Item = P>,
+    {
+        let stmt = stmt.as_statement(self)?;
+        self._exec_batch(&stmt, params, true)
+    }
+
+    /// Inserts the given rows using multi-row `INSERT ... VALUES (...), (...)` statements.
+    ///
+    /// `table_or_stmt` is either a table name or an `INSERT` (or `REPLACE`) statement
+    /// with a single row tuple of `?` placeholders, e.g.
+    /// `INSERT INTO t (a, b) VALUES (?, NOW(), ?) ON DUPLICATE KEY UPDATE b = VALUES(b)`
+    /// (fails with `DriverError::InvalidBulkStatement` otherwise).
+    /// Every statement contains at most `chunk_size` rows and fits into `max_allowed_packet`.
+    ///
+    /// Unlike [`Queryable::exec_batch`], which executes a statement per row, this sends
+    /// a statement per chunk, so it's much faster for large inserts. Values are inlined into
+    /// statements as SQL literals in the same way as by [`Conn::interpolate`] (e.g. NaN
+    /// or infinite floats fail with `DriverError::CouldNotInterpolate`), so rows must contain
+    /// positional parameters.
+    /// Returns the number of affected rows.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT)")?;
+    ///
+    /// let rows = (0..1000).map(|id| (id, format!("name {}", id)));
+    /// let inserted = conn.exec_batch_bulk("mysql.tbl", rows, 100)?;
+    /// assert_eq!(inserted, 1000);
+    /// # });
+    /// ```
+    pub fn exec_batch_bulk<I, P>(
+        &mut self,
+        table_or_stmt: &str,
+        rows: I,
+        chunk_size: usize,
+    ) -> Result<u64>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Params>,
+    {
+        let rows = rows.into_iter().map(|params| match params.into() {
+            Params::Positional(values) => Ok(values),
+            Params::Empty => Ok(Vec::new()),
+            Params::Named(_) => Err(DriverError::NamedParamsForPositionalQuery.into()),
+        });
+        self._exec_batch_bulk(table_or_stmt, rows, chunk_size)
+    }
+
+    /// Inserts the given rows of positional parameters (see [`Conn::exec_batch_bulk`]).
+    pub(crate) fn _exec_batch_bulk<I>(
+        &mut self,
+        table_or_stmt: &str,
+        rows: I,
+        chunk_size: usize,
+    ) -> Result<u64>
+    where
+        I: Iterator<Item = Result<Vec<Value>>>,
+    {
+        let chunk_size = chunk_size.max(1);
+        let max_len = self.stream_ref().codec().max_allowed_packet - 1;
+        let no_backslash_escape = self.no_backslash_escape();
+        let charset = self.literal_charset();
+        // table names are not expected to contain whitespaces
+        let mut template = if table_or_stmt.trim().contains(char::is_whitespace) {
+            match BulkTemplate::parse(table_or_stmt) {
+                Some(template) => Some(template),
+                None => return Err(DriverError::InvalidBulkStatement.into()),
+            }
+        } else {
+            None
+        };
+
+        let mut affected_rows = 0;
+        let mut stmt = String::new();
+        let mut rows_in_stmt = 0;
+        let mut row_sql = String::new();
+        for values in rows {
+            let values = values?;
+            let template = template
+                .get_or_insert_with(|| BulkTemplate::for_table(table_or_stmt, values.len()));
+            if values.len() != template.num_params() {
+                return Err(DriverError::MismatchedStmtParams(
+                    template.num_params() as u16,
+                    values.len(),
+                )
+                .into());
+            }
+
+            row_sql.clear();
+            template.render_row(&values, no_backslash_escape, charset, &mut row_sql)?;
+            let len = stmt.len() + 1 + row_sql.len() + template.suffix.len();
+            if rows_in_stmt > 0 && (rows_in_stmt == chunk_size || len > max_len) {
+                stmt.push_str(&template.suffix);
+                affected_rows += self.query_result(&stmt)?.affected_rows();
+                stmt.clear();
+                rows_in_stmt = 0;
+            }
+            if rows_in_stmt == 0 {
+                stmt.push_str(&template.prefix);
+            } else {
+                stmt.push(',');
+            }
+            stmt.push_str(&row_sql);
+            rows_in_stmt += 1;
+        }
+        if let Some(template) = template.filter(|_| rows_in_stmt > 0) {
+            stmt.push_str(&template.suffix);
+            affected_rows += self.query_result(&stmt)?.affected_rows();
+        }
+        Ok(affected_rows)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bulk_execute_packets, BulkTemplate};
+    use crate::{DriverError, Error, Value};
+
+    #[test]
+    fn should_parse_bulk_template() {
+        let template = BulkTemplate::parse(
+            "insert into t (a, b, c) values (?, CONCAT('?', ?), ?) \
+             on duplicate key update b = values(b)",
+        )
+        .unwrap();
+        assert_eq!(template.prefix, "insert into t (a, b, c) values ");
+        assert_eq!(template.row, ["(", ", CONCAT('?', ", "), ", ")"]);
+        assert_eq!(template.suffix, " on duplicate key update b = values(b)");
+
+        let mut row = String::new();
+        let values = [Value::from(1), Value::from("it's"), Value::NULL];
+        template
+            .render_row(&values, false, "utf8mb4", &mut row)
+            .unwrap();
+        assert_eq!(row, "(1, CONCAT('?', 'it\\'s'), NULL)");
+
+        // values are rendered in the same way as interpolated parameters
+        row.clear();
+        let values = [
+            Value::from(1.5_f64),
+            Value::from("é"),
+            Value::from(vec![0xff_u8]),
+        ];
+        template
+            .render_row(&values, true, "utf8mb4", &mut row)
+            .unwrap();
+        assert_eq!(row, "(1.5e0, CONCAT('?', _utf8mb4 X'C3A9'), 0xFF)");
+        let values = [Value::from(f64::NAN), Value::NULL, Value::NULL];
+        let err = template
+            .render_row(&values, true, "utf8mb4", &mut String::new())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DriverError(DriverError::CouldNotInterpolate(_))
+        ));
+
+        let template = BulkTemplate::parse("REPLACE `values` VALUE(?)").unwrap();
+        assert_eq!(template.prefix, "REPLACE `values` VALUE ");
+        assert_eq!(template.row, ["(", ")"]);
+
+        assert_eq!(BulkTemplate::parse("SELECT ?"), None);
+        assert_eq!(BulkTemplate::parse("INSERT INTO t SELECT ?"), None);
+
+        let template = BulkTemplate::for_table("db.t", 2);
+        assert_eq!(template.prefix, "INSERT INTO `db`.`t` VALUES ");
+        assert_eq!(template.row, ["(", ",", ")"]);
+        assert_eq!(BulkTemplate::for_table("t", 0).num_params(), 0);
+    }
+
+    #[test]
+    fn should_build_bulk_execute_packets() {
+        let rows = vec![
+            vec![Value::Int(1), Value::NULL, Value::NULL],
+            vec![Value::Int(2), Value::from("ab"), Value::NULL],
+        ];
+        let packets = bulk_execute_packets(7, &rows, 64, 1024).unwrap();
+        assert_eq!(
+            packets,
+            [[
+                0xfa, 7, 0, 0, 0, 192, 0, // command, statement id, flags
+                8, 0, 253, 0, 6, 0, // LONGLONG, VAR_STRING, NULL
+                0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1, // first row
+                0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 2, b'a', b'b', 1, // second row
+            ]]
+        );
+
+        // rows are split to fit into packets
+        let packets = bulk_execute_packets(7, &rows, 0, 30).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert!(packets.iter().all(|packet| packet.len() <= 30));
+        assert_eq!(bulk_execute_packets(7, &rows, 0, 20), None);
+
+        // inconsistent types of a parameter
+        let rows = vec![vec![Value::Int(1)], vec![Value::UInt(2)]];
+        assert_eq!(bulk_execute_packets(7, &rows, 0, 1024), None);
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{
+    conn::export::quote_identifier, from_row_opt, prelude::*, CollectedSet, Conn, DriverError,
+    Result, Value,
+};
+
+/// Argument of a stored procedure (see [`Conn::call`]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallArg {
+    /// `IN` parameter.
+    In(Value),
+    /// `OUT` parameter.
+    Out,
+    /// `INOUT` parameter with its initial value.
+    InOut(Value),
+}
+
+impl CallArg {
+    /// Creates an `IN` argument.
+    pub fn input<T: Into<Value>>(value: T) -> Self {
+        CallArg::In(value.into())
+    }
+
+    /// Creates an `INOUT` argument.
+    pub fn in_out<T: Into<Value>>(value: T) -> Self {
+        CallArg::InOut(value.into())
+    }
+}
+
+/// Result of a stored procedure call (see [`Conn::call`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallResult<T> {
+    sets: Vec<CollectedSet>,
+    out: Option<T>,
+}
+
+impl<T> CallResult<T> {
+    /// Result sets returned by the procedure (the final status of the `CALL` is omitted).
+    pub fn sets(&self) -> &[CollectedSet] {
+        &self.sets
+    }
+
+    /// Values of `OUT` and `INOUT` parameters in the order of arguments
+    /// (`None` if there are no such parameters).
+    pub fn out(&self) -> Option<&T> {
+        self.out.as_ref()
+    }
+
+    /// Returns result sets and values of `OUT` and `INOUT` parameters.
+    pub fn into_parts(self) -> (Vec<CollectedSet>, Option<T>) {
+        (self.sets, self.out)
+    }
+}
+
+/// Name of the user variable that binds the `OUT` or `INOUT` argument at the given position.
+fn out_var(position: usize) -> String {
+    format!("@__mysql_call_out_{}", position)
+}
+
+impl Conn {
+    /// Calls the stored procedure and returns its result sets along with the values
+    /// of `OUT` and `INOUT` parameters converted to `T` (e.g. a tuple).
+    ///
+    /// `OUT` and `INOUT` parameters are bound to user variables, so this is the same as
+    /// `SET @a = ?; CALL proc(?, @a, @b); SELECT @a, @b` (`procedure` could be qualified
+    /// with a database name). Fails with `DriverError::OutParamsNotSupported` if `OUT`
+    /// parameters are not supported by the server (see [`crate::CompatMode`]).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("DROP PROCEDURE IF EXISTS mysql.divide")?;
+    /// conn.query_drop(
+    ///     "CREATE PROCEDURE mysql.divide(IN a INT, IN b INT, OUT q INT, INOUT r INT)
+    ///      BEGIN
+    ///          SELECT 'dividing';
+    ///          SET q = a DIV b, r = a MOD b + r;
+    ///      END",
+    /// )?;
+    ///
+    /// let args = vec![CallArg::input(7), CallArg::input(2), CallArg::Out, CallArg::in_out(10)];
+    /// let result = conn.call::<(i32, i32)>("mysql.divide", args)?;
+    /// assert_eq!(result.sets().len(), 1);
+    /// assert_eq!(result.out(), Some(&(3, 11)));
+    /// # });
+    /// ```
+    pub fn call<T: FromRow>(
+        &mut self,
+        procedure: &str,
+        args: Vec<CallArg>,
+    ) -> Result<CallResult<T>> {
+        let has_out = args.iter().any(|arg| !matches!(arg, CallArg::In(_)));
+        if has_out && !self.0.opts.get_compat_mode().supports_out_params() {
+            return Err(DriverError::OutParamsNotSupported.into());
+        }
+
+        let mut initial = Vec::new();
+        let mut params = Vec::new();
+        let mut placeholders = Vec::with_capacity(args.len());
+        let mut out_vars = Vec::new();
+        for (i, arg) in args.into_iter().enumerate() {
+            match arg {
+                CallArg::In(value) => {
+                    params.push(value);
+                    placeholders.push("?".to_owned());
+                }
+                CallArg::Out => {
+                    // clear the value left by a previous call
+                    initial.push((out_var(i), Value::NULL));
+                    placeholders.push(out_var(i));
+                    out_vars.push(out_var(i));
+                }
+                CallArg::InOut(value) => {
+                    initial.push((out_var(i), value));
+                    placeholders.push(out_var(i));
+                    out_vars.push(out_var(i));
+                }
+            }
+        }
+
+        if !initial.is_empty() {
+            let (vars, values): (Vec<_>, Vec<_>) = initial
+                .into_iter()
+                .map(|(var, value)| (format!("{} = ?", var), value))
+                .unzip();
+            self.exec_drop(format!("SET {}", vars.join(", ")), values)?;
+        }
+
+        let procedure = procedure
+            .split('.')
+            .map(quote_identifier)
+            .collect::<Vec<_>>()
+            .join(".");
+        let query = format!("CALL {}({})", procedure, placeholders.join(", "));
+        let sets = self
+            .exec_iter(query, params)?
+            .iter_sets()
+            .filter(|set| !matches!(set, Ok(set) if set.columns().is_empty()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let out = if out_vars.is_empty() {
+            None
+        } else {
+            let row = self.query_first(format!("SELECT {}", out_vars.join(", ")))?;
+            row.map(from_row_opt::<T>).transpose()?
+        };
+        Ok(CallResult { sets, out })
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+use crate::{prelude::*, Conn, Result};
+
+/// Progress of [`Conn::exec_chunked`] reported after every chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkReport {
+    chunk: usize,
+    affected_rows: u64,
+    total_affected_rows: u64,
+    elapsed: Duration,
+}
+
+impl ChunkReport {
+    /// One-based number of the chunk.
+    pub fn chunk(&self) -> usize {
+        self.chunk
+    }
+
+    /// Number of rows affected by this chunk.
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    /// Number of rows affected by all chunks so far.
+    pub fn total_affected_rows(&self) -> u64 {
+        self.total_affected_rows
+    }
+
+    /// Time it took to execute this chunk.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+impl Conn {
+    /// Repeatedly executes a `LIMIT`-ed `UPDATE` or `DELETE` statement until it affects no rows.
+    ///
+    /// The statement must have exactly one parameter – the `LIMIT` value, which is bound
+    /// to `chunk_size`. Every chunk is a separate statement, so locks are held only
+    /// for the duration of a chunk (as long as there is no active transaction).
+    ///
+    /// `f` is called after every chunk that affected some rows. It's the place to throttle
+    /// the job (e.g. sleep to let replicas catch up) or to stop it by returning
+    /// `ControlFlow::Break(())`. Total number of affected rows is returned.
+    ///
+    /// Note that the statement must make progress, i.e. it must not match rows
+    /// that it has already updated, otherwise it'll never finish.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # use std::{ops::ControlFlow, thread, time::Duration};
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// # conn.query_drop("CREATE TEMPORARY TABLE mysql.events (id INT, expired BOOL)")?;
+    /// let purged = conn.exec_chunked(
+    ///     "DELETE FROM mysql.events WHERE expired LIMIT ?",
+    ///     1000,
+    ///     |report| {
+    ///         thread::sleep(report.elapsed() / 2);
+    ///         ControlFlow::Continue(())
+    ///     },
+    /// )?;
+    /// # assert_eq!(purged, 0);
+    /// # });
+    /// ```
+    pub fn exec_chunked<T, F>(&mut self, query: T, chunk_size: u64, mut f: F) -> Result<u64>
+    where
+        T: AsRef<str>,
+        F: FnMut(&ChunkReport) -> ControlFlow<()>,
+    {
+        let stmt = self.prep(query)?;
+        let mut total_affected_rows = 0;
+        let mut chunk = 0;
+        loop {
+            let started = Instant::now();
+            self.exec_drop(&stmt, (chunk_size,))?;
+            let affected_rows = self.affected_rows();
+            if affected_rows == 0 {
+                break;
+            }
+
+            chunk += 1;
+            total_affected_rows += affected_rows;
+            let report = ChunkReport {
+                chunk,
+                affected_rows,
+                total_affected_rows,
+                elapsed: started.elapsed(),
+            };
+            if f(&report).is_break() {
+                break;
+            }
+        }
+        Ok(total_affected_rows)
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+/// Protocol compression algorithm and level (see [`crate::OptsBuilder::compress`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// zlib compression (`CLIENT_COMPRESS`) with the given level,
+    /// where `0` stands for "no compression" and `9` is the best compression.
+    Zlib(u32),
+    /// zstd compression (`CLIENT_ZSTD_COMPRESSION_ALGORITHM`, MySql 8.0.18+)
+    /// with the given level (`1`..=`22`).
+    ///
+    /// Requires the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd(u8),
+}
+
+impl Compression {
+    /// Default zstd compression level (same as the server's default).
+    #[cfg(feature = "zstd")]
+    pub const DEFAULT_ZSTD_LEVEL: u8 = 3;
+
+    /// zlib compression with the given level.
+    pub const fn new(level: u32) -> Self {
+        Compression::Zlib(level)
+    }
+
+    /// zlib compression with no compression at all.
+    pub const fn none() -> Self {
+        Compression::Zlib(0)
+    }
+
+    /// zlib compression optimized for speed.
+    pub const fn fast() -> Self {
+        Compression::Zlib(1)
+    }
+
+    /// zlib compression optimized for size.
+    pub const fn best() -> Self {
+        Compression::Zlib(9)
+    }
+
+    /// Returns the compression level.
+    pub fn level(&self) -> u32 {
+        match *self {
+            Compression::Zlib(level) => level,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => level as u32,
+        }
+    }
+}
+
+impl Default for Compression {
+    /// zlib compression with the default level (`6`).
+    fn default() -> Self {
+        Compression::Zlib(6)
+    }
+}
+
+/// `flate2::Compression` (formerly re-exported as `mysql::Compression`) converts
+/// to zlib compression with the same level.
+impl From<flate2::Compression> for Compression {
+    fn from(compression: flate2::Compression) -> Self {
+        Compression::Zlib(compression.level())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Compression;
+
+    #[test]
+    fn should_convert_flate2_compression() {
+        assert_eq!(
+            Compression::from(flate2::Compression::fast()),
+            Compression::fast()
+        );
+        assert_eq!(
+            Compression::from(flate2::Compression::new(7)),
+            Compression::Zlib(7)
+        );
+        let compression: Compression = flate2::Compression::default().into();
+        assert_eq!(compression, Compression::default());
+    }
+}
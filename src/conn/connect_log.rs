@@ -0,0 +1,157 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{error, fmt, net::SocketAddr};
+
+use crate::Error;
+
+/// A step of a connection attempt (see [`ConnectError`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectEvent {
+    /// Server address was resolved to the given socket addresses.
+    Resolved(Vec<SocketAddr>),
+    /// Could not connect to the given address or socket (the second field is the reason).
+    ConnectFailed(String, String),
+    /// Connected to the given address or socket.
+    Connected(String),
+    /// TLS was requested but not negotiated (the field is the reason).
+    TlsSkipped(String),
+    /// TLS was negotiated.
+    TlsNegotiated,
+    /// Authentication was started using the given plugin.
+    AuthPlugin(String),
+    /// Server asked to switch to the given authentication plugin.
+    AuthSwitch(String),
+    /// Could not reconnect via the socket, so the TCP connection is used
+    /// (see `OptsBuilder::prefer_socket`). Fields are the socket and the reason.
+    SocketFallback(String, String),
+}
+
+impl fmt::Display for ConnectEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectEvent::Resolved(addrs) => {
+                write!(f, "resolved to [")?;
+                for (i, addr) in addrs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", addr)?;
+                }
+                write!(f, "]")
+            }
+            ConnectEvent::ConnectFailed(addr, reason) => {
+                write!(f, "could not connect to `{}': {}", addr, reason)
+            }
+            ConnectEvent::Connected(addr) => write!(f, "connected to `{}'", addr),
+            ConnectEvent::TlsSkipped(reason) => write!(f, "TLS skipped: {}", reason),
+            ConnectEvent::TlsNegotiated => write!(f, "TLS negotiated"),
+            ConnectEvent::AuthPlugin(plugin) => write!(f, "authenticating using `{}'", plugin),
+            ConnectEvent::AuthSwitch(plugin) => write!(f, "switched to `{}' plugin", plugin),
+            ConnectEvent::SocketFallback(socket, reason) => write!(
+                f,
+                "could not reconnect via socket `{}' ({}), using TCP",
+                socket, reason
+            ),
+        }
+    }
+}
+
+/// Error of [`crate::Conn::new_audited`].
+///
+/// It's the error that made the connection attempt fail accompanied by the sequence
+/// of steps taken during the attempt, so that the cause of a failure isn't reduced
+/// to its last symptom.
+#[derive(Debug)]
+pub struct ConnectError {
+    error: Error,
+    events: Vec<ConnectEvent>,
+}
+
+impl ConnectError {
+    pub(crate) fn new(error: Error, events: Vec<ConnectEvent>) -> Self {
+        Self { error, events }
+    }
+
+    /// The error that made the connection attempt fail.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Steps taken during the connection attempt.
+    pub fn events(&self) -> &[ConnectEvent] {
+        &self.events
+    }
+
+    /// Returns the underlying error.
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if !self.events.is_empty() {
+            write!(f, " (")?;
+            for (i, event) in self.events.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "; ")?;
+                }
+                write!(f, "{}", event)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<ConnectError> for Error {
+    fn from(err: ConnectError) -> Self {
+        err.error
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConnectError, ConnectEvent};
+    use crate::{DriverError, Error};
+
+    #[test]
+    fn should_display_connect_error() {
+        let events = vec![
+            ConnectEvent::Resolved(vec![
+                "[::1]:3306".parse().unwrap(),
+                "127.0.0.1:3306".parse().unwrap(),
+            ]),
+            ConnectEvent::ConnectFailed("[::1]:3306".into(), "refused".into()),
+            ConnectEvent::Connected("127.0.0.1:3306".into()),
+            ConnectEvent::AuthPlugin("caching_sha2_password".into()),
+        ];
+        let err = ConnectError::new(Error::DriverError(DriverError::SetupError), events);
+        assert_eq!(
+            err.to_string(),
+            "DriverError { Could not setup connection } \
+             (resolved to [[::1]:3306, 127.0.0.1:3306]; \
+             could not connect to `[::1]:3306': refused; connected to `127.0.0.1:3306'; \
+             authenticating using `caching_sha2_password')"
+        );
+
+        let err = ConnectError::new(Error::DriverError(DriverError::SetupError), vec![]);
+        assert_eq!(
+            err.to_string(),
+            "DriverError { Could not setup connection }"
+        );
+    }
+}
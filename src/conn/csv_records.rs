@@ -0,0 +1,365 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use csv::{ByteRecord, StringRecord};
+
+use std::{error::Error as StdError, fmt, io, str::FromStr, sync::Arc};
+
+use crate::{
+    conn::load_data::{write_bytes_field, write_field},
+    Conn, DriverError, Error, ExecResult, LoadDataBuilder, Result, Value,
+};
+
+type Converter = Arc<dyn Fn(&[u8]) -> StdResult<Value> + Send + Sync>;
+type StdResult<T> = std::result::Result<T, Box<dyn StdError + Send + Sync>>;
+
+/// Record of the `csv` crate, that could be used as a row of positional parameters
+/// (see [`CsvConverters`]).
+///
+/// Implemented for `ByteRecord` and `StringRecord`, references to them and results
+/// of reading them (i.e. items of `csv::Reader::records` and `csv::Reader::byte_records`).
+pub trait CsvRecord {
+    /// Returns fields of this record or the error of reading it.
+    fn byte_record(&self) -> Result<&ByteRecord>;
+}
+
+impl CsvRecord for ByteRecord {
+    fn byte_record(&self) -> Result<&ByteRecord> {
+        Ok(self)
+    }
+}
+
+impl CsvRecord for StringRecord {
+    fn byte_record(&self) -> Result<&ByteRecord> {
+        Ok(self.as_byte_record())
+    }
+}
+
+impl<T: CsvRecord> CsvRecord for &T {
+    fn byte_record(&self) -> Result<&ByteRecord> {
+        (**self).byte_record()
+    }
+}
+
+impl<T: CsvRecord> CsvRecord for csv::Result<T> {
+    fn byte_record(&self) -> Result<&ByteRecord> {
+        match self {
+            Ok(record) => record.byte_record(),
+            Err(err) => Err(DriverError::InvalidCsvRecord(err.to_string()).into()),
+        }
+    }
+}
+
+/// Converts fields of CSV records to parameter values
+/// (see [`Conn::exec_batch_bulk_csv`] and [`LoadDataBuilder::load_csv`]).
+///
+/// Fields are passed as is (i.e. as strings) unless there is a converter for the column.
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// # use mysql::*;
+/// let converters = CsvConverters::new()
+///     .with_null("")
+///     .with_parsed::<i64>(0)
+///     .with_converter(2, |field| Ok(Value::from(field.eq_ignore_ascii_case(b"yes"))));
+///
+/// let record = csv::StringRecord::from(vec!["42", "", "YES"]);
+/// let values = converters.convert(&record)?;
+/// assert_eq!(values, [Value::Int(42), Value::NULL, Value::Int(1)]);
+/// # });
+/// ```
+#[derive(Clone, Default)]
+pub struct CsvConverters {
+    converters: Vec<Option<Converter>>,
+    null: Option<Vec<u8>>,
+}
+
+impl CsvConverters {
+    /// Creates converters, that pass all fields as is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Converts fields of the given column (zero-based) using the given function.
+    pub fn with_converter<F>(mut self, column: usize, converter: F) -> Self
+    where
+        F: Fn(&[u8]) -> StdResult<Value> + Send + Sync + 'static,
+    {
+        if self.converters.len() <= column {
+            self.converters.resize(column + 1, None);
+        }
+        self.converters[column] = Some(Arc::new(converter));
+        self
+    }
+
+    /// Parses fields of the given column (zero-based) as `T`, e.g. `i64` or `f64`.
+    pub fn with_parsed<T>(self, column: usize) -> Self
+    where
+        T: FromStr + Into<Value>,
+        T::Err: StdError + Send + Sync + 'static,
+    {
+        self.with_converter(column, |field| {
+            let parsed = std::str::from_utf8(field)?.trim().parse::<T>()?;
+            Ok(parsed.into())
+        })
+    }
+
+    /// Fields equal to the given marker (e.g. an empty string or `\N`) are converted to `NULL`
+    /// before any converter applies.
+    pub fn with_null<T: Into<Vec<u8>>>(mut self, marker: T) -> Self {
+        self.null = Some(marker.into());
+        self
+    }
+
+    /// Returns the `NULL` marker, if any.
+    pub fn null(&self) -> Option<&[u8]> {
+        self.null.as_deref()
+    }
+
+    fn converter(&self, column: usize) -> Option<&Converter> {
+        self.converters.get(column).and_then(Option::as_ref)
+    }
+
+    fn is_null(&self, field: &[u8]) -> bool {
+        matches!(self.null, Some(ref null) if null == field)
+    }
+
+    /// Converts the given record to positional parameters.
+    pub fn convert<R: CsvRecord>(&self, record: R) -> Result<Vec<Value>> {
+        record
+            .byte_record()?
+            .iter()
+            .enumerate()
+            .map(|(column, field)| self.convert_field(column, field))
+            .collect()
+    }
+
+    fn convert_field(&self, column: usize, field: &[u8]) -> Result<Value> {
+        if self.is_null(field) {
+            return Ok(Value::NULL);
+        }
+        match self.converter(column) {
+            Some(converter) => converter(field).map_err(|err| {
+                let message = format!("column {}: {}", column, err);
+                Error::from(DriverError::InvalidCsvRecord(message))
+            }),
+            None => Ok(Value::Bytes(field.to_vec())),
+        }
+    }
+
+    /// Appends the record in the server's default format of `LOAD DATA`.
+    ///
+    /// Fields without converters are written as is, i.e. without intermediate values.
+    fn write_record(&self, record: &ByteRecord, out: &mut Vec<u8>) -> Result<()> {
+        for (column, field) in record.iter().enumerate() {
+            if column > 0 {
+                out.push(b'\t');
+            }
+            if self.is_null(field) {
+                out.extend_from_slice(b"\\N");
+            } else if self.converter(column).is_some() {
+                write_field(&self.convert_field(column, field)?, out);
+            } else {
+                write_bytes_field(field, out);
+            }
+        }
+        out.push(b'\n');
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CsvConverters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self
+            .converters
+            .iter()
+            .enumerate()
+            .filter(|(_, converter)| converter.is_some())
+            .map(|(column, _)| column)
+            .collect::<Vec<_>>();
+        f.debug_struct("CsvConverters")
+            .field("converted_columns", &columns)
+            .field("null", &self.null.as_deref().map(String::from_utf8_lossy))
+            .finish()
+    }
+}
+
+impl Conn {
+    /// Same as [`Conn::exec_batch_bulk`] but inserts CSV records
+    /// converted using the given converters.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT)")?;
+    ///
+    /// let data = "id,name\n1,foo\n2,\n";
+    /// let mut reader = csv::Reader::from_reader(data.as_bytes());
+    /// let converters = CsvConverters::new().with_parsed::<i64>(0).with_null("");
+    /// let inserted = conn.exec_batch_bulk_csv("mysql.tbl", reader.records(), &converters, 100)?;
+    /// assert_eq!(inserted, 2);
+    /// # });
+    /// ```
+    pub fn exec_batch_bulk_csv<I>(
+        &mut self,
+        table_or_stmt: &str,
+        records: I,
+        converters: &CsvConverters,
+        chunk_size: usize,
+    ) -> Result<u64>
+    where
+        I: IntoIterator,
+        I::Item: CsvRecord,
+    {
+        let rows = records.into_iter().map(|record| converters.convert(record));
+        self._exec_batch_bulk(table_or_stmt, rows, chunk_size)
+    }
+}
+
+impl LoadDataBuilder {
+    /// Loads the given CSV records converted using the given converters.
+    ///
+    /// Records are encoded in the server's default format (see [`LoadDataBuilder::load_rows`]),
+    /// so format options of this builder are ignored. If a record can't be read
+    /// or converted, records sent before it are still loaded.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT)")?;
+    ///
+    /// let data = "1,\"Smith, John\"\n2,\\N\n";
+    /// let mut reader = csv::ReaderBuilder::new()
+    ///     .has_headers(false)
+    ///     .from_reader(data.as_bytes());
+    /// let converters = CsvConverters::new().with_null("\\N");
+    /// let result = LoadDataBuilder::new("mysql.tbl").load_csv(
+    ///     &mut conn,
+    ///     reader.byte_records(),
+    ///     &converters,
+    /// );
+    /// # let result = match result {
+    /// #     Err(Error::MySqlError(e)) if e.code == 1148 => return Ok(()),
+    /// #     result => result?,
+    /// # };
+    /// assert_eq!(result.affected_rows(), 2);
+    /// # });
+    /// ```
+    pub fn load_csv<I>(
+        &self,
+        conn: &mut Conn,
+        records: I,
+        converters: &CsvConverters,
+    ) -> Result<ExecResult>
+    where
+        I: IntoIterator,
+        I::Item: CsvRecord,
+    {
+        let builder = self.default_format();
+        let mut reader = CsvReader {
+            records: records.into_iter(),
+            converters,
+            buf: Vec::new(),
+            pos: 0,
+            error: None,
+        };
+        let result = builder.load(conn, &mut reader);
+        match reader.error {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+}
+
+/// Encodes CSV records in the server's default format of `LOAD DATA` on the fly.
+struct CsvReader<'a, I> {
+    records: I,
+    converters: &'a CsvConverters,
+    buf: Vec<u8>,
+    pos: usize,
+    error: Option<Error>,
+}
+
+impl<I> io::Read for CsvReader<'_, I>
+where
+    I: Iterator,
+    I::Item: CsvRecord,
+{
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            let record = match self.records.next() {
+                Some(record) => record,
+                None => return Ok(0),
+            };
+            self.buf.clear();
+            self.pos = 0;
+            let written = record
+                .byte_record()
+                .and_then(|record| self.converters.write_record(record, &mut self.buf));
+            if let Err(err) = written {
+                let message = err.to_string();
+                self.error = Some(err);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        }
+        let len = out.len().min(self.buf.len() - self.pos);
+        out[..len].copy_from_slice(&self.buf[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use csv::{ByteRecord, StringRecord};
+
+    use std::io::Read;
+
+    use super::{CsvConverters, CsvReader};
+    use crate::Value;
+
+    #[test]
+    fn should_convert_csv_records() {
+        let converters = CsvConverters::new().with_null("\\N").with_parsed::<f64>(1);
+
+        let record = StringRecord::from(vec!["a", " 1.5", "\\N"]);
+        assert_eq!(
+            converters.convert(&record).unwrap(),
+            [Value::from("a"), Value::Double(1.5), Value::NULL]
+        );
+
+        let record: csv::Result<ByteRecord> = Ok(ByteRecord::from(vec!["a", "x"]));
+        let err = converters.convert(record).unwrap_err();
+        assert!(err.to_string().contains("column 1"), "{}", err);
+    }
+
+    #[test]
+    fn should_encode_csv_records() {
+        let converters = CsvConverters::new()
+            .with_null("")
+            .with_converter(1, |field| Ok(Value::from(field.len())));
+        let records = [
+            ByteRecord::from(vec!["a\tb", "xyz", ""]),
+            ByteRecord::from(vec!["c\\d", "", "e\nf"]),
+        ];
+        let mut reader = CsvReader {
+            records: records.iter(),
+            converters: &converters,
+            buf: Vec::new(),
+            pos: 0,
+            error: None,
+        };
+        let mut encoded = String::new();
+        reader.read_to_string(&mut encoded).unwrap();
+        assert_eq!(encoded, "a\\tb\t3\t\\N\nc\\\\d\t\\N\te\\nf\n");
+    }
+}
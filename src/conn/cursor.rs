@@ -0,0 +1,282 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{
+    io::ParseBuf, packets::OkPacket, proto::Binary, row::RowDeserializer, value::ServerSide,
+};
+
+use std::{mem, ops::ControlFlow, sync::Arc};
+
+use crate::{
+    conn::{query_result::Or, trace::TraceSpan, ConnMut},
+    Column, Result, Row,
+};
+
+/// State of a cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CursorState {
+    /// Rows of the current batch are being read.
+    InBatch,
+    /// The current batch is consumed but the cursor is still open on the server side.
+    NeedFetch,
+    /// No more rows.
+    Done,
+}
+
+/// Rows of a statement executed with a server-side cursor
+/// (see [`Conn::exec_iter_cursored`][crate::Conn::exec_iter_cursored]).
+///
+/// It is an iterator over rows. Rows are fetched from the server in batches of `fetch_size`
+/// rows using `COM_STMT_FETCH`, so only one batch is buffered at a time.
+///
+/// Batches could also be pulled explicitly (see [`Cursor::next_batch`]
+/// and [`Cursor::for_each_batch`]), so that the application decides when the next
+/// `COM_STMT_FETCH` happens.
+///
+/// Note that server may decide not to open a cursor (e.g. for statements that don't
+/// produce a result set). In this case rows are streamed as usual.
+///
+/// `Drop` will close the cursor, if it's still open.
+#[derive(Debug)]
+pub struct Cursor<'c, 't, 'tc> {
+    conn: ConnMut<'c, 't, 'tc>,
+    stmt_id: u32,
+    columns: Arc<[Column]>,
+    fetch_size: u32,
+    state: CursorState,
+    ok_packet: Option<OkPacket<'static>>,
+    fetch_span: TraceSpan,
+}
+
+impl<'c, 't, 'tc> Cursor<'c, 't, 'tc> {
+    pub(crate) fn new(
+        conn: ConnMut<'c, 't, 'tc>,
+        stmt_id: u32,
+        meta: Or<Vec<Column>, OkPacket<'static>>,
+        fetch_size: u32,
+    ) -> Self {
+        let (columns, ok_packet, state) = match meta {
+            Or::A(columns) => {
+                let state = if conn.cursor_exists() {
+                    CursorState::NeedFetch
+                } else {
+                    CursorState::InBatch
+                };
+                (columns.into(), None, state)
+            }
+            Or::B(ok) => (Vec::new().into(), Some(ok), CursorState::Done),
+        };
+
+        Self {
+            conn,
+            stmt_id,
+            columns,
+            fetch_size: fetch_size.max(1),
+            state,
+            ok_packet,
+            fetch_span: TraceSpan::default(),
+        }
+    }
+
+    /// Returns columns of this result set.
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Returns the number of rows requested by each `COM_STMT_FETCH`.
+    pub fn fetch_size(&self) -> u32 {
+        self.fetch_size
+    }
+
+    /// Sets the number of rows requested by subsequent `COM_STMT_FETCH` commands
+    /// (e.g. to match the free capacity of a downstream queue).
+    pub fn set_fetch_size(&mut self, fetch_size: u32) {
+        self.fetch_size = fetch_size.max(1);
+    }
+
+    /// Returns the number of affected rows, if the statement didn't produce a result set.
+    pub fn affected_rows(&self) -> u64 {
+        self.ok_packet
+            .as_ref()
+            .map(|ok| ok.affected_rows())
+            .unwrap_or_default()
+    }
+
+    /// Reads the next row of the current batch.
+    fn next_in_batch(&mut self) -> Result<Option<Row>> {
+        let result = self.read_in_batch();
+        match result {
+            Ok(Some(_)) => self.fetch_span.add_row(),
+            _ => mem::take(&mut self.fetch_span).finish(&result),
+        }
+        result
+    }
+
+    fn read_in_batch(&mut self) -> Result<Option<Row>> {
+        match self.conn.next_row_packet()? {
+            Some(pld) => {
+                let mut row: Row = ParseBuf(&pld)
+                    .parse::<RowDeserializer<ServerSide, Binary>>(self.columns.clone())?
+                    .into();
+                self.conn.0.opts.get_float_decoding().apply(&mut row);
+                self.conn.0.opts.get_utf8_decoding().apply(&mut row)?;
+                #[cfg(feature = "alloc-stats")]
+                self.conn.0.alloc_stats.record_row(&row);
+                Ok(Some(row))
+            }
+            None => {
+                self.state = if self.conn.cursor_exists() {
+                    CursorState::NeedFetch
+                } else {
+                    CursorState::Done
+                };
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns the next batch of at most `fetch_size` rows (`None` if there are no more rows).
+    ///
+    /// `COM_STMT_FETCH` is only sent by this call, once the previous batch is consumed,
+    /// so the server won't produce rows faster than the application pulls them.
+    /// If the server hasn't opened a cursor, rows are read from the stream
+    /// in chunks of `fetch_size` rows.
+    pub fn next_batch(&mut self) -> Result<Option<Vec<Row>>> {
+        let result = self.read_batch();
+        if result.is_err() {
+            self.state = CursorState::Done;
+        }
+        result
+    }
+
+    /// Calls `f` for every batch of rows (see [`Cursor::next_batch`]).
+    ///
+    /// The next batch is fetched after `f` returns `ControlFlow::Continue(fetch_size)`,
+    /// where `fetch_size` is the size of the next batch (see [`Cursor::set_fetch_size`]).
+    /// It allows to tie fetching to the capacity of a downstream consumer, e.g. a bounded
+    /// channel. `ControlFlow::Break(())` stops the iteration and closes the cursor on drop.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # use std::{ops::ControlFlow, sync::mpsc, thread};
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let (tx, rx) = mpsc::sync_channel::<Row>(100);
+    /// let consumer = thread::spawn(move || rx.into_iter().count());
+    ///
+    /// let stmt = conn.prep("SELECT * FROM information_schema.columns")?;
+    /// let mut cursor = conn.exec_iter_cursored(&stmt, (), 100)?;
+    /// cursor.for_each_batch(|rows| {
+    ///     for row in rows {
+    ///         // blocks while the channel is full, so the next fetch is delayed
+    ///         if tx.send(row).is_err() {
+    ///             return ControlFlow::Break(());
+    ///         }
+    ///     }
+    ///     ControlFlow::Continue(100)
+    /// })?;
+    /// drop(tx);
+    /// # assert!(consumer.join().unwrap() > 0);
+    /// # });
+    /// ```
+    pub fn for_each_batch<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(Vec<Row>) -> ControlFlow<(), u32>,
+    {
+        while let Some(rows) = self.next_batch()? {
+            match f(rows) {
+                ControlFlow::Continue(fetch_size) => self.set_fetch_size(fetch_size),
+                ControlFlow::Break(()) => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Requests the next batch using `COM_STMT_FETCH`.
+    fn fetch(&mut self) -> Result<()> {
+        let connection_id = self.conn.connection_id();
+        self.fetch_span = TraceSpan::fetch(connection_id, self.stmt_id, self.fetch_size);
+        let result = self.conn.fetch_cursor_rows(self.stmt_id, self.fetch_size);
+        if result.is_err() {
+            mem::take(&mut self.fetch_span).finish(&result);
+        }
+        result?;
+        self.state = CursorState::InBatch;
+        Ok(())
+    }
+
+    fn read_batch(&mut self) -> Result<Option<Vec<Row>>> {
+        let mut rows = Vec::new();
+        loop {
+            match self.state {
+                CursorState::InBatch => {
+                    while rows.len() < self.fetch_size as usize {
+                        match self.next_in_batch()? {
+                            Some(row) => rows.push(row),
+                            None => break,
+                        }
+                    }
+                    if !rows.is_empty() {
+                        return Ok(Some(rows));
+                    }
+                }
+                CursorState::NeedFetch => self.fetch()?,
+                CursorState::Done => return Ok(None),
+            }
+        }
+    }
+
+    fn next_row(&mut self) -> Result<Option<Row>> {
+        loop {
+            match self.state {
+                CursorState::InBatch => {
+                    if let Some(row) = self.next_in_batch()? {
+                        return Ok(Some(row));
+                    }
+                }
+                CursorState::NeedFetch => self.fetch()?,
+                CursorState::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+impl Iterator for Cursor<'_, '_, '_> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_row() {
+            Ok(row) => row.map(Ok),
+            Err(err) => {
+                self.state = CursorState::Done;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Drop for Cursor<'_, '_, '_> {
+    fn drop(&mut self) {
+        while self.state == CursorState::InBatch {
+            if !matches!(self.next_in_batch(), Ok(Some(_))) {
+                break;
+            }
+        }
+        if self.state == CursorState::NeedFetch {
+            let _ = self.conn.reset_stmt(self.stmt_id);
+        }
+        // drain the rest of a multi-result set response (e.g. if server hasn't opened a cursor)
+        while self.conn.more_results_exists() {
+            if self.conn.handle_result_set().is_err() {
+                break;
+            }
+            while let Ok(Some(_)) = self.conn.next_row_packet() {}
+        }
+    }
+}
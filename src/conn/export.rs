@@ -0,0 +1,711 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use flate2::Crc;
+use mysql_common::constants::{ColumnFlags, ColumnType};
+
+use std::io::Write;
+
+use crate::{
+    conn::query_result::next_text_cell,
+    escape::{self, EscapeMode},
+    prelude::*,
+    Column, Conn, QueryResult, Result, Text, Value,
+};
+
+/// Id of the `binary` character set.
+const BINARY_CHARSET: u16 = 63;
+
+/// Default value of the `net_buffer_length` option of `mysqldump`.
+const DEFAULT_NET_BUFFER_LENGTH: usize = 1_046_528;
+
+/// Options of [`Conn::export_table`].
+///
+/// Defaults match the defaults of `mysqldump`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExportOpts {
+    hex_blob: bool,
+    extended_insert: bool,
+    net_buffer_length: usize,
+}
+
+impl ExportOpts {
+    /// Creates default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Dumps binary columns using hexadecimal notation, e.g. `0x4142`
+    /// (defaults to `false`, i.e. `_binary 'AB'`). Same as `mysqldump --hex-blob`.
+    pub fn with_hex_blob(mut self, hex_blob: bool) -> Self {
+        self.hex_blob = hex_blob;
+        self
+    }
+
+    /// Returns the `hex_blob` value (see [`ExportOpts::with_hex_blob`]).
+    pub fn hex_blob(&self) -> bool {
+        self.hex_blob
+    }
+
+    /// Writes multiple rows per `INSERT` statement (defaults to `true`).
+    /// Same as `mysqldump --extended-insert`.
+    pub fn with_extended_insert(mut self, extended_insert: bool) -> Self {
+        self.extended_insert = extended_insert;
+        self
+    }
+
+    /// Returns the `extended_insert` value (see [`ExportOpts::with_extended_insert`]).
+    pub fn extended_insert(&self) -> bool {
+        self.extended_insert
+    }
+
+    /// Maximum length of an extended `INSERT` statement (defaults to `1046528`).
+    /// Same as `mysqldump --net-buffer-length`.
+    pub fn with_net_buffer_length(mut self, net_buffer_length: usize) -> Self {
+        self.net_buffer_length = net_buffer_length;
+        self
+    }
+
+    /// Returns the `net_buffer_length` value (see [`ExportOpts::with_net_buffer_length`]).
+    pub fn net_buffer_length(&self) -> usize {
+        self.net_buffer_length
+    }
+}
+
+impl Default for ExportOpts {
+    fn default() -> Self {
+        Self {
+            hex_blob: false,
+            extended_insert: true,
+            net_buffer_length: DEFAULT_NET_BUFFER_LENGTH,
+        }
+    }
+}
+
+/// Checksum of table data (see [`Conn::export_table_checksummed`] and [`Conn::table_checksum`]).
+///
+/// It's the number of rows and the sum of CRC32 checksums of rows, where a row is represented
+/// as `CONCAT_WS('#', col1, col2, ..., CONCAT(ISNULL(col1), ISNULL(col2), ...))`
+/// (same as `pt-table-checksum`, but summed instead of XOR-ed). It doesn't depend on the order
+/// of rows, so it could be compared with a checksum computed on another server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TableChecksum {
+    rows: u64,
+    checksum: u64,
+}
+
+impl TableChecksum {
+    /// Number of rows.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Sum of CRC32 checksums of rows.
+    pub fn checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    /// Adds a row given as raw text protocol cells (`None` stands for `NULL`).
+    fn add_row<'a, I>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = Option<&'a [u8]>>,
+    {
+        let mut crc = Crc::new();
+        let mut nulls = Vec::new();
+        for cell in cells {
+            match cell {
+                Some(cell) => {
+                    crc.update(cell);
+                    crc.update(b"#");
+                    nulls.push(b'0');
+                }
+                None => nulls.push(b'1'),
+            }
+        }
+        crc.update(&nulls);
+        self.rows += 1;
+        self.checksum = self.checksum.wrapping_add(u64::from(crc.sum()));
+    }
+}
+
+/// Options of [`QueryResult::write_csv`].
+///
+/// Defaults follow RFC 4180, except that lines are terminated by `\n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CsvOpts {
+    delimiter: u8,
+    quote: u8,
+    crlf: bool,
+    header: bool,
+}
+
+impl CsvOpts {
+    /// Creates default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates options for tab-separated values.
+    pub fn tsv() -> Self {
+        Self::default().with_delimiter(b'\t')
+    }
+
+    /// Field delimiter (defaults to `,`).
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the value, defined by [`CsvOpts::with_delimiter`].
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    /// Quote character (defaults to `"`).
+    pub fn with_quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Returns the value, defined by [`CsvOpts::with_quote`].
+    pub fn quote(&self) -> u8 {
+        self.quote
+    }
+
+    /// Terminates lines with `\r\n` instead of `\n` (defaults to `false`).
+    pub fn with_crlf(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    /// Returns the value, defined by [`CsvOpts::with_crlf`].
+    pub fn crlf(&self) -> bool {
+        self.crlf
+    }
+
+    /// Writes column names as the first line (defaults to `true`).
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Returns the value, defined by [`CsvOpts::with_header`].
+    pub fn header(&self) -> bool {
+        self.header
+    }
+}
+
+impl Default for CsvOpts {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            crlf: false,
+            header: true,
+        }
+    }
+}
+
+impl QueryResult<'_, '_, '_, Text> {
+    /// Writes the current result set to `out` as CSV without decoding rows.
+    ///
+    /// Cells are written as sent by the server, i.e. using the character set of this connection.
+    /// `NULL` is written as an empty field, whereas an empty string is written as `""`.
+    /// Fields that contain the delimiter, the quote character or a line break are quoted.
+    /// Returns the number of written rows (excluding the header). Consumes the current result set.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let mut csv = Vec::new();
+    /// let rows = conn
+    ///     .query_iter("SELECT 1 AS id, 'a, \"b\"' AS name UNION ALL SELECT 2, NULL")?
+    ///     .write_csv(&mut csv, CsvOpts::new())?;
+    /// assert_eq!(rows, 2);
+    /// assert_eq!(csv, b"id,name\n1,\"a, \"\"b\"\"\"\n2,\n");
+    /// # });
+    /// ```
+    pub fn write_csv<W: Write>(&mut self, mut out: W, opts: CsvOpts) -> Result<u64> {
+        let columns = self.columns().as_ref().to_vec();
+        let terminator: &[u8] = if opts.crlf { b"\r\n" } else { b"\n" };
+        let mut line = Vec::new();
+
+        if opts.header && !columns.is_empty() {
+            for (i, column) in columns.iter().enumerate() {
+                if i > 0 {
+                    line.push(opts.delimiter);
+                }
+                write_csv_field(&mut line, Some(column.name_ref()), &opts);
+            }
+            line.extend_from_slice(terminator);
+            out.write_all(&line)?;
+        }
+
+        let mut rows = 0;
+        while let Some(pld) = self.next_raw() {
+            let pld = pld?;
+            let mut row = &pld[..];
+            line.clear();
+            for i in 0..columns.len() {
+                if i > 0 {
+                    line.push(opts.delimiter);
+                }
+                write_csv_field(&mut line, next_text_cell(&mut row)?, &opts);
+            }
+            line.extend_from_slice(terminator);
+            out.write_all(&line)?;
+            rows += 1;
+        }
+        Ok(rows)
+    }
+
+    /// Writes the current result set to `out` as newline-delimited JSON objects
+    /// keyed by column names, without decoding rows.
+    ///
+    /// Numbers are written as JSON numbers (`inf` and `nan` as `null`), values of `JSON` columns
+    /// are embedded as is, binary strings are written as hexadecimal strings and other values
+    /// as strings (invalid UTF-8 is replaced). Returns the number of written rows.
+    /// Consumes the current result set.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let mut json = Vec::new();
+    /// conn.query_iter("SELECT 1 AS id, 'foo' AS name, NULL AS note")?
+    ///     .write_ndjson(&mut json)?;
+    /// assert_eq!(json, b"{\"id\":1,\"name\":\"foo\",\"note\":null}\n");
+    /// # });
+    /// ```
+    pub fn write_ndjson<W: Write>(&mut self, mut out: W) -> Result<u64> {
+        let columns = self.columns().as_ref().to_vec();
+        let keys = columns
+            .iter()
+            .map(|column| json_string(&column.name_str()))
+            .collect::<Vec<_>>();
+        let mut line = Vec::new();
+
+        let mut rows = 0;
+        while let Some(pld) = self.next_raw() {
+            let pld = pld?;
+            let mut row = &pld[..];
+            line.clear();
+            line.push(b'{');
+            for (i, (column, key)) in columns.iter().zip(&keys).enumerate() {
+                if i > 0 {
+                    line.push(b',');
+                }
+                line.extend_from_slice(key.as_bytes());
+                line.push(b':');
+                write_json_value(&mut line, next_text_cell(&mut row)?, column);
+            }
+            line.extend_from_slice(b"}\n");
+            out.write_all(&line)?;
+            rows += 1;
+        }
+        Ok(rows)
+    }
+}
+
+impl Conn {
+    /// Writes rows of the given table as SQL in the format of `mysqldump`.
+    ///
+    /// The output is the data section that `mysqldump` writes for a table, i.e. `INSERT`
+    /// statements wrapped into `LOCK TABLES` and `ALTER TABLE ... DISABLE KEYS`.
+    /// Values are quoted the same way:
+    ///
+    /// * numbers are unquoted (`inf` and `nan` are written as `NULL`);
+    /// * binary strings use the `_binary` introducer (or hexadecimal notation,
+    ///   see [`ExportOpts::with_hex_blob`]);
+    /// * other values are single-quoted strings escaped as by `mysql_real_escape_string`.
+    ///
+    /// Non-binary strings are written as is, so the output must be loaded using the character
+    /// set of this connection (e.g. `mysql --default-character-set=utf8mb4`).
+    /// Returns the number of exported rows.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.users (id INT, name TEXT, hash BLOB)")?;
+    /// conn.query_drop("INSERT INTO mysql.users VALUES (1, 'O''Brien', x'00ff')")?;
+    ///
+    /// let mut dump = Vec::new();
+    /// conn.export_table("users", &mut dump, ExportOpts::new())?;
+    /// # let dump = String::from_utf8_lossy(&dump);
+    /// # assert!(dump.contains("INSERT INTO `users` VALUES (1,'O\\'Brien',_binary '\\0"));
+    /// # });
+    /// ```
+    ///
+    /// Note that the table is looked up in the current database.
+    pub fn export_table<W: Write>(&mut self, table: &str, out: W, opts: ExportOpts) -> Result<u64> {
+        self.export_table_checksummed(table, out, opts)
+            .map(|checksum| checksum.rows())
+    }
+
+    /// Same as [`Conn::export_table`], but also computes the checksum of exported rows.
+    ///
+    /// The checksum could be compared with [`Conn::table_checksum`] on the server the dump
+    /// is loaded to, which verifies the data without reading it back.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.src (id INT, name TEXT)")?;
+    /// conn.query_drop("INSERT INTO mysql.src VALUES (1, 'foo'), (2, NULL)")?;
+    /// conn.query_drop("USE mysql")?;
+    ///
+    /// let mut dump = Vec::new();
+    /// let exported = conn.export_table_checksummed("src", &mut dump, ExportOpts::new())?;
+    /// assert_eq!(exported, conn.table_checksum("src")?);
+    /// # });
+    /// ```
+    pub fn export_table_checksummed<W: Write>(
+        &mut self,
+        table: &str,
+        mut out: W,
+        opts: ExportOpts,
+    ) -> Result<TableChecksum> {
+        let table = quote_identifier(table);
+        let query = format!("SELECT /*!40001 SQL_NO_CACHE */ * FROM {}", table);
+        let mut result = self.query_iter(query)?;
+        let columns = result.columns().as_ref().to_vec();
+
+        write!(
+            out,
+            "--\n-- Dumping data for table {table}\n--\n\n\
+             LOCK TABLES {table} WRITE;\n\
+             /*!40000 ALTER TABLE {table} DISABLE KEYS */;\n"
+        )?;
+
+        let insert = format!("INSERT INTO {} VALUES ", table);
+        let mut checksum = TableChecksum::default();
+        // length of the current extended `INSERT` statement (0 if there is none)
+        let mut statement_len = 0;
+        let mut row_sql = Vec::new();
+        for row in result.by_ref() {
+            let row = row?;
+            row_sql.clear();
+            row_sql.push(b'(');
+            for (i, column) in columns.iter().enumerate() {
+                if i > 0 {
+                    row_sql.push(b',');
+                }
+                let value = row.as_ref(i).unwrap_or(&Value::NULL);
+                write_value(&mut row_sql, value, column, opts.hex_blob);
+            }
+            row_sql.push(b')');
+            checksum.add_row((0..columns.len()).map(|i| match row.as_ref(i) {
+                Some(Value::Bytes(bytes)) => Some(&bytes[..]),
+                _ => None,
+            }));
+
+            if !opts.extended_insert {
+                out.write_all(insert.as_bytes())?;
+                out.write_all(&row_sql)?;
+                out.write_all(b";\n")?;
+            } else if statement_len == 0 {
+                out.write_all(insert.as_bytes())?;
+                out.write_all(&row_sql)?;
+                statement_len = insert.len() + row_sql.len();
+            } else if statement_len + row_sql.len() + 1 < opts.net_buffer_length {
+                out.write_all(b",")?;
+                out.write_all(&row_sql)?;
+                statement_len += row_sql.len() + 1;
+            } else {
+                out.write_all(b";\n")?;
+                out.write_all(insert.as_bytes())?;
+                out.write_all(&row_sql)?;
+                statement_len = insert.len() + row_sql.len();
+            }
+        }
+        if statement_len > 0 {
+            out.write_all(b";\n")?;
+        }
+
+        write!(
+            out,
+            "/*!40000 ALTER TABLE {table} ENABLE KEYS */;\n\
+             UNLOCK TABLES;\n"
+        )?;
+        Ok(checksum)
+    }
+
+    /// Computes the checksum of rows of the given table on the server
+    /// (see [`TableChecksum`] for details).
+    ///
+    /// Text columns are checksummed in their character set, so the checksum only matches
+    /// the one computed by [`Conn::export_table_checksummed`] if it's the character set
+    /// of this connection. Note that the table is looked up in the current database.
+    pub fn table_checksum(&mut self, table: &str) -> Result<TableChecksum> {
+        let table = quote_identifier(table);
+        let columns = self
+            .query_iter(format!("SELECT * FROM {} LIMIT 0", table))?
+            .columns()
+            .as_ref()
+            .iter()
+            .map(|column| quote_identifier(&column.name_str()))
+            .collect::<Vec<_>>();
+        let nulls = columns
+            .iter()
+            .map(|column| format!("ISNULL({})", column))
+            .collect::<Vec<_>>();
+        let query = format!(
+            "SELECT COUNT(*), COALESCE(SUM(CRC32(CONCAT_WS('#', {}, CONCAT({})))), 0) FROM {}",
+            columns.join(", "),
+            nulls.join(", "),
+            table
+        );
+        let (rows, checksum): (u64, u64) = self.query_first(query)?.unwrap_or_default();
+        Ok(TableChecksum { rows, checksum })
+    }
+}
+
+/// Quotes the identifier using backticks.
+pub(crate) fn quote_identifier(name: &str) -> String {
+    escape::quote_identifier(name, EscapeMode::default())
+}
+
+/// Returns `true` if values of the column are binary strings.
+fn is_binary(column: &Column) -> bool {
+    column.character_set() == BINARY_CHARSET
+        && matches!(
+            column.column_type(),
+            ColumnType::MYSQL_TYPE_BIT
+                | ColumnType::MYSQL_TYPE_STRING
+                | ColumnType::MYSQL_TYPE_VAR_STRING
+                | ColumnType::MYSQL_TYPE_VARCHAR
+                | ColumnType::MYSQL_TYPE_BLOB
+                | ColumnType::MYSQL_TYPE_TINY_BLOB
+                | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+                | ColumnType::MYSQL_TYPE_LONG_BLOB
+                | ColumnType::MYSQL_TYPE_GEOMETRY
+        )
+}
+
+/// Writes a value of a text protocol row as an SQL literal (same as `mysqldump`).
+fn write_value(out: &mut Vec<u8>, value: &Value, column: &Column, hex_blob: bool) {
+    let bytes = match value {
+        Value::NULL => return out.extend_from_slice(b"NULL"),
+        Value::Bytes(bytes) => bytes,
+        // text protocol rows only contain bytes
+        other => return out.extend_from_slice(other.as_sql(false).as_bytes()),
+    };
+    if bytes.is_empty() {
+        return out.extend_from_slice(b"''");
+    }
+    if column.flags().contains(ColumnFlags::NUM_FLAG) {
+        // `inf`, `-inf` and `nan` are not valid literals
+        let unsigned = bytes.strip_prefix(b"-").unwrap_or(bytes);
+        if matches!(unsigned.first(), Some(x) if x.is_ascii_alphabetic()) {
+            out.extend_from_slice(b"NULL");
+        } else {
+            out.extend_from_slice(bytes);
+        }
+        return;
+    }
+
+    let binary = is_binary(column);
+    if binary && hex_blob {
+        out.extend_from_slice(b"0x");
+        for byte in bytes {
+            out.extend_from_slice(format!("{:02X}", byte).as_bytes());
+        }
+        return;
+    }
+    if binary {
+        out.extend_from_slice(b"_binary ");
+    }
+    out.push(b'\'');
+    for &byte in bytes {
+        match byte {
+            0 => out.extend_from_slice(b"\\0"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'\'' => out.extend_from_slice(b"\\'"),
+            b'"' => out.extend_from_slice(b"\\\""),
+            0x1a => out.extend_from_slice(b"\\Z"),
+            byte => out.push(byte),
+        }
+    }
+    out.push(b'\'');
+}
+
+/// Writes a CSV field, quoting it if necessary (`None` stands for `NULL`).
+fn write_csv_field(out: &mut Vec<u8>, cell: Option<&[u8]>, opts: &CsvOpts) {
+    let cell = match cell {
+        Some(cell) => cell,
+        None => return,
+    };
+    let quoted = cell.is_empty()
+        || cell
+            .iter()
+            .any(|&x| x == opts.delimiter || x == opts.quote || x == b'\n' || x == b'\r');
+    if !quoted {
+        return out.extend_from_slice(cell);
+    }
+    out.push(opts.quote);
+    for &byte in cell {
+        if byte == opts.quote {
+            out.push(byte);
+        }
+        out.push(byte);
+    }
+    out.push(opts.quote);
+}
+
+/// Serializes a string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+/// Writes a cell of a text protocol row as a JSON value (`None` stands for `NULL`).
+fn write_json_value(out: &mut Vec<u8>, cell: Option<&[u8]>, column: &Column) {
+    let cell = match cell {
+        Some(cell) => cell,
+        None => return out.extend_from_slice(b"null"),
+    };
+    if column.flags().contains(ColumnFlags::NUM_FLAG) {
+        // `inf`, `-inf` and `nan` are not valid numbers, same for `.5` and `5.` in JSON
+        let valid = std::str::from_utf8(cell)
+            .ok()
+            .and_then(|x| x.parse::<serde_json::Number>().ok())
+            .is_some();
+        if valid {
+            out.extend_from_slice(cell);
+        } else if cell.iter().any(u8::is_ascii_digit) {
+            out.extend_from_slice(json_string(&String::from_utf8_lossy(cell)).as_bytes());
+        } else {
+            out.extend_from_slice(b"null");
+        }
+    } else if column.column_type() == ColumnType::MYSQL_TYPE_JSON {
+        out.extend_from_slice(cell);
+    } else if is_binary(column) {
+        out.push(b'"');
+        for byte in cell {
+            out.extend_from_slice(format!("{:02X}", byte).as_bytes());
+        }
+        out.push(b'"');
+    } else {
+        out.extend_from_slice(json_string(&String::from_utf8_lossy(cell)).as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::constants::{ColumnFlags, ColumnType};
+
+    use super::{
+        quote_identifier, write_csv_field, write_json_value, write_value, CsvOpts, TableChecksum,
+    };
+    use crate::{Column, Value};
+
+    fn literal(value: Value, column: &Column, hex_blob: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_value(&mut out, &value, column, hex_blob);
+        out
+    }
+
+    #[test]
+    fn should_quote_values_like_mysqldump() {
+        let int = Column::new(ColumnType::MYSQL_TYPE_LONG).with_flags(ColumnFlags::NUM_FLAG);
+        let double = Column::new(ColumnType::MYSQL_TYPE_DOUBLE).with_flags(ColumnFlags::NUM_FLAG);
+        let text = Column::new(ColumnType::MYSQL_TYPE_BLOB).with_character_set(255);
+        let blob = Column::new(ColumnType::MYSQL_TYPE_BLOB).with_character_set(63);
+        let date = Column::new(ColumnType::MYSQL_TYPE_DATE).with_character_set(63);
+
+        assert_eq!(literal(Value::NULL, &int, false), b"NULL");
+        assert_eq!(literal(Value::from("-42"), &int, false), b"-42");
+        assert_eq!(literal(Value::from("-1.5e-7"), &double, false), b"-1.5e-7");
+        assert_eq!(literal(Value::from("-inf"), &double, false), b"NULL");
+        assert_eq!(literal(Value::from("nan"), &double, false), b"NULL");
+        assert_eq!(
+            literal(Value::from("a'b\"c\\d\n\r\0\x1a"), &text, false),
+            br#"'a\'b\"c\\d\n\r\0\Z'"#
+        );
+        assert_eq!(literal(Value::from("é"), &text, true), "'é'".as_bytes());
+        assert_eq!(
+            literal(Value::Bytes(vec![0, b'\'', 0xff]), &blob, false),
+            b"_binary '\\0\\'\xff'"
+        );
+        assert_eq!(literal(Value::Bytes(vec![0, 0xab]), &blob, true), b"0x00AB");
+        assert_eq!(literal(Value::Bytes(vec![]), &blob, true), b"''");
+        assert_eq!(literal(Value::Bytes(vec![]), &blob, false), b"''");
+        assert_eq!(
+            literal(Value::from("2024-01-01"), &date, true),
+            b"'2024-01-01'"
+        );
+        assert_eq!(quote_identifier("we`ird"), "`we``ird`");
+    }
+
+    #[test]
+    fn should_write_csv_fields() {
+        let field = |cell: Option<&[u8]>, opts: CsvOpts| {
+            let mut out = Vec::new();
+            write_csv_field(&mut out, cell, &opts);
+            out
+        };
+
+        assert_eq!(field(None, CsvOpts::new()), b"");
+        assert_eq!(field(Some(b""), CsvOpts::new()), b"\"\"");
+        assert_eq!(field(Some(b"a\tb"), CsvOpts::new()), b"a\tb");
+        assert_eq!(field(Some(b"a\tb"), CsvOpts::tsv()), b"\"a\tb\"");
+        assert_eq!(field(Some(b"a,b"), CsvOpts::new()), b"\"a,b\"");
+        assert_eq!(field(Some(b"a\nb"), CsvOpts::new()), b"\"a\nb\"");
+        assert_eq!(
+            field(Some(b"say \"hi\""), CsvOpts::new()),
+            b"\"say \"\"hi\"\"\""
+        );
+        assert_eq!(
+            field(Some(b"it's"), CsvOpts::new().with_quote(b'\'')),
+            b"'it''s'"
+        );
+    }
+
+    #[test]
+    fn should_write_json_values() {
+        let value = |cell: Option<&[u8]>, column: &Column| {
+            let mut out = Vec::new();
+            write_json_value(&mut out, cell, column);
+            String::from_utf8(out).unwrap()
+        };
+        let int = Column::new(ColumnType::MYSQL_TYPE_LONG).with_flags(ColumnFlags::NUM_FLAG);
+        let double = Column::new(ColumnType::MYSQL_TYPE_DOUBLE).with_flags(ColumnFlags::NUM_FLAG);
+        let text = Column::new(ColumnType::MYSQL_TYPE_BLOB).with_character_set(255);
+        let blob = Column::new(ColumnType::MYSQL_TYPE_BLOB).with_character_set(63);
+        let json = Column::new(ColumnType::MYSQL_TYPE_JSON).with_character_set(63);
+
+        assert_eq!(value(None, &text), "null");
+        assert_eq!(value(Some(b"-42"), &int), "-42");
+        assert_eq!(value(Some(b"1.5e-7"), &double), "1.5e-7");
+        assert_eq!(value(Some(b"-inf"), &double), "null");
+        assert_eq!(value(Some(b"a\"b\n"), &text), r#""a\"b\n""#);
+        assert_eq!(value(Some(b"\0\xff"), &blob), r#""00FF""#);
+        assert_eq!(value(Some(br#"{"a": [1]}"#), &json), r#"{"a": [1]}"#);
+    }
+
+    #[test]
+    fn should_checksum_rows_like_concat_ws() {
+        let mut checksum = TableChecksum::default();
+        checksum.add_row([Some(&b"1"[..]), Some(b"foo")]);
+        // CRC32('1#foo#00')
+        assert_eq!(checksum.checksum(), 3071195875);
+        checksum.add_row([Some(&b"2"[..]), None]);
+        checksum.add_row([None, None]);
+        // + CRC32('2#01') + CRC32('11')
+        assert_eq!(checksum.rows(), 3);
+        assert_eq!(checksum.checksum(), 10156372384);
+    }
+}
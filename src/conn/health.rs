@@ -0,0 +1,184 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{consts::Command, prelude::*, Conn, Error, Result, Row};
+
+/// Status variables queried by [`Conn::health_report`].
+const STATUS_QUERY: &str = "SHOW GLOBAL STATUS WHERE Variable_name IN \
+    ('Uptime', 'Threads_connected', 'Threads_running', \
+    'Innodb_buffer_pool_pages_total', 'Innodb_buffer_pool_pages_free')";
+
+/// Summary of the server state (see [`Conn::health_report`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    ping_rtt: Duration,
+    uptime: Duration,
+    threads_connected: u64,
+    threads_running: u64,
+    buffer_pool_pages_total: Option<u64>,
+    buffer_pool_pages_free: Option<u64>,
+    replica: Option<ReplicaStatus>,
+}
+
+impl HealthReport {
+    /// Round trip time of `COM_PING`.
+    pub fn ping_rtt(&self) -> Duration {
+        self.ping_rtt
+    }
+
+    /// Server uptime.
+    pub fn uptime(&self) -> Duration {
+        self.uptime
+    }
+
+    /// Number of currently open connections.
+    pub fn threads_connected(&self) -> u64 {
+        self.threads_connected
+    }
+
+    /// Number of threads that are not sleeping.
+    pub fn threads_running(&self) -> u64 {
+        self.threads_running
+    }
+
+    /// Total size of the InnoDB buffer pool in pages (`None` if InnoDB is unavailable).
+    pub fn buffer_pool_pages_total(&self) -> Option<u64> {
+        self.buffer_pool_pages_total
+    }
+
+    /// Number of free pages of the InnoDB buffer pool (`None` if InnoDB is unavailable).
+    pub fn buffer_pool_pages_free(&self) -> Option<u64> {
+        self.buffer_pool_pages_free
+    }
+
+    /// Replication status (`None` if the server isn't a replica or if the user lacks
+    /// the privilege to query it).
+    pub fn replica(&self) -> Option<&ReplicaStatus> {
+        self.replica.as_ref()
+    }
+}
+
+/// Replication status of a replica (see [`HealthReport::replica`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaStatus {
+    io_running: bool,
+    sql_running: bool,
+    seconds_behind: Option<u64>,
+    last_error: Option<String>,
+}
+
+impl ReplicaStatus {
+    /// Whether the replication I/O thread is running and connected to the source.
+    pub fn io_running(&self) -> bool {
+        self.io_running
+    }
+
+    /// Whether the replication SQL thread is running.
+    pub fn sql_running(&self) -> bool {
+        self.sql_running
+    }
+
+    /// Replication lag (`None` if it's unknown, e.g. if replication is stopped).
+    pub fn seconds_behind(&self) -> Option<u64> {
+        self.seconds_behind
+    }
+
+    /// The last replication error, if any.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    fn from_row(row: &Row) -> Self {
+        // MySQL 8.0.22 renamed columns of `SHOW REPLICA STATUS`
+        fn get(row: &Row, names: &[&str]) -> Option<String> {
+            names.iter().find_map(|name| row.get_opt(*name)?.ok())
+        }
+
+        ReplicaStatus {
+            io_running: get(row, &["Replica_IO_Running", "Slave_IO_Running"]).as_deref()
+                == Some("Yes"),
+            sql_running: get(row, &["Replica_SQL_Running", "Slave_SQL_Running"]).as_deref()
+                == Some("Yes"),
+            seconds_behind: get(row, &["Seconds_Behind_Source", "Seconds_Behind_Master"])
+                .and_then(|seconds| seconds.parse().ok()),
+            last_error: get(row, &["Last_Error"]).filter(|error| !error.is_empty()),
+        }
+    }
+}
+
+impl Conn {
+    /// Returns a summary of the server state, e.g. for readiness endpoints.
+    ///
+    /// It's assembled from `COM_PING`, `SHOW GLOBAL STATUS` and `SHOW REPLICA STATUS`
+    /// (or `SHOW SLAVE STATUS` for older servers).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let report = conn.health_report()?;
+    /// assert!(report.threads_connected() > 0);
+    /// if let Some(replica) = report.replica() {
+    ///     assert!(replica.io_running() && replica.sql_running());
+    /// }
+    /// # });
+    /// ```
+    pub fn health_report(&mut self) -> Result<HealthReport> {
+        let started = Instant::now();
+        self.write_command(Command::COM_PING, &[])?;
+        self.drop_packet()?;
+        let ping_rtt = started.elapsed();
+
+        let status = self
+            .query_map(STATUS_QUERY, |(name, value): (String, Option<String>)| {
+                (name, value)
+            })?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let get = |name: &str| {
+            status
+                .get(name)
+                .and_then(|value| value.as_deref()?.parse::<u64>().ok())
+        };
+
+        Ok(HealthReport {
+            ping_rtt,
+            uptime: Duration::from_secs(get("Uptime").unwrap_or_default()),
+            threads_connected: get("Threads_connected").unwrap_or_default(),
+            threads_running: get("Threads_running").unwrap_or_default(),
+            buffer_pool_pages_total: get("Innodb_buffer_pool_pages_total"),
+            buffer_pool_pages_free: get("Innodb_buffer_pool_pages_free"),
+            replica: self.replica_status()?,
+        })
+    }
+
+    fn replica_status(&mut self) -> Result<Option<ReplicaStatus>> {
+        let mysql_8_0_22 =
+            !self.server_flavor().is_mariadb() && self.server_version() >= (8, 0, 22);
+        let query = if mysql_8_0_22 {
+            "SHOW REPLICA STATUS"
+        } else {
+            "SHOW SLAVE STATUS"
+        };
+        // there is a row per channel for multi-source replication, so only the first one is used
+        let row = self
+            .query_iter(query)
+            .and_then(|mut result| result.next().transpose());
+        match row {
+            Ok(row) => Ok(row.as_ref().map(ReplicaStatus::from_row)),
+            // e.g. missing REPLICATION CLIENT privilege
+            Err(Error::MySqlError(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{borrow::Cow, fmt, time::Duration};
+
+use crate::{
+    conn::query_result::{Binary, Text},
+    prelude::*,
+    CachePolicy, Params, QueryResult, Result, Statement, StreamedParam, Value,
+};
+
+/// Statement keywords that could be followed by an optimizer hint comment.
+const HINTABLE_KEYWORDS: &[&str] = &["SELECT", "INSERT", "REPLACE", "UPDATE", "DELETE"];
+
+/// Optimizer hints that are attached to queries (see [`Queryable::with_hints`]).
+///
+/// Hints are rendered as a `/*+ ... */` comment that is inserted right after the leading
+/// `SELECT`, `INSERT`, `REPLACE`, `UPDATE` or `DELETE` keyword of a query, or merged into
+/// the hint comment that is already there. Other statements are sent as is.
+///
+/// Note that optimizer hints are supported by MySql 5.7+ (`SET_VAR` requires MySql 8.0.3+),
+/// other servers treat them as regular comments. Invalid hints are ignored by the server
+/// with a warning.
+///
+/// ```
+/// # use mysql::OptimizerHints;
+/// # use std::time::Duration;
+/// let hints = OptimizerHints::new()
+///     .with_max_execution_time(Duration::from_secs(2))
+///     .with_set_var("sort_buffer_size", 16 * 1024 * 1024)
+///     .with_hint("NO_INDEX_MERGE(t)");
+///
+/// assert_eq!(
+///     hints.apply("SELECT * FROM t WHERE a = 1 OR b = 2"),
+///     "SELECT /*+ MAX_EXECUTION_TIME(2000) SET_VAR(sort_buffer_size = 16777216) \
+///      NO_INDEX_MERGE(t) */ * FROM t WHERE a = 1 OR b = 2",
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct OptimizerHints {
+    hints: Vec<String>,
+}
+
+impl OptimizerHints {
+    /// Creates an empty set of hints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `MAX_EXECUTION_TIME(<ms>)` hint, i.e. the server interrupts the statement
+    /// if it takes longer than the given timeout (applies to `SELECT` statements only).
+    pub fn with_max_execution_time(self, timeout: Duration) -> Self {
+        let millis = timeout.as_millis().min(u32::MAX as u128);
+        self.with_hint(format!("MAX_EXECUTION_TIME({})", millis))
+    }
+
+    /// Adds `SET_VAR(<name> = <value>)` hint, i.e. the session variable is set
+    /// for the duration of the statement.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid variable name.
+    pub fn with_set_var<T: Into<Value>>(self, name: &str, value: T) -> Self {
+        assert!(
+            !name.is_empty() && name.bytes().all(|x| x.is_ascii_alphanumeric() || x == b'_'),
+            "invalid variable name `{}`",
+            name
+        );
+        let value = value.into().as_sql(false);
+        self.with_hint(format!("SET_VAR({} = {})", name, value))
+    }
+
+    /// Adds the given hint as is, e.g. `BKA(t1)` or `RESOURCE_GROUP(batch)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the hint contains `*/`, i.e. it would terminate the hint comment.
+    pub fn with_hint<T: Into<String>>(mut self, hint: T) -> Self {
+        let hint = hint.into();
+        assert!(!hint.contains("*/"), "invalid optimizer hint `{}`", hint);
+        self.hints.push(hint);
+        self
+    }
+
+    /// Returns `true` if there are no hints.
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    /// Returns the hints in the order they were added.
+    pub fn hints(&self) -> &[String] {
+        &self.hints
+    }
+
+    /// Attaches these hints to the given query.
+    pub fn apply<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        if self.hints.is_empty() {
+            return Cow::Borrowed(query);
+        }
+        let offset = match keyword_end(query) {
+            Some(offset) => offset,
+            None => return Cow::Borrowed(query),
+        };
+        let (head, tail) = query.split_at(offset);
+        let hints = self.hints.join(" ");
+        let existing = tail.trim_start();
+        match existing.strip_prefix("/*+") {
+            // the server only recognizes the first hint comment of a query block
+            Some(rest) => Cow::Owned(format!("{} /*+ {}{}", head, hints, rest)),
+            None => Cow::Owned(format!("{} /*+ {} */{}", head, hints, tail)),
+        }
+    }
+}
+
+impl fmt::Display for OptimizerHints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/*+ {} */", self.hints.join(" "))
+    }
+}
+
+/// Returns the offset right after the leading keyword, if it could be followed by hints.
+fn keyword_end(query: &str) -> Option<usize> {
+    let start = query.len() - query.trim_start().len();
+    let rest = &query[start..];
+    HINTABLE_KEYWORDS.iter().find_map(|keyword| {
+        let candidate = rest.get(..keyword.len())?;
+        let boundary = !matches!(
+            rest[keyword.len()..].chars().next(),
+            Some(x) if x.is_alphanumeric() || x == '_'
+        );
+        if candidate.eq_ignore_ascii_case(keyword) && boundary {
+            Some(start + keyword.len())
+        } else {
+            None
+        }
+    })
+}
+
+/// Queryable that attaches optimizer hints to queries (see [`Queryable::with_hints`]).
+///
+/// Hints are attached to text queries and to queries that are prepared through this wrapper,
+/// but not to already prepared [`Statement`]s.
+#[derive(Debug)]
+pub struct Hinted<'a, Q> {
+    queryable: &'a mut Q,
+    hints: OptimizerHints,
+}
+
+impl<'a, Q: Queryable> Hinted<'a, Q> {
+    pub(crate) fn new(queryable: &'a mut Q, hints: OptimizerHints) -> Self {
+        Self { queryable, hints }
+    }
+
+    /// Returns the hints that are attached to queries.
+    pub fn hints(&self) -> &OptimizerHints {
+        &self.hints
+    }
+}
+
+impl<Q: Queryable> Queryable for Hinted<'_, Q> {
+    fn query_iter<T: AsRef<str>>(&mut self, query: T) -> Result<QueryResult<'_, '_, '_, Text>> {
+        let query = self.hints.apply(query.as_ref()).into_owned();
+        self.queryable.query_iter(query)
+    }
+
+    fn query_iter_with_attrs<T: AsRef<str>>(
+        &mut self,
+        query: T,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Text>> {
+        let query = self.hints.apply(query.as_ref()).into_owned();
+        self.queryable.query_iter_with_attrs(query, attrs)
+    }
+
+    fn prep<T: AsRef<str>>(&mut self, query: T) -> Result<Statement> {
+        let query = self.hints.apply(query.as_ref()).into_owned();
+        self.queryable.prep(query)
+    }
+
+    fn prep_with_cache_policy<T: AsRef<str>>(
+        &mut self,
+        query: T,
+        policy: CachePolicy,
+    ) -> Result<Statement> {
+        let query = self.hints.apply(query.as_ref()).into_owned();
+        self.queryable.prep_with_cache_policy(query, policy)
+    }
+
+    fn close(&mut self, stmt: Statement) -> Result<()> {
+        self.queryable.close(stmt)
+    }
+
+    fn exec_iter<S, P>(&mut self, stmt: S, params: P) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        let stmt = stmt.as_statement(self)?.into_owned();
+        self.queryable.exec_iter(stmt, params)
+    }
+
+    fn exec_iter_with_attrs<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        let stmt = stmt.as_statement(self)?.into_owned();
+        self.queryable.exec_iter_with_attrs(stmt, params, attrs)
+    }
+
+    fn exec_iter_streamed<S>(
+        &mut self,
+        stmt: S,
+        params: Vec<StreamedParam<'_>>,
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+    {
+        let stmt = stmt.as_statement(self)?.into_owned();
+        self.queryable.exec_iter_streamed(stmt, params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::OptimizerHints;
+
+    #[test]
+    fn should_attach_hints_after_the_leading_keyword() {
+        let hints = OptimizerHints::new()
+            .with_max_execution_time(Duration::from_millis(1500))
+            .with_set_var("optimizer_switch", "mrr=off");
+        assert_eq!(
+            hints.to_string(),
+            "/*+ MAX_EXECUTION_TIME(1500) SET_VAR(optimizer_switch = 'mrr=off') */"
+        );
+
+        assert_eq!(
+            hints.apply("  select\n1"),
+            "  select /*+ MAX_EXECUTION_TIME(1500) SET_VAR(optimizer_switch = 'mrr=off') */\n1"
+        );
+        assert_eq!(
+            hints.apply("SELECT"),
+            "SELECT /*+ MAX_EXECUTION_TIME(1500) SET_VAR(optimizer_switch = 'mrr=off') */"
+        );
+
+        let hints = OptimizerHints::new().with_hint("BKA(t1)");
+        assert_eq!(
+            hints.apply("UPDATE t1 SET a = 1"),
+            "UPDATE /*+ BKA(t1) */ t1 SET a = 1"
+        );
+        assert_eq!(
+            hints.apply("DELETE /*+ NO_BKA(t2) */ FROM t1"),
+            "DELETE /*+ BKA(t1) NO_BKA(t2) */ FROM t1"
+        );
+
+        // statements that can't have hints are left as is
+        assert_eq!(hints.apply("SELECTED"), "SELECTED");
+        assert_eq!(hints.apply("SHOW TABLES"), "SHOW TABLES");
+        assert_eq!(
+            hints.apply("WITH x AS (SELECT 1) SELECT * FROM x"),
+            "WITH x AS (SELECT 1) SELECT * FROM x"
+        );
+        assert_eq!(OptimizerHints::new().apply("SELECT 1"), "SELECT 1");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid variable name")]
+    fn should_reject_invalid_variable_names() {
+        OptimizerHints::new().with_set_var("a = 1) BKA(t", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid optimizer hint")]
+    fn should_reject_hints_that_terminate_the_comment() {
+        OptimizerHints::new().with_hint("BKA(t1) */ DROP TABLE t1; /*");
+    }
+}
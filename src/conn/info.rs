@@ -0,0 +1,352 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt;
+
+use crate::{consts::CapabilityFlags, prelude::*, Conn, Result};
+
+/// Parsed info string of an OK packet (see [`Conn::info`]).
+///
+/// The server reports it for statements that affect multiple rows, e.g.
+/// `Rows matched: 3  Changed: 2  Warnings: 0` for `UPDATE` or
+/// `Records: 3  Duplicates: 1  Warnings: 0` for multi-row `INSERT`.
+/// Counters not mentioned by the server are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct QueryInfo {
+    records: Option<u64>,
+    duplicates: Option<u64>,
+    deleted: Option<u64>,
+    skipped: Option<u64>,
+    rows_matched: Option<u64>,
+    changed: Option<u64>,
+    warnings: Option<u64>,
+}
+
+impl QueryInfo {
+    /// Parses the info string.
+    ///
+    /// Returns `None` if it contains no known counters.
+    pub fn parse(info: &str) -> Option<Self> {
+        let mut parsed = Self::default();
+        let mut known = false;
+        let mut rest = info;
+        while let Some(colon) = rest.find(':') {
+            let key = rest[..colon].trim();
+            let value = rest[colon + 1..].trim_start();
+            let end = value
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(value.len());
+            let field = match key {
+                "Records" => Some(&mut parsed.records),
+                "Duplicates" => Some(&mut parsed.duplicates),
+                "Deleted" => Some(&mut parsed.deleted),
+                "Skipped" => Some(&mut parsed.skipped),
+                "Rows matched" => Some(&mut parsed.rows_matched),
+                "Changed" => Some(&mut parsed.changed),
+                "Warnings" => Some(&mut parsed.warnings),
+                _ => None,
+            };
+            if let (Some(field), Ok(count)) = (field, value[..end].parse()) {
+                *field = Some(count);
+                known = true;
+            }
+            rest = &value[end..];
+        }
+        known.then_some(parsed)
+    }
+
+    /// Number of processed records (`INSERT ... SELECT`, multi-row `INSERT`,
+    /// `LOAD DATA`, `ALTER TABLE`).
+    pub fn records(&self) -> Option<u64> {
+        self.records
+    }
+
+    /// Number of records that duplicate an existing unique key.
+    pub fn duplicates(&self) -> Option<u64> {
+        self.duplicates
+    }
+
+    /// Number of records deleted by `LOAD DATA ... REPLACE`.
+    pub fn deleted(&self) -> Option<u64> {
+        self.deleted
+    }
+
+    /// Number of records skipped by `LOAD DATA`.
+    pub fn skipped(&self) -> Option<u64> {
+        self.skipped
+    }
+
+    /// Number of rows matched by `UPDATE`.
+    pub fn rows_matched(&self) -> Option<u64> {
+        self.rows_matched
+    }
+
+    /// Number of rows actually changed by `UPDATE`.
+    pub fn changed(&self) -> Option<u64> {
+        self.changed
+    }
+
+    /// Number of warnings.
+    pub fn warnings(&self) -> Option<u64> {
+        self.warnings
+    }
+}
+
+/// Interpretation of affected rows of `INSERT ... ON DUPLICATE KEY UPDATE`
+/// (see [`Conn::upsert_outcome`] and [`crate::ExecResult::upsert_outcome`]).
+///
+/// The server counts every inserted row as 1 and every updated row as 2.
+/// A row, that is set to its current values, counts as 0, or as 1 if `CLIENT_FOUND_ROWS`
+/// is set (see [`crate::OptsBuilder::found_rows`]).
+///
+/// For a statement inserting multiple rows the server also reports numbers of records
+/// and duplicates (see [`QueryInfo`]), so all counters are exact. For a single row
+/// with `CLIENT_FOUND_ROWS` set, an inserted row is indistinguishable from an unchanged one,
+/// so it's counted by [`UpsertOutcome::inserted_or_unchanged`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct UpsertOutcome {
+    inserted: u64,
+    updated: u64,
+    unchanged: u64,
+    inserted_or_unchanged: u64,
+}
+
+impl UpsertOutcome {
+    /// Interprets affected rows and the info string of an upsert.
+    ///
+    /// `found_rows` is `true` if `CLIENT_FOUND_ROWS` is set for the connection.
+    /// Returns `None` if the numbers aren't consistent with an upsert.
+    pub fn new(affected_rows: u64, info: Option<QueryInfo>, found_rows: bool) -> Option<Self> {
+        let mut outcome = Self::default();
+        match info.and_then(|info| info.records().zip(info.duplicates())) {
+            // duplicates are the updated rows, or the matched rows if `CLIENT_FOUND_ROWS` is set
+            Some((records, duplicates)) if found_rows => {
+                outcome.inserted = records.checked_sub(duplicates)?;
+                outcome.updated = affected_rows.checked_sub(records)?;
+                outcome.unchanged = duplicates.checked_sub(outcome.updated)?;
+            }
+            Some((records, duplicates)) => {
+                outcome.updated = duplicates;
+                outcome.inserted = affected_rows.checked_sub(duplicates.checked_mul(2)?)?;
+                outcome.unchanged = records.checked_sub(outcome.inserted + duplicates)?;
+            }
+            None => match (affected_rows, found_rows) {
+                (0, false) => outcome.unchanged = 1,
+                (1, false) => outcome.inserted = 1,
+                (1, true) => outcome.inserted_or_unchanged = 1,
+                (2, _) => outcome.updated = 1,
+                _ => return None,
+            },
+        }
+        Some(outcome)
+    }
+
+    /// Number of inserted rows.
+    pub fn inserted(&self) -> u64 {
+        self.inserted
+    }
+
+    /// Number of existing rows, that were updated.
+    pub fn updated(&self) -> u64 {
+        self.updated
+    }
+
+    /// Number of existing rows, that were set to their current values.
+    pub fn unchanged(&self) -> u64 {
+        self.unchanged
+    }
+
+    /// Number of rows, that were either inserted or set to their current values
+    /// (non-zero only for a single row with `CLIENT_FOUND_ROWS` set).
+    pub fn inserted_or_unchanged(&self) -> u64 {
+        self.inserted_or_unchanged
+    }
+
+    /// Returns `true` if inserted rows can't be told apart from unchanged ones.
+    pub fn is_ambiguous(&self) -> bool {
+        self.inserted_or_unchanged > 0
+    }
+}
+
+/// Level of a [`Warning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningLevel {
+    Note,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for WarningLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarningLevel::Note => f.write_str("Note"),
+            WarningLevel::Warning => f.write_str("Warning"),
+            WarningLevel::Error => f.write_str("Error"),
+        }
+    }
+}
+
+/// An entry of the `SHOW WARNINGS` output (see [`Conn::show_warnings`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Warning {
+    level: WarningLevel,
+    code: u16,
+    message: String,
+}
+
+impl Warning {
+    /// Level of the warning.
+    pub fn level(&self) -> WarningLevel {
+        self.level
+    }
+
+    /// Server error code, e.g. `1265` for `Data truncated`.
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// Message of the warning.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.level, self.code, self.message)
+    }
+}
+
+impl Conn {
+    /// Returns the parsed info string of the last query (see [`QueryInfo`]).
+    pub fn info(&self) -> Option<QueryInfo> {
+        QueryInfo::parse(&self.info_str())
+    }
+
+    /// Interprets affected rows of the last query as an upsert (see [`UpsertOutcome`]).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT PRIMARY KEY, name TEXT)")?;
+    /// conn.query_drop("INSERT INTO mysql.tbl VALUES (1, 'foo'), (2, 'bar')")?;
+    /// conn.query_drop(
+    ///     "INSERT INTO mysql.tbl VALUES (1, 'foo'), (2, 'baz'), (3, 'qux')
+    ///      ON DUPLICATE KEY UPDATE name = VALUES(name)",
+    /// )?;
+    /// let outcome = conn.upsert_outcome().unwrap();
+    /// assert_eq!(outcome.inserted(), 1);
+    /// assert_eq!(outcome.updated(), 1);
+    /// assert_eq!(outcome.unchanged(), 1);
+    /// # });
+    /// ```
+    pub fn upsert_outcome(&self) -> Option<UpsertOutcome> {
+        UpsertOutcome::new(
+            self.affected_rows(),
+            self.info(),
+            self.has_capability(CapabilityFlags::CLIENT_FOUND_ROWS),
+        )
+    }
+
+    /// Runs `SHOW WARNINGS` and returns warnings of the last statement.
+    ///
+    /// Use [`Conn::warnings`] to check the number of warnings before running this.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("SELECT CAST('1x' AS SIGNED)")?;
+    /// if conn.warnings() > 0 {
+    ///     for warning in conn.show_warnings()? {
+    ///         println!("{}", warning);
+    ///     }
+    /// }
+    /// # });
+    /// ```
+    pub fn show_warnings(&mut self) -> Result<Vec<Warning>> {
+        self.query_map(
+            "SHOW WARNINGS",
+            |(level, code, message): (String, u16, String)| {
+                let level = match &*level {
+                    "Note" => WarningLevel::Note,
+                    "Error" => WarningLevel::Error,
+                    _ => WarningLevel::Warning,
+                };
+                Warning {
+                    level,
+                    code,
+                    message,
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{QueryInfo, UpsertOutcome};
+
+    #[test]
+    fn should_parse_info() {
+        let info = QueryInfo::parse("Rows matched: 3  Changed: 2  Warnings: 1").unwrap();
+        assert_eq!(info.rows_matched(), Some(3));
+        assert_eq!(info.changed(), Some(2));
+        assert_eq!(info.warnings(), Some(1));
+        assert_eq!(info.records(), None);
+
+        let info = QueryInfo::parse("Records: 10  Deleted: 0  Skipped: 4  Warnings: 4").unwrap();
+        assert_eq!(info.records(), Some(10));
+        assert_eq!(info.deleted(), Some(0));
+        assert_eq!(info.skipped(), Some(4));
+        assert_eq!(info.duplicates(), None);
+
+        let info = QueryInfo::parse("Records: 3  Duplicates: 1  Warnings: 0").unwrap();
+        assert_eq!(info.duplicates(), Some(1));
+
+        assert_eq!(QueryInfo::parse(""), None);
+        assert_eq!(QueryInfo::parse("Unknown: 1"), None);
+    }
+    #[test]
+    fn should_interpret_upsert_outcome() {
+        let single = |affected_rows, found_rows| {
+            UpsertOutcome::new(affected_rows, None, found_rows).map(|outcome| {
+                (
+                    outcome.inserted(),
+                    outcome.updated(),
+                    outcome.unchanged(),
+                    outcome.inserted_or_unchanged(),
+                )
+            })
+        };
+        assert_eq!(single(0, false), Some((0, 0, 1, 0)));
+        assert_eq!(single(1, false), Some((1, 0, 0, 0)));
+        assert_eq!(single(2, false), Some((0, 1, 0, 0)));
+        assert_eq!(single(0, true), None);
+        assert_eq!(single(1, true), Some((0, 0, 0, 1)));
+        assert_eq!(single(2, true), Some((0, 1, 0, 0)));
+        assert_eq!(single(3, false), None);
+
+        // 4 records: 1 inserted, 2 updated, 1 unchanged
+        let info = QueryInfo::parse("Records: 4  Duplicates: 2  Warnings: 0");
+        let outcome = UpsertOutcome::new(5, info, false).unwrap();
+        assert_eq!(
+            (outcome.inserted(), outcome.updated(), outcome.unchanged()),
+            (1, 2, 1)
+        );
+        assert!(!outcome.is_ambiguous());
+        let info_found_rows = QueryInfo::parse("Records: 4  Duplicates: 3  Warnings: 0");
+        assert_eq!(UpsertOutcome::new(6, info_found_rows, true), Some(outcome));
+        assert_eq!(UpsertOutcome::new(3, info, false), None);
+        assert_eq!(UpsertOutcome::new(8, info, false), None);
+        assert_eq!(UpsertOutcome::new(3, info_found_rows, true), None);
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use crate::{Error, Params};
+
+/// Hooks called around every text query and every statement execution of a connection
+/// (see [`crate::OptsBuilder::query_interceptor`]).
+///
+/// Both hooks have no-op default implementations. Executions using a server-side cursor,
+/// streamed parameters or `COM_STMT_BULK_EXECUTE` are not intercepted.
+///
+/// ```rust
+/// # use mysql::*;
+/// # use std::sync::Arc;
+/// /// Tags every text query with a comment and refuses to drop tables.
+/// struct Tagger;
+///
+/// impl QueryInterceptor for Tagger {
+///     fn before(&self, query: &InterceptedQuery<'_>) -> Interception {
+///         if query.sql().trim_start().to_ascii_uppercase().starts_with("DROP TABLE") {
+///             Interception::Veto("tables can't be dropped".into())
+///         } else if query.stmt_id().is_none() {
+///             Interception::Rewrite(format!("{} /*app='billing'*/", query.sql()))
+///         } else {
+///             Interception::Proceed
+///         }
+///     }
+///
+///     fn after(&self, outcome: &QueryOutcome<'_>) {
+///         eprintln!("{} took {:?}", outcome.query().sql(), outcome.elapsed());
+///     }
+/// }
+///
+/// let opts = OptsBuilder::new().query_interceptor(Some(Arc::new(Tagger)));
+/// ```
+pub trait QueryInterceptor: Send + Sync {
+    /// Called before the query is sent to the server.
+    fn before(&self, query: &InterceptedQuery<'_>) -> Interception {
+        let _ = query;
+        Interception::Proceed
+    }
+
+    /// Called once the query is completed, i.e. once its whole result is consumed
+    /// or once it has failed.
+    ///
+    /// The query is the one actually sent, i.e. it's already rewritten by [`Self::before`].
+    fn after(&self, outcome: &QueryOutcome<'_>) {
+        let _ = outcome;
+    }
+}
+
+/// Decision of [`QueryInterceptor::before`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Interception {
+    /// Send the query as is.
+    Proceed,
+    /// Send the given query instead.
+    ///
+    /// A rewritten statement is prepared (and cached) as a separate statement,
+    /// so it must take the same positional parameters as the original one.
+    Rewrite(String),
+    /// Don't send the query – it fails with `DriverError::QueryVetoed` carrying the given reason.
+    Veto(String),
+}
+
+/// Text query or statement execution seen by a [`QueryInterceptor`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterceptedQuery<'a> {
+    sql: &'a str,
+    stmt_id: Option<u32>,
+    params: Option<&'a Params>,
+}
+
+impl<'a> InterceptedQuery<'a> {
+    pub(crate) fn text(sql: &'a str) -> Self {
+        Self {
+            sql,
+            stmt_id: None,
+            params: None,
+        }
+    }
+
+    pub(crate) fn stmt(sql: &'a str, stmt_id: u32, params: Option<&'a Params>) -> Self {
+        Self {
+            sql,
+            stmt_id: Some(stmt_id),
+            params,
+        }
+    }
+
+    /// Text of the query (of the prepared statement, if it's a statement execution).
+    pub fn sql(&self) -> &'a str {
+        self.sql
+    }
+
+    /// Id of the executed statement (`None` for a text query).
+    pub fn stmt_id(&self) -> Option<u32> {
+        self.stmt_id
+    }
+
+    /// Parameters of the executed statement (`None` for a text query).
+    pub fn params(&self) -> Option<&'a Params> {
+        self.params
+    }
+}
+
+/// Completed query given to [`QueryInterceptor::after`].
+#[derive(Debug, Clone, Copy)]
+pub struct QueryOutcome<'a> {
+    pub(crate) query: InterceptedQuery<'a>,
+    pub(crate) elapsed: Duration,
+    pub(crate) error: Option<&'a Error>,
+}
+
+impl<'a> QueryOutcome<'a> {
+    /// The query, that was sent.
+    pub fn query(&self) -> &InterceptedQuery<'a> {
+        &self.query
+    }
+
+    /// Time elapsed from sending the query to reading the end of its result.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The error, if the query has failed.
+    pub fn error(&self) -> Option<&'a Error> {
+        self.error
+    }
+
+    /// Returns `true` if the query has succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Interceptor installed on connection options (compared by pointer).
+#[derive(Clone)]
+pub(crate) struct InterceptorRef(pub(crate) Arc<dyn QueryInterceptor>);
+
+impl PartialEq for InterceptorRef {
+    fn eq(&self, other: &InterceptorRef) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for InterceptorRef {}
+
+impl fmt::Debug for InterceptorRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "QueryInterceptor(...)")
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::named_params::parse_named_params;
+
+use std::fmt::Write as _;
+
+use crate::{
+    conn::query_result::Text, prelude::*, Conn, DriverError, Params, QueryResult, Result, Value,
+};
+
+/// Returns byte offsets of `?` placeholders of the given query.
+///
+/// Placeholders within string literals, quoted identifiers and comments are ignored
+/// (except for executable comments, i.e. `/*! ... */` and `/*+ ... */`).
+fn placeholders(query: &[u8], no_backslash_escape: bool) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    visit_code(query, no_backslash_escape, |i| {
+        if query[i] == b'?' {
+            offsets.push(i);
+        }
+    });
+    offsets
+}
+
+/// Calls `visit` with the offset of every byte of the given query, that is outside of
+/// string literals, quoted identifiers and comments (executable comments, i.e. `/*! ... */`
+/// and `/*+ ... */`, are treated as code).
+pub(crate) fn visit_code(query: &[u8], no_backslash_escape: bool, mut visit: impl FnMut(usize)) {
+    let mut i = 0;
+    while i < query.len() {
+        match query[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < query.len() && query[i] != quote {
+                    if query[i] == b'\\' && quote != b'`' && !no_backslash_escape {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'#' => {
+                while i < query.len() && query[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'-' if query[i..].starts_with(b"--")
+                && !matches!(query.get(i + 2), Some(x) if !x.is_ascii_whitespace()) =>
+            {
+                while i < query.len() && query[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if query[i..].starts_with(b"/*")
+                && !matches!(query.get(i + 2), Some(b'!' | b'+')) =>
+            {
+                i += 2;
+                while i < query.len() && !query[i..].starts_with(b"*/") {
+                    i += 1;
+                }
+                i += 1;
+            }
+            _ => visit(i),
+        }
+        i += 1;
+    }
+}
+
+/// Renders the given value as an SQL literal.
+///
+/// Strings with non-ASCII characters are rendered as hex literals with an introducer
+/// (e.g. `_utf8mb4 X'C3A9'`), so that they are interpreted in the same way regardless of
+/// the `character_set_client` of the session. Bytes, that aren't valid UTF-8, are rendered
+/// as binary hex literals.
+///
+/// Fails for values, that can't be represented as a literal (i.e. NaN and infinite floats).
+pub(crate) fn literal(
+    value: &Value,
+    no_backslash_escape: bool,
+    charset: &str,
+    out: &mut String,
+) -> Result<()> {
+    match *value {
+        Value::Float(x) if !x.is_finite() => {
+            return Err(DriverError::CouldNotInterpolate(x.to_string()).into())
+        }
+        Value::Double(x) if !x.is_finite() => {
+            return Err(DriverError::CouldNotInterpolate(x.to_string()).into())
+        }
+        // exponent notation makes the server treat the literal as a floating point value
+        Value::Float(x) => write!(out, "{:e}", x).expect("infallible"),
+        Value::Double(x) => write!(out, "{:e}", x).expect("infallible"),
+        Value::Bytes(ref bytes) if !bytes.is_ascii() && std::str::from_utf8(bytes).is_ok() => {
+            out.push('_');
+            out.push_str(charset);
+            out.push_str(" X'");
+            for byte in bytes {
+                write!(out, "{:02X}", byte).expect("infallible");
+            }
+            out.push('\'');
+        }
+        ref value => out.push_str(&value.as_sql(no_backslash_escape)),
+    }
+    Ok(())
+}
+
+/// Inlines the given parameters into the given query (see [`Conn::interpolate`]).
+fn interpolate(
+    query: &str,
+    params: Params,
+    no_backslash_escape: bool,
+    charset: &str,
+) -> Result<String> {
+    let (named_params, query) = parse_named_params(query.as_bytes())?;
+    let query = std::str::from_utf8(&query).expect("the query is a valid UTF-8 string");
+    let values = match params {
+        Params::Empty => Vec::new(),
+        Params::Positional(values) => values,
+        Params::Named(map) => match named_params {
+            Some(ref names) => match Params::Named(map).into_positional(names)? {
+                Params::Positional(values) => values,
+                _ => Vec::new(),
+            },
+            None => return Err(DriverError::NamedParamsForPositionalQuery.into()),
+        },
+    };
+
+    let offsets = placeholders(query.as_bytes(), no_backslash_escape);
+    if offsets.len() != values.len() {
+        return Err(DriverError::MismatchedStmtParams(offsets.len() as u16, values.len()).into());
+    }
+
+    let mut out = String::with_capacity(query.len());
+    let mut start = 0;
+    for (offset, value) in offsets.into_iter().zip(&values) {
+        out.push_str(&query[start..offset]);
+        literal(value, no_backslash_escape, charset, &mut out)?;
+        start = offset + 1;
+    }
+    out.push_str(&query[start..]);
+    Ok(out)
+}
+
+impl Conn {
+    /// Inlines the given parameters into the given query, so that it could be executed
+    /// using the text protocol (see [`Conn::query_interpolated`]).
+    ///
+    /// Parameters are given in the same way as for prepared statements, i.e. either
+    /// as positional `?` placeholders or as named `:name` placeholders. Values are escaped
+    /// according to the `NO_BACKSLASH_ESCAPES` SQL mode of the session. Strings with non-ASCII
+    /// characters are inlined as hex literals with a character set introducer, so they don't
+    /// depend on the character set of the session (e.g. after `SET NAMES gbk`).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # let conn = Conn::new(get_opts())?;
+    /// let query = conn.interpolate("SET @name = ?, @age = ?", ("O'Neil", 42))?;
+    /// # let query = query.replace("\\'", "''");
+    /// assert_eq!(query, "SET @name = 'O''Neil', @age = 42");
+    /// # });
+    /// ```
+    pub fn interpolate<Q, P>(&self, query: Q, params: P) -> Result<String>
+    where
+        Q: AsRef<str>,
+        P: Into<Params>,
+    {
+        interpolate(
+            query.as_ref(),
+            params.into(),
+            self.no_backslash_escape(),
+            self.literal_charset(),
+        )
+    }
+
+    /// Character set introducer of non-ASCII string literals (see [`literal`]).
+    pub(crate) fn literal_charset(&self) -> &'static str {
+        if self.server_version() >= (5, 5, 3) {
+            "utf8mb4"
+        } else {
+            "utf8"
+        }
+    }
+
+    /// Executes the given query with parameters inlined on the client side
+    /// (see [`Conn::interpolate`]).
+    ///
+    /// This is an opt-in alternative to prepared statements for queries, that can't be
+    /// prepared (e.g. some DDL statements or `SET` statements) or for proxies, that don't
+    /// support prepared statements. Prefer prepared statements otherwise.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_interpolated("SET @greeting = :greeting", params! { "greeting" => "héllo" })?;
+    /// let greeting: Option<String> = conn.query_first("SELECT @greeting")?;
+    /// assert_eq!(greeting.as_deref(), Some("héllo"));
+    /// # });
+    /// ```
+    pub fn query_interpolated<Q, P>(
+        &mut self,
+        query: Q,
+        params: P,
+    ) -> Result<QueryResult<'_, '_, '_, Text>>
+    where
+        Q: AsRef<str>,
+        P: Into<Params>,
+    {
+        let query = self.interpolate(query, params)?;
+        self.query_iter(query)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{interpolate, placeholders};
+    use crate::{params, Error, Params, Value};
+
+    #[test]
+    fn should_find_placeholders() {
+        let query = br#"SELECT ?, '?', "\"?", `?`, /* ? */ /*+ ? */ ? # ?
+            -- ?
+            --?"#;
+        let offsets = placeholders(query, false);
+        let found = offsets.iter().map(|&i| &query[i..]).collect::<Vec<_>>();
+        assert_eq!(found.len(), 4, "{:?}", offsets);
+        assert_eq!(offsets[0], 7);
+        assert!(found[1].starts_with(b"? */ ? #"));
+        assert!(found[2].starts_with(b"? # ?"));
+        assert_eq!(offsets[3], query.len() - 1);
+
+        // a backslash doesn't escape the quote
+        assert_eq!(placeholders(br"SELECT '\', ?", true), [12]);
+        assert_eq!(placeholders(br"SELECT '\', ?", false), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn should_interpolate_params() {
+        let query = interpolate(
+            "INSERT INTO t VALUES (?, ?, ?, ?, ?, ?)",
+            Params::from((
+                1,
+                "it's",
+                Value::NULL,
+                1.5_f64,
+                vec![0xff_u8, 0x00],
+                Value::Date(2020, 1, 2, 3, 4, 5, 0),
+            )),
+            false,
+            "utf8mb4",
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "INSERT INTO t VALUES (1, 'it\\'s', NULL, 1.5e0, 0xFF00, '2020-01-02 03:04:05')"
+        );
+
+        let query = interpolate(
+            "SELECT :name, ':name', :name",
+            params! { "name" => "é'" },
+            true,
+            "utf8mb4",
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "SELECT _utf8mb4 X'C3A927', ':name', _utf8mb4 X'C3A927'"
+        );
+        let query = interpolate("SELECT ?", ("a'b",).into(), true, "utf8").unwrap();
+        assert_eq!(query, "SELECT 'a''b'");
+    }
+
+    #[test]
+    fn should_reject_invalid_params() {
+        let err = interpolate("SELECT ?, ?", (1,).into(), false, "utf8mb4").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DriverError(crate::DriverError::MismatchedStmtParams(2, 1))
+        ));
+        let err = interpolate("SELECT ?", params! { "a" => 1 }, false, "utf8mb4").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DriverError(crate::DriverError::NamedParamsForPositionalQuery)
+        ));
+        let err = interpolate("SELECT :a", params! { "b" => 1 }, false, "utf8mb4").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DriverError(crate::DriverError::MissingNamedParameter(_))
+        ));
+        let err = interpolate("SELECT ?", (f64::NAN,).into(), false, "utf8mb4").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DriverError(crate::DriverError::CouldNotInterpolate(_))
+        ));
+    }
+}
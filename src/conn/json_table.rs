@@ -0,0 +1,285 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use serde_json::{Number, Value as Json};
+
+use std::fmt::Write as _;
+
+use crate::{conn::export::quote_identifier, DriverError, Params, Result, Value};
+
+/// Set of rows passed to the server as a single JSON parameter and joined
+/// using the `JSON_TABLE` function (see [`crate::Feature::JsonTable`]).
+///
+/// This is a set-based alternative to statements with thousands of parameters
+/// (e.g. `WHERE id IN (?, ?, ...)`): the statement has a single parameter regardless
+/// of the number of rows, so it could be prepared once and cached.
+///
+/// Every column is declared with `ERROR ON ERROR`, so a value, that can't be converted
+/// to the column type, fails the statement rather than becoming `NULL`.
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// # use mysql::*;
+/// # use mysql::prelude::*;
+/// # let mut conn = Conn::new(get_opts())?;
+/// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT)")?;
+/// conn.query_drop("INSERT INTO mysql.tbl VALUES (1, 'foo'), (2, 'bar'), (3, 'baz')")?;
+///
+/// let renames = JsonTable::new("renames")
+///     .with_column("id", "INT")
+///     .with_column("name", "VARCHAR(255)")
+///     .with_rows(vec![(1, "qux"), (3, "quux")]);
+/// conn.exec_drop(
+///     format!(
+///         "UPDATE mysql.tbl JOIN {} USING (id) SET tbl.name = renames.name",
+///         renames.sql()
+///     ),
+///     (renames.param()?,),
+/// )?;
+///
+/// let names: Vec<String> = conn.query("SELECT name FROM mysql.tbl ORDER BY id")?;
+/// assert_eq!(names, ["qux", "bar", "quux"]);
+/// # });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonTable {
+    alias: String,
+    columns: Vec<(String, String)>,
+    rows: Vec<Params>,
+}
+
+impl JsonTable {
+    /// Creates an empty set of rows joined under the given alias.
+    pub fn new<T: Into<String>>(alias: T) -> Self {
+        Self {
+            alias: alias.into(),
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Declares the next column with the given SQL type, e.g. `BIGINT` or `VARCHAR(255)`.
+    ///
+    /// The type is inlined into the SQL as is.
+    pub fn with_column<N, T>(mut self, name: N, sql_type: T) -> Self
+    where
+        N: Into<String>,
+        T: Into<String>,
+    {
+        self.columns.push((name.into(), sql_type.into()));
+        self
+    }
+
+    /// Appends the given rows.
+    ///
+    /// Rows are either positional parameters in the order of columns
+    /// or named parameters keyed by column names.
+    pub fn with_rows<I>(mut self, rows: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Params>,
+    {
+        self.rows.extend(rows.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns the alias of this table.
+    pub fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    /// Returns declared columns and their SQL types.
+    pub fn columns(&self) -> &[(String, String)] {
+        &self.columns
+    }
+
+    /// Returns the number of rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns `true` if there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the table expression with a single `?` placeholder, e.g.
+    /// ``JSON_TABLE(?, '$[*]' COLUMNS (`id` INT PATH '$[0]' ERROR ON ERROR)) AS `ids` ``.
+    pub fn sql(&self) -> String {
+        let mut sql = String::from("JSON_TABLE(?, '$[*]' COLUMNS (");
+        for (i, (name, sql_type)) in self.columns.iter().enumerate() {
+            if i > 0 {
+                sql.push_str(", ");
+            }
+            write!(
+                sql,
+                "{} {} PATH '$[{}]' ERROR ON ERROR",
+                quote_identifier(name),
+                sql_type,
+                i
+            )
+            .expect("infallible");
+        }
+        write!(sql, ")) AS {}", quote_identifier(&self.alias)).expect("infallible");
+        sql
+    }
+
+    /// Encodes rows as a JSON array of arrays, i.e. as the parameter of [`JsonTable::sql`].
+    ///
+    /// Fails with `DriverError::InvalidJsonTableRow` if a row doesn't match columns
+    /// or a value can't be represented in JSON (e.g. non-UTF-8 bytes).
+    pub fn param(&self) -> Result<Value> {
+        let rows = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                self.encode_row(row).map_err(|err| {
+                    DriverError::InvalidJsonTableRow(format!("row {}: {}", index, err))
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Value::Bytes(Json::Array(rows).to_string().into_bytes()))
+    }
+
+    fn encode_row(&self, row: &Params) -> std::result::Result<Json, String> {
+        let values = match row {
+            Params::Empty => Vec::new(),
+            Params::Positional(values) => values.iter().collect(),
+            Params::Named(map) => self
+                .columns
+                .iter()
+                .map(|(name, _)| {
+                    map.get(name.as_bytes())
+                        .ok_or_else(|| format!("missing column `{}'", name))
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+        };
+        if values.len() != self.columns.len() {
+            return Err(format!(
+                "expected {} values, got {}",
+                self.columns.len(),
+                values.len()
+            ));
+        }
+        values
+            .into_iter()
+            .map(encode_value)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map(Json::Array)
+    }
+}
+
+/// Encodes the value so that `JSON_TABLE` converts it back to the same SQL value.
+fn encode_value(value: &Value) -> std::result::Result<Json, String> {
+    let json = match *value {
+        Value::NULL => Json::Null,
+        Value::Int(x) => Json::from(x),
+        Value::UInt(x) => Json::from(x),
+        Value::Float(x) => Number::from_f64(f64::from(x))
+            .map(Json::Number)
+            .ok_or_else(|| format!("{} can't be represented in JSON", x))?,
+        Value::Double(x) => Number::from_f64(x)
+            .map(Json::Number)
+            .ok_or_else(|| format!("{} can't be represented in JSON", x))?,
+        Value::Bytes(ref bytes) => match std::str::from_utf8(bytes) {
+            Ok(string) => Json::from(string),
+            Err(_) => return Err("bytes are not a valid UTF-8 string".into()),
+        },
+        Value::Date(year, month, day, 0, 0, 0, 0) => {
+            Json::from(format!("{:04}-{:02}-{:02}", year, month, day))
+        }
+        Value::Date(year, month, day, hour, minute, second, micros) => {
+            let mut out = format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                year, month, day, hour, minute, second
+            );
+            push_micros(micros, &mut out);
+            Json::from(out)
+        }
+        Value::Time(negative, days, hours, minutes, seconds, micros) => {
+            let mut out = format!(
+                "{}{:02}:{:02}:{:02}",
+                if negative { "-" } else { "" },
+                days * 24 + u32::from(hours),
+                minutes,
+                seconds
+            );
+            push_micros(micros, &mut out);
+            Json::from(out)
+        }
+    };
+    Ok(json)
+}
+
+fn push_micros(micros: u32, out: &mut String) {
+    if micros > 0 {
+        write!(out, ".{:06}", micros).expect("infallible");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JsonTable;
+    use crate::{params, DriverError, Error, Value};
+
+    #[test]
+    fn should_render_json_table() {
+        let table = JsonTable::new("ids")
+            .with_column("id", "BIGINT")
+            .with_column("a`b", "DATETIME(6)");
+        assert_eq!(
+            table.sql(),
+            "JSON_TABLE(?, '$[*]' COLUMNS (`id` BIGINT PATH '$[0]' ERROR ON ERROR, \
+            `a``b` DATETIME(6) PATH '$[1]' ERROR ON ERROR)) AS `ids`"
+        );
+        assert_eq!(table.param().unwrap(), Value::from("[]"));
+    }
+
+    #[test]
+    fn should_encode_rows() {
+        let table = JsonTable::new("t")
+            .with_column("a", "INT")
+            .with_column("b", "TEXT")
+            .with_rows(vec![
+                (Value::Int(-1), Value::from("x\"y")),
+                (Value::UInt(u64::MAX), Value::NULL),
+                (Value::Double(0.5), Value::Date(2020, 1, 2, 0, 0, 0, 0)),
+                (
+                    Value::Time(true, 1, 2, 3, 4, 5),
+                    Value::Date(2020, 1, 2, 3, 4, 5, 6),
+                ),
+            ])
+            .with_rows(vec![params! { "b" => "z", "a" => 7 }]);
+        assert_eq!(table.len(), 5);
+        assert_eq!(
+            table.param().unwrap(),
+            Value::from(
+                r#"[[-1,"x\"y"],[18446744073709551615,null],[0.5,"2020-01-02"],["-26:03:04.000005","2020-01-02 03:04:05.000006"],[7,"z"]]"#
+            )
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_rows() {
+        let table = JsonTable::new("t").with_column("a", "INT");
+        for row in [
+            (1, 2).into(),
+            vec![Value::Bytes(vec![0xff])].into(),
+            vec![Value::Double(f64::NAN)].into(),
+            params! { "b" => 1 },
+        ] {
+            let err = table.clone().with_rows(vec![row]).param().unwrap_err();
+            assert!(
+                matches!(err, Error::DriverError(DriverError::InvalidJsonTableRow(ref msg)) if msg.starts_with("row 0: ")),
+                "{}",
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use lru::LruCache;
+
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Relative width of a sketch bucket (i.e. quantiles are estimated within ~5%).
+const GAMMA: f64 = 1.1;
+
+/// Number of sketch buckets (the last one covers latencies above ~10 hours).
+const NUM_BUCKETS: usize = 256;
+
+/// Weight of an observation is multiplied by this factor for every subsequent observation,
+/// so the weight halves every ~140 executions.
+const DECAY: f64 = 0.995;
+
+/// Weights are rescaled once the increment reaches this value.
+const RESCALE_THRESHOLD: f64 = 1e100;
+
+/// Maximum number of statements tracked at once (the least recently executed
+/// statement is forgotten to make room for a new one).
+const MAX_TRACKED_STMTS: usize = 1024;
+
+/// Latency statistics of a prepared statement
+/// (see [`crate::OptsBuilder::track_stmt_latency`]).
+///
+/// Quantiles are estimated using exponentially decaying weights, so they reflect
+/// recent executions of a statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    count: u64,
+    p50: Duration,
+    p95: Duration,
+}
+
+impl LatencyStats {
+    /// Total number of executions.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Estimated median latency.
+    pub fn p50(&self) -> Duration {
+        self.p50
+    }
+
+    /// Estimated 95th percentile of latency.
+    pub fn p95(&self) -> Duration {
+        self.p95
+    }
+}
+
+/// Log-scale histogram of latencies with exponentially decaying weights.
+///
+/// Instead of decaying existing weights, weights of new observations grow by `1 / DECAY`,
+/// so that recording is `O(1)`.
+#[derive(Debug, Clone)]
+struct LatencySketch {
+    count: u64,
+    buckets: Vec<f64>,
+    increment: f64,
+}
+
+impl LatencySketch {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            buckets: vec![0.0; NUM_BUCKETS],
+            increment: 1.0,
+        }
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.increment /= DECAY;
+        if self.increment > RESCALE_THRESHOLD {
+            for weight in self.buckets.iter_mut() {
+                *weight /= self.increment;
+            }
+            self.increment = 1.0;
+        }
+        self.buckets[bucket_index(elapsed)] += self.increment;
+    }
+
+    fn quantile(&self, q: f64) -> Duration {
+        let total = self.buckets.iter().sum::<f64>();
+        let mut acc = 0.0;
+        for (i, weight) in self.buckets.iter().enumerate() {
+            acc += weight;
+            if *weight > 0.0 && acc >= q * total {
+                return bucket_value(i);
+            }
+        }
+        Duration::default()
+    }
+
+    fn stats(&self) -> LatencyStats {
+        LatencyStats {
+            count: self.count,
+            p50: self.quantile(0.5),
+            p95: self.quantile(0.95),
+        }
+    }
+}
+
+/// Bucket `i` covers latencies in `(GAMMA^(i - 1), GAMMA^i]` microseconds.
+fn bucket_index(elapsed: Duration) -> usize {
+    let micros = elapsed.as_secs_f64() * 1_000_000.0;
+    if micros <= 1.0 {
+        0
+    } else {
+        ((micros.ln() / GAMMA.ln()).ceil() as usize).min(NUM_BUCKETS - 1)
+    }
+}
+
+/// Value of a bucket with the minimal relative error.
+fn bucket_value(i: usize) -> Duration {
+    let micros = 2.0 * GAMMA.powi(i as i32) / (GAMMA + 1.0);
+    Duration::from_secs_f64(micros / 1_000_000.0)
+}
+
+/// Latency statistics of prepared statements, keyed by the statement query.
+///
+/// It's shared between connections of a pool. At most [`MAX_TRACKED_STMTS`] statements
+/// are tracked, so that dynamically built queries don't grow it without limit.
+#[derive(Debug, Clone)]
+pub(crate) struct LatencyTracker(Arc<Mutex<LruCache<Vec<u8>, LatencySketch>>>);
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::new(MAX_TRACKED_STMTS)
+    }
+}
+
+impl LatencyTracker {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self(Arc::new(Mutex::new(LruCache::new(capacity))))
+    }
+
+    pub(crate) fn record(&self, query: &[u8], elapsed: Duration) {
+        let mut sketches = self.0.lock().unwrap();
+        match sketches.get_mut(query) {
+            Some(sketch) => sketch.record(elapsed),
+            None => {
+                let mut sketch = LatencySketch::new();
+                sketch.record(elapsed);
+                sketches.put(query.to_vec(), sketch);
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self) -> HashMap<String, LatencyStats> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(query, sketch)| (String::from_utf8_lossy(query).into_owned(), sketch.stats()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{bucket_index, LatencySketch, LatencyTracker};
+
+    fn assert_close(actual: Duration, expected: Duration) {
+        let ratio = actual.as_secs_f64() / expected.as_secs_f64();
+        assert!(
+            (0.95..=1.05).contains(&ratio),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn should_estimate_quantiles() {
+        let mut sketch = LatencySketch::new();
+        for i in 1..=100 {
+            sketch.record(Duration::from_millis(i));
+        }
+        let stats = sketch.stats();
+        assert_eq!(stats.count(), 100);
+        // recent (i.e. slower) executions weigh more
+        assert!(stats.p50() > Duration::from_millis(50));
+        assert!(stats.p95() > stats.p50());
+
+        let mut sketch = LatencySketch::new();
+        for _ in 0..1000 {
+            sketch.record(Duration::from_millis(3));
+        }
+        assert_close(sketch.stats().p50(), Duration::from_millis(3));
+        assert_close(sketch.stats().p95(), Duration::from_millis(3));
+    }
+
+    #[test]
+    fn should_forget_old_latencies() {
+        let mut sketch = LatencySketch::new();
+        for _ in 0..10_000 {
+            sketch.record(Duration::from_secs(1));
+        }
+        for _ in 0..2_000 {
+            sketch.record(Duration::from_micros(100));
+        }
+        assert_eq!(sketch.stats().count(), 12_000);
+        assert_close(sketch.stats().p95(), Duration::from_micros(100));
+    }
+
+    #[test]
+    fn should_handle_extreme_latencies() {
+        assert_eq!(bucket_index(Duration::default()), 0);
+        assert_eq!(bucket_index(Duration::from_secs(1_000_000)), 255);
+
+        let tracker = LatencyTracker::default();
+        tracker.record(b"SELECT ?", Duration::default());
+        let stats = tracker.stats();
+        assert_eq!(stats["SELECT ?"].count(), 1);
+        assert!(stats["SELECT ?"].p50() <= Duration::from_micros(1));
+    }
+
+    #[test]
+    fn should_forget_least_recently_executed_stmts() {
+        let tracker = LatencyTracker::new(2);
+        tracker.record(b"SELECT 1", Duration::from_millis(1));
+        tracker.record(b"SELECT 2", Duration::from_millis(1));
+        tracker.record(b"SELECT 1", Duration::from_millis(1));
+        tracker.record(b"SELECT 3", Duration::from_millis(1));
+
+        let stats = tracker.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["SELECT 1"].count(), 2);
+        assert_eq!(stats["SELECT 3"].count(), 1);
+    }
+}
@@ -0,0 +1,408 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::io;
+
+use crate::{
+    conn::export::quote_identifier, Conn, DriverError, Error, ExecResult, Params, Result, Value,
+};
+
+/// How `LOAD DATA` handles rows that duplicate an existing unique key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OnDuplicate {
+    /// Server default (for `LOCAL` it's the same as `Ignore`).
+    #[default]
+    Default,
+    /// `REPLACE` existing rows.
+    Replace,
+    /// `IGNORE` new rows.
+    Ignore,
+}
+
+/// Builder of a `LOAD DATA LOCAL INFILE` statement that streams data from the client.
+///
+/// Data is either read from an [`io::Read`] in the format given to the builder
+/// (see [`LoadDataBuilder::load`]) or encoded from rows of values
+/// (see [`LoadDataBuilder::load_rows`]).
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// # use mysql::*;
+/// # use mysql::prelude::*;
+/// # let mut conn = Conn::new(get_opts())?;
+/// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT, score INT)")?;
+///
+/// let csv = "id,name\n1,\"Smith, John\"\n2,Jane\n";
+/// let result = LoadDataBuilder::new("mysql.tbl")
+///     .fields_terminated_by(",")
+///     .fields_enclosed_by("\"", true)
+///     .ignore_lines(1)
+///     .columns(["id", "name"])
+///     .set("score", "0")
+///     .load(&mut conn, csv.as_bytes());
+/// # let result = match result {
+/// #     Err(Error::MySqlError(e)) if e.code == 1148 => return Ok(()),
+/// #     result => result?,
+/// # };
+/// assert_eq!(result.affected_rows(), 2);
+///
+/// let rows = vec![(3, "it's\ttab"), (4, "Jim")];
+/// let result = LoadDataBuilder::new("mysql.tbl")
+///     .columns(["id", "name"])
+///     .load_rows(&mut conn, rows)?;
+/// assert_eq!(result.info().and_then(|info| info.records()), Some(2));
+/// # });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LoadDataBuilder {
+    table: String,
+    columns: Vec<String>,
+    set: Vec<(String, String)>,
+    on_duplicate: OnDuplicate,
+    character_set: Option<String>,
+    fields_terminated_by: String,
+    fields_enclosed_by: Option<(String, bool)>,
+    fields_escaped_by: String,
+    lines_terminated_by: String,
+    ignore_lines: u64,
+}
+
+impl LoadDataBuilder {
+    /// Creates a builder for the given table (could be qualified with a database name).
+    ///
+    /// Defaults to the server's default format, i.e. tab-separated fields without enclosure,
+    /// `\` as the escape character and lines terminated by `\n`.
+    pub fn new<T: Into<String>>(table: T) -> Self {
+        Self {
+            table: table.into(),
+            columns: Vec::new(),
+            set: Vec::new(),
+            on_duplicate: OnDuplicate::Default,
+            character_set: None,
+            fields_terminated_by: "\t".into(),
+            fields_enclosed_by: None,
+            fields_escaped_by: "\\".into(),
+            lines_terminated_by: "\n".into(),
+            ignore_lines: 0,
+        }
+    }
+
+    /// Columns (or user variables, e.g. `@skip`) that receive fields in order
+    /// (defaults to all columns of the table).
+    pub fn columns<I, T>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.columns = columns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Adds `SET column = expr`, e.g. to set a column that isn't in the data
+    /// or to convert a field read into a user variable.
+    pub fn set<C, E>(mut self, column: C, expr: E) -> Self
+    where
+        C: Into<String>,
+        E: Into<String>,
+    {
+        self.set.push((column.into(), expr.into()));
+        self
+    }
+
+    /// Defines how rows that duplicate an existing unique key are handled.
+    pub fn on_duplicate(mut self, on_duplicate: OnDuplicate) -> Self {
+        self.on_duplicate = on_duplicate;
+        self
+    }
+
+    /// Character set of the data (defaults to `character_set_database`).
+    pub fn character_set<T: Into<String>>(mut self, character_set: Option<T>) -> Self {
+        self.character_set = character_set.map(Into::into);
+        self
+    }
+
+    /// Field separator (ignored by [`LoadDataBuilder::load_rows`]).
+    pub fn fields_terminated_by<T: Into<String>>(mut self, terminator: T) -> Self {
+        self.fields_terminated_by = terminator.into();
+        self
+    }
+
+    /// Quote character of fields, e.g. `"` for CSV (ignored by [`LoadDataBuilder::load_rows`]).
+    ///
+    /// If `optionally` is `true`, only some fields are expected to be quoted.
+    pub fn fields_enclosed_by<T: Into<String>>(mut self, enclosure: T, optionally: bool) -> Self {
+        self.fields_enclosed_by = Some((enclosure.into(), optionally));
+        self
+    }
+
+    /// Escape character, empty to disable escaping (ignored by [`LoadDataBuilder::load_rows`]).
+    pub fn fields_escaped_by<T: Into<String>>(mut self, escape: T) -> Self {
+        self.fields_escaped_by = escape.into();
+        self
+    }
+
+    /// Line terminator, e.g. `\r\n` (ignored by [`LoadDataBuilder::load_rows`]).
+    pub fn lines_terminated_by<T: Into<String>>(mut self, terminator: T) -> Self {
+        self.lines_terminated_by = terminator.into();
+        self
+    }
+
+    /// Number of lines to skip at the start, e.g. `1` for a CSV header
+    /// (ignored by [`LoadDataBuilder::load_rows`]).
+    pub fn ignore_lines(mut self, ignore_lines: u64) -> Self {
+        self.ignore_lines = ignore_lines;
+        self
+    }
+
+    /// Generates the `LOAD DATA LOCAL INFILE` statement.
+    pub fn statement(&self, no_backslash_escape: bool) -> String {
+        let literal = |x: &str| Value::from(x).as_sql(no_backslash_escape);
+        let mut stmt = String::from("LOAD DATA LOCAL INFILE 'stream'");
+        match self.on_duplicate {
+            OnDuplicate::Default => (),
+            OnDuplicate::Replace => stmt.push_str(" REPLACE"),
+            OnDuplicate::Ignore => stmt.push_str(" IGNORE"),
+        }
+        stmt.push_str(" INTO TABLE ");
+        stmt.push_str(&quote_path(&self.table));
+        if let Some(ref character_set) = self.character_set {
+            stmt.push_str(" CHARACTER SET ");
+            stmt.push_str(&quote_identifier(character_set));
+        }
+        stmt.push_str(" FIELDS TERMINATED BY ");
+        stmt.push_str(&literal(&self.fields_terminated_by));
+        if let Some((ref enclosure, optionally)) = self.fields_enclosed_by {
+            if optionally {
+                stmt.push_str(" OPTIONALLY");
+            }
+            stmt.push_str(" ENCLOSED BY ");
+            stmt.push_str(&literal(enclosure));
+        }
+        stmt.push_str(" ESCAPED BY ");
+        stmt.push_str(&literal(&self.fields_escaped_by));
+        stmt.push_str(" LINES TERMINATED BY ");
+        stmt.push_str(&literal(&self.lines_terminated_by));
+        if self.ignore_lines > 0 {
+            stmt.push_str(&format!(" IGNORE {} LINES", self.ignore_lines));
+        }
+        if !self.columns.is_empty() {
+            let columns = self
+                .columns
+                .iter()
+                .map(|column| quote_column(column))
+                .collect::<Vec<_>>();
+            stmt.push_str(&format!(" ({})", columns.join(", ")));
+        }
+        if !self.set.is_empty() {
+            let set = self
+                .set
+                .iter()
+                .map(|(column, expr)| format!("{} = {}", quote_column(column), expr))
+                .collect::<Vec<_>>();
+            stmt.push_str(" SET ");
+            stmt.push_str(&set.join(", "));
+        }
+        stmt
+    }
+
+    /// Loads data read from `reader`, which must be in the format given to this builder.
+    ///
+    /// Returns the summary, where [`ExecResult::info`] reports the number of records,
+    /// deleted and skipped rows, and warnings (see [`Conn::show_warnings`]).
+    pub fn load<R: io::Read>(&self, conn: &mut Conn, reader: R) -> Result<ExecResult> {
+        let stmt = self.statement(conn.no_backslash_escape());
+        conn.query_with_infile(stmt, reader)
+    }
+
+    /// Loads the given rows of positional parameters (e.g. tuples).
+    ///
+    /// Rows are encoded in the server's default format, so format options of this builder
+    /// are ignored. If a row can't be encoded, rows sent before it are still loaded.
+    pub fn load_rows<I, P>(&self, conn: &mut Conn, rows: I) -> Result<ExecResult>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Params>,
+    {
+        let builder = self.default_format();
+        let mut reader = RowsReader {
+            rows: rows.into_iter(),
+            buf: Vec::new(),
+            pos: 0,
+            error: None,
+        };
+        let result = builder.load(conn, &mut reader);
+        match reader.error {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+
+    /// Returns a copy of this builder with format options reset to the server's default format.
+    pub(crate) fn default_format(&self) -> Self {
+        Self {
+            fields_terminated_by: "\t".into(),
+            fields_enclosed_by: None,
+            fields_escaped_by: "\\".into(),
+            lines_terminated_by: "\n".into(),
+            ignore_lines: 0,
+            ..self.clone()
+        }
+    }
+}
+
+/// Quotes a name that could be qualified with a database name.
+fn quote_path(name: &str) -> String {
+    name.split('.')
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Quotes a column name, but not a user variable.
+fn quote_column(column: &str) -> String {
+    if column.starts_with('@') {
+        column.to_owned()
+    } else {
+        quote_identifier(column)
+    }
+}
+
+/// Appends a field in the server's default format of `LOAD DATA`.
+pub(crate) fn write_field(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::NULL => out.extend_from_slice(b"\\N"),
+        Value::Bytes(bytes) => write_bytes_field(bytes, out),
+        // numbers, dates and times are written same as SQL literals, but without quotes
+        value => out.extend_from_slice(value.as_sql(false).trim_matches('\'').as_bytes()),
+    }
+}
+
+/// Appends a string field in the server's default format of `LOAD DATA`.
+pub(crate) fn write_bytes_field(bytes: &[u8], out: &mut Vec<u8>) {
+    for &byte in bytes {
+        match byte {
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            0 => out.extend_from_slice(b"\\0"),
+            _ => out.push(byte),
+        }
+    }
+}
+
+/// Encodes rows in the server's default format of `LOAD DATA` on the fly.
+struct RowsReader<I> {
+    rows: I,
+    buf: Vec<u8>,
+    pos: usize,
+    error: Option<Error>,
+}
+
+impl<I, P> io::Read for RowsReader<I>
+where
+    I: Iterator<Item = P>,
+    P: Into<Params>,
+{
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            let values = match self.rows.next().map(Into::into) {
+                None => return Ok(0),
+                Some(Params::Empty) => Vec::new(),
+                Some(Params::Positional(values)) => values,
+                Some(Params::Named(_)) => {
+                    self.error = Some(DriverError::NamedParamsForPositionalQuery.into());
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "named parameters can't be loaded",
+                    ));
+                }
+            };
+            self.buf.clear();
+            self.pos = 0;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    self.buf.push(b'\t');
+                }
+                write_field(value, &mut self.buf);
+            }
+            self.buf.push(b'\n');
+        }
+        let len = out.len().min(self.buf.len() - self.pos);
+        out[..len].copy_from_slice(&self.buf[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use super::{LoadDataBuilder, OnDuplicate, RowsReader};
+    use crate::{Params, Value};
+
+    #[test]
+    fn should_generate_load_data_statement() {
+        let builder = LoadDataBuilder::new("db.tbl")
+            .fields_terminated_by(",")
+            .fields_enclosed_by("\"", true)
+            .lines_terminated_by("\r\n")
+            .ignore_lines(1)
+            .columns(["id", "@name"])
+            .set("name", "UPPER(@name)")
+            .on_duplicate(OnDuplicate::Replace)
+            .character_set(Some("utf8mb4"));
+        assert_eq!(
+            builder.statement(false),
+            "LOAD DATA LOCAL INFILE 'stream' REPLACE INTO TABLE `db`.`tbl` \
+             CHARACTER SET `utf8mb4` FIELDS TERMINATED BY ',' OPTIONALLY ENCLOSED BY '\\\"' \
+             ESCAPED BY '\\\\' LINES TERMINATED BY '\\r\\n' IGNORE 1 LINES \
+             (`id`, @name) SET `name` = UPPER(@name)"
+        );
+        assert_eq!(
+            LoadDataBuilder::new("tbl").statement(true),
+            "LOAD DATA LOCAL INFILE 'stream' INTO TABLE `tbl` FIELDS TERMINATED BY '\t' \
+             ESCAPED BY '\\' LINES TERMINATED BY '\n'"
+        );
+    }
+
+    #[test]
+    fn should_encode_rows() {
+        let rows: Vec<Params> = vec![
+            (1, "a\tb\\c\nd", Value::NULL).into(),
+            (
+                -2.5_f64,
+                Value::Date(2020, 1, 2, 3, 4, 5, 0),
+                Value::from(&b"\0"[..]),
+            )
+                .into(),
+        ];
+        let mut reader = RowsReader {
+            rows: rows.into_iter(),
+            buf: Vec::new(),
+            pos: 0,
+            error: None,
+        };
+        let mut encoded = String::new();
+        reader.read_to_string(&mut encoded).unwrap();
+        assert_eq!(
+            encoded,
+            "1\ta\\tb\\\\c\\nd\t\\N\n-2.5\t2020-01-02 03:04:05\t\\0\n"
+        );
+
+        let mut reader = RowsReader {
+            rows: vec![Params::Named(Default::default())].into_iter(),
+            buf: Vec::new(),
+            pos: 0,
+            error: None,
+        };
+        assert!(reader.read(&mut [0; 16]).is_err());
+        assert!(reader.error.is_some());
+    }
+}
@@ -7,7 +7,8 @@
 // modified, or distributed except according to those terms.
 
 use std::{
-    fmt, io,
+    fmt,
+    io::{self, Write as _},
     sync::{Arc, Mutex},
 };
 
@@ -65,20 +66,91 @@ pub(crate) type LocalInfileInner =
 /// # });
 /// ```
 #[derive(Clone)]
-pub struct LocalInfileHandler(pub(crate) LocalInfileInner);
+pub struct LocalInfileHandler {
+    pub(crate) inner: LocalInfileInner,
+    allowed_files: Option<Arc<[Vec<u8>]>>,
+}
 
 impl LocalInfileHandler {
     pub fn new<F>(f: F) -> Self
     where
         F: for<'a> FnMut(&'a [u8], &'a mut LocalInfile<'_>) -> io::Result<()> + Send + 'static,
     {
-        LocalInfileHandler(Arc::new(Mutex::new(f)))
+        LocalInfileHandler {
+            inner: Arc::new(Mutex::new(f)),
+            allowed_files: None,
+        }
+    }
+
+    /// Creates a handler that streams the given reader regardless of the requested file name.
+    ///
+    /// The reader is shared by all requests, so only the first one gets the data
+    /// (use [`Conn::query_with_infile`] to stream a reader for a single query).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a TEXT)")?;
+    /// let file = std::io::Cursor::new(b"foo\nbar\n".to_vec());
+    /// conn.set_local_infile_handler(Some(LocalInfileHandler::from_reader(file)));
+    /// # match conn.query_drop("LOAD DATA LOCAL INFILE 'data.txt' INTO TABLE mysql.tbl") {
+    /// #     Err(Error::MySqlError(e)) if e.code == 1148 => return Ok(()),
+    /// #     result => result?,
+    /// # }
+    /// # let rows: Vec<String> = conn.query("SELECT a FROM mysql.tbl")?;
+    /// # assert_eq!(rows, ["foo", "bar"]);
+    /// # });
+    /// ```
+    pub fn from_reader<R>(mut reader: R) -> Self
+    where
+        R: io::Read + Send + 'static,
+    {
+        Self::new(move |_, local_infile| io::copy(&mut reader, local_infile).map(drop))
+    }
+
+    /// Creates a handler that streams the given chunks regardless of the requested file name.
+    ///
+    /// Chunks are shared by all requests, so only the first one gets the data.
+    pub fn from_chunks<I>(chunks: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+        I::IntoIter: Send + 'static,
+    {
+        let mut chunks = chunks.into_iter();
+        Self::new(move |_, local_infile| {
+            chunks.try_for_each(|chunk| local_infile.write_all(chunk.as_ref()))
+        })
+    }
+
+    /// Restricts file names this handler accepts.
+    ///
+    /// The server may request any file, regardless of the one in the `LOAD DATA` statement,
+    /// so requests for other files fail with `DriverError::LocalInfileNotAllowed`
+    /// (the handler isn't called then).
+    pub fn with_allowed_files<I, T>(mut self, file_names: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Vec<u8>>,
+    {
+        self.allowed_files = Some(file_names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Returns `false` if the file isn't in the allow-list (see [`Self::with_allowed_files`]).
+    pub fn is_allowed(&self, file_name: &[u8]) -> bool {
+        match self.allowed_files {
+            Some(ref allowed) => allowed.iter().any(|allowed| allowed == file_name),
+            None => true,
+        }
     }
 }
 
 impl PartialEq for LocalInfileHandler {
     fn eq(&self, other: &LocalInfileHandler) -> bool {
-        std::ptr::eq(&*self.0, &*other.0)
+        std::ptr::eq(&*self.inner, &*other.inner) && self.allowed_files == other.allowed_files
     }
 }
 
@@ -0,0 +1,52 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{fmt, io::Read};
+
+use crate::Value;
+
+/// Parameter of a statement executed via
+/// [`Queryable::exec_iter_streamed`][crate::prelude::Queryable::exec_iter_streamed].
+///
+/// A parameter is either a value or a reader, which contents are streamed to the server
+/// in chunks using `COM_STMT_SEND_LONG_DATA`, so it is never materialized in memory.
+///
+/// ```rust
+/// # use mysql::StreamedParam;
+/// # use std::io::Read;
+/// let blob = std::io::repeat(0).take(1024 * 1024 * 1024);
+/// let params = vec![StreamedParam::from(42), StreamedParam::reader(blob)];
+/// ```
+pub enum StreamedParam<'a> {
+    /// Parameter value.
+    Value(Value),
+    /// Parameter which contents should be read from the reader.
+    Reader(Box<dyn Read + 'a>),
+}
+
+impl<'a> StreamedParam<'a> {
+    /// Creates a parameter which contents will be read from the given reader.
+    pub fn reader<R: Read + 'a>(reader: R) -> Self {
+        StreamedParam::Reader(Box::new(reader))
+    }
+}
+
+impl<T: Into<Value>> From<T> for StreamedParam<'_> {
+    fn from(value: T) -> Self {
+        StreamedParam::Value(value.into())
+    }
+}
+
+impl fmt::Debug for StreamedParam<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamedParam::Value(value) => f.debug_tuple("Value").field(value).finish(),
+            StreamedParam::Reader(_) => write!(f, "Reader(...)"),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Metrics of the `metrics` feature.
+//!
+//! Everything here is a no-op without the feature, so callers don't need `cfg` attributes.
+
+use std::time::Duration;
+
+/// Counts a query sent to the server (`kind` is `query`, `execute` or `prepare`).
+pub(crate) fn query(kind: &'static str) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!("mysql_queries_total", "type" => kind).increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = kind;
+}
+
+/// Counts an error reported by the server.
+pub(crate) fn server_error(code: u16) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!("mysql_errors_total", "code" => code.to_string()).increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = code;
+}
+
+pub(crate) fn bytes_sent(bytes: usize) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!("mysql_bytes_sent_total").increment(bytes as u64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = bytes;
+}
+
+pub(crate) fn bytes_received(bytes: usize) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!("mysql_bytes_received_total").increment(bytes as u64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = bytes;
+}
+
+/// Records the duration of a connection attempt (including the init commands).
+pub(crate) fn connect(elapsed: Duration, succeeded: bool) {
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!(
+        "mysql_connect_duration_seconds",
+        "result" => if succeeded { "ok" } else { "error" }
+    )
+    .record(elapsed);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (elapsed, succeeded);
+}
+
+/// Counts a connection taken from a pool (`result` is `ok` or `timeout`).
+pub(crate) fn pool_checkout(result: &'static str) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!("mysql_pool_checkouts_total", "result" => result).increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = result;
+}
@@ -6,20 +6,21 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use bytes::{Buf, BufMut};
+use bytes::{Buf, BufMut, BytesMut};
 use mysql_common::{
     constants::UTF8MB4_GENERAL_CI,
     crypto,
     io::{ParseBuf, ReadMysqlExt},
     named_params::parse_named_params,
     packets::{
-        binlog_request::BinlogRequest, AuthPlugin, AuthSwitchRequest, Column, ComChangeUser,
-        ComChangeUserMoreData, ComStmtClose, ComStmtExecuteRequestBuilder, ComStmtSendLongData,
-        CommonOkPacket, ErrPacket, HandshakePacket, HandshakeResponse, OkPacket,
-        OkPacketDeserializer, OkPacketKind, OldAuthSwitchRequest, OldEofPacket,
+        binlog_request::BinlogRequest, AuthPlugin, AuthPluginData, AuthSwitchRequest, Column,
+        ComChangeUser, ComChangeUserMoreData, ComStmtClose, ComStmtExecuteRequestBuilder,
+        ComStmtSendLongData, CommonOkPacket, ErrPacket, HandshakePacket, HandshakeResponse,
+        OkPacket, OkPacketDeserializer, OkPacketKind, OldAuthSwitchRequest, OldEofPacket,
         ResultSetTerminator, SessionStateInfo,
     },
     proto::{codec::Compression, sync_framed::MySyncFramed, MySerialize},
+    scramble,
 };
 
 use mysql_common::{
@@ -32,11 +33,13 @@ use std::{
     cmp,
     collections::HashMap,
     convert::TryFrom,
-    io::{self, Write as _},
+    io::{self, Read as _, Write as _},
     mem,
     ops::{Deref, DerefMut},
     process,
     sync::Arc,
+    thread,
+    time::Instant,
 };
 
 #[cfg(unix)]
@@ -45,25 +48,41 @@ use std::os::unix::io::{AsRawFd, RawFd};
 use crate::{
     buffer_pool::{get_buffer, Buffer},
     conn::{
+        connect_log::{ConnectError, ConnectEvent},
+        cursor::Cursor,
+        interceptor::{InterceptedQuery, Interception, QueryOutcome},
+        latency::{LatencyStats, LatencyTracker},
         local_infile::LocalInfile,
+        long_data::StreamedParam,
         pool::{Pool, PooledConn},
-        query_result::{Binary, Or, Text},
-        stmt::{InnerStmt, Statement},
-        stmt_cache::StmtCache,
+        query_attrs::{execute_request, query_request},
+        query_result::{Binary, ExecResult, Or, ResultSummary, Text},
+        server_flavor::{Feature, MariadbCapabilities, ServerFlavor},
+        slow_query::{
+            is_explainable, normalize_query, query_digest, PendingQuery, PendingQueryKind,
+            SlowQuery,
+        },
+        stmt::{CachePolicy, InnerStmt, Statement},
+        stmt_cache::{CachedStmt, StmtCache},
+        stmt_registry::StmtRegistry,
+        trace::TraceSpan,
         transaction::{AccessMode, TxOpts},
+        wire_log::{Direction, WireLog, WireLogger},
     },
-    consts::{CapabilityFlags, Command, StatusFlags, MAX_PAYLOAD_LEN},
+    consts::{CapabilityFlags, Command, CursorType, StatusFlags, MAX_PAYLOAD_LEN},
     from_value, from_value_opt,
     io::Stream,
     prelude::*,
     ChangeUserOpts,
     DriverError::{
-        CleartextPluginDisabled, MismatchedStmtParams, NamedParamsForPositionalQuery,
-        OldMysqlPasswordDisabled, Protocol41NotSet, ReadOnlyTransNotSupported, SetupError,
-        UnexpectedPacket, UnknownAuthPlugin, UnsupportedProtocol,
+        CleartextPluginDisabled, ForkedInTransaction, HandshakeTimeout, InvalidVersionRequirement,
+        LocalInfileNotAllowed, MismatchedStmtParams, OldMysqlPasswordDisabled, Protocol41NotSet,
+        QueryVetoed, ReadOnlyTransNotSupported, SetupError, UnexpectedPacket, UnknownAuthPlugin,
+        UnsupportedProtocol,
     },
     Error::{self, DriverError, MySqlError},
-    LocalInfileHandler, Opts, OptsBuilder, Params, QueryResult, Result, Transaction,
+    LocalInfileHandler, Opts, OptsBuilder, Params, QueryResult, ResetPolicy, Result, Row,
+    ServerError, Transaction,
     Value::{self, Bytes, NULL},
 };
 
@@ -72,16 +91,52 @@ use crate::SslOpts;
 
 use self::binlog_stream::BinlogStream;
 
+/// Offset of the flags byte within a `COM_STMT_EXECUTE` packet.
+const COM_STMT_EXECUTE_FLAGS_OFFSET: usize = 5;
+
+/// Max length of a chunk of a parameter streamed from a reader.
+const LONG_DATA_CHUNK_LEN: usize = 1024 * 1024;
+
+pub mod admin;
+#[cfg(feature = "alloc-stats")]
+pub mod alloc_stats;
 pub mod binlog_stream;
+pub mod bulk;
+pub mod call;
+pub mod chunked;
+pub mod compression;
+pub mod connect_log;
+#[cfg(feature = "csv")]
+pub mod csv_records;
+pub mod cursor;
+pub mod export;
+pub mod health;
+pub mod hints;
+pub mod info;
+pub mod interceptor;
+pub mod interpolate;
+pub mod json_table;
+pub mod latency;
+pub mod load_data;
 pub mod local_infile;
+pub mod long_data;
+mod metrics;
 pub mod opts;
+pub mod param_report;
 pub mod pool;
 pub mod query;
+mod query_attrs;
 pub mod query_result;
 pub mod queryable;
+pub mod redaction;
+pub mod server_flavor;
+pub mod slow_query;
 pub mod stmt;
-mod stmt_cache;
+pub mod stmt_cache;
+mod stmt_registry;
+mod trace;
 pub mod transaction;
+pub mod wire_log;
 
 /// Mutable connection.
 #[derive(Debug)]
@@ -164,10 +219,15 @@ struct ConnInner {
     // TODO: clean this up
     server_version: Option<(u16, u16, u16)>,
     mariadb_server_version: Option<(u16, u16, u16)>,
+    server_flavor: ServerFlavor,
+    mariadb_capabilities: MariadbCapabilities,
 
     /// Last Ok packet, if any.
     ok_packet: Option<OkPacket<'static>>,
+    /// Last non-zero insert id reported since the session started.
+    session_insert_id: Option<u64>,
     capability_flags: CapabilityFlags,
+    server_capability_flags: CapabilityFlags,
     connection_id: u32,
     status_flags: StatusFlags,
     character_set: u8,
@@ -179,8 +239,45 @@ struct ConnInner {
     auth_plugin: AuthPlugin<'static>,
     nonce: Vec<u8>,
 
+    /// Stream won't be flushed after a packet is written if `true`
+    /// (used to send multiple commands in a single write).
+    pipelined: bool,
+
+    /// Query which is being tracked for the slow query threshold or latency statistics.
+    pending_query: Option<PendingQuery>,
+    /// Span of the current query or statement execution (see the `tracing` feature).
+    query_span: TraceSpan,
+    /// Latency statistics of prepared statements (shared with the pool, if any).
+    latency_tracker: Option<LatencyTracker>,
+    /// Queries prepared by connections of the pool (see `PoolOpts::with_stmt_registry`).
+    pub(crate) stmt_registry: Option<StmtRegistry>,
+    /// Number of slow queries reported so far.
+    slow_queries: u64,
+    /// Memory allocated for queries (see `Conn::alloc_stats`).
+    #[cfg(feature = "alloc-stats")]
+    alloc_stats: alloc_stats::AllocStats,
+    /// Slow query is being explained if `true` (this query is not tracked).
+    explaining: bool,
+    /// Steps of the connection attempt (cleared once connected).
+    connect_log: Vec<ConnectEvent>,
+    /// Connection setup must complete before this instant (see `Opts::get_handshake_timeout`).
+    handshake_deadline: Option<Instant>,
+    /// When the connection was established (see `PoolOpts::with_max_lifetime`).
+    pub(crate) created_at: Instant,
+    /// When the connection was returned to a pool (`None` if it wasn't pooled yet).
+    pub(crate) idle_since: Option<Instant>,
+
     /// This flag is to opt-in/opt-out from reset upon return to a pool.
     pub(crate) reset_upon_return: bool,
+    /// Statements of the pool's prewarm list are prepared (see `PoolOpts::with_prewarm_stmts`).
+    pub(crate) prewarmed: bool,
+    /// The server reported that it's shutting down (see `Error::is_server_shutdown`).
+    pub(crate) server_shutdown: bool,
+    /// Generation of the pool this connection was created in (see `Pool::drain`).
+    pub(crate) pool_generation: u64,
+    /// Id of the process that established the connection (see `Conn::check_fork`).
+    pid: u32,
+    wire_log: WireLogger,
 }
 
 impl ConnInner {
@@ -189,19 +286,48 @@ impl ConnInner {
             stmt_cache: StmtCache::new(opts.get_stmt_cache_size()),
             stream: None,
             capability_flags: CapabilityFlags::empty(),
+            server_capability_flags: CapabilityFlags::empty(),
             status_flags: StatusFlags::empty(),
             connection_id: 0u32,
             character_set: 0u8,
             ok_packet: None,
+            session_insert_id: None,
             last_command: 0u8,
             connected: false,
             has_results: false,
             server_version: None,
             mariadb_server_version: None,
+            server_flavor: ServerFlavor::MySql,
+            mariadb_capabilities: MariadbCapabilities::default(),
             local_infile_handler: None,
             auth_plugin: AuthPlugin::MysqlNativePassword,
             nonce: Vec::new(),
+            pipelined: false,
+            pending_query: None,
+            query_span: TraceSpan::default(),
+            wire_log: WireLogger::new(match opts.get_wire_log() {
+                WireLog::Off => WireLog::from_env(),
+                level => level,
+            }),
+            latency_tracker: if opts.get_track_stmt_latency() {
+                Some(LatencyTracker::default())
+            } else {
+                None
+            },
+            stmt_registry: None,
+            slow_queries: 0,
+            #[cfg(feature = "alloc-stats")]
+            alloc_stats: Default::default(),
+            explaining: false,
+            connect_log: Vec::new(),
+            handshake_deadline: None,
+            created_at: Instant::now(),
+            idle_since: None,
             reset_upon_return: opts.get_pool_opts().reset_connection(),
+            prewarmed: false,
+            server_shutdown: false,
+            pool_generation: 0,
+            pid: process::id(),
 
             opts,
         }
@@ -219,18 +345,186 @@ impl Conn {
     }
 
     /// Returns version number reported by the server.
+    ///
+    /// It's the actual MariaDB version for MariaDB servers, even though they may report
+    /// `5.5.5-<version>-MariaDB` for compatibility.
     pub fn server_version(&self) -> (u16, u16, u16) {
         self.0
-            .server_version
-            .or(self.0.mariadb_server_version)
+            .mariadb_server_version
+            .or(self.0.server_version)
             .unwrap()
     }
 
+    /// Returns the server implementation detected from the handshake.
+    pub fn server_flavor(&self) -> ServerFlavor {
+        self.0.server_flavor
+    }
+
+    /// Returns extended capabilities advertised by a MariaDB server
+    /// (always empty for other servers).
+    pub fn mariadb_capabilities(&self) -> MariadbCapabilities {
+        self.0.mariadb_capabilities
+    }
+
+    /// Returns `true` if the server supports the given SQL feature
+    /// (see [`Feature::is_supported_by`]).
+    pub fn supports(&self, feature: Feature) -> bool {
+        feature.is_supported_by(self.server_flavor(), self.server_version())
+    }
+
+    /// Calls `f` if [`Conn::server_version`] matches the requirement, e.g. `>=8.0.18`
+    /// or `>=10.2, <10.6` (comparisons are separated by commas).
+    ///
+    /// Returns `None` if the version doesn't match. Note that MariaDB versions are compared
+    /// as is, so the requirement should take the server flavor into account
+    /// (or use [`Conn::supports`] instead).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// if !conn.server_flavor().is_mariadb() {
+    ///     let plan: Option<String> = conn
+    ///         .when_version(">=8.0.18", |conn| conn.query_first("EXPLAIN ANALYZE SELECT 1"))?
+    ///         .flatten();
+    ///     # let _ = plan;
+    /// }
+    ///
+    /// let query = if conn.supports(Feature::WindowFunctions) {
+    ///     "SELECT x, SUM(x) OVER (ORDER BY x) FROM (SELECT 1 AS x UNION ALL SELECT 2) t"
+    /// } else {
+    ///     "SELECT x, (SELECT SUM(y.x) FROM (SELECT 1 AS x UNION ALL SELECT 2) y WHERE y.x <= t.x) \
+    ///      FROM (SELECT 1 AS x UNION ALL SELECT 2) t"
+    /// };
+    /// let sums: Vec<(u8, u8)> = conn.query(format!("{} ORDER BY x", query))?;
+    /// assert_eq!(sums, [(1, 1), (2, 3)]);
+    /// # });
+    /// ```
+    pub fn when_version<T, F>(&mut self, requirement: &str, f: F) -> Result<Option<T>>
+    where
+        F: FnOnce(&mut Conn) -> Result<T>,
+    {
+        match server_flavor::matches_version(requirement, self.server_version()) {
+            Some(true) => f(self).map(Some),
+            Some(false) => Ok(None),
+            None => Err(DriverError(InvalidVersionRequirement(requirement.into()))),
+        }
+    }
+
+    /// Extended capabilities requested in the handshake response.
+    fn requested_mariadb_capabilities(&self) -> MariadbCapabilities {
+        self.0
+            .mariadb_capabilities
+            .intersection(MariadbCapabilities::REQUESTED)
+    }
+
     /// Returns connection identifier.
     pub fn connection_id(&self) -> u32 {
         self.0.connection_id
     }
 
+    /// Returns the server-assigned thread id of this connection.
+    ///
+    /// This is the value of `CONNECTION_ID()` and of the `ID` column of the server's
+    /// processlist (same as [`Conn::connection_id`]).
+    pub fn thread_id(&self) -> u32 {
+        self.0.connection_id
+    }
+
+    /// Returns options of this connection.
+    ///
+    /// These are the effective options, i.e. they reflect [`Conn::change_user`] and
+    /// the socket, chosen due to [`crate::OptsBuilder::prefer_socket`]. The password
+    /// is not shown in the `Debug` output of the returned value.
+    pub fn opts(&self) -> &Opts {
+        &self.0.opts
+    }
+
+    /// Opens a new connection with the same options as this one (see [`Conn::opts`]).
+    ///
+    /// Session state (e.g. variables set by a query or an open transaction) is not copied.
+    /// Useful for side-channel operations, such as `KILL QUERY` or a parallel fetch.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let mut sibling = conn.duplicate()?;
+    /// assert_ne!(sibling.connection_id(), conn.connection_id());
+    /// sibling.query_drop(format!("KILL QUERY {}", conn.connection_id()))?;
+    /// # });
+    /// ```
+    pub fn duplicate(&self) -> Result<Conn> {
+        Conn::new(self.0.opts.clone())
+    }
+
+    /// Returns capabilities announced by the server in the initial handshake.
+    pub fn server_capabilities(&self) -> CapabilityFlags {
+        self.0.server_capability_flags
+    }
+
+    /// Returns capabilities negotiated for this connection
+    /// (i.e. supported by both the client and the server).
+    pub fn capabilities(&self) -> CapabilityFlags {
+        self.0.capability_flags
+    }
+
+    /// Returns the authentication plugin used to establish this connection.
+    pub fn auth_plugin(&self) -> &AuthPlugin<'static> {
+        &self.0.auth_plugin
+    }
+
+    /// Returns `true` if this connection is encrypted using TLS.
+    ///
+    /// Returns `false` if the connection is broken (e.g. after a failed reset).
+    pub fn is_tls(&self) -> bool {
+        self.0.stream.as_ref().is_some_and(|stream| {
+            let stream = stream.get_ref();
+            !stream.is_insecure() && !stream.is_socket()
+        })
+    }
+
+    /// Returns `true` if the protocol compression is active for this connection.
+    ///
+    /// Returns `false` if the connection is broken (e.g. after a failed reset).
+    pub fn is_compressed(&self) -> bool {
+        self.0.stream.is_some()
+            && (self.has_capability(CapabilityFlags::CLIENT_COMPRESS)
+                || self.has_capability(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM))
+    }
+
+    /// Returns latency statistics of prepared statements, keyed by the statement query.
+    ///
+    /// Will be empty unless [`crate::OptsBuilder::track_stmt_latency`] is enabled.
+    /// Statistics of a pooled connection are shared by all connections of the pool
+    /// (see [`Pool::stmt_latency_stats`]).
+    pub fn stmt_latency_stats(&self) -> HashMap<String, LatencyStats> {
+        self.0
+            .latency_tracker
+            .as_ref()
+            .map(LatencyTracker::stats)
+            .unwrap_or_default()
+    }
+
+    /// Returns memory allocated by this connection for queries (requires the `alloc-stats` feature).
+    ///
+    /// Use [`AllocStats::max_query_bytes`] to estimate the memory a connection needs
+    /// to process the largest result of a workload.
+    ///
+    /// [`AllocStats::max_query_bytes`]: crate::AllocStats::max_query_bytes
+    #[cfg(feature = "alloc-stats")]
+    pub fn alloc_stats(&self) -> alloc_stats::AllocStats {
+        self.0.alloc_stats
+    }
+
+    /// Resets statistics returned by [`Conn::alloc_stats`].
+    #[cfg(feature = "alloc-stats")]
+    pub fn reset_alloc_stats(&mut self) {
+        self.0.alloc_stats = Default::default();
+    }
+
     /// Returns number of rows affected by the last query.
     pub fn affected_rows(&self) -> u64 {
         self.0
@@ -251,6 +545,29 @@ impl Conn {
             .unwrap_or_default()
     }
 
+    /// Same as [`Conn::last_insert_id`] but returns `None` if the last query didn't report an id.
+    pub fn last_insert_id_opt(&self) -> Option<u64> {
+        self.0.ok_packet.as_ref().and_then(OkPacket::last_insert_id)
+    }
+
+    /// Returns the last insert id reported since the session started, which mostly follows
+    /// `LAST_INSERT_ID()` but doesn't require a round trip to the server.
+    ///
+    /// Unlike [`Conn::last_insert_id`] it isn't reset by queries that don't insert rows:
+    ///
+    /// *   for a multi-row `INSERT` it's the id generated for the first row;
+    /// *   ids generated by triggers and stored functions are not visible, because
+    ///     the server restores the value once they finish;
+    /// *   `LAST_INSERT_ID(expr)` sets it to `expr` if used in a statement that doesn't
+    ///     return rows, e.g. `UPDATE seq SET id = LAST_INSERT_ID(id + 1)`;
+    /// *   unlike `LAST_INSERT_ID()`, it's also updated by explicit values inserted into
+    ///     an `AUTO_INCREMENT` column, because the server reports them the same way.
+    ///
+    /// Returns `None` if no id was reported since the connection was established or reset.
+    pub fn session_last_insert_id(&self) -> Option<u64> {
+        self.0.session_insert_id
+    }
+
     /// Returns number of warnings, reported by the server.
     pub fn warnings(&self) -> u16 {
         self.0
@@ -286,6 +603,48 @@ impl Conn {
             .unwrap_or_default()
     }
 
+    /// Reads and discards result sets that are left unread, and reports what was discarded.
+    ///
+    /// [`QueryResult`] consumes the rest of the response when dropped, but it's left
+    /// on the connection if the result was leaked (e.g. using [`std::mem::forget`]) or if
+    /// the server sent more result sets than expected. Any command sent in this state
+    /// would fail with `Packet out of sync`. Does nothing if there are no unread results.
+    pub fn drain_results(&mut self) -> Result<Vec<ResultSummary>> {
+        let mut drained = Vec::new();
+        if self.0.has_results {
+            // metadata of the partially read set is gone along with its `QueryResult`
+            let rows = self.drain_rows()?;
+            drained.push(ResultSummary::new(None, rows, self.exec_summary()));
+        }
+        while self.more_results_exists() {
+            let summary = match self.handle_result_set()? {
+                Or::A(columns) => {
+                    let rows = self.drain_rows()?;
+                    ResultSummary::new(Some(columns.into()), rows, self.exec_summary())
+                }
+                Or::B(_) => ResultSummary::new(Some(Vec::new().into()), 0, self.exec_summary()),
+            };
+            drained.push(summary);
+        }
+        Ok(drained)
+    }
+
+    /// Discards rows of the current result set and returns their number.
+    fn drain_rows(&mut self) -> Result<u64> {
+        let mut rows = 0;
+        while self.next_row_packet()?.is_some() {
+            rows += 1;
+        }
+        Ok(rows)
+    }
+
+    fn exec_summary(&self) -> ExecResult {
+        ExecResult::new(
+            self.0.ok_packet.as_ref(),
+            self.has_capability(CapabilityFlags::CLIENT_FOUND_ROWS),
+        )
+    }
+
     pub fn session_state_changes(&self) -> io::Result<Vec<SessionStateInfo<'_>>> {
         self.0
             .ok_packet
@@ -314,7 +673,10 @@ impl Conn {
     /// Check the connection can be improved.
     #[allow(unused_assignments)]
     fn can_improved(&mut self) -> Result<Option<Opts>> {
-        if self.0.opts.get_prefer_socket() && self.0.opts.addr_is_loopback() {
+        if self.0.opts.get_prefer_socket()
+            && self.0.opts.addr_is_loopback()
+            && self.0.opts.get_compat_mode().allows_socket_improvement()
+        {
             let mut socket = None;
             #[cfg(test)]
             {
@@ -336,54 +698,127 @@ impl Conn {
     }
 
     /// Creates new `Conn`.
+    ///
+    /// Use [`Conn::new_audited`] to get the steps taken during a failed connection attempt.
     pub fn new<T, E>(opts: T) -> Result<Conn>
     where
         Opts: TryFrom<T, Error = E>,
         crate::Error: From<E>,
     {
         let opts = Opts::try_from(opts)?;
+        Conn::connect_audited(opts).map_err(ConnectError::into_error)
+    }
+
+    /// Same as [`Conn::new`] but the error carries the sequence of steps taken during
+    /// the connection attempt (resolved addresses, TLS decisions, authentication plugins, etc.).
+    ///
+    /// ```rust
+    /// # use mysql::*;
+    /// let opts = OptsBuilder::new()
+    ///     .ip_or_hostname(Some("localhost"))
+    ///     .tcp_port(1);
+    /// let err = Conn::new_audited(opts).unwrap_err();
+    /// assert!(matches!(err.events()[0], ConnectEvent::Resolved(_)));
+    /// ```
+    pub fn new_audited<T, E>(opts: T) -> std::result::Result<Conn, ConnectError>
+    where
+        Opts: TryFrom<T, Error = E>,
+        crate::Error: From<E>,
+    {
+        let opts = Opts::try_from(opts).map_err(|err| ConnectError::new(err.into(), Vec::new()))?;
+        Conn::connect_audited(opts)
+    }
+
+    fn connect_audited(opts: Opts) -> std::result::Result<Conn, ConnectError> {
+        let span = TraceSpan::connect(&opts);
+        let started = Instant::now();
+        let result = span.in_scope(|| Conn::_connect_audited(opts));
+        metrics::connect(started.elapsed(), result.is_ok());
+        if let Ok(ref conn) = result {
+            span.record_connection_id(conn.connection_id());
+        }
+        span.finish(&result);
+        result
+    }
+
+    fn _connect_audited(opts: Opts) -> std::result::Result<Conn, ConnectError> {
         let mut conn = Conn(Box::new(ConnInner::empty(opts)));
-        conn.connect_stream()?;
-        conn.connect()?;
-        let mut conn = {
-            if let Some(new_opts) = conn.can_improved()? {
+        let result = conn
+            .connect_stream()
+            .and_then(|_| conn.connect())
+            .and_then(|_| conn.can_improved());
+        let mut conn = match result {
+            Ok(Some(new_opts)) => {
+                let socket = new_opts.get_socket().unwrap_or_default().to_owned();
                 let mut improved_conn = Conn(Box::new(ConnInner::empty(new_opts)));
-                improved_conn
+                match improved_conn
                     .connect_stream()
-                    .and_then(|_| {
-                        improved_conn.connect()?;
-                        Ok(improved_conn)
-                    })
-                    .unwrap_or(conn)
-            } else {
-                conn
+                    .and_then(|_| improved_conn.connect())
+                {
+                    Ok(()) => {
+                        log::debug!(target: "mysql::conn", "switched to the socket {}", socket);
+                        improved_conn
+                    }
+                    Err(err) => {
+                        log::debug!(
+                            target: "mysql::conn",
+                            "staying on TCP, unable to connect to the socket {}: {}",
+                            socket,
+                            err
+                        );
+                        conn.0
+                            .connect_log
+                            .push(ConnectEvent::SocketFallback(socket, err.to_string()));
+                        conn
+                    }
+                }
             }
+            Ok(None) => conn,
+            Err(err) => return Err(conn.connect_error(err)),
         };
         for cmd in conn.0.opts.get_init() {
-            conn.query_drop(cmd)?;
+            if let Err(err) = conn.query_drop(cmd) {
+                return Err(conn.connect_error(err));
+            }
         }
+        conn.0.connect_log = Vec::new();
+        log::debug!(
+            target: "mysql::conn",
+            "connection {} established (server version {:?})",
+            conn.connection_id(),
+            conn.server_version()
+        );
         Ok(conn)
     }
 
+    /// Attaches steps of the connection attempt to the error.
+    fn connect_error(&mut self, err: Error) -> ConnectError {
+        log::debug!(target: "mysql::conn", "unable to connect: {}", err);
+        ConnectError::new(err, mem::take(&mut self.0.connect_log))
+    }
+
     fn exec_com_reset_connection(&mut self) -> Result<()> {
         self.write_command(Command::COM_RESET_CONNECTION, &[])?;
         let packet = self.read_packet()?;
         self.handle_ok::<CommonOkPacket>(&packet)?;
         self.0.last_command = 0;
         self.0.stmt_cache.clear();
+        self.0.session_insert_id = None;
         Ok(())
     }
 
     fn exec_com_change_user(&mut self, opts: ChangeUserOpts) -> Result<()> {
         opts.update_opts(&mut self.0.opts);
         let com_change_user = ComChangeUser::new()
-            .with_user(self.0.opts.get_user().map(|x| x.as_bytes()))
+            .with_user(self.0.opts.get_user_bytes())
             .with_database(self.0.opts.get_db_name().map(|x| x.as_bytes()))
             .with_auth_plugin_data(
-                self.0
-                    .auth_plugin
-                    .gen_data(self.0.opts.get_pass(), &self.0.nonce)
-                    .as_deref(),
+                gen_auth_data(
+                    &self.0.auth_plugin,
+                    self.0.opts.get_pass_bytes(),
+                    &self.0.nonce,
+                )
+                .as_deref(),
             )
             .with_more_data(Some(
                 ComChangeUserMoreData::new(if self.server_version() >= (5, 5, 3) {
@@ -398,6 +833,7 @@ impl Conn {
         self.write_command_raw(&com_change_user)?;
         self.0.last_command = 0;
         self.0.stmt_cache.clear();
+        self.0.session_insert_id = None;
         self.continue_auth(false)
     }
 
@@ -415,6 +851,7 @@ impl Conn {
     ///
     /// Re-executes [`Opts::get_init`].
     pub fn reset(&mut self) -> Result<()> {
+        log::trace!(target: "mysql::conn", "resetting connection {}", self.connection_id());
         let reset_result = match (self.0.server_version, self.0.mariadb_server_version) {
             (Some(ref version), _) if *version > (5, 7, 3) => self.exec_com_reset_connection(),
             (_, Some(ref version)) if *version >= (10, 2, 7) => self.exec_com_reset_connection(),
@@ -458,6 +895,7 @@ impl Conn {
     }
 
     fn switch_to_ssl(&mut self, ssl_opts: SslOpts) -> Result<()> {
+        self.apply_handshake_deadline()?;
         let stream = self.0.stream.take().expect("incomplete conn");
         let (in_buf, out_buf, codec, stream) = stream.destruct();
         let stream = stream.make_secure(self.0.opts.get_host(), ssl_opts)?;
@@ -481,7 +919,7 @@ impl Conn {
         let tcp_connect_timeout = opts.get_tcp_connect_timeout();
         let bind_address = opts.bind_address().cloned();
         let stream = if let Some(socket) = opts.get_socket() {
-            Stream::connect_socket(socket, read_timeout, write_timeout)?
+            Stream::connect_socket(socket, read_timeout, write_timeout, &mut self.0.connect_log)?
         } else {
             let port = opts.get_tcp_port();
             let ip_or_hostname = match opts.get_host() {
@@ -504,16 +942,48 @@ impl Conn {
                 tcp_nodelay,
                 tcp_connect_timeout,
                 bind_address,
+                &mut self.0.connect_log,
             )?
         };
         self.0.stream = Some(MySyncFramed::new(stream));
         Ok(())
     }
 
+    /// Limits socket timeouts by the time left until the handshake deadline, if any.
+    fn apply_handshake_deadline(&mut self) -> Result<()> {
+        if let Some(deadline) = self.0.handshake_deadline {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(DriverError(HandshakeTimeout));
+            }
+            self.stream_mut()
+                .get_mut()
+                .set_timeouts(Some(left), Some(left))?;
+        }
+        Ok(())
+    }
+
     fn raw_read_packet(&mut self, buffer: &mut Vec<u8>) -> Result<()> {
-        if !self.stream_mut().next_packet(buffer)? {
+        self.apply_handshake_deadline()?;
+        let read = if self.is_compressed() {
+            profile_scope!("mysql::read_compressed_packet");
+            self.stream_mut().next_packet(buffer)?
+        } else {
+            profile_scope!("mysql::read_packet");
+            self.stream_mut().next_packet(buffer)?
+        };
+        if !read {
             Err(Error::server_disconnected())
         } else {
+            metrics::bytes_received(buffer.len());
+            let connection_id = self.0.connection_id;
+            self.0.wire_log.packet(
+                connection_id,
+                Direction::Received,
+                buffer,
+                buffer.len(),
+                false,
+            );
             Ok(())
         }
     }
@@ -526,7 +996,12 @@ impl Conn {
                     match ParseBuf(&buffer).parse(self.0.capability_flags)? {
                         ErrPacket::Error(server_error) => {
                             self.handle_err();
-                            return Err(MySqlError(From::from(server_error)));
+                            metrics::server_error(server_error.error_code());
+                            let err = MySqlError(From::from(server_error));
+                            if err.is_server_shutdown() {
+                                self.0.server_shutdown = true;
+                            }
+                            return Err(err);
                         }
                         ErrPacket::Progress(_progress_report) => {
                             // TODO: Report progress
@@ -534,7 +1009,11 @@ impl Conn {
                         }
                     }
                 }
-                Ok(()) => return Ok(buffer),
+                Ok(()) => {
+                    #[cfg(feature = "alloc-stats")]
+                    self.0.alloc_stats.record_packet(buffer.len());
+                    return Ok(buffer);
+                }
                 Err(e) => {
                     self.handle_err();
                     return Err(e);
@@ -554,17 +1033,94 @@ impl Conn {
     }
 
     fn write_packet<T: Buf>(&mut self, data: &mut T) -> Result<()> {
-        self.stream_mut().send(data)?;
+        metrics::bytes_sent(data.remaining());
+        if self.0.wire_log.is_enabled() {
+            // the handshake response and `COM_CHANGE_USER` carry credentials
+            let redact = !self.0.connected || self.0.last_command == Command::COM_CHANGE_USER as u8;
+            let (connection_id, len) = (self.0.connection_id, data.remaining());
+            self.0
+                .wire_log
+                .packet(connection_id, Direction::Sent, data.chunk(), len, redact);
+        }
+        if self.0.pipelined {
+            self.stream_mut().write(data)?;
+        } else {
+            self.stream_mut().send(data)?;
+        }
         Ok(())
     }
 
+    /// Writes a command (using `write`) followed by the `commit` query in a single network write
+    /// and then reads both responses.
+    ///
+    /// Returns results of the command and of the `commit` query (`None` if it wasn't sent).
+    /// Note that if `pipeline` is `false` or compression is enabled, then commands are sent
+    /// one by one and `commit` won't be sent if the command fails.
+    fn pipeline_with_commit<P, F>(
+        &mut self,
+        write: F,
+        commit: &str,
+        pipeline: bool,
+    ) -> (Result<()>, Option<Result<()>>)
+    where
+        P: Protocol,
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        // sequence ids of the compressed protocol won't survive the pipelining
+        if !pipeline || self.is_compressed() {
+            let result = write(self)
+                .and_then(|_| self.handle_result_set())
+                .map(|meta| drop(QueryResult::<P>::new(ConnMut::Mut(self), meta)));
+            return match result {
+                Ok(()) => (Ok(()), Some(self.query_drop(commit))),
+                Err(err) => (Err(err), None),
+            };
+        }
+
+        self.0.pipelined = true;
+        let written = write(self);
+        self.0.pipelined = false;
+        if let Err(err) = written.and_then(|_| self.write_query(commit.as_bytes(), &[])) {
+            return (Err(err), None);
+        }
+
+        let result = self
+            .handle_result_set()
+            .map(|meta| drop(QueryResult::<P>::new(ConnMut::Mut(self), meta)));
+        self.expect_next_response();
+        let commit_result = self
+            .handle_result_set()
+            .map(|meta| drop(QueryResult::<Text>::new(ConnMut::Mut(self), meta)));
+        (result, Some(commit_result))
+    }
+
+    /// Prepares the codec to read a response to the next pipelined command.
+    fn expect_next_response(&mut self) {
+        self.reset_seq_id();
+        // Server responds with sequence id `1`, so let's pretend that we've just sent a packet.
+        // Encoded packet is discarded.
+        let _ = self
+            .stream_mut()
+            .codec_mut()
+            .encode(&mut &[][..], &mut BytesMut::new());
+    }
+
     fn handle_handshake(&mut self, hp: &HandshakePacket<'_>) {
+        self.0.server_capability_flags = hp.capabilities();
         self.0.capability_flags = hp.capabilities() & self.get_client_flags();
         self.0.status_flags = hp.status_flags();
         self.0.connection_id = hp.connection_id();
         self.0.character_set = hp.default_collation();
+        self.0.server_flavor = ServerFlavor::detect(hp.server_version_ref());
         self.0.server_version = hp.server_version_parsed();
-        self.0.mariadb_server_version = hp.maria_db_server_version_parsed();
+        // MariaDB 11 doesn't prepend the `5.5.5-` prefix
+        self.0.mariadb_server_version = hp.maria_db_server_version_parsed().or_else(|| {
+            if self.0.server_flavor.is_mariadb() {
+                hp.server_version_parsed()
+            } else {
+                None
+            }
+        });
     }
 
     fn handle_ok<'a, T: OkPacketKind>(
@@ -575,6 +1131,9 @@ impl Conn {
             .parse::<OkPacketDeserializer<T>>(self.0.capability_flags)?
             .into_inner();
         self.0.status_flags = ok.status_flags();
+        if let Some(id) = ok.last_insert_id() {
+            self.0.session_insert_id = Some(id);
+        }
         self.0.ok_packet = Some(ok.clone().into_owned());
         Ok(ok)
     }
@@ -591,6 +1150,17 @@ impl Conn {
             .contains(StatusFlags::SERVER_MORE_RESULTS_EXISTS)
     }
 
+    /// Returns `true` if the last response reports an open cursor with unsent rows.
+    fn cursor_exists(&self) -> bool {
+        self.0
+            .status_flags
+            .contains(StatusFlags::SERVER_STATUS_CURSOR_EXISTS)
+            && !self
+                .0
+                .status_flags
+                .contains(StatusFlags::SERVER_STATUS_LAST_ROW_SENT)
+    }
+
     fn perform_auth_switch(&mut self, auth_switch_request: AuthSwitchRequest<'_>) -> Result<()> {
         if matches!(
             auth_switch_request.auth_plugin(),
@@ -610,18 +1180,23 @@ impl Conn {
 
         self.0.nonce = auth_switch_request.plugin_data().to_vec();
         self.0.auth_plugin = auth_switch_request.auth_plugin().into_owned();
+        if !self.0.connected {
+            self.0.connect_log.push(ConnectEvent::AuthSwitch(
+                String::from_utf8_lossy(self.0.auth_plugin.as_bytes()).into_owned(),
+            ));
+        }
         let plugin_data = match self.0.auth_plugin {
             ref x @ AuthPlugin::MysqlOldPassword => {
                 if self.0.opts.get_secure_auth() {
                     return Err(DriverError(OldMysqlPasswordDisabled));
                 }
-                x.gen_data(self.0.opts.get_pass(), &self.0.nonce)
+                gen_auth_data(x, self.0.opts.get_pass_bytes(), &self.0.nonce)
             }
             ref x @ AuthPlugin::MysqlNativePassword => {
-                x.gen_data(self.0.opts.get_pass(), &self.0.nonce)
+                gen_auth_data(x, self.0.opts.get_pass_bytes(), &self.0.nonce)
             }
             ref x @ AuthPlugin::CachingSha2Password => {
-                x.gen_data(self.0.opts.get_pass(), &self.0.nonce)
+                gen_auth_data(x, self.0.opts.get_pass_bytes(), &self.0.nonce)
             }
             ref x @ AuthPlugin::MysqlClearPassword => {
                 if !self.0.opts.get_enable_cleartext_plugin() {
@@ -630,7 +1205,7 @@ impl Conn {
                     )));
                 }
 
-                x.gen_data(self.0.opts.get_pass(), &self.0.nonce)
+                gen_auth_data(x, self.0.opts.get_pass_bytes(), &self.0.nonce)
             }
             AuthPlugin::Other(_) => None,
         };
@@ -662,16 +1237,29 @@ impl Conn {
         }
 
         self.handle_handshake(&handshake);
+        if self.0.server_flavor.is_mariadb() {
+            self.0.mariadb_capabilities = MariadbCapabilities::parse(&payload);
+        }
 
         if self.is_insecure() {
             if let Some(ssl_opts) = self.0.opts.get_ssl_opts().cloned() {
                 if !self.has_capability(CapabilityFlags::CLIENT_SSL) {
+                    log::warn!(target: "mysql::io", "server does not support TLS");
+                    self.0.connect_log.push(ConnectEvent::TlsSkipped(
+                        "server does not support TLS".into(),
+                    ));
                     return Err(DriverError(TlsNotSupported));
                 } else {
                     self.do_ssl_request()?;
                     self.switch_to_ssl(ssl_opts)?;
+                    log::debug!(target: "mysql::io", "TLS negotiated");
+                    self.0.connect_log.push(ConnectEvent::TlsNegotiated);
                 }
             }
+        } else if self.is_socket() && self.0.opts.get_ssl_opts().is_some() {
+            self.0.connect_log.push(ConnectEvent::TlsSkipped(
+                "socket connections are not secured".into(),
+            ));
         }
 
         // Handshake scramble is always 21 bytes length (20 + zero terminator)
@@ -691,11 +1279,14 @@ impl Conn {
             Some(x @ AuthPlugin::CachingSha2Password) => x.into_owned(),
             _ => AuthPlugin::MysqlNativePassword,
         };
+        self.0.connect_log.push(ConnectEvent::AuthPlugin(
+            String::from_utf8_lossy(self.0.auth_plugin.as_bytes()).into_owned(),
+        ));
 
         self.write_handshake_response()?;
         self.continue_auth(false)?;
 
-        if self.has_capability(CapabilityFlags::CLIENT_COMPRESS) {
+        if self.is_compressed() {
             self.switch_to_compressed();
         }
 
@@ -703,9 +1294,23 @@ impl Conn {
     }
 
     fn switch_to_compressed(&mut self) {
-        self.stream_mut()
-            .codec_mut()
-            .compress(Compression::default());
+        match self.0.opts.get_compress().unwrap_or_default() {
+            crate::Compression::Zlib(_) => {
+                self.stream_mut()
+                    .codec_mut()
+                    .compress(Compression::default());
+            }
+            #[cfg(feature = "zstd")]
+            crate::Compression::Zstd(level) => {
+                // zstd payloads are handled by the stream, so the codec
+                // only does the framing (see `ZstdStream`)
+                let stream = self.0.stream.take().expect("incomplete conn");
+                let (in_buf, out_buf, mut codec, stream) = stream.destruct();
+                codec.compress(Compression::none());
+                let stream = stream.into_zstd(level);
+                self.0.stream = Some(MySyncFramed::construct(in_buf, out_buf, codec, stream));
+            }
+        }
     }
 
     fn get_client_flags(&self) -> CapabilityFlags {
@@ -718,13 +1323,30 @@ impl Conn {
             | CapabilityFlags::CLIENT_MULTI_RESULTS
             | CapabilityFlags::CLIENT_PS_MULTI_RESULTS
             | CapabilityFlags::CLIENT_PLUGIN_AUTH
+            | CapabilityFlags::CLIENT_QUERY_ATTRIBUTES
             | (self.0.capability_flags & CapabilityFlags::CLIENT_LONG_FLAG);
-        if self.0.opts.get_compress().is_some() {
-            client_flags.insert(CapabilityFlags::CLIENT_COMPRESS);
+        match self.0.opts.get_compress() {
+            Some(crate::Compression::Zlib(_)) => {
+                client_flags.insert(CapabilityFlags::CLIENT_COMPRESS);
+            }
+            #[cfg(feature = "zstd")]
+            Some(crate::Compression::Zstd(_)) => {
+                client_flags.insert(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM);
+            }
+            None => (),
         }
         if self.0.opts.get_connect_attrs().is_some() {
             client_flags.insert(CapabilityFlags::CLIENT_CONNECT_ATTRS);
         }
+        if self.0.opts.get_found_rows() {
+            client_flags.insert(CapabilityFlags::CLIENT_FOUND_ROWS);
+        }
+        if self.0.opts.get_ignore_space() {
+            client_flags.insert(CapabilityFlags::CLIENT_IGNORE_SPACE);
+        }
+        if self.0.opts.get_interactive() {
+            client_flags.insert(CapabilityFlags::CLIENT_INTERACTIVE);
+        }
         if let Some(db_name) = self.0.opts.get_db_name() {
             if !db_name.is_empty() {
                 client_flags.insert(CapabilityFlags::CLIENT_CONNECT_WITH_DB);
@@ -733,7 +1355,8 @@ impl Conn {
         if self.is_insecure() && self.0.opts.get_ssl_opts().is_some() {
             client_flags.insert(CapabilityFlags::CLIENT_SSL);
         }
-        client_flags | self.0.opts.get_additional_capabilities()
+        (client_flags | self.0.opts.get_additional_capabilities())
+            - self.0.opts.get_compat_mode().unsupported_capabilities()
     }
 
     fn connect_attrs(&self) -> Option<HashMap<String, String>> {
@@ -782,16 +1405,17 @@ impl Conn {
     }
 
     fn write_handshake_response(&mut self) -> Result<()> {
-        let auth_data = self
-            .0
-            .auth_plugin
-            .gen_data(self.0.opts.get_pass(), &self.0.nonce)
-            .map(|x| x.into_owned());
+        let auth_data = gen_auth_data(
+            &self.0.auth_plugin,
+            self.0.opts.get_pass_bytes(),
+            &self.0.nonce,
+        )
+        .map(|x| x.into_owned());
 
         let handshake_response = HandshakeResponse::new(
             auth_data.as_deref(),
             self.0.server_version.unwrap_or((0, 0, 0)),
-            self.0.opts.get_user().map(str::as_bytes),
+            self.0.opts.get_user_bytes(),
             self.0.opts.get_db_name().map(str::as_bytes),
             Some(self.0.auth_plugin.clone()),
             self.0.capability_flags,
@@ -800,6 +1424,17 @@ impl Conn {
 
         let mut buf = get_buffer();
         handshake_response.serialize(buf.as_mut());
+        // MariaDB reads extended capabilities from the last four bytes of the filler
+        let mariadb_capabilities = self.requested_mariadb_capabilities();
+        if !mariadb_capabilities.is_empty() {
+            buf.as_mut()[28..32].copy_from_slice(&mariadb_capabilities.bits().to_le_bytes());
+        }
+        #[cfg(feature = "zstd")]
+        if let Some(crate::Compression::Zstd(level)) = self.0.opts.get_compress() {
+            if self.has_capability(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM) {
+                buf.as_mut().push(level);
+            }
+        }
         self.write_packet(&mut &*buf)
     }
 
@@ -867,7 +1502,7 @@ impl Conn {
                         let mut pass = self
                             .0
                             .opts
-                            .get_pass()
+                            .get_pass_bytes()
                             .map(Vec::from)
                             .unwrap_or_else(Vec::new);
                         pass.push(0);
@@ -879,7 +1514,7 @@ impl Conn {
                         let mut pass = self
                             .0
                             .opts
-                            .get_pass()
+                            .get_pass_bytes()
                             .map(Vec::from)
                             .unwrap_or_else(Vec::new);
                         pass.push(0);
@@ -905,56 +1540,278 @@ impl Conn {
 
     fn reset_seq_id(&mut self) {
         self.stream_mut().codec_mut().reset_seq_id();
+        self.0.wire_log.reset_seq_id();
     }
 
     fn sync_seq_id(&mut self) {
         self.stream_mut().codec_mut().sync_seq_id();
     }
 
+    /// Replaces the connection inherited from the parent process (after `fork`) with a new one.
+    ///
+    /// The socket is shared with the parent, so it's never written to (or gracefully closed)
+    /// by the child. The new connection is established lazily, i.e. before the first command.
+    /// The session state is lost, so it fails with `DriverError::ForkedInTransaction`
+    /// if the connection was inherited in the middle of a transaction.
+    fn check_fork(&mut self) -> Result<()> {
+        if self.0.pid == process::id() || self.0.stream.is_none() {
+            return Ok(());
+        }
+        let in_transaction = self.in_transaction();
+        self.reconnect()?;
+        if in_transaction {
+            return Err(DriverError(ForkedInTransaction));
+        }
+        Ok(())
+    }
+
     fn write_command_raw<T: MySerialize>(&mut self, cmd: &T) -> Result<()> {
+        self.check_fork()?;
         let mut buf = get_buffer();
         cmd.serialize(buf.as_mut());
         self.reset_seq_id();
         debug_assert!(buf.len() > 0);
         self.0.last_command = buf[0];
+        #[cfg(feature = "alloc-stats")]
+        self.record_command_alloc(&buf);
         self.write_packet(&mut &*buf)
     }
 
     fn write_command(&mut self, cmd: Command, data: &[u8]) -> Result<()> {
+        self.check_fork()?;
         let mut buf = get_buffer();
         buf.as_mut().put_u8(cmd as u8);
         buf.as_mut().extend_from_slice(data);
 
         self.reset_seq_id();
         self.0.last_command = buf[0];
+        #[cfg(feature = "alloc-stats")]
+        self.record_command_alloc(&buf);
         self.write_packet(&mut &*buf)
     }
 
+    /// Accounts the command buffer (`COM_QUERY` and `COM_STMT_EXECUTE` start a new query).
+    #[cfg(feature = "alloc-stats")]
+    fn record_command_alloc(&mut self, buf: &[u8]) {
+        if buf[0] == Command::COM_QUERY as u8 || buf[0] == Command::COM_STMT_EXECUTE as u8 {
+            self.0.alloc_stats.start_query();
+        }
+        self.0.alloc_stats.record_packet(buf.len());
+    }
+
+    /// Writes `COM_QUERY` (attributes are ignored if server doesn't support them).
+    fn write_query(&mut self, query: &[u8], attrs: &[(&str, Value)]) -> Result<()> {
+        if !self.has_capability(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES) {
+            return self.write_command(Command::COM_QUERY, query);
+        }
+        let request = query_request(query, attrs);
+        self.write_command(Command::COM_QUERY, &request[1..])
+    }
+
+    /// Returns `attrs` if `CLIENT_QUERY_ATTRIBUTES` is negotiated.
+    fn query_attrs<'a, 'b>(&self, attrs: &'a [(&'b str, Value)]) -> Option<&'a [(&'b str, Value)]> {
+        if self.has_capability(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES) {
+            Some(attrs)
+        } else {
+            None
+        }
+    }
+
     fn send_long_data(&mut self, stmt_id: u32, params: &[Value]) -> Result<()> {
         for (i, value) in params.iter().enumerate() {
             if let Bytes(bytes) = value {
-                let chunks = bytes.chunks(MAX_PAYLOAD_LEN - 6);
-                let chunks = chunks.chain(if bytes.is_empty() {
-                    Some(&[][..])
-                } else {
-                    None
-                });
-                for chunk in chunks {
-                    let cmd = ComStmtSendLongData::new(stmt_id, i as u16, Cow::Borrowed(chunk));
-                    self.write_command_raw(&cmd)?;
-                }
+                self.send_long_data_bytes(stmt_id, i as u16, bytes)?;
             }
         }
 
         Ok(())
     }
 
+    fn send_long_data_bytes(&mut self, stmt_id: u32, index: u16, bytes: &[u8]) -> Result<()> {
+        let chunks = bytes.chunks(MAX_PAYLOAD_LEN - 6);
+        let chunks = chunks.chain(if bytes.is_empty() {
+            Some(&[][..])
+        } else {
+            None
+        });
+        for chunk in chunks {
+            let cmd = ComStmtSendLongData::new(stmt_id, index, Cow::Borrowed(chunk));
+            self.write_command_raw(&cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Streams contents of the reader as a value of the `index`-th parameter.
+    fn send_long_data_from(
+        &mut self,
+        stmt_id: u32,
+        index: u16,
+        reader: &mut dyn io::Read,
+    ) -> Result<()> {
+        // chunk must fit into max_allowed_packet along with the command header
+        let max_len = self.stream_ref().codec().max_allowed_packet - 7;
+        let mut chunk = vec![0; cmp::min(LONG_DATA_CHUNK_LEN, max_len)];
+        let mut sent = false;
+        loop {
+            let mut len = 0;
+            while len < chunk.len() {
+                match reader.read(&mut chunk[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        // server keeps the data sent so far (for this and other parameters)
+                        // and would use it for the next execution of the statement
+                        self.reset_stmt(stmt_id)?;
+                        return Err(err.into());
+                    }
+                }
+            }
+            // empty value still needs to be sent
+            if len > 0 || !sent {
+                let cmd = ComStmtSendLongData::new(stmt_id, index, Cow::Borrowed(&chunk[..len]));
+                self.write_command_raw(&cmd)?;
+                sent = true;
+            }
+            if len < chunk.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Executes the statement streaming `Value::Bytes` parameters and readers
+    /// using `COM_STMT_SEND_LONG_DATA`.
+    fn _execute_streamed(
+        &mut self,
+        stmt: &Statement,
+        params: Vec<StreamedParam<'_>>,
+    ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
+        if stmt.num_params() as usize != params.len() {
+            return Err(DriverError(MismatchedStmtParams(
+                stmt.num_params(),
+                params.len(),
+            )));
+        }
+
+        let mut values = Vec::with_capacity(params.len());
+        for (i, param) in params.into_iter().enumerate() {
+            match param {
+                StreamedParam::Value(value) => {
+                    if let Bytes(ref bytes) = value {
+                        self.send_long_data_bytes(stmt.id(), i as u16, bytes)?;
+                    }
+                    values.push(value);
+                }
+                StreamedParam::Reader(mut reader) => {
+                    self.send_long_data_from(stmt.id(), i as u16, &mut reader)?;
+                    values.push(Bytes(Vec::new()));
+                }
+            }
+        }
+
+        let request = execute_request(
+            stmt.id(),
+            CursorType::CURSOR_TYPE_NO_CURSOR,
+            &values,
+            true,
+            self.query_attrs(&[]),
+        );
+        self.write_command(Command::COM_STMT_EXECUTE, &request[1..])?;
+        self.handle_result_set()
+    }
+
     fn _execute(
         &mut self,
         stmt: &Statement,
         params: Params,
     ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
-        let exec_request = match &params {
+        self._execute_with_attrs(stmt, params, &[])
+    }
+
+    fn _execute_with_attrs(
+        &mut self,
+        stmt: &Statement,
+        params: Params,
+        attrs: &[(&str, Value)],
+    ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
+        let rewritten = self.intercept_stmt(stmt, &params)?;
+        let stmt = rewritten.as_ref().unwrap_or(stmt);
+        self.0.query_span = TraceSpan::execute(self.connection_id(), stmt.id(), stmt.inner.query());
+        metrics::query("execute");
+        if self.tracks_slow_queries() || self.intercepts_queries() {
+            self.start_tracking(PendingQueryKind::Stmt(stmt.clone(), Some(params.clone())));
+        } else if self.0.latency_tracker.is_some() {
+            self.start_tracking(PendingQueryKind::Stmt(stmt.clone(), None));
+        }
+        let in_transaction = self.in_transaction();
+        let mut result = self
+            .write_execute_request(stmt, &params, CursorType::CURSOR_TYPE_NO_CURSOR, attrs)
+            .and_then(|_| self.handle_result_set());
+        if let Err(err) = result {
+            // retry once with a freshly prepared statement
+            result = if err.is_stale_stmt_error() || self.should_reconnect(&err, in_transaction) {
+                trace::retry(self.connection_id(), &err);
+                self.reprepare(stmt, &err).and_then(|stmt| {
+                    self.write_execute_request(
+                        &stmt,
+                        &params,
+                        CursorType::CURSOR_TYPE_NO_CURSOR,
+                        attrs,
+                    )
+                    .and_then(|_| self.handle_result_set())
+                })
+            } else {
+                Err(err)
+            };
+        }
+        if let Err(ref err) = result {
+            self.finish_failed_query(err);
+        }
+        result
+    }
+
+    /// Closes the given statement (if it belongs to this connection) and prepares it again
+    /// (see [`Statement::refresh`]).
+    pub(crate) fn refresh_stmt(&mut self, stmt: &Statement) -> Result<Statement> {
+        if stmt.connection_id() == self.connection_id() {
+            self.close(stmt.clone())?;
+        }
+        let schema = stmt.inner.schema().map(|x| &**x);
+        let inner = self._prepare_in(schema, stmt.inner.query())?;
+        let mut refreshed = Statement::new(inner, stmt.named_params.clone());
+        refreshed.missing_as_null = stmt.missing_as_null;
+        Ok(refreshed)
+    }
+
+    /// Prepares the statement again after `err` revealed that it's stale
+    /// or that the connection is lost (in which case it reconnects first).
+    ///
+    /// Statement invalidated by `ER_NEED_REPREPARE` is closed and evicted from the cache.
+    fn reprepare(&mut self, stmt: &Statement, err: &Error) -> Result<Statement> {
+        match err {
+            _ if err.is_connection_lost() => self.reconnect()?,
+            MySqlError(err) if err.code == ServerError::ER_NEED_REPREPARE as u16 => {
+                self.close(stmt.clone())?;
+            }
+            _ if stmt.connection_id() == self.connection_id() => {
+                // server doesn't know this statement, so it's pointless to close it
+                self.0.stmt_cache.remove(stmt.id());
+            }
+            _ => (),
+        }
+        let schema = stmt.inner.schema().map(|x| &**x);
+        let inner = self._prepare_in(schema, stmt.inner.query())?;
+        Ok(Statement::new(inner, stmt.named_params.clone()))
+    }
+
+    fn write_execute_request(
+        &mut self,
+        stmt: &Statement,
+        params: &Params,
+        cursor_type: CursorType,
+        attrs: &[(&str, Value)],
+    ) -> Result<()> {
+        let exec_request = match params {
             Params::Empty => {
                 if stmt.num_params() != 0 {
                     return Err(DriverError(MismatchedStmtParams(stmt.num_params(), 0)));
@@ -981,17 +1838,64 @@ impl Conn {
                 body
             }
             Params::Named(_) => {
-                if let Some(named_params) = stmt.named_params.as_ref() {
-                    return self._execute(stmt, params.into_positional(named_params)?);
-                } else {
-                    return Err(DriverError(NamedParamsForPositionalQuery));
-                }
+                let params = stmt.positional_params(params.clone())?;
+                return self.write_execute_request(stmt, &params, cursor_type, attrs);
             }
         };
-        self.write_command_raw(&exec_request)?;
+
+        if let Some(attrs) = self.query_attrs(attrs) {
+            let values = match params {
+                Params::Positional(values) => &values[..],
+                _ => &[],
+            };
+            let request = execute_request(
+                stmt.id(),
+                cursor_type,
+                values,
+                exec_request.as_long_data(),
+                Some(attrs),
+            );
+            return self.write_command(Command::COM_STMT_EXECUTE, &request[1..]);
+        }
+
+        if cursor_type.is_empty() {
+            return self.write_command_raw(&exec_request);
+        }
+
+        // mysql_common always sends CURSOR_TYPE_NO_CURSOR, so we need to patch the flags byte
+        let mut buf = get_buffer();
+        exec_request.serialize(buf.as_mut());
+        buf.as_mut()[COM_STMT_EXECUTE_FLAGS_OFFSET] = cursor_type.bits();
+        self.write_command(Command::COM_STMT_EXECUTE, &buf[1..])
+    }
+
+    /// Executes the statement using a read-only server-side cursor.
+    fn _execute_cursored(
+        &mut self,
+        stmt: &Statement,
+        params: Params,
+    ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
+        self.write_execute_request(stmt, &params, CursorType::CURSOR_TYPE_READ_ONLY, &[])?;
         self.handle_result_set()
     }
 
+    /// Requests the next `num_rows` rows of an open cursor.
+    fn fetch_cursor_rows(&mut self, stmt_id: u32, num_rows: u32) -> Result<()> {
+        let mut data = [0_u8; 8];
+        data[..4].copy_from_slice(&stmt_id.to_le_bytes());
+        data[4..].copy_from_slice(&num_rows.to_le_bytes());
+        self.write_command(Command::COM_STMT_FETCH, &data)?;
+        self.0.has_results = true;
+        Ok(())
+    }
+
+    /// Closes an open cursor (statement remains prepared).
+    fn reset_stmt(&mut self, stmt_id: u32) -> Result<()> {
+        self.write_command(Command::COM_STMT_RESET, &stmt_id.to_le_bytes())?;
+        self.read_packet()?;
+        Ok(())
+    }
+
     fn _start_transaction(&mut self, tx_opts: TxOpts) -> Result<()> {
         if let Some(i_level) = tx_opts.isolation_level() {
             self.query_drop(format!("SET TRANSACTION ISOLATION LEVEL {}", i_level))?;
@@ -1019,7 +1923,13 @@ impl Conn {
         Ok(())
     }
 
-    fn send_local_infile(&mut self, file_name: &[u8]) -> Result<OkPacket<'static>> {
+    fn send_local_infile(
+        &mut self,
+        file_name: &[u8],
+        reader: Option<&mut dyn io::Read>,
+    ) -> Result<OkPacket<'static>> {
+        // reported once the server has completed the request, so the connection stays usable
+        let mut error = None;
         {
             let buffer_size = cmp::min(
                 MAX_PAYLOAD_LEN - 4,
@@ -1032,19 +1942,47 @@ impl Conn {
                 .clone()
                 .or_else(|| self.0.opts.get_local_infile_handler().cloned());
             let mut local_infile = LocalInfile::new(io::Cursor::new(chunk), self);
-            if let Some(handler) = maybe_handler {
-                // Unwrap won't panic because we have exclusive access to `self` and this
-                // method is not re-entrant, because `LocalInfile` does not expose the
-                // connection.
-                let handler_fn = &mut *handler.0.lock()?;
-                handler_fn(file_name, &mut local_infile)?;
+            if let Some(reader) = reader {
+                if let Err(err) = io::copy(reader, &mut local_infile) {
+                    error = Some(err.into());
+                }
+            } else if let Some(handler) = maybe_handler {
+                if handler.is_allowed(file_name) {
+                    // Unwrap won't panic because we have exclusive access to `self` and this
+                    // method is not re-entrant, because `LocalInfile` does not expose the
+                    // connection.
+                    let handler_fn = &mut *handler.inner.lock()?;
+                    handler_fn(file_name, &mut local_infile)?;
+                } else {
+                    let file_name = String::from_utf8_lossy(file_name).into_owned();
+                    error = Some(DriverError(LocalInfileNotAllowed(file_name)));
+                }
             }
             local_infile.flush()?;
         }
         self.write_packet(&mut &[][..])?;
         let payload = self.read_packet()?;
         let ok = self.handle_ok::<CommonOkPacket>(&payload)?;
-        Ok(ok.into_owned())
+        match error {
+            Some(err) => Err(err),
+            None => Ok(ok.into_owned()),
+        }
+    }
+
+    /// Runs the query and streams `reader` if the server requests a local file
+    /// (see [`Conn::query_with_infile`]).
+    fn _query_with_infile(
+        &mut self,
+        query: &str,
+        reader: &mut dyn io::Read,
+    ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
+        self.write_query(query.as_bytes(), &[])?;
+        let pld = self.read_packet()?;
+        if pld[0] == 0xfb {
+            self.send_local_infile(&pld[1..], Some(reader)).map(Or::B)
+        } else {
+            self.handle_result_set_packet(pld)
+        }
     }
 
     fn handle_result_set(&mut self) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
@@ -1053,12 +1991,20 @@ impl Conn {
         }
 
         let pld = self.read_packet()?;
+        self.handle_result_set_packet(pld)
+    }
+
+    /// Handles the first packet of a response.
+    fn handle_result_set_packet(
+        &mut self,
+        pld: Buffer,
+    ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
         match pld[0] {
             0x00 => {
                 let ok = self.handle_ok::<CommonOkPacket>(&pld)?;
                 Ok(Or::B(ok.into_owned()))
             }
-            0xfb => match self.send_local_infile(&pld[1..]) {
+            0xfb => match self.send_local_infile(&pld[1..], None) {
                 Ok(ok) => Ok(Or::B(ok)),
                 Err(err) => Err(err),
             },
@@ -1068,11 +2014,17 @@ impl Conn {
                 let mut columns: Vec<Column> = Vec::with_capacity(column_count as usize);
                 for _ in 0..column_count {
                     let pld = self.read_packet()?;
+                    profile_scope!("mysql::decode_column");
                     let column = ParseBuf(&pld).parse(())?;
                     columns.push(column);
                 }
-                // skip eof packet
-                self.drop_packet()?;
+                // skip eof packet, but keep its status flags (see SERVER_STATUS_CURSOR_EXISTS)
+                let eof = self.read_packet()?;
+                if let Ok(eof) = ParseBuf(&eof)
+                    .parse::<OkPacketDeserializer<OldEofPacket>>(self.0.capability_flags)
+                {
+                    self.0.status_flags = eof.into_inner().status_flags();
+                }
                 self.0.has_results = column_count > 0;
                 Ok(Or::A(columns))
             }
@@ -1080,8 +2032,307 @@ impl Conn {
     }
 
     fn _query(&mut self, query: &str) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
-        self.write_command(Command::COM_QUERY, query.as_bytes())?;
-        self.handle_result_set()
+        self._query_with_attrs(query, &[])
+    }
+
+    fn _query_with_attrs(
+        &mut self,
+        query: &str,
+        attrs: &[(&str, Value)],
+    ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
+        let rewritten = self.intercept(&InterceptedQuery::text(query))?;
+        let query = rewritten.as_deref().unwrap_or(query);
+        self.0.query_span = TraceSpan::query(self.connection_id(), query);
+        metrics::query("query");
+        if self.tracks_slow_queries() || self.intercepts_queries() {
+            self.start_tracking(PendingQueryKind::Text(query.into()));
+        }
+        let in_transaction = self.in_transaction();
+        let mut result = self
+            .write_query(query.as_bytes(), attrs)
+            .and_then(|_| self.handle_result_set());
+        if let Err(err) = result {
+            result = if self.should_reconnect(&err, in_transaction) {
+                trace::retry(self.connection_id(), &err);
+                self.reconnect().and_then(|_| {
+                    self.write_query(query.as_bytes(), attrs)
+                        .and_then(|_| self.handle_result_set())
+                })
+            } else {
+                Err(err)
+            };
+        }
+        if let Err(ref err) = result {
+            self.finish_failed_query(err);
+        }
+        result
+    }
+
+    fn in_transaction(&self) -> bool {
+        self.0
+            .status_flags
+            .contains(StatusFlags::SERVER_STATUS_IN_TRANS)
+    }
+
+    /// Whether the query, that failed with `err`, should be retried on a new connection
+    /// (see `OptsBuilder::auto_reconnect`).
+    fn should_reconnect(&self, err: &Error, in_transaction: bool) -> bool {
+        self.0.opts.get_auto_reconnect() && !in_transaction && err.is_connection_lost()
+    }
+
+    /// Replaces the lost connection with a new one.
+    ///
+    /// The new connection executes init commands and prepares statements cached by the lost one.
+    /// Statements that could not be prepared (e.g. because a table was dropped) are skipped.
+    fn reconnect(&mut self) -> Result<()> {
+        log::info!(
+            target: "mysql::conn",
+            "connection {} is lost, reconnecting",
+            self.connection_id()
+        );
+        let mut conn = Conn::connect_audited(self.0.opts.clone()).map_err(|err| {
+            log::warn!(target: "mysql::conn", "unable to reconnect: {}", err);
+            err
+        })?;
+        conn.0.local_infile_handler = self.0.local_infile_handler.take();
+        conn.0.pending_query = self.0.pending_query.take();
+        conn.0.query_span = mem::take(&mut self.0.query_span);
+        conn.0.latency_tracker = self.0.latency_tracker.take();
+        conn.0.stmt_registry = self.0.stmt_registry.take();
+        conn.0.slow_queries = self.0.slow_queries;
+        #[cfg(feature = "alloc-stats")]
+        {
+            conn.0.alloc_stats = self.0.alloc_stats;
+        }
+        conn.0.idle_since = self.0.idle_since;
+        conn.0.reset_upon_return = self.0.reset_upon_return;
+        // let the pool know that the old server went away
+        conn.0.server_shutdown = self.0.server_shutdown;
+        conn.0.pool_generation = self.0.pool_generation;
+
+        let mut stmt_cache = mem::replace(&mut self.0.stmt_cache, StmtCache::new(0));
+        // there is no one to send `COM_STMT_CLOSE` and `COM_QUIT` to
+        self.0.stream = None;
+        mem::swap(&mut self.0, &mut conn.0);
+
+        // uncached and expiring statements aren't worth preparing again
+        stmt_cache.take_transient();
+        for (_, entry) in stmt_cache.into_iter() {
+            if entry.expires_at.is_some() {
+                continue;
+            }
+            match self._prepare_in(entry.stmt.schema().map(|x| &**x), &entry.query.0) {
+                Ok(_) => (),
+                Err(MySqlError(err)) => log::debug!(
+                    target: "mysql::conn",
+                    "unable to prepare a cached statement again: {}",
+                    err
+                ),
+                Err(err) => return Err(err),
+            }
+        }
+        log::debug!(
+            target: "mysql::conn",
+            "reconnected as connection {}",
+            self.connection_id()
+        );
+        trace::reconnect(conn.connection_id(), self.connection_id());
+        Ok(())
+    }
+
+    /// Whether queries should be tracked for the slow query threshold.
+    fn tracks_slow_queries(&self) -> bool {
+        !self.0.explaining
+            && self.0.opts.get_slow_query_threshold().is_some()
+            && self.0.opts.get_slow_query_handler().is_some()
+    }
+
+    /// Whether queries should be given to the query interceptor.
+    fn intercepts_queries(&self) -> bool {
+        !self.0.explaining && self.0.opts.get_query_interceptor().is_some()
+    }
+
+    /// Gives the query to [`QueryInterceptor::before`].
+    ///
+    /// Returns the rewritten query, if any.
+    fn intercept(&self, query: &InterceptedQuery<'_>) -> Result<Option<String>> {
+        let interceptor = match self.0.opts.get_query_interceptor() {
+            Some(interceptor) if !self.0.explaining => interceptor,
+            _ => return Ok(None),
+        };
+        match interceptor.before(query) {
+            Interception::Proceed => Ok(None),
+            Interception::Rewrite(query) => Ok(Some(query)),
+            Interception::Veto(reason) => Err(DriverError(QueryVetoed(reason))),
+        }
+    }
+
+    /// Gives the statement execution to [`QueryInterceptor::before`].
+    ///
+    /// Returns the rewritten statement (prepared on this connection), if any.
+    fn intercept_stmt(&mut self, stmt: &Statement, params: &Params) -> Result<Option<Statement>> {
+        if !self.intercepts_queries() {
+            return Ok(None);
+        }
+        let query = String::from_utf8_lossy(stmt.inner.query());
+        let intercepted = InterceptedQuery::stmt(&query, stmt.id(), Some(params));
+        match self.intercept(&intercepted)? {
+            Some(query) => {
+                let inner = self._prepare(query.as_bytes())?;
+                Ok(Some(Statement::new(inner, stmt.named_params.clone())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn start_tracking(&mut self, kind: PendingQueryKind) {
+        self.0.pending_query = Some(PendingQuery {
+            started: Instant::now(),
+            kind,
+        });
+    }
+
+    /// Stops tracking of the current query, updates latency statistics and reports the query,
+    /// if it exceeded the slow query threshold.
+    ///
+    /// Called once the whole response to the query is consumed.
+    fn finish_query(&mut self) {
+        self.finish_query_with(None)
+    }
+
+    /// Same as [`Conn::finish_query`] but for a query, that has failed with the given error.
+    fn finish_failed_query(&mut self, err: &Error) {
+        self.finish_query_with(Some(err))
+    }
+
+    fn finish_query_with(&mut self, error: Option<&Error>) {
+        mem::take(&mut self.0.query_span).finish_with(error.map(|err| err as _));
+        let pending = match self.0.pending_query.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        let elapsed = pending.started.elapsed();
+        if let (Some(tracker), PendingQueryKind::Stmt(ref stmt, _)) =
+            (&self.0.latency_tracker, &pending.kind)
+        {
+            tracker.record(stmt.inner.query(), elapsed);
+        }
+        let interceptor = self
+            .0
+            .opts
+            .get_query_interceptor()
+            .filter(|_| !self.0.explaining);
+        if let Some(interceptor) = interceptor {
+            let stmt_query;
+            let query = match pending.kind {
+                PendingQueryKind::Text(ref query) => InterceptedQuery::text(query),
+                PendingQueryKind::Stmt(ref stmt, ref params) => {
+                    stmt_query = String::from_utf8_lossy(stmt.inner.query());
+                    InterceptedQuery::stmt(&stmt_query, stmt.id(), params.as_ref())
+                }
+            };
+            interceptor.after(&QueryOutcome {
+                query,
+                elapsed,
+                error,
+            });
+        }
+        let handler = match (
+            self.0.opts.get_slow_query_threshold(),
+            self.0.opts.get_slow_query_handler(),
+        ) {
+            (Some(threshold), Some(handler)) if elapsed >= threshold => handler.clone(),
+            _ => return,
+        };
+        self.0.slow_queries += 1;
+
+        let (query, stmt, params) = match pending.kind {
+            PendingQueryKind::Text(query) => (query, None, None),
+            PendingQueryKind::Stmt(stmt, params) => {
+                let query = String::from_utf8_lossy(stmt.inner.query()).into_owned();
+                (query, Some(stmt), params)
+            }
+        };
+        let params_count = stmt.as_ref().map(Statement::num_params).unwrap_or_default();
+        // must be read before `EXPLAIN`, that is also recorded in the statement history
+        let rows_examined = self.rows_examined();
+
+        let explain = self
+            .0
+            .opts
+            .get_explain_slow_queries()
+            .should_explain(self.0.slow_queries);
+        let named_params = stmt.and_then(|stmt| stmt.named_params);
+        let plan = if explain && is_explainable(&query, self.no_backslash_escape()) {
+            let params = params.clone();
+            self.explain(&query, named_params.clone(), params).ok()
+        } else {
+            None
+        };
+        let params = params.map(|params| {
+            self.0
+                .opts
+                .get_redaction_policy()
+                .redact_params(&params, named_params.as_deref())
+        });
+
+        let normalized_query = normalize_query(&query);
+        (handler.0)(&SlowQuery {
+            digest: query_digest(&normalized_query),
+            query,
+            normalized_query,
+            params,
+            params_count,
+            elapsed,
+            rows_examined,
+            plan,
+        });
+    }
+
+    /// Reads the number of rows examined by the last statement from the performance schema.
+    ///
+    /// Doesn't affect the last Ok packet.
+    fn rows_examined(&mut self) -> Option<u64> {
+        let ok_packet = self.0.ok_packet.clone();
+        self.0.explaining = true;
+
+        let result = self.query_first(
+            "SELECT h.ROWS_EXAMINED FROM performance_schema.events_statements_history h \
+             JOIN performance_schema.threads t USING (THREAD_ID) \
+             WHERE t.PROCESSLIST_ID = CONNECTION_ID() ORDER BY h.EVENT_ID DESC LIMIT 1",
+        );
+
+        self.0.explaining = false;
+        self.0.ok_packet = ok_packet;
+        result.ok().flatten()
+    }
+
+    /// Runs `EXPLAIN` for the given query (it's prepared if `params` is not `None`).
+    ///
+    /// Doesn't affect the last Ok packet.
+    fn explain(
+        &mut self,
+        query: &str,
+        named_params: Option<Vec<Vec<u8>>>,
+        params: Option<Params>,
+    ) -> Result<Vec<Row>> {
+        let ok_packet = self.0.ok_packet.clone();
+        self.0.explaining = true;
+
+        let explain = format!("EXPLAIN {}", query);
+        let result = match params {
+            Some(params) => self._true_prepare(explain.as_bytes()).and_then(|inner| {
+                let stmt = Statement::new(Arc::new(inner), named_params);
+                let rows = self.exec(&stmt, params);
+                self.close(stmt)?;
+                rows
+            }),
+            None => self.query(explain),
+        };
+
+        self.0.explaining = false;
+        self.0.ok_packet = ok_packet;
+        result
     }
 
     /// Executes [`COM_PING`](http://dev.mysql.com/doc/internals/en/com-ping.html)
@@ -1096,10 +2347,175 @@ impl Conn {
     /// Executes [`COM_INIT_DB`](https://dev.mysql.com/doc/internals/en/com-init-db.html)
     /// on `Conn`.
     pub fn select_db(&mut self, schema: &str) -> bool {
-        match self.write_command(Command::COM_INIT_DB, schema.as_bytes()) {
-            Ok(_) => self.drop_packet().is_ok(),
-            _ => false,
+        self.init_db(schema).is_ok()
+    }
+
+    fn init_db(&mut self, schema: &str) -> Result<()> {
+        self.write_command(Command::COM_INIT_DB, schema.as_bytes())?;
+        self.drop_packet()
+    }
+
+    /// Prepares the given query against the given schema, i.e. unqualified names are resolved
+    /// in `db` regardless of the current default database of this connection.
+    ///
+    /// The statement stays pinned to `db` (it is cached separately from the statements
+    /// prepared using [`Queryable::prep`] and is re-prepared against `db` if needed).
+    /// The default database is switched for the time of preparation and then restored,
+    /// unless there was no default database, in which case `db` stays selected.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let stmt = conn.prep_in("mysql", "SELECT COUNT(*) FROM user WHERE User = ?")?;
+    /// conn.select_db("information_schema");
+    /// let count: Option<u32> = conn.exec_first(&stmt, ("root",))?;
+    /// # assert!(count.is_some());
+    /// # });
+    /// ```
+    pub fn prep_in<T: AsRef<str>>(&mut self, db: &str, query: T) -> Result<Statement> {
+        let (named_params, real_query) = parse_named_params(query.as_ref().as_bytes())?;
+        self._prepare_in(Some(db), real_query.borrow())
+            .map(|inner| Statement::new(inner, named_params))
+    }
+
+    /// Returns statements cached by this connection, the most recently used first
+    /// (see [`crate::OptsBuilder::stmt_cache_size`]).
+    ///
+    /// Every cached statement holds a server-side prepared statement, so this helps
+    /// to find out what exhausts the server's [max_prepared_stmt_count][1].
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.exec_drop("SELECT :foo", params! { "foo" => 1 })?;
+    /// conn.exec_drop("SELECT :foo", params! { "foo" => 2 })?;
+    /// let cached = conn.cached_statements();
+    /// assert_eq!(cached[0].query(), "SELECT ?");
+    /// assert_eq!(cached[0].hits(), 1);
+    ///
+    /// assert!(conn.evict_stmt("SELECT ?")?);
+    /// assert!(conn.cached_statements().is_empty());
+    /// # });
+    /// ```
+    ///
+    /// [1]: https://dev.mysql.com/doc/refman/8.0/en/server-system-variables.html#sysvar_max_prepared_stmt_count
+    pub fn cached_statements(&self) -> Vec<CachedStmt> {
+        self.0.stmt_cache.describe()
+    }
+
+    /// Exports queries of the statement cache, most used first.
+    ///
+    /// The snapshot is meant to be persisted and imported upon the next startup, either
+    /// using [`Conn::prewarm_stmts`] or [`crate::PoolOpts::with_prewarm_stmts`], so that
+    /// hot statements are prepared before the first request. Statements pinned to a schema
+    /// (see [`Conn::prep_in`]) and non-UTF-8 queries are not exported.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.exec_drop("SELECT :foo", params! { "foo" => 1 })?;
+    /// conn.exec_drop("SELECT 1 + ?", (1,))?;
+    /// conn.exec_drop("SELECT 1 + ?", (2,))?;
+    /// let snapshot = conn.export_stmt_cache();
+    /// assert_eq!(snapshot, ["SELECT 1 + ?", "SELECT ?"]);
+    ///
+    /// let mut new_conn = Conn::new(get_opts())?;
+    /// assert_eq!(new_conn.prewarm_stmts(&snapshot)?, 2);
+    /// # });
+    /// ```
+    pub fn export_stmt_cache(&self) -> Vec<String> {
+        let mut cached = self.cached_statements();
+        // the sort is stable, so equally used statements remain in the LRU order
+        cached.sort_by_key(|stmt| std::cmp::Reverse(stmt.hits()));
+        cached
+            .into_iter()
+            .filter(|stmt| stmt.schema().is_none())
+            .filter_map(|stmt| String::from_utf8(stmt.query_bytes().to_vec()).ok())
+            .collect()
+    }
+
+    /// Prepares and caches the given statements, e.g. a snapshot exported by
+    /// [`Conn::export_stmt_cache`] or [`crate::Pool::export_stmt_cache`].
+    ///
+    /// Statements that are already cached are skipped, as well as statements that could not be
+    /// prepared (e.g. because a table was dropped since the snapshot was taken). Returns
+    /// the number of newly prepared statements. Note that the statement cache should be large
+    /// enough to hold these statements (see [`crate::OptsBuilder::stmt_cache_size`]).
+    ///
+    /// # Failure
+    ///
+    /// Fails only if the connection is lost.
+    pub fn prewarm_stmts<I, T>(&mut self, stmts: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let mut prepared = 0;
+        for query in stmts {
+            let query = query.as_ref();
+            if self.has_stmt(query.as_bytes()) {
+                continue;
+            }
+            match self.prep(query) {
+                Ok(_) => prepared += 1,
+                Err(err) if err.is_connectivity_error() => return Err(err),
+                Err(err) => log::debug!(
+                    target: "mysql::conn",
+                    "skipping prewarm statement `{}`: {}",
+                    query,
+                    err
+                ),
+            }
+        }
+        Ok(prepared)
+    }
+
+    /// Removes statements of the given query from the statement cache and closes them.
+    ///
+    /// The query should be given as it's cached, i.e. with named parameters replaced by `?`
+    /// (see [`CachedStmt::query_bytes`]). Returns `false` if the query isn't cached.
+    ///
+    /// [`Statement`]s previously prepared from this query are still usable,
+    /// but will be prepared again upon execution.
+    pub fn evict_stmt<T: AsRef<[u8]>>(&mut self, query: T) -> Result<bool> {
+        let evicted = self.0.stmt_cache.take_by_query(query.as_ref());
+        let found = !evicted.is_empty();
+        for stmt in evicted {
+            self.close(Statement::new(stmt, None))?;
+        }
+        Ok(found)
+    }
+
+    /// Closes all statements cached by this connection.
+    pub fn clear_stmt_cache(&mut self) -> Result<()> {
+        let cap = self.0.stmt_cache.cap();
+        let stmt_cache = mem::replace(&mut self.0.stmt_cache, StmtCache::new(cap));
+        for (_, entry) in stmt_cache.into_iter() {
+            self.close(Statement::new(entry.stmt, None))?;
+        }
+        Ok(())
+    }
+
+    /// Changes the size of the statement cache of this connection
+    /// (see [`crate::OptsBuilder::stmt_cache_size`]).
+    ///
+    /// The least recently used statements, that don't fit into the new size, are closed.
+    /// The size is kept if the connection is re-established (see
+    /// [`crate::OptsBuilder::auto_reconnect`]).
+    pub fn set_stmt_cache_size(&mut self, size: usize) -> Result<()> {
+        self.0.opts = OptsBuilder::from_opts(self.0.opts.clone())
+            .stmt_cache_size(size)
+            .into();
+        for stmt in self.0.stmt_cache.set_cap(size) {
+            self.close(Statement::new(stmt, None))?;
         }
+        Ok(())
     }
 
     /// Starts new transaction with provided options.
@@ -1109,10 +2525,52 @@ impl Conn {
         Ok(Transaction::new(self.into()))
     }
 
+    /// Runs the given closure within a transaction.
+    ///
+    /// The transaction is committed if the closure returns `Ok` and rolled back otherwise.
+    ///
+    /// If the transaction fails with `ER_LOCK_DEADLOCK` or `ER_LOCK_WAIT_TIMEOUT`
+    /// (or with a write conflict in [`crate::CompatMode::TiDB`]), then it will be retried
+    /// up to [`TxOpts::max_retries`] times (see [`TxOpts::set_retry_backoff`]),
+    /// so the closure may be called more than once.
+    pub fn with_transaction<F, T>(&mut self, tx_opts: TxOpts, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut Transaction<'_>) -> Result<T>,
+    {
+        let compat_mode = self.0.opts.get_compat_mode();
+        let mut backoff = tx_opts.retry_backoff();
+        let mut retries = 0;
+        loop {
+            let mut tx = self.start_transaction(tx_opts)?;
+            let result = match f(&mut tx) {
+                Ok(output) => tx.commit().map(|_| output),
+                Err(err) => {
+                    // the original error is more important than the rollback error
+                    let _ = tx.rollback();
+                    Err(err)
+                }
+            };
+
+            match result {
+                Err(err)
+                    if retries < tx_opts.max_retries()
+                        && compat_mode.is_retryable_tx_error(&err) =>
+                {
+                    retries += 1;
+                    thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+    }
+
     fn _true_prepare(&mut self, query: &[u8]) -> Result<InnerStmt> {
         self.write_command(Command::COM_STMT_PREPARE, query)?;
         let pld = self.read_packet()?;
-        let mut stmt = ParseBuf(&pld).parse::<InnerStmt>(self.connection_id())?;
+        let mut stmt = ParseBuf(&pld)
+            .parse::<InnerStmt>(self.connection_id())?
+            .with_query(Arc::new(query.into()));
         if stmt.num_params() > 0 {
             let mut params: Vec<Column> = Vec::with_capacity(stmt.num_params() as usize);
             for _ in 0..stmt.num_params() {
@@ -1134,18 +2592,87 @@ impl Conn {
         Ok(stmt)
     }
 
+    /// Prepares the statement against the given schema, switching the default database
+    /// for the time of preparation.
+    fn _true_prepare_in(&mut self, schema: &str, query: &[u8]) -> Result<InnerStmt> {
+        // it's a side request, so it must not affect the state of the current query
+        let pending_query = self.0.pending_query.take();
+        let query_span = mem::take(&mut self.0.query_span);
+        let ok_packet = self.0.ok_packet.clone();
+        let result = self
+            .query_first::<Option<String>, _>("SELECT DATABASE()")
+            .and_then(|current| {
+                let current = current.flatten();
+                if current.as_deref() == Some(schema) {
+                    return self._true_prepare(query);
+                }
+                self.init_db(schema)?;
+                let stmt = self._true_prepare(query);
+                if let Some(current) = current {
+                    self.init_db(&current)?;
+                }
+                stmt
+            });
+        self.0.pending_query = pending_query;
+        self.0.query_span = query_span;
+        self.0.ok_packet = ok_packet;
+        Ok(result?.with_schema(Some(schema.into())))
+    }
+
     fn _prepare(&mut self, query: &[u8]) -> Result<Arc<InnerStmt>> {
-        if let Some(entry) = self.0.stmt_cache.by_query(query) {
-            return Ok(entry.stmt.clone());
+        self._prepare_in(None, query)
+    }
+
+    /// Prepares the statement (pinned to the given schema, if any) unless it's cached.
+    fn _prepare_in(&mut self, schema: Option<&str>, query: &[u8]) -> Result<Arc<InnerStmt>> {
+        self._prepare_with(schema, query, CachePolicy::Default)
+    }
+
+    /// Same as `_prepare_in` but caches a newly prepared statement according to `policy`.
+    fn _prepare_with(
+        &mut self,
+        schema: Option<&str>,
+        query: &[u8],
+        policy: CachePolicy,
+    ) -> Result<Arc<InnerStmt>> {
+        for expired in self.0.stmt_cache.take_expired(Instant::now()) {
+            log::trace!(target: "mysql::conn", "closing expired statement {}", expired.id());
+            self.close(Statement::new(expired, None))?;
         }
 
-        let inner_st = Arc::new(self._true_prepare(query)?);
+        let cached = match schema {
+            Some(schema) => self.0.stmt_cache.by_query_in(schema, query),
+            None => self.0.stmt_cache.by_query(query),
+        };
+        if let Some(entry) = cached {
+            return Ok(entry.stmt.clone());
+        }
 
-        if let Some(old_stmt) = self
-            .0
-            .stmt_cache
-            .put(Arc::new(query.into()), inner_st.clone())
-        {
+        let span = TraceSpan::prepare(self.connection_id(), query);
+        metrics::query("prepare");
+        let prepared = span.in_scope(|| match schema {
+            Some(schema) => self._true_prepare_in(schema, query),
+            None => self._true_prepare(query),
+        });
+        span.finish(&prepared);
+        let inner_st = Arc::new(prepared?);
+
+        let query = inner_st.query().clone();
+        let old_stmt = match policy {
+            CachePolicy::Default => {
+                if let Some(ref registry) = self.0.stmt_registry {
+                    registry.register(&query);
+                }
+                self.0.stmt_cache.put(query, inner_st.clone(), None)
+            }
+            CachePolicy::NoCache => self.0.stmt_cache.put_transient(query, inner_st.clone()),
+            CachePolicy::Ttl(ttl) => {
+                let expires_at = Instant::now().checked_add(ttl);
+                self.0.stmt_cache.put(query, inner_st.clone(), expires_at)
+            }
+        };
+        if let Some(old_stmt) = old_stmt {
+            log::trace!(target: "mysql::conn", "closing evicted statement {}", old_stmt.id());
             self.close(Statement::new(old_stmt, None))?;
         }
 
@@ -1156,6 +2683,35 @@ impl Conn {
         if self.0.connected {
             return Ok(());
         }
+        self.0.handshake_deadline = self
+            .0
+            .opts
+            .get_handshake_timeout()
+            .map(|timeout| Instant::now() + timeout);
+        let span = TraceSpan::handshake();
+        let result = span.in_scope(|| self.setup());
+        span.record_connection_id(self.0.connection_id);
+        span.finish(&result);
+        if self.0.handshake_deadline.take().is_none() {
+            return result;
+        }
+        let read_timeout = self.0.opts.get_read_timeout().cloned();
+        let write_timeout = self.0.opts.get_write_timeout().cloned();
+        let restored = match self.0.stream.as_mut() {
+            Some(stream) => stream.get_mut().set_timeouts(read_timeout, write_timeout),
+            None => Ok(()),
+        };
+        result.and(restored.map_err(Error::from)).map_err(|err| {
+            if err.is_io_timeout() {
+                DriverError(HandshakeTimeout)
+            } else {
+                err
+            }
+        })
+    }
+
+    /// Performs the handshake and reads `max_allowed_packet`.
+    fn setup(&mut self) -> Result<()> {
         self.do_handshake()
             .and_then(|_| {
                 Ok(from_value_opt::<usize>(
@@ -1197,6 +2753,7 @@ impl Conn {
             return Ok(None);
         }
 
+        self.0.query_span.add_row();
         Ok(Some(pld))
     }
 
@@ -1214,6 +2771,58 @@ impl Conn {
         self.0.local_infile_handler = handler;
     }
 
+    /// Runs the query and streams `reader` if the server requests a local file,
+    /// i.e. for `LOAD DATA LOCAL INFILE` (the handler of this connection isn't used).
+    ///
+    /// If `reader` fails, the data read so far is still loaded, and the error is returned
+    /// once the server completes the statement.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a TEXT)")?;
+    /// let data = "foo\nbar\n";
+    /// let query = "LOAD DATA LOCAL INFILE 'data.txt' INTO TABLE mysql.tbl";
+    /// let result = match conn.query_with_infile(query, data.as_bytes()) {
+    ///     Ok(result) => result,
+    ///     // functionality is not supported by the server
+    ///     Err(Error::MySqlError(e)) if e.code == 1148 => return Ok(()),
+    ///     Err(err) => return Err(err.into()),
+    /// };
+    /// assert_eq!(result.affected_rows(), 2);
+    /// # });
+    /// ```
+    pub fn query_with_infile<Q, R>(&mut self, query: Q, mut reader: R) -> Result<ExecResult>
+    where
+        Q: AsRef<str>,
+        R: io::Read,
+    {
+        let meta = self._query_with_infile(query.as_ref(), &mut reader)?;
+        QueryResult::<Text>::new(ConnMut::Mut(self), meta).exec_result()
+    }
+
+    /// Executes the given `stmt` with the given `params` using a read-only server-side cursor.
+    ///
+    /// Rows are fetched in batches of `fetch_size` rows (using `COM_STMT_FETCH`), so huge
+    /// result sets could be streamed without buffering them on either side.
+    pub fn exec_iter_cursored<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+        fetch_size: u32,
+    ) -> Result<Cursor<'_, '_, '_>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        let statement = stmt.as_statement(self)?;
+        let stmt_id = statement.id();
+        let meta = self._execute_cursored(&statement, params.into())?;
+        Ok(Cursor::new(ConnMut::Mut(self), stmt_id, meta, fetch_size))
+    }
+
     pub fn no_backslash_escape(&self) -> bool {
         self.0
             .status_flags
@@ -1248,10 +2857,42 @@ impl Conn {
         Ok(BinlogStream::new(self))
     }
 
+    /// Prepares statements of the pool's warm-up list (see `PoolOpts::with_warm_up_stmts`).
+    fn prepare_warm_up_stmts(&mut self) -> Result<()> {
+        let pool_opts = self.0.opts.get_pool_opts().clone();
+        for query in pool_opts.warm_up_stmts() {
+            self.prep(query)?;
+        }
+        Ok(())
+    }
+
+    /// Prepares statements of the pool's prewarm list (see `PoolOpts::with_prewarm_stmts`).
+    pub(crate) fn prewarm_pooled(&mut self) -> Result<()> {
+        let pool_opts = self.0.opts.get_pool_opts().clone();
+        self.prewarm_stmts(pool_opts.prewarm_stmts())?;
+        self.0.prewarmed = true;
+        Ok(())
+    }
+
     fn cleanup_for_pool(&mut self) -> Result<()> {
         self.set_local_infile_handler(None);
         if self.0.reset_upon_return {
-            self.reset()?;
+            match self.0.opts.get_pool_opts().reset_policy() {
+                ResetPolicy::Full => {
+                    self.exec_com_change_user(ChangeUserOpts::DEFAULT)?;
+                    for cmd in self.0.opts.get_init() {
+                        self.query_drop(cmd)?;
+                    }
+                }
+                ResetPolicy::None | ResetPolicy::Fast => self.reset()?,
+            }
+            if let Some(init_handler) = self.0.opts.get_pool_opts().init_handler().cloned() {
+                init_handler.call(self)?;
+            }
+            self.prepare_warm_up_stmts()?;
+            if self.0.prewarmed {
+                self.prewarm_pooled()?;
+            }
         }
 
         self.0.reset_upon_return = self.0.opts.get_pool_opts().reset_connection();
@@ -1273,6 +2914,15 @@ impl Queryable for Conn {
         Ok(QueryResult::new(ConnMut::Mut(self), meta))
     }
 
+    fn query_iter_with_attrs<T: AsRef<str>>(
+        &mut self,
+        query: T,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Text>> {
+        let meta = self._query_with_attrs(query.as_ref(), attrs)?;
+        Ok(QueryResult::new(ConnMut::Mut(self), meta))
+    }
+
     fn prep<T: AsRef<str>>(&mut self, query: T) -> Result<Statement> {
         let query = query.as_ref();
         let (named_params, real_query) = parse_named_params(query.as_bytes())?;
@@ -1280,6 +2930,17 @@ impl Queryable for Conn {
             .map(|inner| Statement::new(inner, named_params))
     }
 
+    fn prep_with_cache_policy<T: AsRef<str>>(
+        &mut self,
+        query: T,
+        policy: CachePolicy,
+    ) -> Result<Statement> {
+        let query = query.as_ref();
+        let (named_params, real_query) = parse_named_params(query.as_bytes())?;
+        self._prepare_with(None, real_query.borrow(), policy)
+            .map(|inner| Statement::new(inner, named_params))
+    }
+
     fn close(&mut self, stmt: Statement) -> Result<()> {
         self.0.stmt_cache.remove(stmt.id());
         let cmd = ComStmtClose::new(stmt.id());
@@ -1295,31 +2956,123 @@ impl Queryable for Conn {
         let meta = self._execute(&statement, params.into())?;
         Ok(QueryResult::new(ConnMut::Mut(self), meta))
     }
+
+    /// Sends all rows in bulk if the server supports `COM_STMT_BULK_EXECUTE` (MariaDB 10.2+).
+    fn exec_batch<S, P, I>(&mut self, stmt: S, params: I) -> Result<()>
+    where
+        Self: Sized,
+        S: AsStatement,
+        P: Into<Params>,
+        I: IntoIterator<Item = P>,
+    {
+        let stmt = stmt.as_statement(self)?;
+        self._exec_batch(&stmt, params, false).map(drop)
+    }
+
+    fn exec_iter_with_attrs<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        let statement = stmt.as_statement(self)?;
+        let meta = self._execute_with_attrs(&statement, params.into(), attrs)?;
+        Ok(QueryResult::new(ConnMut::Mut(self), meta))
+    }
+
+    fn exec_iter_streamed<S>(
+        &mut self,
+        stmt: S,
+        params: Vec<StreamedParam<'_>>,
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+    {
+        let statement = stmt.as_statement(self)?;
+        let meta = self._execute_streamed(&statement, params)?;
+        Ok(QueryResult::new(ConnMut::Mut(self), meta))
+    }
 }
 
 impl Drop for Conn {
     fn drop(&mut self) {
+        if self.0.pid != process::id() {
+            // the socket is shared with the parent process, so just close it
+            self.0.stream = None;
+            return;
+        }
+
+        log::trace!(target: "mysql::conn", "closing connection {}", self.0.connection_id);
+
+        let linger = self.0.opts.get_quit_linger().filter(|x| !x.is_zero());
+
+        if let (Some(linger), Some(stream)) = (linger, self.0.stream.as_mut()) {
+            // this connection is going away, so it's fine to override its timeouts
+            let _ = stream.get_mut().set_timeouts(Some(linger), Some(linger));
+        }
+
         let stmt_cache = mem::replace(&mut self.0.stmt_cache, StmtCache::new(0));
 
         for (_, entry) in stmt_cache.into_iter() {
             let _ = self.close(Statement::new(entry.stmt, None));
         }
 
-        if self.0.stream.is_some() {
-            let _ = self.write_command(Command::COM_QUIT, &[]);
+        if self.0.stream.is_some() && self.0.opts.get_quit_on_drop() {
+            let sent = self.write_command(Command::COM_QUIT, &[]).is_ok();
+            if sent && linger.is_some() {
+                // Server closes the connection upon COM_QUIT, so wait for EOF.
+                let stream = self.stream_mut().get_mut();
+                let mut buf = [0_u8; 64];
+                while let Ok(1..) = stream.read(&mut buf) {}
+            }
         }
     }
 }
 
-#[cfg(test)]
-#[allow(non_snake_case)]
+/// Same as `AuthPlugin::gen_data`, but the password may be an arbitrary bytes
+/// (e.g. a latin1 password of a legacy account).
+fn gen_auth_data<'a>(
+    plugin: &AuthPlugin<'_>,
+    pass: Option<&'a [u8]>,
+    nonce: &[u8],
+) -> Option<AuthPluginData<'a>> {
+    match pass {
+        Some(pass) if !pass.is_empty() => match plugin {
+            AuthPlugin::CachingSha2Password => {
+                scramble::scramble_sha256(nonce, pass).map(AuthPluginData::Sha2)
+            }
+            AuthPlugin::MysqlNativePassword => {
+                scramble::scramble_native(nonce, pass).map(AuthPluginData::Native)
+            }
+            AuthPlugin::MysqlOldPassword => {
+                scramble::scramble_323(nonce.chunks(8).next().unwrap_or_default(), pass)
+                    .map(AuthPluginData::Old)
+            }
+            AuthPlugin::MysqlClearPassword => Some(AuthPluginData::Clear(Cow::Borrowed(pass))),
+            AuthPlugin::Other(_) => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
 mod test {
     mod my_conn {
         use std::{
             collections::HashMap,
             io::Write,
-            iter, process,
-            sync::mpsc::{channel, sync_channel},
+            iter,
+            ops::ControlFlow,
+            process,
+            sync::{
+                mpsc::{channel, sync_channel},
+                Arc, Mutex,
+            },
             thread::spawn,
             time::Duration,
         };
@@ -1332,11 +3085,17 @@ mod test {
             from_row, from_value, params,
             prelude::*,
             test_misc::get_opts,
-            Conn,
-            DriverError::{MissingNamedParameter, NamedParamsForPositionalQuery},
+            CachePolicy, Conn, CsvOpts,
+            DriverError::{
+                CouldNotDeserializeRow, InvalidVersionRequirement, LocalInfileNotAllowed,
+                MissingNamedParameter, NamedParamsForPositionalQuery,
+            },
             Error::DriverError,
-            LocalInfileHandler, Opts, OptsBuilder, Pool, TxOpts,
+            ExplainMode, ExportOpts, Feature, FloatDecoding, JsonTable, LoadDataBuilder,
+            LocalInfileHandler, MissingAsNull, OnDuplicate, OptimizerHints, Opts, OptsBuilder,
+            Pool, ServerFlavor, SlowQueryHandler, StreamedParam, TxOpts, Utf8Decoding,
             Value::{self, Bytes, Date, Float, Int, NULL},
+            WarningLevel,
         };
 
         fn get_system_variable<T>(conn: &mut Conn, name: &str) -> T
@@ -1369,6 +3128,27 @@ mod test {
             }
         }
 
+        #[test]
+        fn should_expose_connection_metadata() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            let thread_id: u32 = conn.query_first("SELECT CONNECTION_ID()").unwrap().unwrap();
+            assert_eq!(conn.thread_id(), thread_id);
+            assert!(conn
+                .server_capabilities()
+                .contains(crate::consts::CapabilityFlags::CLIENT_PROTOCOL_41));
+            assert!(conn.server_capabilities().contains(conn.capabilities()));
+            assert!(!conn.auth_plugin().as_bytes().is_empty());
+            assert_eq!(conn.is_tls(), crate::test_misc::test_ssl());
+            assert_eq!(conn.is_compressed(), crate::test_misc::test_compression());
+
+            // getters don't panic if the connection is broken
+            let stream = conn.0.stream.take();
+            assert!(!conn.is_tls());
+            assert!(!conn.is_compressed());
+            conn.0.stream = stream;
+        }
+
         #[test]
         fn mysql_async_issue_107() -> crate::Result<()> {
             let mut conn = Conn::new(get_opts())?;
@@ -1754,27 +3534,1428 @@ mod test {
             let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
             tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (3,))
                 .unwrap();
-            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (4,))
+            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (4,))
+                .unwrap();
+            tx.commit().unwrap();
+            assert_eq!(
+                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
+                    .unwrap()
+                    .next()
+                    .unwrap()
+                    .unwrap()
+                    .unwrap(),
+                vec![Bytes(b"4".to_vec())]
+            );
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (5,))
+                .unwrap();
+            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (6,))
+                .unwrap();
+            drop(tx);
+            assert_eq!(
+                conn.query_first("SELECT COUNT(a) from mysql.tbl").unwrap(),
+                Some(4_usize),
+            );
+        }
+
+        #[test]
+        fn should_fetch_rows_using_cursor() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a INT)")
+                .unwrap();
+            let stmt = conn.prep("INSERT INTO mysql.tbl(a) VALUES (?)").unwrap();
+            let cursor = conn.exec_iter_cursored(&stmt, (0,), 3).unwrap();
+            assert_eq!(cursor.affected_rows(), 1);
+            drop(cursor);
+            conn.exec_batch(&stmt, (1..10).map(|x| (x,))).unwrap();
+
+            let stmt = conn
+                .prep("SELECT a FROM mysql.tbl WHERE a >= ? ORDER BY a")
+                .unwrap();
+            let cursor = conn.exec_iter_cursored(&stmt, (2,), 3).unwrap();
+            assert_eq!(cursor.columns().len(), 1);
+            let values = cursor
+                .map(|row| row.map(from_row::<i32>))
+                .collect::<crate::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(values, (2..10).collect::<Vec<_>>());
+
+            // dropped in the middle of a batch and between batches
+            for n in [2, 3] {
+                let mut cursor = conn.exec_iter_cursored(&stmt, (0,), 3).unwrap();
+                for _ in 0..n {
+                    cursor.next().unwrap().unwrap();
+                }
+            }
+
+            // batches are pulled explicitly
+            let mut cursor = conn.exec_iter_cursored(&stmt, (1,), 4).unwrap();
+            let batch = cursor.next_batch().unwrap().unwrap();
+            assert_eq!(batch.len(), 4);
+            cursor.set_fetch_size(2);
+            let mut sizes = vec![];
+            cursor
+                .for_each_batch(|rows| {
+                    sizes.push(rows.len());
+                    ControlFlow::Continue(3)
+                })
+                .unwrap();
+            assert_eq!(sizes, vec![2, 3]);
+            assert!(cursor.next_batch().unwrap().is_none());
+            drop(cursor);
+
+            let count: usize = conn
+                .exec_first("SELECT COUNT(*) FROM mysql.tbl WHERE a >= ?", (5,))
+                .unwrap()
+                .unwrap();
+            assert_eq!(count, 5);
+        }
+
+        #[test]
+        fn should_pipeline_commit_with_the_last_statement() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a INT PRIMARY KEY)")
+                .unwrap();
+
+            let tx = conn.start_transaction(TxOpts::default()).unwrap();
+            tx.query_drop_and_commit("INSERT INTO mysql.tbl(a) VALUES (1)")
+                .unwrap();
+
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            tx.query_drop("INSERT INTO mysql.tbl(a) VALUES (2)")
+                .unwrap();
+            tx.exec_drop_and_commit("SELECT a FROM mysql.tbl WHERE a > ?", (0,))
+                .unwrap();
+
+            // `COMMIT` is executed even if the statement fails
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            tx.query_drop("INSERT INTO mysql.tbl(a) VALUES (3)")
+                .unwrap();
+            assert!(tx
+                .exec_drop_and_commit("INSERT INTO mysql.tbl(a) VALUES (?)", (1,))
+                .is_err());
+
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            let nested = tx.start_transaction().unwrap();
+            nested
+                .query_drop_and_commit("INSERT INTO mysql.tbl(a) VALUES (4)")
+                .unwrap();
+            tx.rollback().unwrap();
+
+            let values: Vec<i32> = conn.query("SELECT a FROM mysql.tbl ORDER BY a").unwrap();
+            assert_eq!(values, vec![1, 2, 3]);
+            assert_eq!(conn.query_first::<u8, _>("SELECT 1").unwrap(), Some(1));
+        }
+
+        #[test]
+        fn should_not_pipeline_commit_with_LOCAL_INFILE() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a TEXT)")
+                .unwrap();
+            let handler = LocalInfileHandler::from_chunks(vec!["foo\n", "bar\n"]);
+            conn.set_local_infile_handler(Some(handler.with_allowed_files(["data.txt"])));
+
+            let tx = conn.start_transaction(TxOpts::default()).unwrap();
+            match tx.query_drop_and_commit("LOAD DATA LOCAL INFILE 'data.txt' INTO TABLE mysql.tbl")
+            {
+                Ok(()) => (),
+                Err(ref err) if err.to_string().contains("not allowed") => return,
+                Err(err) => panic!("ERROR {}", err),
+            }
+
+            // `COMMIT` isn't sent if the local infile request fails
+            let tx = conn.start_transaction(TxOpts::default()).unwrap();
+            assert!(tx
+                .query_drop_and_commit("LOAD DATA LOCAL INFILE 'other.txt' INTO TABLE mysql.tbl")
+                .is_err());
+
+            let rows: Vec<String> = conn.query("SELECT a FROM mysql.tbl").unwrap();
+            assert_eq!(rows, ["foo", "bar"]);
+            assert_eq!(conn.query_first::<u8, _>("SELECT 1").unwrap(), Some(1));
+        }
+
+        #[test]
+        fn should_report_and_explain_slow_queries() {
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let reported_clone = reported.clone();
+            let opts = OptsBuilder::from_opts(get_opts())
+                .slow_query_threshold(Some(Duration::from_millis(50)))
+                .slow_query_handler(Some(SlowQueryHandler::new(move |slow_query| {
+                    reported_clone.lock().unwrap().push(slow_query.clone());
+                })))
+                .explain_slow_queries(ExplainMode::Always);
+            let mut conn = Conn::new(opts).unwrap();
+
+            conn.query_drop("SELECT 1").unwrap();
+            conn.query_drop("DO SLEEP(0.1)").unwrap();
+            assert_eq!(conn.affected_rows(), 0);
+            let stmt = conn.prep("SELECT :a + SLEEP(0.1)").unwrap();
+            let value: Option<u8> = conn.exec_first(&stmt, params! { "a" => 1 }).unwrap();
+            assert_eq!(value, Some(1));
+
+            let reported = reported.lock().unwrap();
+            assert_eq!(reported.len(), 2);
+            assert_eq!(reported[0].query(), "DO SLEEP(0.1)");
+            assert_eq!(reported[0].params(), None);
+            assert!(reported[0].elapsed() >= Duration::from_millis(50));
+            assert!(reported[0].plan().is_none());
+            assert_eq!(reported[0].normalized_query(), "DO SLEEP(?)");
+            assert_eq!(reported[0].params_count(), 0);
+            assert_eq!(reported[1].query(), "SELECT ? + SLEEP(0.1)");
+            assert_eq!(reported[1].normalized_query(), "SELECT ? + SLEEP(?)");
+            assert_eq!(reported[1].params_count(), 1);
+            assert!(reported[1].params().is_some());
+            assert!(!reported[1].plan().unwrap().is_empty());
+            assert_eq!(reported[0].digest().len(), 16);
+            assert_ne!(reported[0].digest(), reported[1].digest());
+        }
+
+        #[test]
+        fn should_stream_params_from_readers() {
+            use std::io::Read;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(id INT, a LONGBLOB, b TEXT)")
+                .unwrap();
+            let stmt = conn.prep("INSERT INTO mysql.tbl VALUES (?, ?, ?)").unwrap();
+
+            let len = 3 * 1024 * 1024 + 5;
+            let params = vec![
+                StreamedParam::from(1),
+                StreamedParam::reader(std::io::repeat(b'a').take(len)),
+                StreamedParam::from("foo"),
+            ];
+            conn.exec_iter_streamed(&stmt, params).unwrap();
+            let params = vec![
+                StreamedParam::from(2),
+                StreamedParam::reader(&b""[..]),
+                StreamedParam::from(NULL),
+            ];
+            conn.exec_iter_streamed(&stmt, params).unwrap();
+
+            let params = vec![StreamedParam::from(3)];
+            assert!(conn.exec_iter_streamed(&stmt, params).is_err());
+
+            // reader fails after some chunks are sent
+            struct Failing;
+            impl Read for Failing {
+                fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                    Err(std::io::Error::other("broken"))
+                }
+            }
+            let params = vec![
+                StreamedParam::from(3),
+                StreamedParam::reader(std::io::repeat(b'b').take(len).chain(Failing)),
+                StreamedParam::from("bar"),
+            ];
+            assert!(conn.exec_iter_streamed(&stmt, params).is_err());
+            // partially sent data is discarded
+            let params = vec![
+                StreamedParam::from(3),
+                StreamedParam::reader(&b"aaa"[..]),
+                StreamedParam::from("baz"),
+            ];
+            conn.exec_iter_streamed(&stmt, params).unwrap();
+
+            let rows: Vec<(u32, u64, Option<String>, bool)> = conn
+                .query("SELECT id, LENGTH(a), b, a = REPEAT('a', LENGTH(a)) FROM mysql.tbl")
+                .unwrap();
+            assert_eq!(
+                rows,
+                vec![
+                    (1, len, Some("foo".into()), true),
+                    (2, 0, None, true),
+                    (3, 3, Some("baz".into()), true)
+                ]
+            );
+        }
+
+        #[test]
+        fn should_send_query_attributes() {
+            use crate::consts::CapabilityFlags;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let attrs = [("foo", Value::from("bar")), ("baz", NULL)];
+
+            let result = conn
+                .query_iter_with_attrs("SELECT 42", &attrs)
+                .unwrap()
+                .map(|row| from_row::<u8>(row.unwrap()))
+                .collect::<Vec<_>>();
+            assert_eq!(result, vec![42]);
+
+            let stmt = conn.prep("SELECT :foo, :foo").unwrap();
+            let result = conn
+                .exec_iter_with_attrs(&stmt, params! { "foo" => 1 }, &attrs)
+                .unwrap()
+                .map(|row| from_row::<(u8, u8)>(row.unwrap()))
+                .collect::<Vec<_>>();
+            assert_eq!(result, vec![(1, 1)]);
+            // statements without parameters still carry attributes
+            let result = conn
+                .exec_iter_with_attrs("SELECT 42", (), &attrs)
+                .unwrap()
+                .map(|row| from_row::<u8>(row.unwrap()))
+                .collect::<Vec<_>>();
+            assert_eq!(result, vec![42]);
+
+            // attributes are readable if the `query_attributes` component is installed
+            if conn.has_capability(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES)
+                && conn
+                    .query_drop("SELECT mysql_query_attribute_string('foo')")
+                    .is_ok()
+            {
+                let query = "SELECT mysql_query_attribute_string('foo'), \
+                    mysql_query_attribute_string('baz')";
+                let result: Option<(Option<String>, Option<String>)> = conn
+                    .query_iter_with_attrs(query, &attrs)
+                    .unwrap()
+                    .next()
+                    .map(|row| from_row(row.unwrap()));
+                assert_eq!(result, Some((Some("bar".into()), None)));
+            }
+        }
+
+        #[test]
+        fn should_generate_auth_data_for_non_utf8_passwords() {
+            use mysql_common::{packets::AuthPlugin, scramble::scramble_native};
+
+            use super::super::gen_auth_data;
+
+            let nonce = b"12345678901234567890";
+            for plugin in [
+                AuthPlugin::CachingSha2Password,
+                AuthPlugin::MysqlNativePassword,
+                AuthPlugin::MysqlOldPassword,
+                AuthPlugin::MysqlClearPassword,
+            ] {
+                assert_eq!(
+                    gen_auth_data(&plugin, Some(b"p\xc3\xa4ss"), nonce),
+                    plugin.gen_data(Some("p\u{e4}ss"), nonce),
+                );
+                assert_eq!(gen_auth_data(&plugin, Some(b""), nonce), None);
+            }
+
+            let auth_data =
+                gen_auth_data(&AuthPlugin::MysqlNativePassword, Some(b"p\xe4ss"), nonce).unwrap();
+            assert_eq!(
+                &*auth_data,
+                &scramble_native(nonce, b"p\xe4ss").unwrap()[..]
+            );
+        }
+
+        #[test]
+        fn should_describe_params() {
+            use mysql_common::constants::{ColumnType, StmtExecuteParamFlags};
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT :a, :b, :a").unwrap();
+            let report = stmt
+                .describe_params(params! { "a" => 1_u8, "b" => "foo" })
+                .unwrap();
+            assert_eq!(report.len(), 3);
+            assert_eq!(report[0].name(), Some("a"));
+            assert_eq!(report[0].column_type(), ColumnType::MYSQL_TYPE_LONGLONG);
+            assert_eq!(report[0].flags(), StmtExecuteParamFlags::UNSIGNED);
+            assert_eq!(report[1].column_type(), ColumnType::MYSQL_TYPE_VAR_STRING);
+            assert_eq!(report[1].len(), 4);
+            assert!(!report[1].long_data());
+            assert_eq!(report[2].name(), Some("a"));
+
+            assert!(stmt.describe_params((1,)).is_err());
+        }
+
+        #[test]
+        fn should_reject_extra_rows_in_strict_first() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let first: Option<u8> = conn.query_first("SELECT 1 UNION SELECT 2").unwrap();
+            assert_eq!(first, Some(1));
+
+            let mut conn =
+                Conn::new(OptsBuilder::from_opts(get_opts()).strict_first(true)).unwrap();
+            let first: Option<u8> = conn.query_first("SELECT 1").unwrap();
+            assert_eq!(first, Some(1));
+            let first: Option<u8> = conn
+                .exec_first("SELECT 1 FROM dual WHERE 1 = 0", ())
+                .unwrap();
+            assert_eq!(first, None);
+            match conn.exec_first::<u8, _, _>("SELECT 1 UNION SELECT 2 UNION SELECT 3", ()) {
+                Err(DriverError(crate::DriverError::TooManyRows)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            // the connection is still usable
+            let first: Option<u8> = conn.query_first("SELECT 4").unwrap();
+            assert_eq!(first, Some(4));
+        }
+
+        #[test]
+        fn should_report_server_flavor() {
+            let conn = Conn::new(get_opts()).unwrap();
+            let is_mariadb = conn.0.mariadb_server_version.is_some();
+            assert_eq!(conn.server_flavor().is_mariadb(), is_mariadb);
+            if is_mariadb {
+                assert!(conn.server_version() >= (10, 0, 0));
+            } else {
+                assert!(conn.mariadb_capabilities().is_empty());
+            }
+        }
+
+        #[test]
+        fn should_reconnect_automatically() {
+            let opts = OptsBuilder::from_opts(get_opts())
+                .init(vec!["SET @init = 42"])
+                .auto_reconnect(true);
+            let mut conn = Conn::new(opts).unwrap();
+            let stmt = conn.prep("SELECT ? + @init").unwrap();
+
+            let id = conn.connection_id();
+            conn.cancel_handle().kill_connection().unwrap();
+            let result: Option<u32> = conn.exec_first(&stmt, (1,)).unwrap();
+            assert_eq!(result, Some(43));
+            assert_ne!(conn.connection_id(), id);
+            assert!(conn.0.stmt_cache.contains_query(&b"SELECT ? + @init"[..]));
+
+            conn.cancel_handle().kill_connection().unwrap();
+            let result: Option<u32> = conn.query_first("SELECT @init").unwrap();
+            assert_eq!(result, Some(42));
+
+            // connection lost within a transaction is not recovered
+            let handle = conn.cancel_handle();
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            handle.kill_connection().unwrap();
+            assert!(tx.query_drop("SELECT 1").is_err());
+        }
+
+        #[test]
+        fn should_report_health() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let report = conn.health_report().unwrap();
+            assert!(report.threads_connected() >= 1);
+            assert!(report.threads_running() >= 1);
+            assert!(report.uptime() > Duration::from_secs(0));
+            assert!(report.ping_rtt() < Duration::from_secs(10));
+            if let (Some(total), Some(free)) = (
+                report.buffer_pool_pages_total(),
+                report.buffer_pool_pages_free(),
+            ) {
+                assert!(free <= total);
+            }
+        }
+
+        #[test]
+        fn should_reprepare_stale_statements() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            // the statement is unknown to the server
+            let stmt = conn.prep("SELECT ?").unwrap();
+            conn.close(stmt.clone()).unwrap();
+            let result: Option<u8> = conn.exec_first(&stmt, (42,)).unwrap();
+            assert_eq!(result, Some(42));
+
+            let stmt = conn.prep("SELECT :foo").unwrap();
+            conn.close(stmt.clone()).unwrap();
+            let result: Option<u8> = conn.exec_first(&stmt, params! { "foo" => 42 }).unwrap();
+            assert_eq!(result, Some(42));
+        }
+
+        #[test]
+        #[cfg(feature = "alloc-stats")]
+        fn should_account_allocations() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.reset_alloc_stats();
+            conn.query_drop("DO 1").unwrap();
+            assert!(conn.alloc_stats().last_query_bytes() < 1024);
+
+            let _: Vec<Vec<u8>> = conn.query("SELECT REPEAT('x', 100000)").unwrap();
+            let stats = conn.alloc_stats();
+            assert_eq!(stats.queries(), 2);
+            assert!(stats.last_query_bytes() > 200_000);
+            assert_eq!(stats.max_query_bytes(), stats.last_query_bytes());
+            assert!(stats.max_packet_bytes() > 100_000);
+            assert!(stats.max_row_bytes() >= 100_000);
+        }
+
+        #[test]
+        fn should_export_table() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name VARCHAR(10))")
+                .unwrap();
+            conn.query_drop("INSERT INTO mysql.tbl VALUES (1, 'a'), (2, NULL), (3, '')")
+                .unwrap();
+            conn.query_drop("USE mysql").unwrap();
+
+            let opts = ExportOpts::new().with_net_buffer_length(45);
+            let mut dump = Vec::new();
+            assert_eq!(conn.export_table("tbl", &mut dump, opts).unwrap(), 3);
+            let dump = String::from_utf8(dump).unwrap();
+            assert!(dump.contains(
+                "INSERT INTO `tbl` VALUES (1,'a'),(2,NULL);\nINSERT INTO `tbl` VALUES (3,'');\n"
+            ));
+
+            // the dump could be restored
+            conn.query_drop("DELETE FROM tbl").unwrap();
+            conn.query_drop(dump).unwrap();
+            let count: Option<u8> = conn.query_first("SELECT COUNT(*) FROM tbl").unwrap();
+            assert_eq!(count, Some(3));
+        }
+
+        #[test]
+        fn should_report_info_and_warnings() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, v TINYINT)")
+                .unwrap();
+            conn.query_drop("INSERT INTO mysql.tbl VALUES (1, 1), (2, 1), (3, 2)")
+                .unwrap();
+            let info = conn.info().unwrap();
+            assert_eq!(info.records(), Some(3));
+            assert_eq!(info.duplicates(), Some(0));
+
+            let result = conn.query_iter("UPDATE mysql.tbl SET v = 2").unwrap();
+            let info = result.info().unwrap();
+            assert_eq!(info.rows_matched(), Some(3));
+            assert_eq!(info.changed(), Some(2));
+            drop(result);
+
+            conn.query_drop("SELECT CAST('1x' AS SIGNED)").unwrap();
+            assert_eq!(conn.warnings(), 1);
+            let warnings = conn.show_warnings().unwrap();
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].level(), WarningLevel::Warning);
+            assert_eq!(warnings[0].code(), 1292);
+        }
+
+        #[test]
+        fn should_introspect_generated_columns() {
+            use crate::schema::GenerationKind;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("DROP TABLE IF EXISTS mysql.generated_tbl")
+                .unwrap();
+            conn.query_drop(
+                "CREATE TABLE mysql.generated_tbl (a INT NOT NULL, \
+                 b INT AS (a + 1) VIRTUAL, c INT AS (a * 2) STORED)",
+            )
+            .unwrap();
+            let columns = conn.columns("mysql", "generated_tbl").unwrap();
+            conn.query_drop("DROP TABLE mysql.generated_tbl").unwrap();
+
+            assert_eq!(columns.len(), 3);
+            assert_eq!(columns[0].name(), "a");
+            assert!(!columns[0].is_nullable());
+            assert!(columns[0].is_writable());
+            let b = columns[1].generated().unwrap();
+            assert_eq!(b.kind(), GenerationKind::Virtual);
+            assert!(b.expression().contains('+'));
+            let c = columns[2].generated().unwrap();
+            assert_eq!(c.kind(), GenerationKind::Stored);
+            assert!(conn.columns("mysql", "no_such_table").unwrap().is_empty());
+        }
+
+        #[test]
+        fn should_introspect_ddl_metadata() {
+            use crate::schema::ColumnDefault;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("DROP TABLE IF EXISTS mysql.ddl_tbl")
+                .unwrap();
+            conn.query_drop(
+                "CREATE TABLE mysql.ddl_tbl (\
+                 a INT DEFAULT 1 COMMENT 'first', \
+                 b VARCHAR(10) DEFAULT 'x', \
+                 c TIMESTAMP NULL DEFAULT CURRENT_TIMESTAMP, \
+                 CONSTRAINT a_positive CHECK (a > 0))",
+            )
+            .unwrap();
+            let columns = conn.columns("mysql", "ddl_tbl").unwrap();
+            let checks = conn.check_constraints("mysql", "ddl_tbl").unwrap();
+            conn.query_drop("DROP TABLE mysql.ddl_tbl").unwrap();
+
+            assert_eq!(columns[0].comment(), "first");
+            assert_eq!(
+                columns[0].default(),
+                Some(&ColumnDefault::Literal("1".into()))
+            );
+            assert_eq!(
+                columns[1].default(),
+                Some(&ColumnDefault::Literal("x".into()))
+            );
+            assert_eq!(columns[1].comment(), "");
+            assert!(matches!(
+                columns[2].default(),
+                Some(ColumnDefault::Expression(_))
+            ));
+
+            assert_eq!(checks.len(), 1);
+            assert_eq!(checks[0].name(), "a_positive");
+            assert!(checks[0].clause().contains('>'));
+            assert!(checks[0].is_enforced());
+        }
+
+        #[test]
+        fn should_introspect_tables_and_indexes() {
+            use crate::schema::TableType;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("DROP VIEW IF EXISTS mysql.indexed_view")
+                .unwrap();
+            conn.query_drop("DROP TABLE IF EXISTS mysql.indexed_tbl")
+                .unwrap();
+            conn.query_drop(
+                "CREATE TABLE mysql.indexed_tbl (\
+                 id INT NOT NULL, part INT NOT NULL, name VARCHAR(100), \
+                 PRIMARY KEY (id, part), UNIQUE KEY by_name (name(10)), KEY by_part (part)) \
+                 COMMENT 'indexed'",
+            )
+            .unwrap();
+            conn.query_drop("CREATE VIEW mysql.indexed_view AS SELECT id FROM mysql.indexed_tbl")
+                .unwrap();
+            let tables = conn.tables("mysql").unwrap();
+            let primary_key = conn.primary_key("mysql", "indexed_tbl").unwrap();
+            let indexes = conn.indexes("mysql", "indexed_tbl").unwrap();
+            conn.query_drop("DROP VIEW mysql.indexed_view").unwrap();
+            conn.query_drop("DROP TABLE mysql.indexed_tbl").unwrap();
+
+            let table = tables.iter().find(|x| x.name() == "indexed_tbl").unwrap();
+            assert_eq!(table.table_type(), &TableType::BaseTable);
+            assert!(table.engine().is_some());
+            assert_eq!(table.comment(), "indexed");
+            let view = tables.iter().find(|x| x.name() == "indexed_view").unwrap();
+            assert_eq!(view.table_type(), &TableType::View);
+            assert_eq!(view.engine(), None);
+
+            assert_eq!(primary_key, ["id", "part"]);
+            assert_eq!(
+                indexes.iter().map(|x| x.name()).collect::<Vec<_>>(),
+                ["PRIMARY", "by_name", "by_part"]
+            );
+            assert!(indexes[0].is_primary());
+            assert!(indexes[1].is_unique());
+            assert_eq!(indexes[1].columns()[0].sub_part(), Some(10));
+            assert!(!indexes[2].is_unique());
+            assert_eq!(indexes[2].columns()[0].name(), Some("part"));
+            assert!(conn.indexes("mysql", "no_such_table").unwrap().is_empty());
+        }
+
+        #[test]
+        fn should_introspect_partitions() {
+            use crate::schema::{PartitionMethod, PartitionOp};
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("DROP TABLE IF EXISTS mysql.partitioned_tbl")
+                .unwrap();
+            conn.query_drop(
+                "CREATE TABLE mysql.partitioned_tbl (id INT NOT NULL, year INT NOT NULL) \
+                 PARTITION BY RANGE (year) SUBPARTITION BY HASH (id) SUBPARTITIONS 2 (\
+                 PARTITION p2023 VALUES LESS THAN (2024), \
+                 PARTITION pmax VALUES LESS THAN MAXVALUE COMMENT 'catch-all')",
+            )
+            .unwrap();
+            conn.query_drop("INSERT INTO mysql.partitioned_tbl VALUES (1, 2023), (2, 2025)")
+                .unwrap();
+            let partitioning = conn.partitioning("mysql", "partitioned_tbl").unwrap();
+            let plain = conn.partitioning("mysql", "user").unwrap();
+            let missing = conn.partitioning("mysql", "no_such_table").unwrap();
+
+            let mode = conn.escape_mode().unwrap();
+            let partitioning = partitioning.unwrap();
+            let oldest = &partitioning.partitions()[0];
+            let ids: Vec<u32> = conn
+                .query(format!(
+                    "SELECT id FROM {}",
+                    oldest.target("mysql", "partitioned_tbl", mode)
+                ))
+                .unwrap();
+            conn.query_drop(PartitionOp::Drop.sql(
+                "mysql",
+                "partitioned_tbl",
+                &[oldest.name()],
+                mode,
+            ))
+            .unwrap();
+            let count: Option<u32> = conn
+                .query_first("SELECT COUNT(*) FROM mysql.partitioned_tbl")
+                .unwrap();
+            conn.query_drop("DROP TABLE mysql.partitioned_tbl").unwrap();
+
+            assert_eq!(partitioning.method(), &PartitionMethod::Range);
+            assert_eq!(
+                partitioning.subpartition_method(),
+                Some(&PartitionMethod::Hash)
+            );
+            let names = partitioning
+                .partitions()
+                .iter()
+                .map(|x| x.name())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["p2023", "pmax"]);
+            assert_eq!(oldest.description(), Some("2024"));
+            assert_eq!(oldest.subpartitions().len(), 2);
+            assert_eq!(partitioning.partitions()[1].comment(), "catch-all");
+            assert_eq!(ids, [1]);
+            assert_eq!(count, Some(1));
+            assert_eq!(plain, None);
+            assert_eq!(missing, None);
+        }
+
+        #[test]
+        fn should_return_exec_result() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl (id INT AUTO_INCREMENT PRIMARY KEY, a INT)",
+            )
+            .unwrap();
+
+            let result = conn
+                .exec_result("INSERT INTO mysql.tbl (a) VALUES (?), (?)", (1, 2))
+                .unwrap();
+            assert_eq!(result.affected_rows(), 2);
+            assert_eq!(result.last_insert_id(), Some(1));
+            assert_eq!(result.info().unwrap().records(), Some(2));
+
+            let result = conn
+                .query_result("SELECT * FROM mysql.tbl; UPDATE mysql.tbl SET a = 2")
+                .unwrap();
+            assert_eq!(result.affected_rows(), 1);
+            assert_eq!(result.last_insert_id(), None);
+            assert_eq!(result.info().unwrap().rows_matched(), Some(2));
+
+            assert!(conn
+                .query_result("SELECT 1; SELECT * FROM no_such_table")
+                .is_err());
+            assert_eq!(conn.query_result("DO 1").unwrap().affected_rows(), 0);
+        }
+
+        #[test]
+        fn should_prepare_in_schema() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE DATABASE IF NOT EXISTS prep_in_a")
+                .unwrap();
+            conn.query_drop("CREATE DATABASE IF NOT EXISTS prep_in_b")
+                .unwrap();
+            conn.query_drop("CREATE TABLE IF NOT EXISTS prep_in_a.t (v TEXT)")
+                .unwrap();
+            conn.query_drop("CREATE TABLE IF NOT EXISTS prep_in_b.t (v TEXT)")
+                .unwrap();
+            conn.query_drop("REPLACE INTO prep_in_a.t VALUES ('a')")
+                .unwrap();
+            conn.query_drop("REPLACE INTO prep_in_b.t VALUES ('b')")
+                .unwrap();
+            conn.query_drop("USE mysql").unwrap();
+
+            let a = conn.prep_in("prep_in_a", "SELECT v FROM t").unwrap();
+            let b = conn.prep_in("prep_in_b", "SELECT v FROM t").unwrap();
+            assert_ne!(a.id(), b.id());
+            assert_eq!(a.schema(), Some("prep_in_a"));
+            assert_eq!(
+                conn.prep_in("prep_in_a", "SELECT v FROM t").unwrap().id(),
+                a.id()
+            );
+
+            // the default database is restored
+            let db: Option<String> = conn.query_first("SELECT DATABASE()").unwrap();
+            assert_eq!(db.as_deref(), Some("mysql"));
+            assert!(conn.prep("SELECT v FROM t").is_err());
+
+            let v: Option<String> = conn.exec_first(&a, ()).unwrap();
+            assert_eq!(v.as_deref(), Some("a"));
+            let v: Option<String> = conn.exec_first(&b, ()).unwrap();
+            assert_eq!(v.as_deref(), Some("b"));
+
+            conn.query_drop("DROP DATABASE prep_in_a").unwrap();
+            conn.query_drop("DROP DATABASE prep_in_b").unwrap();
+        }
+
+        #[test]
+        fn should_iterate_over_result_sets() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT)")
+                .unwrap();
+
+            let mut result = conn
+                .query_iter(
+                    "INSERT INTO mysql.tbl VALUES (1), (2); \
+                     SELECT id FROM mysql.tbl ORDER BY id; \
+                     SELECT * FROM no_such_table; \
+                     SELECT 'foo', 'bar'",
+                )
+                .unwrap();
+            let sets = result.iter_sets().collect::<Vec<_>>();
+            drop(result);
+            assert_eq!(sets.len(), 3);
+
+            let insert = sets[0].as_ref().unwrap();
+            assert!(insert.columns().is_empty());
+            assert_eq!(insert.affected_rows(), 2);
+
+            let select = sets[1].as_ref().unwrap();
+            assert_eq!(select.columns().len(), 1);
+            let ids = select
+                .clone()
+                .into_iter()
+                .map(from_row::<u8>)
+                .collect::<Vec<_>>();
+            assert_eq!(ids, [1, 2]);
+
+            assert!(sets[2].is_err());
+        }
+
+        #[test]
+        fn should_replace_connection_inherited_by_forked_process() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT ?").unwrap();
+            let connection_id = conn.connection_id();
+
+            // pretend that the connection was established by the parent process
+            conn.0.pid = 0;
+            let x: Option<u8> = conn.exec_first(&stmt, (42,)).unwrap();
+            assert_eq!(x, Some(42));
+            assert_ne!(conn.connection_id(), connection_id);
+            assert_eq!(conn.0.pid, std::process::id());
+
+            conn.query_drop("BEGIN").unwrap();
+            conn.0.pid = 0;
+            match conn.query_drop("SELECT 1") {
+                Err(DriverError(crate::DriverError::ForkedInTransaction)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            conn.query_drop("SELECT 1").unwrap();
+        }
+
+        #[test]
+        fn should_call_procedure_with_out_params() {
+            use crate::{CallArg, CompatMode, Row};
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("DROP PROCEDURE IF EXISTS mysql.call_test")
+                .unwrap();
+            conn.query_drop(
+                "CREATE PROCEDURE mysql.call_test(IN a INT, OUT b TEXT, INOUT c INT) \
+                 BEGIN SELECT a; SELECT a + 1, a + 2; SET b = 'foo', c = c * a; END",
+            )
+            .unwrap();
+
+            let args = vec![CallArg::input(3), CallArg::Out, CallArg::in_out(5)];
+            let result = conn.call::<(String, i32)>("mysql.call_test", args).unwrap();
+            assert_eq!(result.sets().len(), 2);
+            assert_eq!(result.sets()[1].columns().len(), 2);
+            assert_eq!(result.out(), Some(&("foo".to_owned(), 15)));
+
+            let args = vec![CallArg::input(3), CallArg::input(4), CallArg::input(5)];
+            assert!(conn.call::<Row>("mysql.call_test", args).is_err());
+
+            let opts = OptsBuilder::from_opts(get_opts()).compat_mode(CompatMode::TiDB);
+            let mut conn = Conn::new(opts).unwrap();
+            match conn.call::<Row>("mysql.call_test", vec![CallArg::Out]) {
+                Err(DriverError(crate::DriverError::OutParamsNotSupported)) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        #[test]
+        fn should_exec_batch_bulk() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl (id INT PRIMARY KEY, name TEXT, n INT)",
+            )
+            .unwrap();
+
+            let rows = (0..250).map(|id| (id, format!("it's {}", id), id));
+            let affected = conn.exec_batch_bulk("mysql.tbl", rows, 100).unwrap();
+            assert_eq!(affected, 250);
+
+            let rows = (240..260).map(|id| (id, "new"));
+            let affected = conn
+                .exec_batch_bulk(
+                    "INSERT INTO mysql.tbl (id, name, n) VALUES (?, ?, 0) \
+                     ON DUPLICATE KEY UPDATE name = VALUES(name)",
+                    rows,
+                    7,
+                )
+                .unwrap();
+            // 2 for each updated row and 1 for each inserted row
+            assert_eq!(affected, 30);
+
+            let count: Option<(u32, String)> = conn
+                .query_first("SELECT COUNT(*), MAX(name) FROM mysql.tbl")
+                .unwrap();
+            assert_eq!(count, Some((260, "new".to_owned())));
+
+            assert!(conn
+                .exec_batch_bulk("UPDATE mysql.tbl SET n = ?", vec![(1,)], 10)
+                .is_err());
+            assert!(conn.exec_batch_bulk("mysql.tbl", vec![(1, 2)], 10).is_err());
+        }
+
+        #[test]
+        fn should_collect_rows_in_parallel() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT)")
+                .unwrap();
+            let rows = (0..1000).map(|id| (id, id.to_string()));
+            conn.exec_batch_bulk("mysql.tbl", rows, 1000).unwrap();
+
+            let expected = (0..1000).map(|id| (id, id.to_string())).collect::<Vec<_>>();
+            let mut result = conn
+                .query_iter("SELECT id, name FROM mysql.tbl ORDER BY id")
+                .unwrap();
+            let rows: Vec<(i32, String)> = result.collect_parallel(3).unwrap();
+            assert_eq!(rows, expected);
+            drop(result);
+
+            let mut result = conn
+                .exec_iter("SELECT id, name FROM mysql.tbl ORDER BY id", ())
+                .unwrap();
+            let rows: Vec<(i32, String)> = result.collect_parallel(1).unwrap();
+            assert_eq!(rows, expected);
+            drop(result);
+
+            let mut result = conn.query_iter("SELECT 'foo'").unwrap();
+            assert!(result.collect_parallel::<i32>(2).is_err());
+        }
+
+        #[test]
+        fn should_exec_batch_in_bulk() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl \
+                 (id INT PRIMARY KEY AUTO_INCREMENT, name TEXT, n INT)",
+            )
+            .unwrap();
+
+            // `NULL`s and mixed parameter types are sent as is or row by row
+            let rows = (0..100).map(|i| match i % 3 {
+                0 => (Value::NULL, Value::from("foo"), Value::from(i)),
+                1 => (Value::NULL, Value::NULL, Value::from(i as u64)),
+                _ => (Value::NULL, Value::from(i.to_string()), Value::from(i)),
+            });
+            conn.exec_batch("INSERT INTO mysql.tbl (id, name, n) VALUES (?, ?, ?)", rows)
+                .unwrap();
+            let stats: Option<(u32, u32, u32)> = conn
+                .query_first("SELECT COUNT(*), COUNT(name), SUM(n) FROM mysql.tbl")
+                .unwrap();
+            assert_eq!(stats, Some((100, 67, 4950)));
+
+            let ids = conn
+                .exec_batch_ids(
+                    "INSERT INTO mysql.tbl (name) VALUES (:name)",
+                    vec![params! { "name" => "bar" }, params! { "name" => "baz" }],
+                )
+                .unwrap();
+            assert_eq!(ids, [Some(101), Some(102)]);
+
+            conn.exec_batch(
+                "UPDATE mysql.tbl SET n = ? WHERE id = ?",
+                vec![(0, 1), (0, 2)],
+            )
+            .unwrap();
+            let sum: Option<u32> = conn.query_first("SELECT SUM(n) FROM mysql.tbl").unwrap();
+            assert_eq!(sum, Some(4949));
+
+            assert!(conn
+                .exec_batch("INSERT INTO mysql.tbl (name) VALUES (?)", vec![(1, 2)])
+                .is_err());
+        }
+
+        #[test]
+        fn should_drain_unread_results() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert!(conn.drain_results().unwrap().is_empty());
+
+            let mut result = conn
+                .query_iter("SELECT 1 UNION SELECT 2; DO 1; SELECT 'a', 'b'")
+                .unwrap();
+            result.next().unwrap().unwrap();
+            std::mem::forget(result);
+
+            let drained = conn.drain_results().unwrap();
+            assert_eq!(drained.len(), 3);
+            assert_eq!(drained[0].columns(), None);
+            assert_eq!(drained[0].rows(), 1);
+            assert_eq!(drained[1].columns().map(<[_]>::len), Some(0));
+            assert_eq!(drained[2].columns().map(<[_]>::len), Some(2));
+            assert_eq!(drained[2].rows(), 1);
+
+            assert!(conn.drain_results().unwrap().is_empty());
+            assert_eq!(conn.query_first("SELECT 42").unwrap(), Some(42));
+        }
+
+        #[test]
+        fn should_stream_LOCAL_INFILE_from_reader() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a TEXT)")
+                .unwrap();
+            let query = "LOAD DATA LOCAL INFILE 'data.txt' INTO TABLE mysql.tbl";
+            match conn.query_with_infile(query, &b"foo\nbar\n"[..]) {
+                Ok(result) => assert_eq!(result.affected_rows(), 2),
+                Err(ref err) if err.to_string().contains("not allowed") => return,
+                Err(err) => panic!("ERROR {}", err),
+            }
+
+            let handler = LocalInfileHandler::from_chunks(vec!["baz\n", "qux\n"]);
+            conn.set_local_infile_handler(Some(handler.with_allowed_files(["data.txt"])));
+            conn.query_drop(query).unwrap();
+            // chunks are already consumed
+            conn.query_drop(query).unwrap();
+            match conn.query_drop("LOAD DATA LOCAL INFILE '/etc/passwd' INTO TABLE mysql.tbl") {
+                Err(DriverError(LocalInfileNotAllowed(name))) => {
+                    assert_eq!(name, "/etc/passwd")
+                }
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            let rows: Vec<String> = conn.query("SELECT a FROM mysql.tbl").unwrap();
+            assert_eq!(rows, ["foo", "bar", "baz", "qux"]);
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1));
+        }
+
+        #[test]
+        fn should_track_session_last_insert_id() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert_eq!(conn.session_last_insert_id(), None);
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id SERIAL, a INT)")
+                .unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.seq (id INT NOT NULL)")
+                .unwrap();
+            conn.query_drop("INSERT INTO mysql.seq VALUES (10)")
+                .unwrap();
+            assert_eq!(conn.last_insert_id_opt(), None);
+
+            conn.query_drop("INSERT INTO mysql.tbl (a) VALUES (1), (2), (3)")
+                .unwrap();
+            assert_eq!(conn.last_insert_id_opt(), Some(1));
+            conn.query_drop("UPDATE mysql.tbl SET a = 0").unwrap();
+            assert_eq!(conn.last_insert_id(), 0);
+            assert_eq!(conn.last_insert_id_opt(), None);
+            assert_eq!(conn.session_last_insert_id(), Some(1));
+
+            conn.query_drop("UPDATE mysql.seq SET id = LAST_INSERT_ID(id + 1)")
+                .unwrap();
+            assert_eq!(conn.session_last_insert_id(), Some(11));
+            let server: Option<u64> = conn.query_first("SELECT LAST_INSERT_ID()").unwrap();
+            assert_eq!(server, Some(11));
+            assert_eq!(conn.session_last_insert_id(), Some(11));
+
+            conn.reset().unwrap();
+            assert_eq!(conn.session_last_insert_id(), None);
+        }
+
+        #[test]
+        fn should_load_data_with_builder() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT PRIMARY KEY, a TEXT, b INT)")
+                .unwrap();
+            let csv = "id,a\r\n1,\"x, y\"\r\n2,z\r\n";
+            let builder = LoadDataBuilder::new("mysql.tbl")
+                .fields_terminated_by(",")
+                .fields_enclosed_by("\"", true)
+                .lines_terminated_by("\r\n")
+                .ignore_lines(1)
+                .columns(["id", "a"])
+                .set("b", "id * 10");
+            match builder.load(&mut conn, csv.as_bytes()) {
+                Ok(result) => assert_eq!(result.affected_rows(), 2),
+                Err(ref err) if err.to_string().contains("not allowed") => return,
+                Err(err) => panic!("ERROR {}", err),
+            }
+
+            let rows = vec![(2, Some("a\tb\\c"), 0), (3, None, 30)];
+            let result = LoadDataBuilder::new("mysql.tbl")
+                .on_duplicate(OnDuplicate::Replace)
+                .load_rows(&mut conn, rows)
+                .unwrap();
+            assert_eq!(result.affected_rows(), 3);
+            assert_eq!(
+                result.info().map(|info| (info.records(), info.deleted())),
+                Some((Some(2), Some(1)))
+            );
+
+            let rows: Vec<(i32, Option<String>, i32)> = conn
+                .query("SELECT id, a, b FROM mysql.tbl ORDER BY id")
+                .unwrap();
+            assert_eq!(
+                rows,
+                [
+                    (1, Some("x, y".into()), 10),
+                    (2, Some("a\tb\\c".into()), 0),
+                    (3, None, 30)
+                ]
+            );
+        }
+
+        #[test]
+        fn should_write_result_set_as_csv_and_ndjson() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl (id INT, name VARCHAR(10), data BLOB, price DECIMAL(5,2))",
+            )
+            .unwrap();
+            conn.query_drop(
+                "INSERT INTO mysql.tbl VALUES (1, 'a;b', x'00ff', 1.50), (2, NULL, NULL, NULL), (3, '', '', 0)",
+            )
+            .unwrap();
+            let query = "SELECT * FROM mysql.tbl ORDER BY id";
+
+            let mut csv = Vec::new();
+            let opts = CsvOpts::new().with_delimiter(b';').with_crlf(true);
+            let mut result = conn.query_iter(query).unwrap();
+            assert_eq!(result.write_csv(&mut csv, opts).unwrap(), 3);
+            drop(result);
+            assert_eq!(
+                csv,
+                &b"id;name;data;price\r\n1;\"a;b\";\0\xff;1.50\r\n2;;;\r\n3;\"\";\"\";0.00\r\n"[..]
+            );
+
+            let mut json = Vec::new();
+            let mut result = conn.query_iter(query).unwrap();
+            assert_eq!(result.write_ndjson(&mut json).unwrap(), 3);
+            drop(result);
+            assert_eq!(
+                String::from_utf8(json).unwrap(),
+                "{\"id\":1,\"name\":\"a;b\",\"data\":\"00FF\",\"price\":1.50}\n\
+                 {\"id\":2,\"name\":null,\"data\":null,\"price\":null}\n\
+                 {\"id\":3,\"name\":\"\",\"data\":\"\",\"price\":0.00}\n"
+            );
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1));
+        }
+
+        #[test]
+        fn should_gate_sql_by_version() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let (major, minor, patch) = conn.server_version();
+            let current = format!("={}.{}.{}", major, minor, patch);
+            assert_eq!(conn.when_version(&current, |_| Ok(42)).unwrap(), Some(42));
+            assert_eq!(
+                conn.when_version(&format!(">{}", major), |_| Ok(42))
+                    .unwrap(),
+                None
+            );
+            match conn.when_version("8.0", |_| Ok(42)) {
+                Err(DriverError(InvalidVersionRequirement(requirement))) => {
+                    assert_eq!(requirement, "8.0")
+                }
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            if conn.supports(Feature::CommonTableExpressions) {
+                let sum: Option<u32> = conn
+                    .query_first(
+                        "WITH RECURSIVE t (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM t WHERE x < 10) \
+                         SELECT SUM(x) FROM t",
+                    )
+                    .unwrap();
+                assert_eq!(sum, Some(55));
+            }
+        }
+
+        #[test]
+        fn should_attach_optimizer_hints() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let supported = matches!(
+                conn.server_flavor(),
+                ServerFlavor::MySql | ServerFlavor::Percona
+            ) && conn.server_version() >= (8, 0, 3);
+            let hints = OptimizerHints::new().with_set_var("unique_checks", "OFF");
+
+            let text: Option<u8> = conn
+                .with_hints(hints.clone())
+                .query_first("SELECT @@unique_checks")
+                .unwrap();
+            let binary: Option<u8> = conn
+                .with_hints(hints)
+                .exec_first("SELECT @@unique_checks", ())
+                .unwrap();
+            // other servers ignore hints
+            let expected = if supported { 0 } else { 1 };
+            assert_eq!(text, Some(expected));
+            assert_eq!(binary, Some(expected));
+
+            // the variable is only set for the hinted statement
+            let checks: Option<u8> = conn.query_first("SELECT @@unique_checks").unwrap();
+            assert_eq!(checks, Some(1));
+        }
+
+        #[test]
+        fn should_estimate_rows() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert!(conn.select_db("mysql"));
+            conn.query_drop("DROP TABLE IF EXISTS estimated").unwrap();
+            conn.query_drop("CREATE TABLE estimated (id INT PRIMARY KEY, a INT)")
+                .unwrap();
+            conn.exec_batch(
+                "INSERT INTO estimated VALUES (?, ?)",
+                (0..100).map(|x| (x, x % 10)),
+            )
+            .unwrap();
+            conn.query_drop("ANALYZE TABLE estimated").unwrap();
+
+            assert!(conn.estimate_rows("estimated").unwrap().is_some());
+            assert_eq!(conn.estimate_rows("missing").unwrap(), None);
+
+            let all = conn
+                .estimate_rows_for("SELECT * FROM estimated")
+                .unwrap()
+                .unwrap();
+            assert!(all > 0);
+            let one = conn
+                .estimate_rows_for("SELECT * FROM estimated WHERE id = 42")
+                .unwrap();
+            assert_eq!(one, Some(1));
+            assert_eq!(conn.estimate_rows_for("SELECT 1").unwrap(), None);
+            conn.query_drop("DROP TABLE estimated").unwrap();
+        }
+
+        #[test]
+        fn should_checksum_exported_table() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl (id INT, name VARCHAR(10), data BLOB, at DATETIME(3))",
+            )
+            .unwrap();
+            conn.query_drop(
+                "INSERT INTO mysql.tbl VALUES (1, 'a#b', x'00ff', '2024-01-02 03:04:05.678'), \
+                 (2, NULL, NULL, NULL), (3, '', '', NULL)",
+            )
+            .unwrap();
+            conn.query_drop("USE mysql").unwrap();
+
+            let mut dump = Vec::new();
+            let exported = conn
+                .export_table_checksummed("tbl", &mut dump, ExportOpts::new().with_hex_blob(true))
+                .unwrap();
+            assert_eq!(exported.rows(), 3);
+            assert_eq!(exported, conn.table_checksum("tbl").unwrap());
+
+            // the checksum of restored data matches
+            conn.query_drop("DELETE FROM tbl").unwrap();
+            assert_eq!(conn.table_checksum("tbl").unwrap().rows(), 0);
+            conn.query_drop(String::from_utf8(dump).unwrap()).unwrap();
+            assert_eq!(conn.table_checksum("tbl").unwrap(), exported);
+
+            conn.query_drop("UPDATE tbl SET name = 'a#c' WHERE id = 1")
+                .unwrap();
+            assert_ne!(conn.table_checksum("tbl").unwrap(), exported);
+        }
+
+        #[test]
+        fn should_deserialize_rows_with_serde() {
+            #[derive(Debug, PartialEq, Deserialize)]
+            struct Item {
+                id: u32,
+                price: String,
+                data: Vec<u8>,
+                meta: Option<Vec<String>>,
+            }
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl \
+                 (id INT, price DECIMAL(10, 2), data BLOB, meta JSON)",
+            )
+            .unwrap();
+            conn.query_drop(
+                r#"INSERT INTO mysql.tbl VALUES (1, 9.99, x'00ff', '["a"]'), (2, 0, '', NULL)"#,
+            )
+            .unwrap();
+
+            let expected = vec![
+                Item {
+                    id: 1,
+                    price: "9.99".into(),
+                    data: vec![0, 0xff],
+                    meta: Some(vec!["a".into()]),
+                },
+                Item {
+                    id: 2,
+                    price: "0.00".into(),
+                    data: vec![],
+                    meta: None,
+                },
+            ];
+            let query = "SELECT * FROM mysql.tbl ORDER BY id";
+            assert_eq!(conn.query_as::<Item, _>(query).unwrap(), expected);
+            assert_eq!(conn.exec_as::<Item, _, _>(query, ()).unwrap(), expected);
+
+            let err = conn.query_as::<Item, _>("SELECT 'x' AS id").unwrap_err();
+            assert!(matches!(err, DriverError(CouldNotDeserializeRow(_))));
+        }
+
+        #[test]
+        fn should_exec_chunked() {
+            use std::ops::ControlFlow;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(id INT NOT NULL PRIMARY KEY)")
+                .unwrap();
+            conn.exec_batch("INSERT INTO mysql.tbl VALUES (?)", (0..25).map(|x| (x,)))
+                .unwrap();
+
+            let mut chunks = Vec::new();
+            let total = conn
+                .exec_chunked("DELETE FROM mysql.tbl WHERE id >= 5 LIMIT ?", 7, |report| {
+                    chunks.push((report.chunk(), report.affected_rows()));
+                    assert_eq!(
+                        report.total_affected_rows(),
+                        chunks.iter().map(|(_, rows)| rows).sum::<u64>()
+                    );
+                    ControlFlow::Continue(())
+                })
+                .unwrap();
+            assert_eq!(total, 20);
+            assert_eq!(chunks, vec![(1, 7), (2, 7), (3, 6)]);
+
+            let total = conn
+                .exec_chunked("DELETE FROM mysql.tbl LIMIT ?", 2, |_| {
+                    ControlFlow::Break(())
+                })
+                .unwrap();
+            assert_eq!(total, 2);
+            let count: Option<u32> = conn.query_first("SELECT COUNT(*) FROM mysql.tbl").unwrap();
+            assert_eq!(count, Some(3));
+        }
+
+        #[test]
+        fn should_track_stmt_latency() {
+            let opts = OptsBuilder::from_opts(get_opts()).track_stmt_latency(true);
+
+            let mut conn = Conn::new(opts.clone()).unwrap();
+            for i in 0..10 {
+                conn.exec_drop("SELECT ?", (i,)).unwrap();
+            }
+            conn.exec_drop("SELECT :a + SLEEP(0.05)", params! { "a" => 1 })
+                .unwrap();
+            conn.query_drop("SELECT 1").unwrap();
+
+            let stats = conn.stmt_latency_stats();
+            assert_eq!(stats.len(), 2);
+            assert_eq!(stats["SELECT ?"].count(), 10);
+            let slow = stats["SELECT ? + SLEEP(0.05)"];
+            assert_eq!(slow.count(), 1);
+            assert!(slow.p50() >= Duration::from_millis(45));
+            assert!(stats["SELECT ?"].p95() < slow.p50());
+
+            let pool = Pool::new(opts).unwrap();
+            for _ in 0..3 {
+                pool.get_conn()
+                    .unwrap()
+                    .exec_drop("SELECT ?", (1,))
+                    .unwrap();
+            }
+            let mut conns = (0..2).map(|_| pool.get_conn().unwrap()).collect::<Vec<_>>();
+            for conn in conns.iter_mut() {
+                conn.exec_drop("SELECT ?", (1,)).unwrap();
+            }
+            assert_eq!(pool.stmt_latency_stats()["SELECT ?"].count(), 5);
+            assert_eq!(conns[0].stmt_latency_stats()["SELECT ?"].count(), 5);
+
+            let conn = Conn::new(get_opts()).unwrap();
+            assert!(conn.stmt_latency_stats().is_empty());
+        }
+
+        #[test]
+        fn should_fold_raw_column_bytes() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let (total_len, nulls) = conn
+                .query_fold_bytes(
+                    "SELECT 1, 'foo' UNION ALL SELECT 2, NULL UNION ALL SELECT 3, 'barbaz'",
+                    1,
+                    (0, 0),
+                    |(len, nulls), cell| match cell {
+                        Some(bytes) => (len + bytes.len(), nulls),
+                        None => (len, nulls + 1),
+                    },
+                )
+                .unwrap();
+            assert_eq!((total_len, nulls), (9, 1));
+
+            conn.query_fold_bytes("SELECT 1", 1, (), |_, _| ())
+                .unwrap_err();
+            assert!(conn.ping());
+        }
+
+        #[test]
+        fn should_handle_savepoints_and_nested_transactions() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a INT)")
+                .unwrap();
+
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            tx.query_drop("INSERT INTO mysql.tbl(a) VALUES (1)")
+                .unwrap();
+            tx.savepoint("first").unwrap();
+            tx.query_drop("INSERT INTO mysql.tbl(a) VALUES (2)")
+                .unwrap();
+            tx.rollback_to("first").unwrap();
+            tx.release("first").unwrap();
+            tx.release("first").unwrap_err();
+
+            let mut nested = tx.start_transaction().unwrap();
+            nested
+                .query_drop("INSERT INTO mysql.tbl(a) VALUES (3)")
+                .unwrap();
+            let mut nested_2 = nested.start_transaction().unwrap();
+            nested_2
+                .query_drop("INSERT INTO mysql.tbl(a) VALUES (4)")
+                .unwrap();
+            drop(nested_2);
+            nested.commit().unwrap();
+
+            let mut nested = tx.start_transaction().unwrap();
+            nested
+                .query_drop("INSERT INTO mysql.tbl(a) VALUES (5)")
                 .unwrap();
+            nested.rollback().unwrap();
+
             tx.commit().unwrap();
-            assert_eq!(
-                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
-                    .unwrap()
-                    .next()
-                    .unwrap()
-                    .unwrap()
-                    .unwrap(),
-                vec![Bytes(b"4".to_vec())]
-            );
-            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
-            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (5,))
+
+            let values: Vec<i32> = conn.query("SELECT a FROM mysql.tbl ORDER BY a").unwrap();
+            assert_eq!(values, vec![1, 3]);
+        }
+
+        #[test]
+        fn should_run_closure_within_transaction() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a INT)")
                 .unwrap();
-            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (6,))
+
+            let count = conn
+                .with_transaction(TxOpts::default(), |tx| {
+                    tx.query_drop("INSERT INTO mysql.tbl(a) VALUES (1), (2)")?;
+                    tx.query_first::<usize, _>("SELECT COUNT(*) FROM mysql.tbl")
+                })
                 .unwrap();
-            drop(tx);
+            assert_eq!(count, Some(2));
+
+            conn.with_transaction(TxOpts::default(), |tx| {
+                tx.query_drop("INSERT INTO mysql.tbl(a) VALUES (3)")?;
+                tx.query_drop("SELECT * FROM mysql.no_such_table")
+            })
+            .unwrap_err();
             assert_eq!(
-                conn.query_first("SELECT COUNT(a) from mysql.tbl").unwrap(),
-                Some(4_usize),
+                conn.query_first("SELECT COUNT(*) FROM mysql.tbl").unwrap(),
+                Some(2_usize),
+            );
+
+            let mut attempts = 0;
+            let tx_opts = TxOpts::default()
+                .set_max_retries(2)
+                .set_retry_backoff(Duration::from_millis(1));
+            conn.with_transaction(tx_opts, |tx| {
+                attempts += 1;
+                tx.query_drop("INSERT INTO mysql.tbl(a) VALUES (4)")?;
+                if attempts < 3 {
+                    return Err(crate::Error::MySqlError(crate::MySqlError {
+                        state: "40001".into(),
+                        message: "Deadlock found when trying to get lock".into(),
+                        code: crate::ServerError::ER_LOCK_DEADLOCK as u16,
+                    }));
+                }
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(attempts, 3);
+            assert_eq!(
+                conn.query_first("SELECT COUNT(*) FROM mysql.tbl").unwrap(),
+                Some(3_usize),
             );
         }
         #[test]
@@ -2163,6 +5344,253 @@ mod test {
             }
         }
 
+        #[test]
+        fn should_return_raw_row_packets() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let query = "SELECT 1, 'foo', NULL UNION ALL SELECT 2, 'bar', 1.5";
+            let expected: Vec<crate::Row> = conn.exec(query, ()).unwrap();
+
+            let mut result = conn.exec_iter(query, ()).unwrap();
+            let mut rows = Vec::new();
+            while let Some(row) = result.next_raw_row() {
+                let row = row.unwrap();
+                assert_eq!(row.columns().len(), 3);
+                rows.push(row.decode().unwrap());
+            }
+            assert!(result.next_raw_row().is_none());
+            drop(result);
+            assert_eq!(
+                rows.into_iter().map(crate::Row::unwrap).collect::<Vec<_>>(),
+                expected
+                    .into_iter()
+                    .map(crate::Row::unwrap)
+                    .collect::<Vec<_>>()
+            );
+
+            // sets without rows yield nothing, next sets are still available
+            let mut result = conn.query_iter("DO 1; SELECT 42").unwrap();
+            assert!(result.next_raw_row().is_none());
+            let mut set = result.iter().unwrap();
+            assert_eq!(set.next().unwrap().unwrap().unwrap(), [Int(42)]);
+        }
+
+        #[test]
+        fn should_refresh_statement_metadata() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl \
+                 (id INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY)",
+            )
+            .unwrap();
+            let mut stmt = conn.prep("SELECT * FROM mysql.tbl").unwrap();
+            let copy = stmt.clone();
+            assert_eq!(stmt.num_columns(), 1);
+
+            conn.query_drop(
+                "ALTER TABLE mysql.tbl ADD COLUMN name VARCHAR(10) CHARACTER SET utf8mb4",
+            )
+            .unwrap();
+            stmt.refresh(&mut conn).unwrap();
+            assert_ne!(stmt.id(), copy.id());
+            assert_eq!(stmt.num_columns(), 2);
+
+            let columns = stmt.columns();
+            assert!(columns[0].is_auto_increment());
+            assert!(columns[0].is_unsigned());
+            assert!(!columns[0].is_nullable());
+            assert!(columns[1].is_nullable());
+            assert_eq!(columns[1].charset_name(), Some("utf8mb4"));
+            assert_eq!(columns[1].display_width(), 10);
+
+            // the old statement is closed, so it's prepared again
+            conn.exec_drop(&copy, ()).unwrap();
+        }
+
+        #[test]
+        fn should_decode_float_columns() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (f FLOAT)")
+                .unwrap();
+            conn.exec_drop("INSERT INTO mysql.tbl (f) VALUES (?)", (1.1_f32,))
+                .unwrap();
+
+            // `f32` parameters are bound as `FLOAT`
+            let count: Option<u32> = conn
+                .exec_first("SELECT COUNT(*) FROM mysql.tbl WHERE f = ?", (1.1_f32,))
+                .unwrap();
+            assert_eq!(count, Some(1));
+
+            let text: Option<f64> = conn.query_first("SELECT f FROM mysql.tbl").unwrap();
+            let binary: Option<f64> = conn.exec_first("SELECT f FROM mysql.tbl", ()).unwrap();
+            assert_eq!(text, Some(1.1));
+            assert_eq!(binary, Some(f64::from(1.1_f32)));
+
+            let opts = OptsBuilder::from_opts(get_opts()).float_decoding(FloatDecoding::Decimal);
+            let mut conn = Conn::new(opts).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (f FLOAT)")
+                .unwrap();
+            conn.exec_drop("INSERT INTO mysql.tbl (f) VALUES (?)", (1.1_f32,))
+                .unwrap();
+            let binary: Option<(f64, f32)> =
+                conn.exec_first("SELECT f, f FROM mysql.tbl", ()).unwrap();
+            assert_eq!(binary, Some((1.1, 1.1)));
+        }
+
+        #[test]
+        fn should_decode_invalid_utf8_according_to_policy() {
+            // latin1 values are sent as is, i.e. they aren't valid UTF-8
+            let query = "SELECT CONVERT(X'61FF62' USING latin1), X'FF'";
+            for (policy, expected) in [
+                (Utf8Decoding::Passthrough, None),
+                (Utf8Decoding::Lossy, Some("a\u{fffd}b")),
+                (Utf8Decoding::Strict, None),
+            ] {
+                let opts = OptsBuilder::from_opts(get_opts()).utf8_decoding(policy);
+                let mut conn = Conn::new(opts).unwrap();
+                conn.query_drop("SET character_set_results = NULL").unwrap();
+
+                let text = conn.query_first::<crate::Row, _>(query);
+                let binary = conn.exec_first::<crate::Row, _, _>(query, ());
+                for result in [text, binary] {
+                    match policy {
+                        Utf8Decoding::Strict => assert!(matches!(
+                            result,
+                            Err(DriverError(crate::DriverError::InvalidUtf8(_)))
+                        )),
+                        _ => {
+                            let row = result.unwrap().unwrap();
+                            let value = row.get_opt::<String, _>(0).unwrap().ok();
+                            assert_eq!(value.as_deref(), expected);
+                            assert_eq!(row.get::<Vec<u8>, _>(1), Some(vec![0xff]));
+                        }
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn should_join_json_table() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            if !conn.supports(Feature::JsonTable) {
+                return;
+            }
+            let rows = (0..2000).map(|id| {
+                (
+                    id,
+                    format!("név {}", id),
+                    Value::Date(2020, 1, 2, 3, 4, 5, 6),
+                )
+            });
+            let table = JsonTable::new("t")
+                .with_column("id", "INT")
+                .with_column("name", "VARCHAR(255)")
+                .with_column("at", "DATETIME(6)")
+                .with_rows(rows);
+            let stats: Option<(u64, u64, String, Value)> = conn
+                .exec_first(
+                    format!(
+                        "SELECT COUNT(*), SUM(id), MAX(name), MIN(at) FROM {}",
+                        table.sql()
+                    ),
+                    (table.param().unwrap(),),
+                )
+                .unwrap();
+            assert_eq!(
+                stats,
+                Some((
+                    2000,
+                    1999 * 1000,
+                    "név 999".to_owned(),
+                    Date(2020, 1, 2, 3, 4, 5, 6)
+                ))
+            );
+        }
+
+        #[test]
+        fn should_intercept_queries() {
+            use crate::{InterceptedQuery, Interception, QueryInterceptor, QueryOutcome};
+            use std::sync::{Arc, Mutex};
+
+            #[derive(Default)]
+            struct Recorder(Mutex<Vec<(String, Option<u32>, bool)>>);
+
+            impl QueryInterceptor for Recorder {
+                fn before(&self, query: &InterceptedQuery<'_>) -> Interception {
+                    if query.sql().starts_with("DO") {
+                        Interception::Veto("no DO".into())
+                    } else if query.sql().starts_with("SELECT 1") {
+                        Interception::Rewrite(query.sql().replace('1', "2"))
+                    } else {
+                        Interception::Proceed
+                    }
+                }
+
+                fn after(&self, outcome: &QueryOutcome<'_>) {
+                    let query = outcome.query();
+                    assert_eq!(query.params().is_some(), query.stmt_id().is_some());
+                    self.0.lock().unwrap().push((
+                        query.sql().to_owned(),
+                        query.stmt_id(),
+                        outcome.is_ok(),
+                    ));
+                }
+            }
+
+            let recorder = Arc::new(Recorder::default());
+            let opts = OptsBuilder::from_opts(get_opts()).query_interceptor(Some(recorder.clone()));
+            let mut conn = Conn::new(opts).unwrap();
+            recorder.0.lock().unwrap().clear();
+
+            let result: Option<u8> = conn.query_first("SELECT 1").unwrap();
+            assert_eq!(result, Some(2));
+            let result: Option<u8> = conn.exec_first("SELECT 1 + ?", (1,)).unwrap();
+            assert_eq!(result, Some(3));
+            assert!(matches!(
+                conn.query_drop("DO 1"),
+                Err(DriverError(crate::DriverError::QueryVetoed(ref reason))) if reason == "no DO"
+            ));
+            assert!(conn.query_drop("SELECT * FROM unknown_table").is_err());
+
+            let events = recorder.0.lock().unwrap().clone();
+            assert_eq!(events.len(), 3, "{:?}", events);
+            assert_eq!(events[0], ("SELECT 2".to_owned(), None, true));
+            assert_eq!(events[1].0, "SELECT 2 + ?");
+            assert!(events[1].1.is_some() && events[1].2);
+            assert_eq!(
+                events[2],
+                ("SELECT * FROM unknown_table".to_owned(), None, false)
+            );
+        }
+
+        #[test]
+        fn should_bind_missing_named_parameters_as_null() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT :a, :b, :a, :c").unwrap();
+            let result: Option<(u8, Option<u8>, u8, Option<u8>)> = conn
+                .exec_first(MissingAsNull(&stmt), params! {"a" => 1, "c" => 3})
+                .unwrap();
+            assert_eq!(result, Some((1, None, 1, Some(3))));
+
+            // the statement itself is left intact
+            let result = conn.exec_drop(&stmt, params! {"a" => 1, "c" => 3});
+            assert!(matches!(
+                result,
+                Err(DriverError(MissingNamedParameter(ref x))) if x == "b"
+            ));
+
+            // works for queries and batches as well
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (a INT, b TEXT)")
+                .unwrap();
+            conn.exec_batch(
+                MissingAsNull("INSERT INTO mysql.tbl (a, b) VALUES (:a, :b)"),
+                vec![params! {"a" => 1}, params! {"b" => "foo"}],
+            )
+            .unwrap();
+            let rows: Vec<(Option<u8>, Option<String>)> =
+                conn.query("SELECT a, b FROM mysql.tbl ORDER BY a").unwrap();
+            assert_eq!(rows, vec![(None, Some("foo".into())), (Some(1), None)]);
+        }
+
         #[test]
         fn should_return_error_on_named_params_for_positional_statement() {
             let mut conn = Conn::new(get_opts()).unwrap();
@@ -2193,6 +5621,62 @@ mod test {
             }
         }
 
+        #[test]
+        fn should_handle_handshake_timeout() {
+            use crate::error::{DriverError::HandshakeTimeout, Error::DriverError};
+            use std::{
+                net::TcpListener,
+                time::{Duration, Instant},
+            };
+
+            // accepts connections but never sends the greeting
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let opts = OptsBuilder::new()
+                .ip_or_hostname(Some("127.0.0.1"))
+                .tcp_port(listener.local_addr().unwrap().port())
+                .prefer_socket(false)
+                .handshake_timeout(Some(Duration::from_millis(500)));
+
+            let started = Instant::now();
+            match Conn::new(opts).unwrap_err() {
+                DriverError(HandshakeTimeout) => {}
+                err => panic!("Unexpected error: {}", err),
+            }
+            assert!(started.elapsed() < Duration::from_secs(5));
+        }
+
+        #[test]
+        fn should_record_connection_attempts() {
+            use crate::{error::DriverError::CouldNotConnect, ConnectEvent};
+            use std::net::{SocketAddr, TcpListener};
+
+            // nobody listens on this port once the listener is dropped
+            let addr = TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap();
+            let opts = OptsBuilder::new()
+                .ip_or_hostname(Some("127.0.0.1"))
+                .tcp_port(addr.port())
+                .prefer_socket(false);
+
+            // `Conn::new` returns the error as is
+            assert!(matches!(
+                Conn::new(opts.clone()).unwrap_err(),
+                DriverError(CouldNotConnect(_))
+            ));
+            let err = Conn::new_audited(opts).unwrap_err();
+            assert!(matches!(err.error(), DriverError(CouldNotConnect(_))));
+            assert_eq!(err.events().len(), 2);
+            assert_eq!(err.events()[0], ConnectEvent::Resolved(vec![addr]));
+            match &err.events()[1] {
+                ConnectEvent::ConnectFailed(failed, _) => {
+                    assert_eq!(failed.parse::<SocketAddr>().unwrap(), addr)
+                }
+                event => panic!("Unexpected event: {}", event),
+            }
+        }
+
         #[test]
         fn should_set_additional_capabilities() {
             use crate::consts::CapabilityFlags;
@@ -2211,6 +5695,191 @@ mod test {
             assert_eq!(result.affected_rows(), 1);
         }
 
+        #[test]
+        fn should_compare_result_sets() {
+            use crate::{
+                result_diff::{assert_result_eq, result_diff, ResultTable},
+                Row,
+            };
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT, at DATETIME(6), d TIME)",
+            )
+            .unwrap();
+            conn.query_drop(
+                "INSERT INTO mysql.tbl VALUES (1, 'foo', '2020-01-02 03:04:05.5', '-27:00:01'),
+                 (2, NULL, '2020-01-02 00:00:00', '00:00:00')",
+            )
+            .unwrap();
+            let query = "SELECT * FROM mysql.tbl ORDER BY id";
+            let text: Vec<Row> = conn.query(query).unwrap();
+            let binary: Vec<Row> = conn.exec(query, ()).unwrap();
+            assert_result_eq(&*text, binary);
+            assert_result_eq(
+                text.clone(),
+                ResultTable::new(["id", "name", "at", "d"]).with_rows(vec![
+                    (
+                        Value::Int(1),
+                        Value::from("foo"),
+                        Value::Date(2020, 1, 2, 3, 4, 5, 500_000),
+                        Value::Time(true, 1, 3, 0, 1, 0),
+                    ),
+                    (
+                        Value::Int(2),
+                        Value::NULL,
+                        Value::Date(2020, 1, 2, 0, 0, 0, 0),
+                        Value::Time(false, 0, 0, 0, 0, 0),
+                    ),
+                ]),
+            );
+
+            let other: Vec<Row> = conn
+                .query(
+                    "SELECT id, CAST(name AS CHAR(10)) AS name, at, d FROM mysql.tbl ORDER BY id",
+                )
+                .unwrap();
+            assert!(result_diff(text, other)
+                .unwrap()
+                .starts_with("column 1 (`name`): type MYSQL_TYPE_BLOB != MYSQL_TYPE_VAR_STRING"));
+        }
+
+        #[test]
+        fn should_work_with_wire_log() {
+            for wire_log in [crate::WireLog::Headers, crate::WireLog::Hexdump] {
+                let opts = OptsBuilder::from_opts(get_opts()).wire_log(wire_log);
+                let mut conn = Conn::new(opts).unwrap();
+                assert!(conn.0.wire_log.is_enabled());
+                let big = "x".repeat(0x10000);
+                let echoed: Option<String> = conn.exec_first("SELECT ?", (&big,)).unwrap();
+                assert_eq!(echoed.as_deref(), Some(&*big));
+                conn.reset().unwrap();
+                assert!(conn.ping());
+            }
+        }
+
+        #[test]
+        fn should_set_semantic_capabilities() {
+            use crate::consts::CapabilityFlags;
+
+            let opts = OptsBuilder::from_opts(get_opts())
+                .ignore_space(true)
+                .interactive(true);
+            let mut conn = Conn::new(opts).unwrap();
+            assert!(conn.capabilities().contains(
+                CapabilityFlags::CLIENT_IGNORE_SPACE | CapabilityFlags::CLIENT_INTERACTIVE
+            ));
+            let (sql_mode, wait_timeout, interactive_timeout): (String, u64, u64) = conn
+                .query_first(
+                    "SELECT @@session.sql_mode, @@session.wait_timeout, @@global.interactive_timeout",
+                )
+                .unwrap()
+                .unwrap();
+            assert!(sql_mode.contains("IGNORE_SPACE"));
+            assert_eq!(wait_timeout, interactive_timeout);
+            assert_eq!(
+                conn.query_first::<u64, _>("SELECT COUNT (*)").unwrap(),
+                Some(1)
+            );
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert!(!conn
+                .capabilities()
+                .contains(CapabilityFlags::CLIENT_IGNORE_SPACE));
+            let sql_mode: String = conn
+                .query_first("SELECT @@session.sql_mode")
+                .unwrap()
+                .unwrap();
+            assert!(!sql_mode.contains("IGNORE_SPACE"));
+        }
+
+        #[test]
+        fn should_export_and_prewarm_stmt_cache() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.exec_drop("SELECT ?", (1,)).unwrap();
+            conn.exec_drop("SELECT 1 + ?", (1,)).unwrap();
+            conn.exec_drop("SELECT 1 + ?", (2,)).unwrap();
+            conn.exec_drop("SELECT 2 + ?", (1,)).unwrap();
+            let snapshot = conn.export_stmt_cache();
+            assert_eq!(snapshot, ["SELECT 1 + ?", "SELECT 2 + ?", "SELECT ?"]);
+
+            let mut new_conn = Conn::new(get_opts()).unwrap();
+            new_conn.exec_drop("SELECT ?", (1,)).unwrap();
+            let prepared = new_conn
+                .prewarm_stmts(
+                    snapshot
+                        .iter()
+                        .map(String::as_str)
+                        .chain(["SELECT * FROM no_such_table"]),
+                )
+                .unwrap();
+            assert_eq!(prepared, 2);
+            assert!(new_conn.has_stmt(b"SELECT 2 + ?"));
+        }
+
+        #[test]
+        fn should_duplicate_connection() {
+            let opts = OptsBuilder::from_opts(get_opts()).init(vec!["SET @dup = 42"]);
+            let mut conn = Conn::new(opts).unwrap();
+            conn.query_drop("SET @dup = 1").unwrap();
+
+            let mut sibling = conn.duplicate().unwrap();
+            assert_ne!(sibling.connection_id(), conn.connection_id());
+            assert_eq!(sibling.opts(), conn.opts());
+            // init commands are executed, session state is not copied
+            assert_eq!(
+                sibling.query_first::<u8, _>("SELECT @dup").unwrap(),
+                Some(42)
+            );
+            assert_eq!(conn.query_first::<u8, _>("SELECT @dup").unwrap(), Some(1));
+        }
+
+        #[test]
+        fn should_interpret_upsert_outcome() {
+            for found_rows in [false, true] {
+                let opts = OptsBuilder::from_opts(get_opts()).found_rows(found_rows);
+                let mut conn = Conn::new(opts).unwrap();
+                assert_eq!(
+                    conn.capabilities()
+                        .contains(crate::consts::CapabilityFlags::CLIENT_FOUND_ROWS),
+                    found_rows
+                );
+                conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT PRIMARY KEY, b TEXT)")
+                    .unwrap();
+
+                let upsert =
+                    "INSERT INTO mysql.tbl VALUES (?, ?) ON DUPLICATE KEY UPDATE b = VALUES(b)";
+                conn.exec_drop(upsert, (1, "foo")).unwrap();
+                let outcome = conn.upsert_outcome().unwrap();
+                assert_eq!(outcome.is_ambiguous(), found_rows);
+                assert_eq!(outcome.inserted(), u64::from(!found_rows));
+                conn.exec_drop(upsert, (1, "bar")).unwrap();
+                assert_eq!(conn.upsert_outcome().unwrap().updated(), 1);
+                conn.exec_drop(upsert, (1, "bar")).unwrap();
+                let outcome = conn.upsert_outcome().unwrap();
+                assert_eq!(outcome.unchanged(), u64::from(!found_rows));
+                assert_eq!(outcome.inserted_or_unchanged(), u64::from(found_rows));
+
+                let summary = conn
+                    .query_result(
+                        "INSERT INTO mysql.tbl VALUES (1, 'bar'), (2, 'baz'), (3, 'qux')
+                         ON DUPLICATE KEY UPDATE b = VALUES(b)",
+                    )
+                    .unwrap();
+                conn.query_drop("INSERT INTO mysql.tbl VALUES (2, 'quux'), (4, 'a') ON DUPLICATE KEY UPDATE b = VALUES(b)").unwrap();
+                let outcome = summary.upsert_outcome().unwrap();
+                assert_eq!(
+                    (outcome.inserted(), outcome.updated(), outcome.unchanged()),
+                    (2, 0, 1)
+                );
+                let outcome = conn.upsert_outcome().unwrap();
+                assert_eq!(
+                    (outcome.inserted(), outcome.updated(), outcome.unchanged()),
+                    (1, 1, 0)
+                );
+            }
+        }
+
         #[test]
         fn should_bind_before_connect() {
             let port = 28000 + (rand::random::<u16>() % 2000);
@@ -2300,6 +5969,159 @@ mod test {
             assert_eq!(order, &[b"DO 3", b"DO 5", b"DO 6"]);
         }
 
+        #[test]
+        fn should_respect_per_call_cache_policy() {
+            let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(2);
+            let mut conn = Conn::new(opts).unwrap();
+
+            conn.exec_drop("DO 1", ()).unwrap();
+            conn.exec_drop("DO 2", ()).unwrap();
+            conn.exec_drop(CachePolicy::NoCache.apply("DO 3"), ())
+                .unwrap();
+            // uncached statement is reused while it's alive
+            conn.exec_drop(CachePolicy::NoCache.apply("DO 3"), ())
+                .unwrap();
+            conn.exec_drop(CachePolicy::NoCache.apply("DO 4"), ())
+                .unwrap();
+
+            let status: (String, usize) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_close'")
+                .unwrap()
+                .unwrap();
+            assert_eq!(status.1, 1);
+            assert!(conn.0.stmt_cache.contains_query(&b"DO 1"[..]));
+            assert!(conn.0.stmt_cache.contains_query(&b"DO 2"[..]));
+            assert!(!conn.0.stmt_cache.contains_query(&b"DO 4"[..]));
+
+            let ttl = CachePolicy::Ttl(Duration::from_millis(50));
+            conn.exec_drop(ttl.apply("DO 5"), ()).unwrap();
+            assert!(conn.0.stmt_cache.contains_query(&b"DO 5"[..]));
+            std::thread::sleep(Duration::from_millis(100));
+            conn.exec_drop("DO 1", ()).unwrap();
+            assert!(!conn.0.stmt_cache.contains_query(&b"DO 5"[..]));
+
+            let status: (String, usize) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_close'")
+                .unwrap()
+                .unwrap();
+            // `DO 1` is evicted by `DO 5`, then `DO 5` expires
+            assert_eq!(status.1, 3);
+        }
+
+        #[test]
+        fn should_introspect_and_evict_cached_statements() {
+            let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(3);
+            let mut conn = Conn::new(opts).unwrap();
+
+            conn.exec_drop("DO 1", ()).unwrap();
+            conn.exec_drop("DO :foo", params! { "foo" => 2 }).unwrap();
+            conn.exec_drop("DO 1", ()).unwrap();
+            conn.exec_drop("DO 1", ()).unwrap();
+            conn.prep_in("mysql", "DO 1").unwrap();
+
+            let cached = conn.cached_statements();
+            let described = cached
+                .iter()
+                .map(|stmt| (stmt.query().into_owned(), stmt.schema(), stmt.hits()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                described,
+                vec![
+                    ("DO 1".to_string(), Some("mysql"), 0),
+                    ("DO 1".to_string(), None, 2),
+                    ("DO ?".to_string(), None, 0),
+                ]
+            );
+            assert!(cached.iter().all(|stmt| stmt.expires_at().is_none()));
+
+            // both the pinned and the unpinned statements are evicted
+            assert!(conn.evict_stmt("DO 1").unwrap());
+            assert!(!conn.evict_stmt("DO 1").unwrap());
+            assert_eq!(conn.cached_statements().len(), 1);
+            conn.exec_drop("DO 1", ()).unwrap();
+
+            conn.set_stmt_cache_size(1).unwrap();
+            assert_eq!(conn.0.opts.get_stmt_cache_size(), 1);
+            let cached = conn.cached_statements();
+            assert_eq!(cached.len(), 1);
+            assert_eq!(cached[0].query_bytes(), b"DO 1");
+
+            conn.clear_stmt_cache().unwrap();
+            assert!(conn.cached_statements().is_empty());
+            conn.exec_drop("DO 2", ()).unwrap();
+            conn.exec_drop("DO 3", ()).unwrap();
+            assert_eq!(conn.cached_statements().len(), 1);
+
+            let status: (String, usize) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_close'")
+                .unwrap()
+                .unwrap();
+            // two evicted, one resized away, one cleared and one evicted by `DO 3`
+            assert_eq!(status.1, 5);
+        }
+
+        #[test]
+        fn should_execute_interpolated_queries() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let values = (
+                "it's a \\ \"test\"\n",
+                "ünïcödé '\\",
+                vec![0_u8, 0xff, b'\''],
+                -1.5e-7_f64,
+                Value::NULL,
+            );
+            for sql_mode in ["", "NO_BACKSLASH_ESCAPES"] {
+                conn.query_drop(format!("SET SESSION sql_mode = '{}'", sql_mode))
+                    .unwrap();
+                conn.query_interpolated(
+                    "SET @a = ?, @b = ?, @c = ?, @d = ?, @e = ?",
+                    values.clone(),
+                )
+                .unwrap();
+                let row: (String, String, Vec<u8>, f64, Option<u8>) = conn
+                    .query_first("SELECT @a, @b, @c, @d, @e")
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(
+                    row,
+                    (
+                        values.0.to_string(),
+                        values.1.to_string(),
+                        values.2.clone(),
+                        values.3,
+                        None
+                    )
+                );
+            }
+        }
+
+        #[test]
+        fn should_escape_according_to_sql_mode() {
+            use crate::escape::{escape_like_pattern, escape_string, quote_identifier};
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let value = "it's a \\ \"100%\"\n";
+            for sql_mode in ["", "ANSI_QUOTES,NO_BACKSLASH_ESCAPES"] {
+                conn.query_drop(format!("SET SESSION sql_mode = '{}'", sql_mode))
+                    .unwrap();
+                let mode = conn.escape_mode().unwrap();
+                assert_eq!(mode.ansi_quotes(), !sql_mode.is_empty());
+                assert_eq!(mode.no_backslash_escapes(), !sql_mode.is_empty());
+
+                let query = format!(
+                    "SELECT {value} AS {column}, {value} LIKE {pattern} {escape}, 'x' LIKE {pattern}",
+                    value = escape_string(value, mode),
+                    column = quote_identifier("a\"b`c", mode),
+                    pattern = escape_string(&escape_like_pattern(value), mode),
+                    escape = mode.like_escape_clause(),
+                );
+                let mut result = conn.query_iter(query).unwrap();
+                assert_eq!(result.columns().as_ref()[0].name_str(), "a\"b`c");
+                let row = from_row::<(String, bool, bool)>(result.next().unwrap().unwrap());
+                assert_eq!(row, (value.to_string(), true, false));
+            }
+        }
+
         #[test]
         fn should_handle_json_columns() {
             use crate::{Deserialized, Serialized};
@@ -0,0 +1,436 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{DriverError, Error, Opts, OptsBuilder, Result, SslOpts};
+
+/// Maximum depth of nested `!include` and `!includedir` directives.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// Option of an option file (name is normalized, i.e. `_` is replaced with `-`
+/// and the `loose-` prefix is removed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OptionValue {
+    name: String,
+    value: Option<String>,
+}
+
+/// Reads options of the `client` group and the given group of a MySQL option file
+/// (see `Opts::from_mysql_config`).
+pub(crate) fn read(path: &Path, group: &str) -> Result<Opts> {
+    let mut options = Vec::new();
+    read_file(path, &["client", group], 0, &mut options)?;
+    apply(OptsBuilder::new(), &options).map(Into::into)
+}
+
+fn invalid(path: &Path, line: usize, reason: impl std::fmt::Display) -> Error {
+    DriverError::InvalidOptionFile(format!("{}:{}: {}", path.display(), line, reason)).into()
+}
+
+fn read_file(
+    path: &Path,
+    groups: &[&str],
+    depth: usize,
+    options: &mut Vec<OptionValue>,
+) -> Result<()> {
+    let contents = fs::read_to_string(path)?;
+    parse(path, &contents, groups, depth, options)
+}
+
+/// Parses contents of the option file at `path`. Options of the given groups are appended
+/// to `options` in the order they appear (so that the last one wins).
+fn parse(
+    path: &Path,
+    contents: &str,
+    groups: &[&str],
+    depth: usize,
+    options: &mut Vec<OptionValue>,
+) -> Result<()> {
+    let mut in_group = false;
+    for (i, line) in contents.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(directive) = line.strip_prefix('!') {
+            let (name, arg) = directive
+                .split_once(char::is_whitespace)
+                .unwrap_or((directive, ""));
+            let target = path
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(arg.trim());
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(invalid(path, line_no, "too many nested includes"));
+            }
+            match name {
+                "include" => read_file(&target, groups, depth + 1, options)?,
+                "includedir" => {
+                    let mut files = fs::read_dir(&target)?
+                        .map(|entry| entry.map(|entry| entry.path()))
+                        .collect::<std::io::Result<Vec<PathBuf>>>()?;
+                    files.retain(|file| matches!(file.extension(), Some(ext) if ext == "cnf"));
+                    files.sort();
+                    for file in files {
+                        read_file(&file, groups, depth + 1, options)?;
+                    }
+                }
+                _ => {
+                    return Err(invalid(
+                        path,
+                        line_no,
+                        format!("unknown directive `{}'", name),
+                    ))
+                }
+            }
+            continue;
+        }
+
+        if let Some(group) = line.strip_prefix('[') {
+            let group = group
+                .strip_suffix(']')
+                .ok_or_else(|| invalid(path, line_no, "unterminated group name"))?;
+            in_group = groups.contains(&group.trim());
+            continue;
+        }
+
+        if !in_group {
+            continue;
+        }
+
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (line, None),
+        };
+        let name = name.trim().replace('_', "-");
+        let name = name.strip_prefix("loose-").unwrap_or(&name).to_owned();
+        let value = value
+            .map(|value| parse_value(value.trim()))
+            .transpose()
+            .map_err(|reason| invalid(path, line_no, reason))?;
+        options.push(OptionValue { name, value });
+    }
+    Ok(())
+}
+
+/// Removes an inline comment and quotes and handles escape sequences.
+fn parse_value(value: &str) -> std::result::Result<String, &'static str> {
+    let quote = value.chars().next().filter(|c| *c == '\'' || *c == '"');
+    let raw = match quote {
+        Some(quote) => {
+            let rest = &value[1..];
+            let end = rest.rfind(quote).ok_or("unterminated quoted value")?;
+            let trailing = rest[end + 1..].trim();
+            if !trailing.is_empty() && !trailing.starts_with('#') {
+                return Err("unexpected characters after a quoted value");
+            }
+            &rest[..end]
+        }
+        None => value.split('#').next().unwrap_or_default().trim_end(),
+    };
+
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push('\u{8}'),
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('s') => out.push(' '),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    Ok(out)
+}
+
+fn parse_flag(value: Option<&str>) -> Option<bool> {
+    match value {
+        None | Some("1") | Some("true") | Some("TRUE") | Some("on") | Some("ON") => Some(true),
+        Some("0") | Some("false") | Some("FALSE") | Some("off") | Some("OFF") => Some(false),
+        Some(_) => None,
+    }
+}
+
+/// Applies options to the builder. Options, that aren't related to the driver, are ignored.
+fn apply(mut builder: OptsBuilder, options: &[OptionValue]) -> Result<OptsBuilder> {
+    let mut init = Vec::new();
+    let mut ssl_mode = None;
+    let mut ssl_ca = None;
+    let mut ssl_cert = None;
+    let mut ssl_key = None;
+
+    for option in options {
+        let value = option.value.as_deref();
+        let invalid_value = || {
+            Error::from(DriverError::InvalidOptionFile(format!(
+                "invalid value `{}' for option `{}'",
+                value.unwrap_or_default(),
+                option.name
+            )))
+        };
+        let required = || value.ok_or_else(invalid_value);
+        let (name, negated) = match option.name.strip_prefix("skip-") {
+            Some(name) => (name, true),
+            None => (option.name.as_str(), false),
+        };
+        let flag = || {
+            parse_flag(value)
+                .map(|flag| flag != negated)
+                .ok_or_else(invalid_value)
+        };
+
+        match name {
+            "user" => builder = builder.user(Some(required()?)),
+            "password" => builder = builder.pass(Some(required()?)),
+            "host" => builder = builder.ip_or_hostname(Some(required()?)),
+            "port" => {
+                let port = required()?.parse().map_err(|_| invalid_value())?;
+                builder = builder.tcp_port(port);
+            }
+            "socket" => builder = builder.socket(Some(required()?)),
+            "database" => builder = builder.db_name(Some(required()?)),
+            "init-command" => init.push(required()?.to_owned()),
+            "connect-timeout" => {
+                let secs = required()?.parse().map_err(|_| invalid_value())?;
+                builder = builder.tcp_connect_timeout(Some(Duration::from_secs(secs)));
+            }
+            "compress" => {
+                builder = builder.compress(flag()?.then(crate::Compression::default));
+            }
+            "enable-cleartext-plugin" => builder = builder.enable_cleartext_plugin(flag()?),
+            "ssl-mode" => ssl_mode = Some(required()?.to_owned()),
+            "ssl-ca" => ssl_ca = Some(PathBuf::from(required()?)),
+            "ssl-cert" => ssl_cert = Some(PathBuf::from(required()?)),
+            "ssl-key" => ssl_key = Some(PathBuf::from(required()?)),
+            _ => (),
+        }
+    }
+
+    if !init.is_empty() {
+        builder = builder.init(init);
+    }
+
+    let tls_requested = ssl_ca.is_some() || ssl_cert.is_some();
+    let ssl_opts = ssl_opts(ssl_mode.as_deref(), ssl_ca, tls_requested).ok_or_else(|| {
+        DriverError::InvalidOptionFile(format!(
+            "invalid value `{}' for option `ssl-mode'",
+            ssl_mode.unwrap_or_default()
+        ))
+    })?;
+    let ssl_opts = match (ssl_opts, ssl_cert, ssl_key) {
+        (ssl_opts, None, None) => ssl_opts,
+        #[cfg(feature = "rustls-tls")]
+        (Some(ssl_opts), Some(cert), Some(key)) => {
+            Some(ssl_opts.with_client_identity(Some(crate::ClientIdentity::new(cert, key))))
+        }
+        #[cfg(feature = "rustls-tls")]
+        (Some(_), _, _) => {
+            return Err(DriverError::InvalidOptionFile(
+                "`ssl-cert' and `ssl-key' must be given together".into(),
+            )
+            .into())
+        }
+        (None, _, _) => None,
+        #[cfg(not(feature = "rustls-tls"))]
+        (Some(_), _, _) => {
+            return Err(DriverError::InvalidOptionFile(
+                "`ssl-cert' and `ssl-key' options require the rustls-tls feature".into(),
+            )
+            .into())
+        }
+    };
+
+    Ok(builder.ssl_opts(ssl_opts))
+}
+
+/// Builds TLS options according to the `ssl-mode` (case-insensitive) and `ssl-ca` options,
+/// the same way MySQL clients do, i.e. the server certificate is verified if the CA is given.
+///
+/// Returns `None` if the mode is invalid.
+pub(crate) fn ssl_opts(
+    ssl_mode: Option<&str>,
+    ssl_ca: Option<PathBuf>,
+    tls_requested: bool,
+) -> Option<Option<SslOpts>> {
+    let ssl_mode = ssl_mode.map(str::to_ascii_uppercase);
+    let ssl_opts = match ssl_mode.as_deref() {
+        Some("DISABLED") => None,
+        None | Some("PREFERRED") if !tls_requested => None,
+        Some("REQUIRED") if ssl_ca.is_none() => Some(
+            SslOpts::default()
+                .with_danger_accept_invalid_certs(true)
+                .with_danger_skip_domain_validation(true),
+        ),
+        None | Some("PREFERRED") | Some("REQUIRED") | Some("VERIFY_CA") => {
+            Some(SslOpts::default().with_danger_skip_domain_validation(true))
+        }
+        Some("VERIFY_IDENTITY") => Some(SslOpts::default()),
+        Some(_) => return None,
+    };
+    Some(ssl_opts.map(|ssl_opts| ssl_opts.with_root_cert_path(ssl_ca)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::Path, time::Duration};
+
+    use super::{apply, parse, parse_value, OptionValue};
+    use crate::{Opts, OptsBuilder};
+
+    fn parse_str(contents: &str, group: &str) -> crate::Result<Opts> {
+        let mut options = Vec::new();
+        parse(
+            Path::new("my.cnf"),
+            contents,
+            &["client", group],
+            0,
+            &mut options,
+        )?;
+        apply(OptsBuilder::new(), &options).map(Into::into)
+    }
+
+    #[test]
+    fn should_parse_values() {
+        assert_eq!(parse_value("foo # comment").unwrap(), "foo");
+        assert_eq!(parse_value("'p#ss word' # comment").unwrap(), "p#ss word");
+        assert_eq!(parse_value(r#""a\tb\\c\s""#).unwrap(), "a\tb\\c ");
+        assert!(parse_value("'foo").is_err());
+        assert!(parse_value("'foo' bar").is_err());
+    }
+
+    #[test]
+    fn should_read_client_and_custom_groups() {
+        let opts = parse_str(
+            r#"
+# comment
+[mysqld]
+user = mysql
+port = 3307
+
+[client]
+user=root
+password = "s3cr3t"
+host = db.local
+port = 3308
+loose_default-character-set = utf8mb4
+prompt = '\u@\h> '
+
+[backup]
+user = backup
+database = app
+init-command = SET NAMES utf8mb4
+compress
+connect_timeout = 5
+"#,
+            "backup",
+        )
+        .unwrap();
+        assert_eq!(opts.get_user(), Some("backup"));
+        assert_eq!(opts.get_pass(), Some("s3cr3t"));
+        assert_eq!(opts.get_ip_or_hostname(), "db.local");
+        assert_eq!(opts.get_tcp_port(), 3308);
+        assert_eq!(opts.get_db_name(), Some("app"));
+        assert_eq!(opts.get_init(), ["SET NAMES utf8mb4"]);
+        assert!(opts.get_compress().is_some());
+        assert_eq!(opts.get_tcp_connect_timeout(), Some(Duration::from_secs(5)));
+        assert!(opts.get_ssl_opts().is_none());
+
+        let opts = parse_str("[client]\nuser = root\n[backup]\nuser = backup\n", "mysql").unwrap();
+        assert_eq!(opts.get_user(), Some("root"));
+    }
+
+    #[test]
+    fn should_read_ssl_options() {
+        let opts = parse_str("[client]\nssl-ca = /etc/ca.pem\n", "client").unwrap();
+        let ssl_opts = opts.get_ssl_opts().unwrap();
+        assert_eq!(ssl_opts.root_cert_path(), Some(Path::new("/etc/ca.pem")));
+        assert!(ssl_opts.skip_domain_validation());
+        assert!(!ssl_opts.accept_invalid_certs());
+
+        let opts = parse_str("[client]\nssl-mode = required\n", "client").unwrap();
+        assert!(opts.get_ssl_opts().unwrap().accept_invalid_certs());
+
+        let opts = parse_str(
+            "[client]\nssl-ca = /etc/ca.pem\nssl-mode = VERIFY_IDENTITY\n",
+            "client",
+        )
+        .unwrap();
+        assert!(!opts.get_ssl_opts().unwrap().skip_domain_validation());
+
+        let opts = parse_str(
+            "[client]\nssl-ca = /etc/ca.pem\nssl-mode = DISABLED\n",
+            "client",
+        )
+        .unwrap();
+        assert!(opts.get_ssl_opts().is_none());
+
+        assert!(parse_str("[client]\nssl-mode = SOMETIMES\n", "client").is_err());
+    }
+
+    #[test]
+    fn should_reject_invalid_files() {
+        assert!(parse_str("[client]\nport = http\n", "client").is_err());
+        assert!(parse_str("[client\n", "client").is_err());
+        assert!(parse_str("[client]\nuser\n", "client").is_err());
+        assert!(parse_str("[client]\nskip-compress = maybe\n", "client").is_err());
+        assert!(parse_str("!exec rm -rf /\n", "client").is_err());
+
+        let options = [OptionValue {
+            name: "skip-compress".into(),
+            value: None,
+        }];
+        let opts: Opts = apply(
+            OptsBuilder::new().compress(Some(Default::default())),
+            &options,
+        )
+        .unwrap()
+        .into();
+        assert!(opts.get_compress().is_none());
+    }
+
+    #[test]
+    fn should_follow_includes() {
+        let dir = std::env::temp_dir().join(format!("mysql-option-file-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+        std::fs::write(
+            dir.join("my.cnf"),
+            "[client]\nuser = root\n!includedir conf.d\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("conf.d/b.cnf"), "[client]\nport = 3310\n").unwrap();
+        std::fs::write(
+            dir.join("conf.d/a.cnf"),
+            "[client]\nport = 3309\nuser = app\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("conf.d/c.txt"), "[client]\nport = 1\n").unwrap();
+
+        let opts = Opts::from_mysql_config(dir.join("my.cnf"), "client").unwrap();
+        assert_eq!(opts.get_user(), Some("app"));
+        assert_eq!(opts.get_tcp_port(), 3310);
+
+        std::fs::write(dir.join("loop.cnf"), "!include loop.cnf\n").unwrap();
+        assert!(Opts::from_mysql_config(dir.join("loop.cnf"), "client").is_err());
+        assert!(Opts::from_mysql_config(dir.join("missing.cnf"), "client").is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -6,6 +6,10 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+use std::{fmt, hash, str::FromStr, sync::Arc, time::Duration};
+
+use crate::{Conn, Result};
+
 macro_rules! const_assert {
     ($name:ident, $($xs:expr),+ $(,)*) => {
         #[allow(unknown_lints, clippy::eq_op)]
@@ -25,8 +29,18 @@ macro_rules! const_assert {
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct PoolOpts {
     constraints: PoolConstraints,
-    reset_connection: bool,
-    check_health: bool,
+    reset_policy: ResetPolicy,
+    validation_mode: ValidationMode,
+    validation_query: Option<String>,
+    max_lifetime: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    checkout_timeout: Option<Duration>,
+    queue_strategy: QueueStrategy,
+    warm_up: Option<usize>,
+    stmt_registry: bool,
+    warm_up_stmts: Vec<String>,
+    prewarm_stmts: Vec<String>,
+    init_handler: Option<InitHandler>,
 }
 
 impl PoolOpts {
@@ -68,6 +82,9 @@ impl PoolOpts {
     ///
     /// It is also possible to selectively opt-in/out using [`crate::PooledConn::reset_connection`].
     ///
+    /// This is a shortcut for [`PoolOpts::with_reset_policy`] with either
+    /// [`ResetPolicy::Fast`] or [`ResetPolicy::None`] (`true` keeps [`ResetPolicy::Full`]).
+    ///
     /// # Connection URL
     ///
     /// You can use `reset_connection` URL parameter to set this value. E.g.
@@ -81,19 +98,54 @@ impl PoolOpts {
     /// # Ok(()) }
     /// ```
     pub fn with_reset_connection(mut self, reset_connection: bool) -> Self {
-        self.reset_connection = reset_connection;
+        self.reset_policy = match (reset_connection, self.reset_policy) {
+            (false, _) => ResetPolicy::None,
+            (true, ResetPolicy::None) => ResetPolicy::Fast,
+            (true, policy) => policy,
+        };
         self
     }
 
     /// Returns the `reset_connection` value (see [`PoolOpts::with_reset_connection`]).
     pub fn reset_connection(&self) -> bool {
-        self.reset_connection
+        self.reset_policy != ResetPolicy::None
+    }
+
+    /// Sets how a connection is reset upon returning it to a pool
+    /// (defaults to [`ResetPolicy::Fast`]).
+    ///
+    /// The reset makes sure that session variables, temporary tables, user locks
+    /// and open transactions of one checkout can't leak to the next one
+    /// (see [`PoolOpts::with_reset_connection`] for the pros and cons).
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `reset_policy` URL parameter to set this value (`none`, `fast` or `full`). E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?reset_policy=full")?;
+    /// assert_eq!(opts.get_pool_opts().reset_policy(), ResetPolicy::Full);
+    /// # Ok(()) }
+    /// ```
+    pub fn with_reset_policy(mut self, reset_policy: ResetPolicy) -> Self {
+        self.reset_policy = reset_policy;
+        self
+    }
+
+    /// Returns the `reset_policy` value (see [`PoolOpts::with_reset_policy`]).
+    pub fn reset_policy(&self) -> ResetPolicy {
+        self.reset_policy
     }
 
     /// Sets whether to check connection health upon retrieving it from a pool (defaults to `true`).
     ///
     /// If `true`, then `Conn::ping` will be invoked on a non-fresh pooled connection.
     ///
+    /// This is a shortcut for [`PoolOpts::with_validation_mode`] with either
+    /// [`ValidationMode::OnCheckout`] or [`ValidationMode::None`].
+    ///
     /// # Connection URL
     ///
     /// Use `check_health` URL parameter to set this value. E.g.
@@ -107,12 +159,389 @@ impl PoolOpts {
     /// # Ok(()) }
     /// ```
     pub fn with_check_health(mut self, check_health: bool) -> Self {
-        self.check_health = check_health;
+        self.validation_mode = if check_health {
+            ValidationMode::OnCheckout
+        } else {
+            ValidationMode::None
+        };
         self
     }
 
+    /// Returns `true` unless the validation mode is [`ValidationMode::None`].
     pub fn check_health(&self) -> bool {
-        self.check_health
+        self.validation_mode != ValidationMode::None
+    }
+
+    /// Defines when a pooled connection is validated upon retrieving it from a pool
+    /// (defaults to [`ValidationMode::OnCheckout`]).
+    ///
+    /// Connection that fails validation is dropped and another one is retrieved, so that
+    /// connections killed by the server (e.g. due to `wait_timeout`) or lost after a failover
+    /// are never handed out. Fresh connections are never validated.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `validation_mode` URL parameter to set this value
+    /// (`none`, `on_checkout` or `idle:<milliseconds>`). E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?validation_mode=idle:30000")?;
+    /// assert_eq!(
+    ///     opts.get_pool_opts().validation_mode(),
+    ///     ValidationMode::IfIdleLongerThan(Duration::from_secs(30)),
+    /// );
+    /// # Ok(()) }
+    /// ```
+    pub fn with_validation_mode(mut self, validation_mode: ValidationMode) -> Self {
+        self.validation_mode = validation_mode;
+        self
+    }
+
+    /// Returns the validation mode (see [`PoolOpts::with_validation_mode`]).
+    pub fn validation_mode(&self) -> ValidationMode {
+        self.validation_mode
+    }
+
+    /// Sets the query used to validate a pooled connection (defaults to `None`).
+    ///
+    /// If `None`, then `COM_PING` is used, otherwise the query is executed using
+    /// the text protocol and its result is dropped (e.g. `SELECT 1 FROM dual` to make sure
+    /// that the server is able to execute queries, not only respond to pings).
+    ///
+    /// # Connection URL
+    ///
+    /// Use `validation_query` URL parameter to set this value.
+    pub fn with_validation_query<T: Into<String>>(mut self, validation_query: Option<T>) -> Self {
+        self.validation_query = validation_query.map(Into::into);
+        self
+    }
+
+    /// Returns the validation query (see [`PoolOpts::with_validation_query`]).
+    pub fn validation_query(&self) -> Option<&str> {
+        self.validation_query.as_deref()
+    }
+
+    /// Sets the maximum lifetime of a pooled connection (defaults to `None`).
+    ///
+    /// Connection that is older than this is closed instead of being handed out or returned
+    /// to a pool. Use a value that is less than the connection lifetime enforced
+    /// by load balancers or proxies in front of the server, so that they never kill
+    /// a connection that the pool considers alive.
+    ///
+    /// Connections are reaped lazily, i.e. when a connection is retrieved from
+    /// or returned to a pool.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `max_lifetime_ms` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?max_lifetime_ms=1800000")?;
+    /// assert_eq!(opts.get_pool_opts().max_lifetime(), Some(Duration::from_secs(1800)));
+    /// # Ok(()) }
+    /// ```
+    pub fn with_max_lifetime(mut self, max_lifetime: Option<Duration>) -> Self {
+        self.max_lifetime = max_lifetime;
+        self
+    }
+
+    /// Returns the maximum lifetime of a pooled connection
+    /// (see [`PoolOpts::with_max_lifetime`]).
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        self.max_lifetime
+    }
+
+    /// Sets how long a connection is allowed to be idle in a pool (defaults to `None`).
+    ///
+    /// Idle connections are closed as long as the pool holds more than
+    /// [`PoolConstraints::min`] connections. As with [`PoolOpts::with_max_lifetime`]
+    /// connections are reaped lazily.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `idle_timeout_ms` URL parameter to set this value.
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Returns the idle timeout (see [`PoolOpts::with_idle_timeout`]).
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Sets how long [`crate::Pool::get_conn`] waits for a connection if the pool is exhausted
+    /// (defaults to `None`, i.e. it waits indefinitely).
+    ///
+    /// `DriverError::Timeout` is returned if the timeout is reached.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `checkout_timeout_ms` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?checkout_timeout_ms=500")?;
+    /// assert_eq!(
+    ///     opts.get_pool_opts().checkout_timeout(),
+    ///     Some(Duration::from_millis(500)),
+    /// );
+    /// # Ok(()) }
+    /// ```
+    pub fn with_checkout_timeout(mut self, checkout_timeout: Option<Duration>) -> Self {
+        self.checkout_timeout = checkout_timeout;
+        self
+    }
+
+    /// Returns the checkout timeout (see [`PoolOpts::with_checkout_timeout`]).
+    pub fn checkout_timeout(&self) -> Option<Duration> {
+        self.checkout_timeout
+    }
+
+    /// Sets the order in which idle connections are reused (defaults to [`QueueStrategy::Fifo`]).
+    ///
+    /// # Connection URL
+    ///
+    /// Use `queue_strategy` URL parameter to set this value (`fifo` or `lifo`).
+    pub fn with_queue_strategy(mut self, queue_strategy: QueueStrategy) -> Self {
+        self.queue_strategy = queue_strategy;
+        self
+    }
+
+    /// Returns the queue strategy (see [`PoolOpts::with_queue_strategy`]).
+    pub fn queue_strategy(&self) -> QueueStrategy {
+        self.queue_strategy
+    }
+
+    /// Sets the number of connections established upon pool creation
+    /// (defaults to `None`, i.e. [`PoolConstraints::min`]).
+    ///
+    /// The value is capped at [`PoolConstraints::max`]. Use zero to create the pool
+    /// without connecting to the server.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `pool_warm_up` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?pool_min=2&pool_max=20&pool_warm_up=5")?;
+    /// assert_eq!(opts.get_pool_opts().warm_up(), 5);
+    /// # Ok(()) }
+    /// ```
+    pub fn with_warm_up(mut self, warm_up: Option<usize>) -> Self {
+        self.warm_up = warm_up;
+        self
+    }
+
+    /// Returns the number of connections established upon pool creation
+    /// (see [`PoolOpts::with_warm_up`]).
+    pub fn warm_up(&self) -> usize {
+        self.warm_up
+            .unwrap_or(self.constraints.min())
+            .min(self.constraints.max())
+    }
+
+    /// Sets whether the pool shares prepared statements between its connections
+    /// (defaults to `false`).
+    ///
+    /// If `true`, then the pool remembers queries prepared by any of its connections
+    /// (up to `stmt_cache_size` most recent ones), and a connection retrieved from the pool
+    /// prepares the ones it doesn't have yet. So after a deploy a statement is prepared
+    /// once per connection upon checkout, rather than in the middle of a request that
+    /// happened to get a cold connection.
+    ///
+    /// Queries that fail to prepare are forgotten. This option has no effect
+    /// if [`PoolOpts::reset_connection`] is `true` (the reset kills the statement cache)
+    /// or if the statement cache is disabled.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `stmt_registry` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?reset_connection=false&stmt_registry=true")?;
+    /// assert!(opts.get_pool_opts().stmt_registry());
+    /// # Ok(()) }
+    /// ```
+    pub fn with_stmt_registry(mut self, stmt_registry: bool) -> Self {
+        self.stmt_registry = stmt_registry;
+        self
+    }
+
+    /// Returns the `stmt_registry` value (see [`PoolOpts::with_stmt_registry`]).
+    pub fn stmt_registry(&self) -> bool {
+        self.stmt_registry
+    }
+
+    /// Defines statements, that are prepared by every new connection of the pool
+    /// (defaults to an empty list).
+    ///
+    /// Statements are prepared right after the init handler is called
+    /// (see [`PoolOpts::with_init_handler`]), so hot statements are cached by a connection
+    /// before it's checked out for the first time. Same as the init handler, statements
+    /// are prepared again after the connection is reset, because the reset kills
+    /// the statement cache (see [`PoolOpts::with_reset_connection`]).
+    ///
+    /// The connection is dropped if a statement could not be prepared (the error is returned
+    /// to the caller that requested the connection). Note that the statement cache should be
+    /// large enough to hold these statements (see [`crate::OptsBuilder::stmt_cache_size`]).
+    ///
+    /// ```
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// let pool_opts = PoolOpts::default().with_warm_up_stmts(["SELECT :id", "SELECT 1 + ?"]);
+    /// let opts = OptsBuilder::from_opts(get_opts()).pool_opts(pool_opts);
+    /// let pool = Pool::new(opts)?;
+    /// // the statement is already prepared by the connection
+    /// let id: Option<u32> = pool.get_conn()?.exec_first("SELECT :id", params! { "id" => 42 })?;
+    /// assert_eq!(id, Some(42));
+    /// # });
+    /// ```
+    pub fn with_warm_up_stmts<I, T>(mut self, stmts: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.warm_up_stmts = stmts.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns statements prepared by every new connection of the pool
+    /// (see [`PoolOpts::with_warm_up_stmts`]).
+    pub fn warm_up_stmts(&self) -> &[String] {
+        &self.warm_up_stmts
+    }
+
+    /// Defines statements to prewarm connections of the pool with (defaults to empty).
+    ///
+    /// It's meant for a snapshot of the statement cache, exported by a previous run
+    /// (see [`crate::Pool::export_stmt_cache`]). Unlike [`PoolOpts::with_warm_up_stmts`]:
+    ///
+    /// *   connections established upon pool creation (see [`PoolOpts::with_warm_up`])
+    ///     are prewarmed in a background thread, so [`crate::Pool::new`] isn't blocked;
+    ///     other connections are prewarmed before they are checked out for the first time;
+    /// *   statements that could not be prepared (e.g. because a table was dropped)
+    ///     are skipped (see [`crate::Conn::prewarm_stmts`]).
+    ///
+    /// Statements are prepared again after the connection is reset.
+    ///
+    /// ```
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// let pool = Pool::new(get_opts())?;
+    /// pool.get_conn()?.exec_drop("SELECT 1 + ?", (1,))?;
+    /// let snapshot = pool.export_stmt_cache();
+    ///
+    /// // upon the next startup
+    /// let pool_opts = PoolOpts::default().with_prewarm_stmts(snapshot);
+    /// let pool = Pool::new(OptsBuilder::from_opts(get_opts()).pool_opts(pool_opts))?;
+    /// let sum: Option<u32> = pool.get_conn()?.exec_first("SELECT 1 + ?", (41,))?;
+    /// assert_eq!(sum, Some(42));
+    /// # });
+    /// ```
+    pub fn with_prewarm_stmts<I, T>(mut self, stmts: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.prewarm_stmts = stmts.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns statements to prewarm connections of the pool with
+    /// (see [`PoolOpts::with_prewarm_stmts`]).
+    pub fn prewarm_stmts(&self) -> &[String] {
+        &self.prewarm_stmts
+    }
+
+    /// Defines a handler, that is called for every new connection of the pool
+    /// (after [`crate::Opts::get_init`] queries, before the connection is checked out).
+    ///
+    /// It's a way to set up a session programmatically, e.g. to prepare statements
+    /// or to verify server settings. The connection is dropped if the handler returns an error
+    /// (the error is returned to the caller that requested the connection).
+    ///
+    /// Same as `init` queries, the handler is called again after the connection is reset
+    /// (see [`PoolOpts::with_reset_connection`]).
+    ///
+    /// ```
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// let pool_opts = PoolOpts::default().with_init_handler(Some(InitHandler::new(|conn| {
+    ///     let sql_mode: String = conn.query_first("SELECT @@sql_mode")?.unwrap_or_default();
+    ///     if !sql_mode.contains("STRICT_TRANS_TABLES") {
+    ///         conn.query_drop("SET SESSION sql_mode = CONCAT(@@sql_mode, ',STRICT_TRANS_TABLES')")?;
+    ///     }
+    ///     Ok(())
+    /// })));
+    /// let opts = OptsBuilder::from_opts(get_opts()).pool_opts(pool_opts);
+    /// let pool = Pool::new(opts)?;
+    /// let sql_mode: Option<String> = pool.get_conn()?.query_first("SELECT @@sql_mode")?;
+    /// assert!(sql_mode.unwrap().contains("STRICT_TRANS_TABLES"));
+    /// # });
+    /// ```
+    pub fn with_init_handler(mut self, init_handler: Option<InitHandler>) -> Self {
+        self.init_handler = init_handler;
+        self
+    }
+
+    /// Returns the init handler (see [`PoolOpts::with_init_handler`]).
+    pub fn init_handler(&self) -> Option<&InitHandler> {
+        self.init_handler.as_ref()
+    }
+}
+
+/// Handler, that sets up every new connection of a pool (see [`PoolOpts::with_init_handler`]).
+#[derive(Clone)]
+pub struct InitHandler(Arc<InitFn>);
+
+type InitFn = dyn Fn(&mut Conn) -> Result<()> + Send + Sync;
+
+impl InitHandler {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&mut Conn) -> Result<()> + Send + Sync + 'static,
+    {
+        InitHandler(Arc::new(f))
+    }
+
+    pub(crate) fn call(&self, conn: &mut Conn) -> Result<()> {
+        (self.0)(conn)
+    }
+}
+
+impl PartialEq for InitHandler {
+    fn eq(&self, other: &InitHandler) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for InitHandler {}
+
+impl hash::Hash for InitHandler {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const ()).hash(state)
+    }
+}
+
+impl fmt::Debug for InitHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InitHandler(...)")
     }
 }
 
@@ -120,8 +549,100 @@ impl Default for PoolOpts {
     fn default() -> Self {
         Self {
             constraints: PoolConstraints::DEFAULT,
-            reset_connection: true,
-            check_health: true,
+            reset_policy: ResetPolicy::Fast,
+            validation_mode: ValidationMode::OnCheckout,
+            validation_query: None,
+            max_lifetime: None,
+            idle_timeout: None,
+            checkout_timeout: None,
+            queue_strategy: QueueStrategy::Fifo,
+            warm_up: None,
+            stmt_registry: false,
+            warm_up_stmts: Vec::new(),
+            prewarm_stmts: Vec::new(),
+            init_handler: None,
+        }
+    }
+}
+
+/// Defines when a pooled connection is validated (see [`PoolOpts::with_validation_mode`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ValidationMode {
+    /// Never validate.
+    None,
+    /// Validate every time a connection is retrieved from a pool.
+    #[default]
+    OnCheckout,
+    /// Validate if a connection was idle in a pool for longer than the given duration.
+    IfIdleLongerThan(Duration),
+}
+
+/// Parses `none`, `on_checkout` or `idle:<milliseconds>`.
+impl FromStr for ValidationMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(ValidationMode::None),
+            "on_checkout" => Ok(ValidationMode::OnCheckout),
+            _ => s
+                .strip_prefix("idle:")
+                .and_then(|ms| ms.parse().ok())
+                .map(|ms| ValidationMode::IfIdleLongerThan(Duration::from_millis(ms)))
+                .ok_or(()),
+        }
+    }
+}
+
+/// How a connection is reset upon returning it to a pool (see [`PoolOpts::with_reset_policy`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ResetPolicy {
+    /// Don't reset, so the session state survives the return to a pool.
+    None,
+    /// Use `COM_RESET_CONNECTION` with a fallback to `COM_CHANGE_USER`
+    /// on older servers (see [`crate::Conn::reset`]).
+    #[default]
+    Fast,
+    /// Use `COM_CHANGE_USER`, which also re-authenticates the user,
+    /// so that e.g. a changed password or revoked privileges take effect.
+    Full,
+}
+
+/// Parses `none`, `fast` or `full`.
+impl FromStr for ResetPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(ResetPolicy::None),
+            "fast" => Ok(ResetPolicy::Fast),
+            "full" => Ok(ResetPolicy::Full),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Order in which idle connections are reused (see [`PoolOpts::with_queue_strategy`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum QueueStrategy {
+    /// Reuse the connection that was idle the longest, so that the load is spread
+    /// across all pooled connections.
+    #[default]
+    Fifo,
+    /// Reuse the most recently returned connection, so that extra connections
+    /// stay idle and could be reaped (see [`PoolOpts::with_idle_timeout`]).
+    Lifo,
+}
+
+/// Parses `fifo` or `lifo`.
+impl FromStr for QueueStrategy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" => Ok(QueueStrategy::Fifo),
+            "lifo" => Ok(QueueStrategy::Lifo),
+            _ => Err(()),
         }
     }
 }
@@ -1,7 +1,7 @@
 #![cfg(feature = "rustls-tls")]
 
 use rustls::{Certificate, PrivateKey};
-use rustls_pemfile::{certs, rsa_private_keys};
+use rustls_pemfile::{certs, read_all, Item};
 
 use std::{borrow::Cow, path::Path};
 
@@ -16,6 +16,8 @@ impl ClientIdentity {
     ///
     /// `cert_chain_path` - path to a certificate chain (in PEM or DER)
     /// `priv_key_path` - path to a private key (in DER or PEM) (it'll take the first one)
+    ///
+    /// PEM private key could be a PKCS#1 (RSA), PKCS#8 or SEC1 (EC) key.
     pub fn new<T, U>(cert_chain_path: T, priv_key_path: U) -> Self
     where
         T: Into<Cow<'static, Path>>,
@@ -67,12 +69,14 @@ impl ClientIdentity {
             cert_chain.push(Certificate(cert_data));
         }
 
-        let mut priv_key = None;
-        for key in rsa_private_keys(&mut &*key_data)?.into_iter().take(1) {
-            priv_key = Some(PrivateKey(key));
-        }
-
-        let priv_key = priv_key.unwrap_or_else(|| PrivateKey(key_data));
+        let priv_key = read_all(&mut &*key_data)?
+            .into_iter()
+            .find_map(|item| match item {
+                Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => Some(key),
+                _ => None,
+            })
+            .unwrap_or(key_data);
+        let priv_key = PrivateKey(priv_key);
 
         Ok((cert_chain, priv_key))
     }
@@ -0,0 +1,190 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{
+    constants::{ColumnType, StmtExecuteParamFlags},
+    packets::ComStmtExecuteRequestBuilder,
+};
+
+use std::fmt;
+
+use crate::{
+    conn::query_attrs::param_type, DriverError::MismatchedStmtParams, Error::DriverError, Params,
+    Result, Statement,
+};
+
+/// How a statement parameter is encoded for the binary protocol
+/// (see [`Statement::describe_params`]).
+///
+/// It helps to debug type coercion mismatches, i.e. cases where a query works in a client
+/// that sends text, but behaves differently when executed with parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamEncoding {
+    index: usize,
+    name: Option<String>,
+    column_type: ColumnType,
+    flags: StmtExecuteParamFlags,
+    len: u64,
+    long_data: bool,
+    server_type: Option<ColumnType>,
+}
+
+impl ParamEncoding {
+    /// Zero-based position of the parameter.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Name of the parameter (`None` for a positional parameter).
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Type sent to the server.
+    pub fn column_type(&self) -> ColumnType {
+        self.column_type
+    }
+
+    /// Flags sent along with the type (i.e. `UNSIGNED`).
+    pub fn flags(&self) -> StmtExecuteParamFlags {
+        self.flags
+    }
+
+    /// Length of the encoded value (`0` for `NULL`, which is sent in the null-bitmap).
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the value is empty on the wire (i.e. it's `NULL`).
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the value is sent separately using `COM_STMT_SEND_LONG_DATA`.
+    pub fn long_data(&self) -> bool {
+        self.long_data
+    }
+
+    /// Parameter type reported by the server upon prepare, if any.
+    ///
+    /// Older servers report `MYSQL_TYPE_VAR_STRING` for every parameter.
+    pub fn server_type(&self) -> Option<ColumnType> {
+        self.server_type
+    }
+}
+
+impl fmt::Display for ParamEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.index)?;
+        if let Some(ref name) = self.name {
+            write!(f, " `{}'", name)?;
+        }
+        write!(f, ": {:?}", self.column_type)?;
+        if self.flags.contains(StmtExecuteParamFlags::UNSIGNED) {
+            write!(f, " UNSIGNED")?;
+        }
+        write!(f, ", {} bytes", self.len)?;
+        if self.long_data {
+            write!(f, " (long data)")?;
+        }
+        if let Some(server_type) = self.server_type {
+            write!(f, ", server expects {:?}", server_type)?;
+        }
+        Ok(())
+    }
+}
+
+impl Statement {
+    /// Reports how the given parameters would be encoded when this statement is executed.
+    ///
+    /// Parameters are validated the same way as for an execution, but nothing is sent
+    /// to the server.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let stmt = conn.prep("SELECT :id, :name")?;
+    /// for param in stmt.describe_params(params! { "id" => 42_u32, "name" => "foo" })? {
+    ///     println!("{}", param);
+    /// }
+    /// # });
+    /// ```
+    pub fn describe_params<T: Into<Params>>(&self, params: T) -> Result<Vec<ParamEncoding>> {
+        let values = match self.positional_params(params.into())? {
+            Params::Positional(values) => values,
+            _ => Vec::new(),
+        };
+
+        if self.num_params() as usize != values.len() {
+            return Err(DriverError(MismatchedStmtParams(
+                self.num_params(),
+                values.len(),
+            )));
+        }
+
+        let (_, long_data) = ComStmtExecuteRequestBuilder::new(self.id()).build(&values);
+        let names = self.named_params.as_deref().unwrap_or_default();
+        let server_types = self.params();
+
+        Ok(values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let (column_type, flags) = param_type(value);
+                ParamEncoding {
+                    index,
+                    name: names
+                        .get(index)
+                        .map(|name| String::from_utf8_lossy(name).into_owned()),
+                    column_type,
+                    flags,
+                    len: value.bin_len(),
+                    long_data: long_data && matches!(value, crate::Value::Bytes(_)),
+                    server_type: server_types.get(index).map(|column| column.column_type()),
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::constants::{ColumnType, StmtExecuteParamFlags};
+
+    use super::ParamEncoding;
+
+    #[test]
+    fn should_display_param_encoding() {
+        let encoding = ParamEncoding {
+            index: 1,
+            name: Some("id".into()),
+            column_type: ColumnType::MYSQL_TYPE_LONGLONG,
+            flags: StmtExecuteParamFlags::UNSIGNED,
+            len: 8,
+            long_data: false,
+            server_type: Some(ColumnType::MYSQL_TYPE_LONG),
+        };
+        assert_eq!(
+            encoding.to_string(),
+            "#1 `id': MYSQL_TYPE_LONGLONG UNSIGNED, 8 bytes, server expects MYSQL_TYPE_LONG"
+        );
+
+        let encoding = ParamEncoding {
+            index: 0,
+            name: None,
+            column_type: ColumnType::MYSQL_TYPE_NULL,
+            flags: StmtExecuteParamFlags::empty(),
+            len: 0,
+            long_data: false,
+            server_type: None,
+        };
+        assert_eq!(encoding.to_string(), "#0: MYSQL_TYPE_NULL, 0 bytes");
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Time and connect functions used by the pool.
+//!
+//! These are only injectable with the `testing` feature (see [`crate::testing`]),
+//! otherwise the pool always uses [`SystemClock`] and [`DefaultConnector`].
+
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{Conn, Opts, Result};
+
+/// Source of time for the pool's checkout timeout, reaper and validation logic.
+pub trait Clock: Send + Sync {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+
+    /// Called before the pool blocks waiting for a connection for at most `timeout`.
+    ///
+    /// Returns the duration the pool should actually block for.
+    fn wait(&self, timeout: Duration) -> Duration {
+        timeout
+    }
+}
+
+/// Establishes connections of the pool.
+pub trait Connector: Send + Sync {
+    /// Creates a new connection (fresh connections are never validated by the pool).
+    fn connect(&self, opts: &Opts) -> Result<Conn>;
+}
+
+/// The [`Clock`] that uses the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The [`Connector`] that uses [`Conn::new`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultConnector;
+
+impl Connector for DefaultConnector {
+    fn connect(&self, opts: &Opts) -> Result<Conn> {
+        Conn::new(opts.clone())
+    }
+}
+
+/// A [`Clock`] that only advances explicitly.
+///
+/// Waits for a connection take no time – the clock is advanced by the waited duration
+/// instead, so checkout timeouts are reached immediately and deterministically.
+#[cfg(feature = "testing")]
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<std::sync::Mutex<Instant>>,
+}
+
+#[cfg(feature = "testing")]
+impl ManualClock {
+    /// Creates a clock that starts at the current instant.
+    pub fn new() -> Self {
+        ManualClock {
+            now: Arc::new(std::sync::Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(feature = "testing")]
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "testing")]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn wait(&self, timeout: Duration) -> Duration {
+        self.advance(timeout);
+        Duration::ZERO
+    }
+}
+
+/// Injectable functions of a pool (see [`crate::Pool::new_with_hooks`]).
+#[derive(Clone)]
+pub struct PoolHooks {
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) connector: Arc<dyn Connector>,
+}
+
+impl PoolHooks {
+    /// Creates hooks that use [`SystemClock`] and [`DefaultConnector`].
+    pub fn new() -> Self {
+        PoolHooks {
+            clock: Arc::new(SystemClock),
+            connector: Arc::new(DefaultConnector),
+        }
+    }
+
+    /// Sets the clock.
+    #[cfg(feature = "testing")]
+    pub fn with_clock<T: Clock + 'static>(mut self, clock: T) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the connector.
+    #[cfg(feature = "testing")]
+    pub fn with_connector<T: Connector + 'static>(mut self, connector: T) -> Self {
+        self.connector = Arc::new(connector);
+        self
+    }
+}
+
+impl Default for PoolHooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for PoolHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PoolHooks(...)")
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod test {
+    use std::time::Duration;
+
+    use super::{Clock, Connector, ManualClock, PoolHooks};
+    use crate::{Conn, DriverError, Error, Opts, OptsBuilder, Pool, PoolConstraints, PoolOpts};
+
+    struct Unreachable;
+
+    impl Connector for Unreachable {
+        fn connect(&self, _: &Opts) -> crate::Result<Conn> {
+            Err(DriverError::SetupError.into())
+        }
+    }
+
+    #[test]
+    fn should_advance_manual_clock() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(clock.wait(Duration::from_secs(2)), Duration::ZERO);
+        assert_eq!(clock.now() - start, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn should_inject_connect_failures() {
+        let opts = OptsBuilder::new()
+            .pool_opts(PoolOpts::new().with_constraints(PoolConstraints::new_const::<0, 1>()));
+        let hooks = PoolHooks::new()
+            .with_clock(ManualClock::new())
+            .with_connector(Unreachable);
+        let pool = Pool::new_with_hooks(opts, hooks).unwrap();
+        for _ in 0..2 {
+            match pool.get_conn() {
+                Err(Error::DriverError(DriverError::SetupError)) => (),
+                _ => panic!("expected the injected error"),
+            }
+        }
+        assert_eq!(pool.inner.count(), 0);
+
+        let opts = OptsBuilder::new()
+            .pool_opts(PoolOpts::new().with_constraints(PoolConstraints::new_const::<1, 1>()));
+        let hooks = PoolHooks::new().with_connector(Unreachable);
+        assert!(Pool::new_with_hooks(opts, hooks).is_err());
+    }
+}
@@ -1,42 +1,84 @@
 use std::{
     collections::VecDeque,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         Condvar, Mutex,
     },
+    time::{Duration, Instant},
 };
 
-use crate::{Conn, Opts, PoolOpts};
+use super::hooks::PoolHooks;
+use crate::{
+    conn::{latency::LatencyTracker, stmt_registry::StmtRegistry},
+    Conn, Opts, PoolOpts, QueueStrategy,
+};
 
 #[derive(Debug)]
 pub struct Protected {
     opts: Opts,
     connections: VecDeque<Conn>,
+    latency_tracker: Option<LatencyTracker>,
+    stmt_registry: Option<StmtRegistry>,
+    hooks: PoolHooks,
 }
 
 impl Protected {
-    fn new(opts: Opts) -> crate::Result<Protected> {
-        let constraints = opts.get_pool_opts().constraints();
+    fn new(
+        opts: Opts,
+        latency_tracker: Option<LatencyTracker>,
+        stmt_registry: Option<StmtRegistry>,
+        hooks: PoolHooks,
+    ) -> crate::Result<Protected> {
+        let pool_opts = opts.get_pool_opts();
+        let capacity = pool_opts.constraints().max();
+        let warm_up = pool_opts.warm_up();
 
         let mut this = Protected {
-            connections: VecDeque::with_capacity(constraints.max()),
+            connections: VecDeque::with_capacity(capacity),
             opts,
+            latency_tracker,
+            stmt_registry,
+            hooks,
         };
 
-        for _ in 0..constraints.min() {
-            this.new_conn()?;
+        for _ in 0..warm_up {
+            this.new_conn(0)?;
         }
 
         Ok(this)
     }
 
-    pub fn new_conn(&mut self) -> crate::Result<()> {
-        match Conn::new(self.opts.clone()) {
-            Ok(conn) => {
+    /// Establishes a new connection that belongs to the given pool generation.
+    pub fn new_conn(&mut self, generation: u64) -> crate::Result<()> {
+        let conn = self
+            .hooks
+            .connector
+            .connect(&self.opts)
+            .and_then(|mut conn| {
+                if let Some(init_handler) = self.opts.get_pool_opts().init_handler() {
+                    init_handler.call(&mut conn)?;
+                }
+                conn.prepare_warm_up_stmts()?;
+                Ok(conn)
+            });
+        match conn {
+            Ok(mut conn) => {
+                conn.0.created_at = self.hooks.clock.now();
+                conn.0.pool_generation = generation;
+                conn.0.latency_tracker = self.latency_tracker.clone();
+                conn.0.stmt_registry = self.stmt_registry.clone();
+                log::debug!(
+                    target: "mysql::pool",
+                    "new pooled connection {}",
+                    conn.connection_id()
+                );
                 self.connections.push_back(conn);
                 Ok(())
             }
-            Err(err) => Err(err),
+            Err(err) => {
+                log::warn!(target: "mysql::pool", "unable to create a connection: {}", err);
+                Err(err)
+            }
         }
     }
 
@@ -51,19 +93,96 @@ impl Protected {
         }
     }
 
-    pub fn pop_front(&mut self) -> Option<Conn> {
-        self.connections.pop_front()
+    /// Takes an idle connection according to the given queue strategy
+    /// (connections are returned to the back of the queue).
+    pub fn pop(&mut self, queue_strategy: QueueStrategy) -> Option<Conn> {
+        match queue_strategy {
+            QueueStrategy::Fifo => self.connections.pop_front(),
+            QueueStrategy::Lifo => self.connections.pop_back(),
+        }
+    }
+
+    /// Removes connections that exceeded the maximum lifetime or the idle timeout.
+    ///
+    /// Idle connections are only removed while there are more than `min` connections,
+    /// given that the pool has `count` connections. Removed connections are returned,
+    /// so that they could be dropped outside of the lock.
+    pub fn reap(&mut self, pool_opts: &PoolOpts, mut count: usize) -> Vec<Conn> {
+        let max_lifetime = pool_opts.max_lifetime();
+        let idle_timeout = pool_opts.idle_timeout();
+        if max_lifetime.is_none() && idle_timeout.is_none() {
+            return Vec::new();
+        }
+
+        let now = self.hooks.clock.now();
+        let min = pool_opts.constraints().min();
+        let mut reaped = Vec::new();
+        let mut i = 0;
+        while i < self.connections.len() {
+            let conn = &self.connections[i];
+            let idle = match (idle_timeout, conn.0.idle_since) {
+                (Some(idle_timeout), Some(idle_since)) => {
+                    now.saturating_duration_since(idle_since) > idle_timeout
+                }
+                _ => false,
+            };
+            if is_expired(conn, max_lifetime, now) || (idle && count > min) {
+                log::debug!(
+                    target: "mysql::pool",
+                    "reaping {} connection {}",
+                    if idle { "idle" } else { "expired" },
+                    conn.connection_id()
+                );
+                reaped.extend(self.connections.remove(i));
+                count -= 1;
+            } else {
+                i += 1;
+            }
+        }
+        reaped
+    }
+
+    /// Returns idle connections.
+    pub fn idle(&self) -> impl Iterator<Item = &Conn> {
+        self.connections.iter()
+    }
+
+    /// Takes an idle connection that isn't prewarmed yet (see `PoolOpts::with_prewarm_stmts`).
+    pub fn take_unprewarmed(&mut self) -> Option<Conn> {
+        match self.connections.iter().position(|conn| !conn.0.prewarmed) {
+            Some(position) => self.connections.remove(position),
+            None => None,
+        }
+    }
+
+    /// Removes all idle connections, so that they could be dropped outside of the lock.
+    pub fn take_all(&mut self) -> Vec<Conn> {
+        self.connections.drain(..).collect()
     }
 
-    pub fn push_back(&mut self, conn: Conn) {
+    pub fn push_back(&mut self, mut conn: Conn) {
+        conn.0.idle_since = Some(self.hooks.clock.now());
         self.connections.push_back(conn)
     }
 }
 
+/// Returns `true` if the connection exceeded the maximum lifetime by the given instant.
+pub fn is_expired(conn: &Conn, max_lifetime: Option<Duration>, now: Instant) -> bool {
+    match max_lifetime {
+        Some(max_lifetime) => now.saturating_duration_since(conn.0.created_at) > max_lifetime,
+        None => false,
+    }
+}
+
 pub struct Inner {
     protected: (Mutex<Protected>, Condvar),
     pool_opts: PoolOpts,
     count: AtomicUsize,
+    /// Connections created before the current generation are stale (see `Pool::drain`).
+    generation: AtomicU64,
+    latency_tracker: Option<LatencyTracker>,
+    stmt_registry: Option<StmtRegistry>,
+    hooks: PoolHooks,
 }
 
 impl Inner {
@@ -83,6 +202,20 @@ impl Inner {
         value
     }
 
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Makes all existing connections stale.
+    pub fn next_generation(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Returns `true` if the connection was created before the pool was drained.
+    pub fn is_stale(&self, conn: &Conn) -> bool {
+        conn.0.pool_generation < self.generation()
+    }
+
     pub fn is_full(&self) -> bool {
         self.count() == self.max_constraint()
     }
@@ -99,11 +232,49 @@ impl Inner {
         &self.protected
     }
 
-    pub fn new(opts: Opts) -> crate::Result<Self> {
+    pub fn latency_tracker(&self) -> Option<&LatencyTracker> {
+        self.latency_tracker.as_ref()
+    }
+
+    pub fn stmt_registry(&self) -> Option<&StmtRegistry> {
+        self.stmt_registry.as_ref()
+    }
+
+    pub fn hooks(&self) -> &PoolHooks {
+        &self.hooks
+    }
+
+    pub fn new(opts: Opts, hooks: PoolHooks) -> crate::Result<Self> {
+        let latency_tracker = if opts.get_track_stmt_latency() {
+            Some(LatencyTracker::default())
+        } else {
+            None
+        };
+        let pool_opts = opts.get_pool_opts();
+        let stmt_registry = if pool_opts.stmt_registry()
+            && !pool_opts.reset_connection()
+            && opts.get_stmt_cache_size() > 0
+        {
+            Some(StmtRegistry::new(opts.get_stmt_cache_size()))
+        } else {
+            None
+        };
         Ok(Self {
-            count: AtomicUsize::new(opts.get_pool_opts().constraints().min()),
-            pool_opts: opts.get_pool_opts().clone(),
-            protected: (Mutex::new(Protected::new(opts)?), Condvar::new()),
+            count: AtomicUsize::new(pool_opts.warm_up()),
+            generation: AtomicU64::new(0),
+            pool_opts: pool_opts.clone(),
+            protected: (
+                Mutex::new(Protected::new(
+                    opts,
+                    latency_tracker.clone(),
+                    stmt_registry.clone(),
+                    hooks.clone(),
+                )?),
+                Condvar::new(),
+            ),
+            latency_tracker,
+            stmt_registry,
+            hooks,
         })
     }
 }
@@ -6,20 +6,21 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::{
-    fmt,
-    ops::Deref,
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::{cmp::Reverse, collections::HashMap, fmt, ops::Deref, sync::Arc, thread, time::Duration};
 
 use crate::{
-    conn::query_result::{Binary, Text},
+    conn::{
+        latency::LatencyTracker,
+        metrics,
+        query_result::{Binary, Text},
+    },
     prelude::*,
-    ChangeUserOpts, Conn, DriverError, LocalInfileHandler, Opts, Params, QueryResult, Result,
-    Statement, Transaction, TxOpts,
+    CachePolicy, ChangeUserOpts, Conn, Cursor, DriverError, LatencyStats, LocalInfileHandler, Opts,
+    Params, QueryResult, Result, Statement, StreamedParam, Transaction, TxOpts, ValidationMode,
+    Value,
 };
 
+pub(crate) mod hooks;
 mod inner;
 
 /// Thread-safe cloneable smart pointer to a connection pool.
@@ -57,6 +58,10 @@ mod inner;
 /// # });
 /// ```
 ///
+/// It's safe to keep using the pool after `fork` (e.g. in a pre-fork server). Connections
+/// inherited from the parent process are never written to by the child, they are replaced
+/// with new connections before the first command instead.
+///
 /// For more info on how to work with mysql connection please look at
 /// [`PooledConn`](struct.PooledConn.html) documentation.
 #[derive(Clone)]
@@ -74,10 +79,29 @@ impl Pool {
         timeout: Option<Duration>,
         mut call_ping: bool,
     ) -> Result<PooledConn> {
-        let times = timeout.map(|timeout| (Instant::now(), timeout));
+        let clock = &self.inner.hooks().clock;
+        let times = timeout.map(|timeout| (clock.now(), timeout));
+        // retries must not extend the overall timeout
+        let remaining = || {
+            times.map(|(start, timeout)| {
+                timeout.saturating_sub(clock.now().saturating_duration_since(start))
+            })
+        };
 
         let (protected, condvar) = self.inner.protected();
 
+        let reaped = protected
+            .lock()?
+            .reap(self.inner.opts(), self.inner.count());
+        if !reaped.is_empty() {
+            for _ in 0..reaped.len() {
+                self.inner.decrease();
+            }
+            // there is a room for new connections
+            condvar.notify_all();
+            drop(reaped);
+        }
+
         let conn = if !self.inner.opts().reset_connection() {
             // stmt cache considered enabled if reset_connection is false
             if let Some(ref query) = stmt {
@@ -94,20 +118,24 @@ impl Pool {
         } else {
             let mut protected = protected.lock()?;
             loop {
-                if let Some(conn) = protected.pop_front() {
+                if let Some(conn) = protected.pop(self.inner.opts().queue_strategy()) {
                     drop(protected);
                     break conn;
                 } else if self.inner.is_full() {
                     protected = if let Some((start, timeout)) = times {
-                        if start.elapsed() > timeout {
+                        let elapsed = clock.now().saturating_duration_since(start);
+                        if elapsed >= timeout {
+                            metrics::pool_checkout("timeout");
                             return Err(DriverError::Timeout.into());
                         }
-                        condvar.wait_timeout(protected, timeout)?.0
+                        condvar
+                            .wait_timeout(protected, clock.wait(timeout - elapsed))?
+                            .0
                     } else {
                         condvar.wait(protected)?
                     }
                 } else {
-                    protected.new_conn()?;
+                    protected.new_conn(self.inner.generation())?;
                     self.inner.increase();
                     // we do not have to call ping for a fresh connection
                     call_ping = false;
@@ -115,56 +143,341 @@ impl Pool {
             }
         };
 
-        if call_ping && self.inner.opts().check_health() && !conn.ping() {
+        if self.inner.is_stale(&conn) {
+            // the pool was drained after this connection was returned, retrying..
+            log::debug!(
+                target: "mysql::pool",
+                "dropping stale connection {}",
+                conn.connection_id()
+            );
+            self.inner.decrease();
+            drop(conn);
+            return self._get_conn(stmt, remaining(), call_ping);
+        }
+
+        if call_ping && self.needs_validation(&conn) && !self.validate(&mut conn) {
             // existing connection seem to be dead, retrying..
+            log::info!(
+                target: "mysql::pool",
+                "connection {} failed validation, dropping it",
+                conn.connection_id()
+            );
+            self.inner.decrease();
+            return self._get_conn(stmt, remaining(), call_ping);
+        }
+
+        if self.warm_up_stmts(&mut conn).is_err() {
+            // the connection is lost while preparing statements, retrying..
+            log::info!(
+                target: "mysql::pool",
+                "connection {} is lost while preparing registered statements",
+                conn.connection_id()
+            );
             self.inner.decrease();
-            return self._get_conn(stmt, timeout, call_ping);
+            return self._get_conn(stmt, remaining(), call_ping);
         }
 
+        if !conn.0.prewarmed && conn.prewarm_pooled().is_err() {
+            // the connection is lost while preparing statements, retrying..
+            log::info!(
+                target: "mysql::pool",
+                "connection {} is lost while preparing prewarm statements",
+                conn.connection_id()
+            );
+            self.inner.decrease();
+            return self._get_conn(stmt, remaining(), call_ping);
+        }
+
+        metrics::pool_checkout("ok");
         Ok(PooledConn {
             pool: self.clone(),
             conn: Some(conn),
         })
     }
 
+    /// Returns `true` if the pooled connection should be validated before it's handed out
+    /// (see [`crate::PoolOpts::with_validation_mode`]).
+    fn needs_validation(&self, conn: &Conn) -> bool {
+        match self.inner.opts().validation_mode() {
+            ValidationMode::None => false,
+            ValidationMode::OnCheckout => true,
+            ValidationMode::IfIdleLongerThan(idle) => match conn.0.idle_since {
+                Some(idle_since) => {
+                    let now = self.inner.hooks().clock.now();
+                    now.saturating_duration_since(idle_since) > idle
+                }
+                None => true,
+            },
+        }
+    }
+
+    /// Validates the connection using either the validation query or `COM_PING`.
+    fn validate(&self, conn: &mut Conn) -> bool {
+        match self.inner.opts().validation_query() {
+            Some(query) => conn.query_drop(query).is_ok(),
+            None => conn.ping(),
+        }
+    }
+
+    /// Prepares registered statements that are unknown to the connection
+    /// (see [`crate::PoolOpts::with_stmt_registry`]).
+    ///
+    /// Returns an error only if the connection is lost.
+    fn warm_up_stmts(&self, conn: &mut Conn) -> Result<()> {
+        if let Some(registry) = self.inner.stmt_registry() {
+            for query in registry.queries() {
+                if conn.has_stmt(&query) {
+                    continue;
+                }
+                match conn._prepare(&query) {
+                    Ok(_) => (),
+                    Err(err) if err.is_connectivity_error() => return Err(err),
+                    Err(_) => registry.unregister(&query),
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Creates new pool with the given options (see [`Opts`]).
     pub fn new<T, E>(opts: T) -> Result<Pool>
     where
         Opts: TryFrom<T, Error = E>,
         crate::Error: From<E>,
     {
-        Ok(Pool {
-            inner: Arc::new(inner::Inner::new(Opts::try_from(opts)?)?),
-        })
+        let pool = Pool {
+            inner: Arc::new(inner::Inner::new(
+                Opts::try_from(opts)?,
+                hooks::PoolHooks::default(),
+            )?),
+        };
+        pool.spawn_prewarm();
+        Ok(pool)
+    }
+
+    /// Creates new pool with the given options and injected time and connect functions.
+    ///
+    /// It allows to test the pool's checkout timeout, reaper and validation logic
+    /// deterministically (see [`crate::testing`]).
+    ///
+    /// ```rust
+    /// # use mysql::{*, testing::*};
+    /// # use std::time::Duration;
+    /// struct Unreachable;
+    ///
+    /// impl Connector for Unreachable {
+    ///     fn connect(&self, _: &Opts) -> Result<Conn> {
+    ///         Err(DriverError::SetupError.into())
+    ///     }
+    /// }
+    ///
+    /// let clock = ManualClock::new();
+    /// let hooks = PoolHooks::new()
+    ///     .with_clock(clock.clone())
+    ///     .with_connector(Unreachable);
+    /// let opts = OptsBuilder::new().pool_opts(
+    ///     PoolOpts::new().with_constraints(PoolConstraints::new_const::<0, 1>()),
+    /// );
+    /// let pool = Pool::new_with_hooks(opts, hooks)?;
+    /// assert!(pool.get_conn().is_err());
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[cfg(feature = "testing")]
+    pub fn new_with_hooks<T, E>(opts: T, hooks: hooks::PoolHooks) -> Result<Pool>
+    where
+        Opts: TryFrom<T, Error = E>,
+        crate::Error: From<E>,
+    {
+        let pool = Pool {
+            inner: Arc::new(inner::Inner::new(Opts::try_from(opts)?, hooks)?),
+        };
+        pool.spawn_prewarm();
+        Ok(pool)
+    }
+
+    /// Prewarms connections established upon pool creation in a background thread
+    /// (see [`crate::PoolOpts::with_prewarm_stmts`]).
+    fn spawn_prewarm(&self) {
+        if self.inner.opts().prewarm_stmts().is_empty() || self.inner.count() == 0 {
+            return;
+        }
+        let pool = self.clone();
+        let spawned = thread::Builder::new()
+            .name("mysql-prewarm".into())
+            .spawn(move || pool.prewarm_idle());
+        if let Err(err) = spawned {
+            // connections will be prewarmed upon checkout
+            log::warn!(target: "mysql::pool", "unable to spawn prewarm thread: {}", err);
+        }
+    }
+
+    /// Prewarms idle connections one by one, so that others are available meanwhile.
+    fn prewarm_idle(&self) {
+        loop {
+            let conn = match self.inner.protected().0.lock() {
+                Ok(mut protected) => protected.take_unprewarmed(),
+                Err(_) => return,
+            };
+            let mut conn = match conn {
+                Some(conn) => conn,
+                None => return,
+            };
+            // statements are prepared just now, so there is nothing to reset
+            conn.0.reset_upon_return = false;
+            if let Err(err) = conn.prewarm_pooled() {
+                log::debug!(
+                    target: "mysql::pool",
+                    "discarding connection {}: {}",
+                    conn.connection_id(),
+                    err
+                );
+                self.inner.decrease();
+                self.inner.protected().1.notify_one();
+                continue;
+            }
+            // returns the connection to the pool
+            drop(PooledConn {
+                pool: self.clone(),
+                conn: Some(conn),
+            });
+        }
     }
 
     /// Gives you a [`PooledConn`](struct.PooledConn.html).
+    ///
+    /// Blocks until a connection is available if the pool is exhausted, unless
+    /// [`crate::PoolOpts::with_checkout_timeout`] is set (see also [`Pool::get_conn_timeout`]
+    /// and [`Pool::try_get_conn_now`]).
     pub fn get_conn(&self) -> Result<PooledConn> {
-        self._get_conn(None::<String>, None, true)
+        self._get_conn(None::<String>, self.inner.opts().checkout_timeout(), true)
     }
 
     /// Will try to get connection for the duration of `timeout`.
     ///
+    /// The timeout covers the whole checkout, including retries caused by connections
+    /// that turn out to be stale or fail validation.
+    ///
     /// # Failure
     /// This function will return `Error::DriverError(DriverError::Timeout)` if timeout was
     /// reached while waiting for new connection to become available.
-    pub fn try_get_conn(&self, timeout: Duration) -> Result<PooledConn> {
+    pub fn get_conn_timeout(&self, timeout: Duration) -> Result<PooledConn> {
         self._get_conn(None::<String>, Some(timeout), true)
     }
 
+    /// Will try to get connection for the duration of `timeout`.
+    #[deprecated = "Please use Pool::get_conn_timeout"]
+    pub fn try_get_conn(&self, timeout: Duration) -> Result<PooledConn> {
+        self.get_conn_timeout(timeout)
+    }
+
+    /// Gives you a [`PooledConn`](struct.PooledConn.html) without waiting for the pool.
+    ///
+    /// Returns `Ok(None)` if the pool is exhausted, i.e. all `max` connections are in use.
+    /// Note that it still may establish a new connection if the pool isn't full.
+    pub fn try_get_conn_now(&self) -> Result<Option<PooledConn>> {
+        match self._get_conn(None::<String>, Some(Duration::ZERO), true) {
+            Ok(conn) => Ok(Some(conn)),
+            Err(crate::Error::DriverError(DriverError::Timeout)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Shortcut for `pool.get_conn()?.start_transaction(..)`.
     pub fn start_transaction(&self, tx_opts: TxOpts) -> Result<Transaction<'static>> {
-        let conn = self._get_conn(None::<String>, None, false)?;
+        let timeout = self.inner.opts().checkout_timeout();
+        let conn = self._get_conn(None::<String>, timeout, false)?;
         let result = conn.pooled_start_transaction(tx_opts);
         match result {
             Ok(trans) => Ok(trans),
-            Err(ref e) if e.is_connectivity_error() => {
-                let conn = self._get_conn(None::<String>, None, true)?;
+            Err(ref e) if e.is_connectivity_error() || e.is_server_shutdown() => {
+                let conn = self._get_conn(None::<String>, timeout, true)?;
                 conn.pooled_start_transaction(tx_opts)
             }
             Err(e) => Err(e),
         }
     }
+
+    /// Drains the pool, i.e. makes all connections established so far stale.
+    ///
+    /// Idle connections are closed immediately, connections that are in use are closed
+    /// once they are returned to the pool, and new connections are established on demand.
+    /// It's useful to move the pool to another server after a failover or before a planned
+    /// maintenance.
+    ///
+    /// The pool drains itself once a connection receives a server shutdown error
+    /// (see [`crate::Error::is_server_shutdown`]), so that callers don't have to
+    /// stumble upon the other connections to a server that is going away.
+    pub fn drain(&self) {
+        self.inner.next_generation();
+        let (protected, condvar) = self.inner.protected();
+        let drained = match protected.lock() {
+            Ok(mut protected) => protected.take_all(),
+            // stale connections will be closed upon checkout
+            Err(_) => return,
+        };
+        log::info!(
+            target: "mysql::pool",
+            "pool drained, closing {} idle connections",
+            drained.len()
+        );
+        for _ in 0..drained.len() {
+            self.inner.decrease();
+        }
+        // there is a room for new connections
+        condvar.notify_all();
+        drop(drained);
+    }
+
+    /// Returns latency statistics of prepared statements executed by connections of this pool,
+    /// keyed by the statement query.
+    ///
+    /// Will be empty unless [`crate::OptsBuilder::track_stmt_latency`] is enabled.
+    pub fn stmt_latency_stats(&self) -> HashMap<String, LatencyStats> {
+        self.inner
+            .latency_tracker()
+            .map(LatencyTracker::stats)
+            .unwrap_or_default()
+    }
+
+    /// Exports queries of statement caches of idle connections, most used first
+    /// (see [`Conn::export_stmt_cache`]).
+    ///
+    /// Connections that are in use at the moment are not considered. Statements registered
+    /// by [`crate::PoolOpts::with_stmt_registry`] are exported as well. The snapshot is meant
+    /// to be imported upon the next startup (see [`crate::PoolOpts::with_prewarm_stmts`]).
+    pub fn export_stmt_cache(&self) -> Vec<String> {
+        let mut index = HashMap::<String, usize>::new();
+        let mut queries: Vec<(String, u64)> = Vec::new();
+        let mut add = |query: String, hits: u64| match index.get(&query) {
+            Some(&i) => queries[i].1 += hits,
+            None => {
+                index.insert(query.clone(), queries.len());
+                queries.push((query, hits));
+            }
+        };
+        if let Ok(protected) = self.inner.protected().0.lock() {
+            for conn in protected.idle() {
+                for stmt in conn.cached_statements() {
+                    if let (None, Ok(query)) =
+                        (stmt.schema(), std::str::from_utf8(stmt.query_bytes()))
+                    {
+                        add(query.to_owned(), stmt.hits());
+                    }
+                }
+            }
+        }
+        if let Some(registry) = self.inner.stmt_registry() {
+            // the most recently registered query goes last
+            for query in registry.queries().into_iter().rev() {
+                if let Ok(query) = String::from_utf8(query.to_vec()) {
+                    add(query, 0);
+                }
+            }
+        }
+        // the sort is stable, so equally used statements remain in the order they were found
+        queries.sort_by_key(|(_, hits)| Reverse(*hits));
+        queries.into_iter().map(|(query, _)| query).collect()
+    }
 }
 
 impl fmt::Debug for Pool {
@@ -227,6 +540,29 @@ impl Deref for PooledConn {
 impl Drop for PooledConn {
     fn drop(&mut self) {
         if let Some(mut conn) = self.conn.take() {
+            if conn.0.server_shutdown && !self.pool.inner.is_stale(&conn) {
+                // other connections to this server are going away too
+                log::warn!(
+                    target: "mysql::pool",
+                    "server is shutting down, draining the pool"
+                );
+                self.pool.drain();
+            }
+
+            let now = self.pool.inner.hooks().clock.now();
+            if self.pool.inner.is_stale(&conn)
+                || inner::is_expired(&conn, self.pool.inner.opts().max_lifetime(), now)
+            {
+                log::debug!(
+                    target: "mysql::pool",
+                    "discarding stale or expired connection {}",
+                    conn.connection_id()
+                );
+                self.pool.inner.decrease();
+                self.pool.inner.protected().1.notify_one();
+                return;
+            }
+
             match conn.cleanup_for_pool() {
                 Ok(_) => {
                     let (protected, condvar) = self.pool.inner.protected();
@@ -242,8 +578,14 @@ impl Drop for PooledConn {
                         }
                     }
                 }
-                Err(_) => {
+                Err(err) => {
                     // the connection is broken
+                    log::debug!(
+                        target: "mysql::pool",
+                        "discarding connection {}: {}",
+                        conn.connection_id(),
+                        err
+                    );
                     self.pool.inner.decrease();
                 }
             }
@@ -258,6 +600,14 @@ impl PooledConn {
         self.conn.as_mut().unwrap().start_transaction(tx_opts)
     }
 
+    /// Redirects to [`Conn::with_transaction`].
+    pub fn with_transaction<F, T>(&mut self, tx_opts: TxOpts, f: F) -> Result<T>
+    where
+        F: FnMut(&mut Transaction<'_>) -> Result<T>,
+    {
+        self.conn.as_mut().unwrap().with_transaction(tx_opts, f)
+    }
+
     /// Turns this connection into a binlog stream (see [`Conn::get_binlog_stream`]).
     pub fn get_binlog_stream(
         mut self,
@@ -306,6 +656,23 @@ impl PooledConn {
             .change_user(ChangeUserOpts::default())
     }
 
+    /// Executes the statement using a server-side cursor (see [`Conn::exec_iter_cursored`]).
+    pub fn exec_iter_cursored<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+        fetch_size: u32,
+    ) -> Result<Cursor<'_, '_, '_>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        self.conn
+            .as_mut()
+            .unwrap()
+            .exec_iter_cursored(stmt, params, fetch_size)
+    }
+
     /// Turns on/off automatic connection reset upon return to a pool (see [`Opts::get_pool_opts`]).
     ///
     /// Initial value is taken from [`crate::PoolOpts::reset_connection`].
@@ -321,10 +688,32 @@ impl Queryable for PooledConn {
         self.conn.as_mut().unwrap().query_iter(query)
     }
 
+    fn query_iter_with_attrs<T: AsRef<str>>(
+        &mut self,
+        query: T,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Text>> {
+        self.conn
+            .as_mut()
+            .unwrap()
+            .query_iter_with_attrs(query, attrs)
+    }
+
     fn prep<T: AsRef<str>>(&mut self, query: T) -> Result<Statement> {
         self.conn.as_mut().unwrap().prep(query)
     }
 
+    fn prep_with_cache_policy<T: AsRef<str>>(
+        &mut self,
+        query: T,
+        policy: CachePolicy,
+    ) -> Result<Statement> {
+        self.conn
+            .as_mut()
+            .unwrap()
+            .prep_with_cache_policy(query, policy)
+    }
+
     fn close(&mut self, stmt: Statement) -> Result<()> {
         self.conn.as_mut().unwrap().close(stmt)
     }
@@ -336,17 +725,62 @@ impl Queryable for PooledConn {
     {
         self.conn.as_mut().unwrap().exec_iter(stmt, params)
     }
+
+    fn exec_batch<S, P, I>(&mut self, stmt: S, params: I) -> Result<()>
+    where
+        Self: Sized,
+        S: AsStatement,
+        P: Into<Params>,
+        I: IntoIterator<Item = P>,
+    {
+        self.conn.as_mut().unwrap().exec_batch(stmt, params)
+    }
+
+    fn exec_iter_with_attrs<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        self.conn
+            .as_mut()
+            .unwrap()
+            .exec_iter_with_attrs(stmt, params, attrs)
+    }
+
+    fn exec_iter_streamed<S>(
+        &mut self,
+        stmt: S,
+        params: Vec<StreamedParam<'_>>,
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+    {
+        self.conn.as_mut().unwrap().exec_iter_streamed(stmt, params)
+    }
 }
 
 #[cfg(test)]
 #[allow(non_snake_case)]
 mod test {
     mod pool {
-        use std::{thread, time::Duration};
+        use std::{
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+            thread,
+            time::Duration,
+        };
 
         use crate::{
-            from_value, prelude::*, test_misc::get_opts, DriverError, Error, OptsBuilder, Pool,
-            PoolConstraints, PoolOpts, TxOpts, Value,
+            from_value, prelude::*, test_misc::get_opts, Conn, DriverError, Error, InitHandler,
+            OptsBuilder, Pool, PoolConstraints, PoolOpts, QueueStrategy, ResetPolicy, TxOpts,
+            ValidationMode, Value,
         };
 
         #[test]
@@ -462,6 +896,192 @@ mod test {
             thread::sleep(Duration::from_millis(250));
             pool.start_transaction(TxOpts::default()).unwrap();
         }
+        #[test]
+        fn should_validate_idle_connections() {
+            let pool = Pool::new(
+                get_opts().pool_opts(
+                    PoolOpts::default()
+                        .with_constraints(PoolConstraints::new_const::<1, 1>())
+                        .with_validation_mode(ValidationMode::IfIdleLongerThan(
+                            Duration::from_millis(100),
+                        ))
+                        .with_validation_query(Some("SELECT 1 FROM dual")),
+                ),
+            )
+            .unwrap();
+
+            let id = pool.get_conn().unwrap().connection_id();
+            Conn::new(get_opts())
+                .unwrap()
+                .query_drop(format!("KILL {}", id))
+                .unwrap();
+            thread::sleep(Duration::from_millis(250));
+
+            // the killed connection is replaced before it's handed out
+            let mut conn = pool.get_conn().unwrap();
+            assert_ne!(conn.connection_id(), id);
+            conn.query_drop("SELECT 1").unwrap();
+        }
+
+        #[test]
+        fn should_reap_expired_connections() {
+            let pool = Pool::new(
+                get_opts().pool_opts(
+                    PoolOpts::default()
+                        .with_constraints(PoolConstraints::new_const::<1, 2>())
+                        .with_max_lifetime(Some(Duration::from_millis(300)))
+                        .with_idle_timeout(Some(Duration::from_millis(100))),
+                ),
+            )
+            .unwrap();
+
+            let conn1 = pool.get_conn().unwrap();
+            let conn2 = pool.get_conn().unwrap();
+            let id2 = conn2.connection_id();
+            drop(conn2);
+            drop(conn1);
+            assert_eq!(pool.inner.count(), 2);
+
+            // one idle connection is closed, another one is kept due to `min`
+            thread::sleep(Duration::from_millis(150));
+            let conn = pool.get_conn().unwrap();
+            assert_eq!(pool.inner.count(), 1);
+            assert_ne!(conn.connection_id(), id2);
+            let id1 = conn.connection_id();
+            drop(conn);
+
+            // the remaining connection exceeds its lifetime
+            thread::sleep(Duration::from_millis(200));
+            let conn = pool.get_conn().unwrap();
+            assert_ne!(conn.connection_id(), id1);
+        }
+
+        #[test]
+        fn should_drain_connections() {
+            let pool = Pool::new(get_opts().pool_opts(
+                PoolOpts::default().with_constraints(PoolConstraints::new_const::<1, 2>()),
+            ))
+            .unwrap();
+
+            let in_use = pool.get_conn().unwrap();
+            let idle = pool.get_conn().unwrap();
+            let (in_use_id, idle_id) = (in_use.connection_id(), idle.connection_id());
+            drop(idle);
+            assert_eq!(pool.inner.count(), 2);
+
+            // idle connection is closed immediately
+            pool.drain();
+            assert_eq!(pool.inner.count(), 1);
+
+            // connection that is in use is closed upon return
+            drop(in_use);
+            assert_eq!(pool.inner.count(), 0);
+
+            let mut conn = pool.get_conn().unwrap();
+            assert_ne!(conn.connection_id(), in_use_id);
+            assert_ne!(conn.connection_id(), idle_id);
+            conn.query_drop("SELECT 1").unwrap();
+        }
+
+        #[test]
+        fn should_run_init_handler_for_new_connections() {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let handler = {
+                let calls = calls.clone();
+                InitHandler::new(move |conn| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    conn.query_drop("SET @init_handler = 42")
+                })
+            };
+            let pool_opts = PoolOpts::default()
+                .with_constraints(PoolConstraints::new_const::<0, 1>())
+                .with_reset_connection(false)
+                .with_init_handler(Some(handler));
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+
+            let mut conn = pool.get_conn().unwrap();
+            let value: Option<u32> = conn.query_first("SELECT @init_handler").unwrap();
+            assert_eq!(value, Some(42));
+            drop(conn);
+
+            // idle connection is reused without calling the handler again
+            pool.get_conn().unwrap();
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+            let handler = InitHandler::new(|conn| conn.query_drop("SELECT * FROM no_such_table"));
+            let pool_opts = PoolOpts::default()
+                .with_constraints(PoolConstraints::new_const::<0, 1>())
+                .with_init_handler(Some(handler));
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+            assert!(matches!(pool.get_conn(), Err(Error::MySqlError(_))));
+            assert_eq!(pool.inner.count(), 0);
+        }
+
+        #[test]
+        fn should_prepare_warm_up_stmts_on_new_connections() {
+            let pool_opts = PoolOpts::default()
+                .with_constraints(PoolConstraints::new_const::<0, 1>())
+                .with_warm_up_stmts(["SELECT :foo", "SELECT ?, ?"]);
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+
+            let conn = pool.get_conn().unwrap();
+            assert!(conn.has_stmt(b"SELECT ?"));
+            assert!(conn.has_stmt(b"SELECT ?, ?"));
+            drop(conn);
+
+            // statements are prepared again after the reset
+            let conn = pool.get_conn().unwrap();
+            assert!(conn.has_stmt(b"SELECT ?"));
+            assert!(conn.has_stmt(b"SELECT ?, ?"));
+            drop(conn);
+
+            let pool_opts = PoolOpts::default()
+                .with_constraints(PoolConstraints::new_const::<0, 1>())
+                .with_warm_up_stmts(["SELECT * FROM no_such_table"]);
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+            assert!(matches!(pool.get_conn(), Err(Error::MySqlError(_))));
+            assert_eq!(pool.inner.count(), 0);
+        }
+
+        #[test]
+        fn should_export_and_prewarm_stmt_cache() {
+            let pool_opts = PoolOpts::default()
+                .with_constraints(PoolConstraints::new_const::<1, 1>())
+                .with_reset_connection(false);
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+            let mut conn = pool.get_conn().unwrap();
+            conn.exec_drop("SELECT ?", (1,)).unwrap();
+            conn.exec_drop("SELECT 1 + ?", (1,)).unwrap();
+            conn.exec_drop("SELECT 1 + ?", (2,)).unwrap();
+            // connections in use are not considered
+            assert!(pool.export_stmt_cache().is_empty());
+            drop(conn);
+            let snapshot = pool.export_stmt_cache();
+            assert_eq!(snapshot, ["SELECT 1 + ?", "SELECT ?"]);
+
+            let pool_opts = PoolOpts::default()
+                .with_constraints(PoolConstraints::new_const::<2, 2>())
+                .with_warm_up(Some(2))
+                .with_prewarm_stmts(
+                    snapshot
+                        .iter()
+                        .map(String::as_str)
+                        .chain(["SELECT * FROM no_such_table"]),
+                );
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+            let conns = [pool.get_conn().unwrap(), pool.get_conn().unwrap()];
+            for conn in &conns {
+                assert!(conn.has_stmt(b"SELECT ?"));
+                assert!(conn.has_stmt(b"SELECT 1 + ?"));
+                assert!(!conn.has_stmt(b"SELECT * FROM no_such_table"));
+            }
+            drop(conns);
+
+            // statements are prepared again after the reset
+            let conn = pool.get_conn().unwrap();
+            assert!(conn.has_stmt(b"SELECT 1 + ?"));
+        }
+
         #[test]
         fn should_execute_queries_on_PooledConn() {
             let pool = Pool::new(get_opts()).unwrap();
@@ -485,15 +1105,132 @@ mod test {
                 PoolOpts::default().with_constraints(PoolConstraints::new_const::<0, 1>()),
             ))
             .unwrap();
-            let conn1 = pool.try_get_conn(Duration::from_millis(357)).unwrap();
-            let conn2 = pool.try_get_conn(Duration::from_millis(357));
+            let conn1 = pool.get_conn_timeout(Duration::from_millis(357)).unwrap();
+            let conn2 = pool.get_conn_timeout(Duration::from_millis(357));
             assert!(conn2.is_err());
             match conn2 {
                 Err(Error::DriverError(DriverError::Timeout)) => assert!(true),
                 _ => assert!(false),
             }
             drop(conn1);
-            assert!(pool.try_get_conn(Duration::from_millis(357)).is_ok());
+            assert!(pool.get_conn_timeout(Duration::from_millis(357)).is_ok());
+            #[allow(deprecated)]
+            let conn = pool.try_get_conn(Duration::from_millis(357));
+            assert!(conn.is_ok());
+        }
+
+        #[test]
+        fn should_not_wait_for_exhausted_pool() {
+            let pool = Pool::new(get_opts().pool_opts(
+                PoolOpts::default().with_constraints(PoolConstraints::new_const::<0, 1>()),
+            ))
+            .unwrap();
+            let conn1 = pool.try_get_conn_now().unwrap().unwrap();
+            assert!(pool.try_get_conn_now().unwrap().is_none());
+            drop(conn1);
+            assert!(pool.try_get_conn_now().unwrap().is_some());
+        }
+
+        #[test]
+        fn should_respect_checkout_timeout_and_queue_strategy() {
+            let pool = Pool::new(
+                get_opts().pool_opts(
+                    PoolOpts::default()
+                        .with_constraints(PoolConstraints::new_const::<0, 2>())
+                        .with_warm_up(Some(2))
+                        .with_checkout_timeout(Some(Duration::from_millis(100)))
+                        .with_queue_strategy(QueueStrategy::Lifo),
+                ),
+            )
+            .unwrap();
+            assert_eq!(pool.inner.count(), 2);
+
+            let conn1 = pool.get_conn().unwrap();
+            let conn2 = pool.get_conn().unwrap();
+            match pool.get_conn() {
+                Err(Error::DriverError(DriverError::Timeout)) => (),
+                _ => panic!("expected timeout"),
+            }
+
+            // the most recently returned connection is reused
+            let id = conn2.connection_id();
+            drop(conn1);
+            drop(conn2);
+            assert_eq!(pool.get_conn().unwrap().connection_id(), id);
+        }
+
+        #[test]
+        fn should_reset_session_state_upon_return() {
+            for policy in [ResetPolicy::None, ResetPolicy::Fast, ResetPolicy::Full] {
+                let pool = Pool::new(
+                    get_opts().pool_opts(
+                        PoolOpts::default()
+                            .with_constraints(PoolConstraints::new_const::<1, 1>())
+                            .with_reset_policy(policy),
+                    ),
+                )
+                .unwrap();
+
+                let mut conn = pool.get_conn().unwrap();
+                conn.query_drop("SET @leaked = 1").unwrap();
+                conn.query_drop("CREATE TEMPORARY TABLE mysql.leaked (id INT)")
+                    .unwrap();
+                conn.query_drop("DO GET_LOCK('leaked', 0)").unwrap();
+                drop(conn);
+
+                let mut conn = pool.get_conn().unwrap();
+                let var: Option<Option<u8>> = conn.query_first("SELECT @leaked").unwrap();
+                let lock: Option<Option<u64>> =
+                    conn.query_first("SELECT IS_USED_LOCK('leaked')").unwrap();
+                let table = conn.query_drop("SELECT * FROM mysql.leaked");
+                if policy == ResetPolicy::None {
+                    assert_eq!(var, Some(Some(1)));
+                    assert!(matches!(lock, Some(Some(_))));
+                    assert!(table.is_ok());
+                } else {
+                    assert_eq!(var, Some(None));
+                    assert_eq!(lock, Some(None));
+                    assert!(table.is_err());
+                }
+            }
+        }
+
+        #[test]
+        fn should_share_statements_between_connections() {
+            let pool = Pool::new(
+                get_opts().pool_opts(
+                    PoolOpts::default()
+                        .with_constraints(PoolConstraints::new_const::<2, 2>())
+                        .with_reset_connection(false)
+                        .with_stmt_registry(true),
+                ),
+            )
+            .unwrap();
+
+            let mut conn1 = pool.get_conn().unwrap();
+            let conn2 = pool.get_conn().unwrap();
+            conn1.prep("SELECT :foo").unwrap();
+            assert!(!conn2.has_stmt(b"SELECT ?"));
+            drop(conn2);
+
+            // the statement is prepared upon checkout
+            let conn2 = pool.get_conn().unwrap();
+            assert!(conn2.has_stmt(b"SELECT ?"));
+            assert!(conn1.has_stmt(b"SELECT ?"));
+
+            // statements that can't be prepared are forgotten
+            conn1
+                .query_drop("CREATE TABLE IF NOT EXISTS mysql.stmt_registry (id INT)")
+                .unwrap();
+            conn1.prep("SELECT id FROM mysql.stmt_registry").unwrap();
+            conn1.query_drop("DROP TABLE mysql.stmt_registry").unwrap();
+            drop(conn2);
+            let conn2 = pool.get_conn().unwrap();
+            assert!(!conn2.has_stmt(b"SELECT id FROM mysql.stmt_registry"));
+            assert_eq!(
+                pool.inner.stmt_registry().unwrap().queries(),
+                vec![std::sync::Arc::new(b"SELECT ?".to_vec())]
+            );
         }
 
         #[test]
@@ -12,8 +12,8 @@ use std::{convert::TryInto, result::Result as StdResult};
 
 use crate::{
     conn::{queryable::AsStatement, ConnMut},
-    from_row, from_row_opt,
     prelude::FromRow,
+    row::{from_row, from_row_opt},
     Binary, Error, Params, QueryResult, Result, Text,
 };
 
@@ -0,0 +1,249 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Serialization of `COM_QUERY` and `COM_STMT_EXECUTE` requests carrying query attributes.
+//!
+//! Once `CLIENT_QUERY_ATTRIBUTES` is negotiated every such request must carry
+//! the (possibly empty) set of attributes, so these requests can't be built by `mysql_common`.
+
+use mysql_common::{
+    constants::{ColumnType, Command, CursorType, StmtExecuteParamFlags},
+    io::WriteMysqlExt,
+    packets::NullBitmap,
+    proto::MySerialize,
+    value::ClientSide,
+};
+
+use crate::Value;
+
+/// `PARAMETER_COUNT_AVAILABLE` flag of a `COM_STMT_EXECUTE` request.
+const PARAMETER_COUNT_AVAILABLE: u8 = 0x08;
+
+/// Serializes `COM_QUERY` request with the given query attributes
+/// (assumes that `CLIENT_QUERY_ATTRIBUTES` is negotiated).
+pub(crate) fn query_request(query: &[u8], attrs: &[(&str, Value)]) -> Vec<u8> {
+    let mut buf = vec![Command::COM_QUERY as u8];
+    buf.write_lenenc_int(attrs.len() as u64).unwrap();
+    // parameter set count
+    buf.write_lenenc_int(1).unwrap();
+    if !attrs.is_empty() {
+        let values = attrs.iter().map(|(_, value)| value).collect::<Vec<_>>();
+        let names = attrs.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        write_params(&mut buf, &values, Some(&names), false);
+    }
+    buf.extend_from_slice(query);
+    buf
+}
+
+/// Serializes `COM_STMT_EXECUTE` request.
+///
+/// * `long_data` – whether `Value::Bytes` parameters are sent using `COM_STMT_SEND_LONG_DATA`;
+/// * `attrs` – query attributes or `None` if `CLIENT_QUERY_ATTRIBUTES` isn't negotiated.
+///
+/// Once `CLIENT_QUERY_ATTRIBUTES` is negotiated the server expects the parameter count
+/// and a name for every parameter, even if there are no attributes.
+pub(crate) fn execute_request(
+    stmt_id: u32,
+    cursor_type: CursorType,
+    params: &[Value],
+    long_data: bool,
+    attrs: Option<&[(&str, Value)]>,
+) -> Vec<u8> {
+    let mut buf = vec![Command::COM_STMT_EXECUTE as u8];
+    buf.extend_from_slice(&stmt_id.to_le_bytes());
+    match attrs {
+        Some(_) => buf.push(cursor_type.bits() | PARAMETER_COUNT_AVAILABLE),
+        None => buf.push(cursor_type.bits()),
+    }
+    // iteration count
+    buf.extend_from_slice(&1_u32.to_le_bytes());
+
+    match attrs {
+        Some(attrs) => {
+            let values = params
+                .iter()
+                .chain(attrs.iter().map(|(_, value)| value))
+                .collect::<Vec<_>>();
+            buf.write_lenenc_int(values.len() as u64).unwrap();
+            if !values.is_empty() {
+                // statement parameters are nameless
+                let names = params
+                    .iter()
+                    .map(|_| "")
+                    .chain(attrs.iter().map(|(name, _)| *name))
+                    .collect::<Vec<_>>();
+                write_params(&mut buf, &values, Some(&names), long_data);
+            }
+        }
+        None if !params.is_empty() => {
+            let values = params.iter().collect::<Vec<_>>();
+            write_params(&mut buf, &values, None, long_data);
+        }
+        None => (),
+    }
+
+    buf
+}
+
+/// Writes null-bitmap, types (and names, if given) and values of parameters.
+fn write_params(buf: &mut Vec<u8>, params: &[&Value], names: Option<&[&str]>, long_data: bool) {
+    let mut bitmap = NullBitmap::<ClientSide>::new(params.len());
+    for (i, param) in params.iter().enumerate() {
+        bitmap.set(i, **param == Value::NULL);
+    }
+    buf.extend_from_slice(bitmap.as_ref());
+    // new params bound
+    buf.push(1);
+
+    for (i, param) in params.iter().enumerate() {
+        let (column_type, flags) = match param {
+            Value::Bytes(_) if long_data => {
+                (ColumnType::MYSQL_TYPE_BLOB, StmtExecuteParamFlags::empty())
+            }
+            _ => param_type(param),
+        };
+        buf.extend_from_slice(&[column_type as u8, flags.bits()]);
+        if let Some(names) = names {
+            buf.write_lenenc_str(names[i].as_bytes()).unwrap();
+        }
+    }
+
+    for param in params {
+        match param {
+            Value::NULL => (),
+            Value::Bytes(_) if long_data => (),
+            _ => param.serialize(buf),
+        }
+    }
+}
+
+/// Type and flags of a parameter of a `COM_STMT_EXECUTE` request
+/// (same as in `mysql_common`).
+pub(crate) fn param_type(param: &Value) -> (ColumnType, StmtExecuteParamFlags) {
+    match param {
+        Value::NULL => (ColumnType::MYSQL_TYPE_NULL, StmtExecuteParamFlags::empty()),
+        Value::Bytes(_) => (
+            ColumnType::MYSQL_TYPE_VAR_STRING,
+            StmtExecuteParamFlags::empty(),
+        ),
+        Value::Int(_) => (
+            ColumnType::MYSQL_TYPE_LONGLONG,
+            StmtExecuteParamFlags::empty(),
+        ),
+        Value::UInt(_) => (
+            ColumnType::MYSQL_TYPE_LONGLONG,
+            StmtExecuteParamFlags::UNSIGNED,
+        ),
+        Value::Float(_) => (ColumnType::MYSQL_TYPE_FLOAT, StmtExecuteParamFlags::empty()),
+        Value::Double(_) => (
+            ColumnType::MYSQL_TYPE_DOUBLE,
+            StmtExecuteParamFlags::empty(),
+        ),
+        Value::Date(..) => (
+            ColumnType::MYSQL_TYPE_DATETIME,
+            StmtExecuteParamFlags::empty(),
+        ),
+        Value::Time(..) => (ColumnType::MYSQL_TYPE_TIME, StmtExecuteParamFlags::empty()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        constants::CursorType, packets::ComStmtExecuteRequestBuilder, proto::MySerialize,
+    };
+
+    use super::{execute_request, query_request};
+    use crate::Value;
+
+    #[test]
+    fn should_serialize_execute_request() {
+        let params = vec![
+            Value::Int(-1),
+            Value::NULL,
+            Value::UInt(1),
+            Value::Double(1.5),
+            Value::Date(2020, 1, 2, 3, 4, 5, 6),
+            Value::Bytes(b"foo".to_vec()),
+        ];
+        let mut expected = Vec::new();
+        ComStmtExecuteRequestBuilder::new(42)
+            .build(&params)
+            .0
+            .serialize(&mut expected);
+        let request = execute_request(42, CursorType::CURSOR_TYPE_NO_CURSOR, &params, false, None);
+        assert_eq!(request, expected);
+
+        let mut expected = Vec::new();
+        ComStmtExecuteRequestBuilder::new(42)
+            .build(&[])
+            .0
+            .serialize(&mut expected);
+        let request = execute_request(42, CursorType::CURSOR_TYPE_NO_CURSOR, &[], false, None);
+        assert_eq!(request, expected);
+
+        // bytes are sent separately, so only the type is written
+        let params = [Value::Bytes(b"foo".to_vec())];
+        let request = execute_request(42, CursorType::CURSOR_TYPE_NO_CURSOR, &params, true, None);
+        assert_eq!(&request[10..], &[0, 1, 252, 0]);
+    }
+
+    #[test]
+    fn should_serialize_query_attributes() {
+        let request = query_request(b"SELECT 1", &[]);
+        assert_eq!(request, b"\x03\x00\x01SELECT 1");
+
+        let attrs = [("a", Value::Int(1)), ("bc", Value::NULL)];
+        let request = query_request(b"SELECT 1", &attrs);
+        let mut expected = vec![0x03, 2, 1];
+        // null-bitmap and new-params-bound flag
+        expected.extend_from_slice(&[0b10, 1]);
+        // types and names
+        expected.extend_from_slice(&[8, 0, 1, b'a', 6, 0, 2, b'b', b'c']);
+        expected.extend_from_slice(&1_i64.to_le_bytes());
+        expected.extend_from_slice(b"SELECT 1");
+        assert_eq!(request, expected);
+
+        let request = execute_request(
+            42,
+            CursorType::CURSOR_TYPE_READ_ONLY,
+            &[Value::UInt(2)],
+            false,
+            Some(&[("a", Value::Int(1))]),
+        );
+        let mut expected = vec![0x17, 42, 0, 0, 0, 0x09, 1, 0, 0, 0];
+        // parameter count, null-bitmap and new-params-bound flag
+        expected.extend_from_slice(&[2, 0, 1]);
+        expected.extend_from_slice(&[8, 0x80, 0, 8, 0, 1, b'a']);
+        expected.extend_from_slice(&2_u64.to_le_bytes());
+        expected.extend_from_slice(&1_i64.to_le_bytes());
+        assert_eq!(request, expected);
+    }
+
+    #[test]
+    fn should_name_params_if_query_attributes_are_negotiated() {
+        // no attributes, but the count and empty names are still written
+        let request = execute_request(
+            42,
+            CursorType::CURSOR_TYPE_NO_CURSOR,
+            &[Value::Int(1), Value::NULL],
+            false,
+            Some(&[]),
+        );
+        let mut expected = vec![0x17, 42, 0, 0, 0, 0x08, 1, 0, 0, 0];
+        // parameter count, null-bitmap and new-params-bound flag
+        expected.extend_from_slice(&[2, 0b10, 1]);
+        // types and empty names
+        expected.extend_from_slice(&[8, 0, 0, 6, 0, 0]);
+        expected.extend_from_slice(&1_i64.to_le_bytes());
+        assert_eq!(request, expected);
+
+        let request = execute_request(42, CursorType::CURSOR_TYPE_NO_CURSOR, &[], false, Some(&[]));
+        assert_eq!(request, [0x17, 42, 0, 0, 0, 0x08, 1, 0, 0, 0, 0]);
+    }
+}
@@ -8,11 +8,142 @@
 
 pub use mysql_common::proto::{Binary, Text};
 
-use mysql_common::{io::ParseBuf, packets::OkPacket, row::RowDeserializer, value::ServerSide};
+use mysql_common::{
+    io::{ParseBuf, ReadMysqlExt},
+    packets::OkPacket,
+    row::RowDeserializer,
+    value::ServerSide,
+};
 
-use std::{borrow::Cow, marker::PhantomData, sync::Arc};
+use std::{
+    borrow::Cow,
+    io,
+    marker::PhantomData,
+    mem,
+    str::FromStr,
+    sync::{mpsc, Arc},
+    thread,
+};
 
-use crate::{conn::ConnMut, Column, Conn, Error, Result, Row};
+use crate::{
+    buffer_pool::Buffer, conn::ConnMut, consts::CapabilityFlags, prelude::FromRow,
+    row::from_row_opt, Column, Conn, DriverError, Error, QueryInfo, Result, Row, UpsertOutcome,
+    Value,
+};
+
+/// Number of rows decoded by a worker at once (see [`QueryResult::collect_parallel`]).
+const PARALLEL_BATCH_LEN: usize = 256;
+
+/// How values of `FLOAT` columns are decoded (see [`crate::OptsBuilder::float_decoding`]).
+///
+/// The binary protocol sends `FLOAT` values as `f32`, so they are decoded as `Value::Float`,
+/// and converting them to `f64` exposes the binary representation of the `f32` value
+/// (e.g. `FLOAT` value `1.1` becomes `1.100000023841858`). The text protocol sends
+/// the decimal representation of the value, so the same column reads as `1.1`.
+///
+/// Parameters are not affected – `f32` values are bound as `MYSQL_TYPE_FLOAT` and `f64` values
+/// are bound as `MYSQL_TYPE_DOUBLE`, so use `f32` parameters to compare `FLOAT` columns
+/// for equality (the server widens both sides the same way).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FloatDecoding {
+    /// `FLOAT` values are decoded as is, i.e. as `Value::Float`.
+    #[default]
+    Exact,
+    /// `FLOAT` values are decoded as their shortest decimal representation, i.e. same as
+    /// in the text protocol (`FLOAT` value `1.1` reads as `1.1_f64` and as `1.1_f32`).
+    Decimal,
+}
+
+impl FloatDecoding {
+    /// Converts `FLOAT` values of the given row according to this policy.
+    pub(crate) fn apply(self, row: &mut Row) {
+        if self == FloatDecoding::Exact {
+            return;
+        }
+        for index in 0..row.len() {
+            if let Some(Value::Float(value)) = row.as_ref(index) {
+                let value = value.to_string().into_bytes();
+                row.place(index, Value::Bytes(value));
+            }
+        }
+    }
+}
+
+/// Parses `exact` or `decimal`.
+impl FromStr for FloatDecoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(FloatDecoding::Exact),
+            "decimal" => Ok(FloatDecoding::Decimal),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Character set number of binary strings.
+const BINARY_CHARSET: u16 = 63;
+
+/// How values of text columns, that aren't valid UTF-8, are decoded
+/// (see [`crate::OptsBuilder::utf8_decoding`]).
+///
+/// Values of text columns are expected to be UTF-8 (`utf8mb4` is the default connection
+/// character set), but a column may still contain invalid data (e.g. bytes stored
+/// using a wrong `SET NAMES`). Columns with the `binary` character set are not affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Utf8Decoding {
+    /// Values are passed as is, i.e. converting them to `String` fails
+    /// (and `from_row` panics), while converting them to `Vec<u8>` succeeds.
+    #[default]
+    Passthrough,
+    /// Invalid sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    Lossy,
+    /// Reading a row, that contains an invalid value, fails
+    /// with [`DriverError::InvalidUtf8`].
+    Strict,
+}
+
+impl Utf8Decoding {
+    /// Checks or converts values of text columns of the given row according to this policy.
+    pub(crate) fn apply(self, row: &mut Row) -> Result<()> {
+        if self == Utf8Decoding::Passthrough {
+            return Ok(());
+        }
+        for index in 0..row.len() {
+            if row.columns_ref()[index].character_set() == BINARY_CHARSET {
+                continue;
+            }
+            let value = match row.as_ref(index) {
+                Some(Value::Bytes(bytes)) => match String::from_utf8_lossy(bytes) {
+                    Cow::Borrowed(_) => continue,
+                    Cow::Owned(value) => value,
+                },
+                _ => continue,
+            };
+            if self == Utf8Decoding::Strict {
+                let column = row.columns_ref()[index].name_str().into_owned();
+                return Err(DriverError::InvalidUtf8(column).into());
+            }
+            row.place(index, Value::Bytes(value.into_bytes()));
+        }
+        Ok(())
+    }
+}
+
+/// Parses `passthrough`, `lossy` or `strict`.
+impl FromStr for Utf8Decoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "passthrough" => Ok(Utf8Decoding::Passthrough),
+            "lossy" => Ok(Utf8Decoding::Lossy),
+            "strict" => Ok(Utf8Decoding::Strict),
+            _ => Err(()),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Or<A, B> {
@@ -22,30 +153,38 @@ pub enum Or<A, B> {
 
 /// Result set kind.
 pub trait Protocol: 'static + Send + Sync {
-    fn next(conn: &mut Conn, columns: Arc<[Column]>) -> Result<Option<Row>>;
-}
+    /// Decodes a row packet.
+    fn decode(pld: &[u8], columns: Arc<[Column]>) -> Result<Row>;
 
-impl Protocol for Text {
     fn next(conn: &mut Conn, columns: Arc<[Column]>) -> Result<Option<Row>> {
         match conn.next_row_packet()? {
             Some(pld) => {
-                let row = ParseBuf(&pld).parse::<RowDeserializer<(), Text>>(columns)?;
-                Ok(Some(row.into()))
+                profile_scope!("mysql::decode_row");
+                let mut row = Self::decode(&pld, columns)?;
+                conn.0.opts.get_float_decoding().apply(&mut row);
+                conn.0.opts.get_utf8_decoding().apply(&mut row)?;
+                #[cfg(feature = "alloc-stats")]
+                conn.0.alloc_stats.record_row(&row);
+                Ok(Some(row))
             }
             None => Ok(None),
         }
     }
 }
 
+impl Protocol for Text {
+    fn decode(pld: &[u8], columns: Arc<[Column]>) -> Result<Row> {
+        Ok(ParseBuf(pld)
+            .parse::<RowDeserializer<(), Text>>(columns)?
+            .into())
+    }
+}
+
 impl Protocol for Binary {
-    fn next(conn: &mut Conn, columns: Arc<[Column]>) -> Result<Option<Row>> {
-        match conn.next_row_packet()? {
-            Some(pld) => {
-                let row = ParseBuf(&pld).parse::<RowDeserializer<ServerSide, Binary>>(columns)?;
-                Ok(Some(row.into()))
-            }
-            None => Ok(None),
-        }
+    fn decode(pld: &[u8], columns: Arc<[Column]>) -> Result<Row> {
+        Ok(ParseBuf(pld)
+            .parse::<RowDeserializer<ServerSide, Binary>>(columns)?
+            .into())
     }
 }
 
@@ -109,6 +248,58 @@ impl From<Or<Vec<Column>, OkPacket<'static>>> for SetIteratorState {
     }
 }
 
+/// Summary of a query or statement execution (see [`Queryable::exec_result`]).
+///
+/// [`Queryable::exec_result`]: crate::prelude::Queryable::exec_result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ExecResult {
+    affected_rows: u64,
+    last_insert_id: Option<u64>,
+    warnings: u16,
+    info: Option<QueryInfo>,
+    found_rows: bool,
+}
+
+impl ExecResult {
+    pub(crate) fn new(ok: Option<&OkPacket<'_>>, found_rows: bool) -> Self {
+        match ok {
+            Some(ok) => Self {
+                affected_rows: ok.affected_rows(),
+                last_insert_id: ok.last_insert_id(),
+                warnings: ok.warnings(),
+                info: ok.info_str().and_then(|info| QueryInfo::parse(&info)),
+                found_rows,
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Returns the number of affected rows.
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    /// Returns the last insert id, if any.
+    pub fn last_insert_id(&self) -> Option<u64> {
+        self.last_insert_id
+    }
+
+    /// Returns the warnings count.
+    pub fn warnings(&self) -> u16 {
+        self.warnings
+    }
+
+    /// Returns the parsed info string (see [`QueryInfo`]).
+    pub fn info(&self) -> Option<QueryInfo> {
+        self.info
+    }
+
+    /// Interprets the number of affected rows as an upsert (see [`UpsertOutcome`]).
+    pub fn upsert_outcome(&self) -> Option<UpsertOutcome> {
+        UpsertOutcome::new(self.affected_rows, self.info, self.found_rows)
+    }
+}
+
 /// Response to a query or statement execution.
 ///
 /// It is an iterator:
@@ -161,6 +352,7 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
             self.set_index += 1;
         } else {
             self.state = SetIteratorState::Done;
+            self.conn.finish_query();
         }
     }
 
@@ -244,6 +436,28 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
         }
     }
 
+    /// Returns an iterator over the remaining result sets.
+    ///
+    /// Unlike [`QueryResult::iter`] it's a regular `Iterator`, but every result set is read
+    /// into memory before it's yielded. An errored result set is yielded as `Err`
+    /// (note that the server stops executing a multi-statement query upon an error).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let mut result = conn.query_iter("SELECT 1, 2; DO 1; SELECT 'foo'")?;
+    /// for set in result.iter_sets() {
+    ///     let set = set?;
+    ///     println!("{} columns, {} rows", set.columns().len(), set.rows().len());
+    /// }
+    /// # });
+    /// ```
+    pub fn iter_sets(&mut self) -> ResultSets<'_, 'c, 't, 'tc, T> {
+        ResultSets { inner: self }
+    }
+
     /// Returns the number of affected rows for the current result set.
     pub fn affected_rows(&self) -> u64 {
         self.state
@@ -292,12 +506,294 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
             .unwrap_or_else(|| "".into())
     }
 
+    /// Parsed [`QueryResult::info_str`] for the current result set (see [`QueryInfo`]).
+    pub fn info(&self) -> Option<QueryInfo> {
+        QueryInfo::parse(&self.info_str())
+    }
+
+    /// Consumes all result sets and returns the summary of the last one.
+    ///
+    /// Unlike dropping the result, this will return the first error, if any.
+    pub fn exec_result(mut self) -> Result<ExecResult> {
+        while let Some(set) = self.iter() {
+            for row in set {
+                row?;
+            }
+        }
+        Ok(ExecResult::new(
+            self.conn.0.ok_packet.as_ref(),
+            self.conn.has_capability(CapabilityFlags::CLIENT_FOUND_ROWS),
+        ))
+    }
+
     /// Returns columns of the current result rest.
     pub fn columns(&self) -> SetColumns {
         SetColumns {
             inner: self.state.columns().map(Into::into),
         }
     }
+
+    /// Returns the first row of the current result set.
+    ///
+    /// Fails with `DriverError::TooManyRows` if there is another row and `Opts::get_strict_first`
+    /// is `true` (remaining rows are dropped along with `self`).
+    pub(crate) fn first_row(&mut self) -> Result<Option<Row>> {
+        let row = self.next().transpose()?;
+        if row.is_some() && self.conn.0.opts.get_strict_first() {
+            if let Some(next) = self.next() {
+                next?;
+                return Err(DriverError::TooManyRows.into());
+            }
+        }
+        Ok(row)
+    }
+
+    /// Collects rows of the current result set decoding them on `workers` threads.
+    ///
+    /// Rows are read by the current thread and decoded (and converted to `R`) by worker threads
+    /// in batches, so decoding overlaps with network reads. It's useful for large result sets
+    /// with many columns, otherwise decoding is cheap compared to network reads.
+    /// The order of rows is preserved. Consumes the current result set.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let mut result = conn.query_iter("SELECT * FROM information_schema.columns")?;
+    /// let rows: Vec<(String, String)> = result.collect_parallel(4)?;
+    /// # assert!(!rows.is_empty());
+    /// # });
+    /// ```
+    pub fn collect_parallel<R>(&mut self, workers: usize) -> Result<Vec<R>>
+    where
+        R: FromRow + Send,
+    {
+        let columns = match self.state.columns() {
+            Some(columns) => columns.clone(),
+            None => {
+                for row in self.by_ref() {
+                    row?;
+                }
+                return Ok(Vec::new());
+            }
+        };
+
+        let float_decoding = self.conn.0.opts.get_float_decoding();
+        let utf8_decoding = self.conn.0.opts.get_utf8_decoding();
+        thread::scope(|scope| {
+            let (results_tx, results_rx) = mpsc::channel();
+            let mut batches_txs = Vec::new();
+            for _ in 0..workers.max(1) {
+                let (batches_tx, batches_rx) = mpsc::sync_channel::<(usize, Vec<Buffer>)>(2);
+                let results_tx = results_tx.clone();
+                let columns = columns.clone();
+                scope.spawn(move || {
+                    for (index, batch) in batches_rx {
+                        let decoded = batch
+                            .iter()
+                            .map(|pld| {
+                                profile_scope!("mysql::decode_row");
+                                let mut row = T::decode(pld, columns.clone())?;
+                                float_decoding.apply(&mut row);
+                                utf8_decoding.apply(&mut row)?;
+                                Ok(from_row_opt(row)?)
+                            })
+                            .collect::<Result<Vec<R>>>();
+                        if results_tx.send((index, decoded)).is_err() {
+                            break;
+                        }
+                    }
+                });
+                batches_txs.push(batches_tx);
+            }
+            drop(results_tx);
+
+            let mut read_result = Ok(());
+            let mut batch = Vec::with_capacity(PARALLEL_BATCH_LEN);
+            let mut index = 0;
+            loop {
+                let pld = match self.next_raw() {
+                    Some(Ok(pld)) => Some(pld),
+                    Some(Err(err)) => {
+                        read_result = Err(err);
+                        None
+                    }
+                    None => None,
+                };
+                let done = pld.is_none();
+                batch.extend(pld);
+                if batch.len() == PARALLEL_BATCH_LEN || (done && !batch.is_empty()) {
+                    let batch = mem::replace(&mut batch, Vec::with_capacity(PARALLEL_BATCH_LEN));
+                    // a worker only stops if its results are not needed anymore
+                    let _ = batches_txs[index % batches_txs.len()].send((index, batch));
+                    index += 1;
+                }
+                if done {
+                    break;
+                }
+            }
+            drop(batches_txs);
+
+            let mut decoded = results_rx.into_iter().collect::<Vec<_>>();
+            read_result?;
+            decoded.sort_by_key(|(index, _)| *index);
+            let mut rows = Vec::new();
+            for (_, batch) in decoded {
+                rows.extend(batch?);
+            }
+            Ok(rows)
+        })
+    }
+
+    /// Same as `Iterator::next` but returns the row packet as is, i.e. without decoding it.
+    ///
+    /// Returns `None` at the end of the current result set and moves to the next one
+    /// (same as [`QueryResult::iter`] does once the set is consumed).
+    ///
+    /// It's a low-level API for proxies and caching layers that forward results verbatim.
+    /// Rows are not affected by [`crate::OptsBuilder::float_decoding`].
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let mut result = conn.query_iter("SELECT 'foo', NULL UNION ALL SELECT 'bar', 42")?;
+    /// let mut packets = Vec::new();
+    /// while let Some(row) = result.next_raw_row() {
+    ///     let row = row?;
+    ///     assert_eq!(row.columns().len(), 2);
+    ///     packets.push(row.as_bytes().to_vec());
+    /// }
+    /// assert_eq!(packets[0], b"\x03foo\xfb");
+    /// assert_eq!(packets[1], b"\x03bar\x0242");
+    /// # });
+    /// ```
+    pub fn next_raw_row(&mut self) -> Option<Result<RawRow<T>>> {
+        let columns = self.state.columns().cloned();
+        let packet = self.next_raw()?;
+        Some(packet.map(|packet| RawRow {
+            packet,
+            // row packets only belong to sets with columns
+            columns: columns.unwrap_or_else(|| Vec::new().into()),
+            protocol: PhantomData,
+        }))
+    }
+
+    /// Same as `Iterator::next` but returns a raw row packet.
+    pub(crate) fn next_raw(&mut self) -> Option<Result<Buffer>> {
+        use SetIteratorState::*;
+
+        let state = std::mem::replace(&mut self.state, OnBoundary);
+
+        match state {
+            InSet(cols) => match self.conn.next_row_packet() {
+                Ok(Some(pld)) => {
+                    self.state = InSet(cols);
+                    Some(Ok(pld))
+                }
+                Ok(None) => {
+                    self.handle_next();
+                    None
+                }
+                Err(e) => {
+                    self.handle_next();
+                    Some(Err(e))
+                }
+            },
+            InEmptySet(_) => {
+                self.handle_next();
+                None
+            }
+            Errored(err) => {
+                self.handle_next();
+                Some(Err(err))
+            }
+            OnBoundary => None,
+            Done => {
+                self.state = Done;
+                None
+            }
+        }
+    }
+}
+
+/// Row packet of a result set, that isn't decoded (see [`QueryResult::next_raw_row`]).
+#[derive(Debug)]
+pub struct RawRow<T> {
+    packet: Buffer,
+    columns: Arc<[Column]>,
+    protocol: PhantomData<T>,
+}
+
+impl<T: crate::prelude::Protocol> RawRow<T> {
+    /// Returns the payload of the row packet (without the packet header),
+    /// encoded according to the protocol `T`.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.packet
+    }
+
+    /// Returns columns of the result set this row belongs to.
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Decodes this row.
+    pub fn decode(&self) -> Result<Row> {
+        T::decode(&self.packet, self.columns.clone())
+    }
+}
+
+impl QueryResult<'_, '_, '_, Text> {
+    /// Folds the current result set over raw bytes of the given column.
+    ///
+    /// Cells aren't decoded into `Value`s, i.e. `f` receives bytes exactly as they were sent
+    /// by the server (`None` stands for `NULL`), other columns are skipped. Consumes the current
+    /// result set.
+    pub fn fold_bytes<U, F>(&mut self, column: usize, init: U, mut f: F) -> Result<U>
+    where
+        F: FnMut(U, Option<&[u8]>) -> U,
+    {
+        let num_columns = self.columns().as_ref().len();
+        if num_columns > 0 && column >= num_columns {
+            // consume the set anyway
+            while let Some(pld) = self.next_raw() {
+                pld?;
+            }
+            return Err(DriverError::ColumnIndexOutOfRange(column, num_columns).into());
+        }
+
+        let mut acc = init;
+        while let Some(pld) = self.next_raw() {
+            let pld = pld?;
+            acc = f(acc, text_cell(&pld, column)?);
+        }
+        Ok(acc)
+    }
+}
+
+/// Extracts a cell from a text protocol row without decoding it.
+fn text_cell(mut row: &[u8], column: usize) -> io::Result<Option<&[u8]>> {
+    for _ in 0..column {
+        next_text_cell(&mut row)?;
+    }
+    next_text_cell(&mut row)
+}
+
+/// Splits the next cell off a text protocol row without decoding it (`None` stands for `NULL`).
+pub(crate) fn next_text_cell<'a>(row: &mut &'a [u8]) -> io::Result<Option<&'a [u8]>> {
+    if row.first() == Some(&0xfb) {
+        *row = &row[1..];
+        return Ok(None);
+    }
+    let len = row.read_lenenc_int()? as usize;
+    if row.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (cell, rest) = row.split_at(len);
+    *row = rest;
+    Ok(Some(cell))
 }
 
 impl<'c, 't, 'tc, T: crate::prelude::Protocol> Drop for QueryResult<'c, 't, 'tc, T> {
@@ -378,6 +874,140 @@ impl<T: crate::prelude::Protocol> Drop for ResultSet<'_, '_, '_, '_, T> {
     }
 }
 
+/// Iterator over result sets of a [`QueryResult`] (see [`QueryResult::iter_sets`]).
+#[derive(Debug)]
+pub struct ResultSets<'d, 'a, 'b, 'c, T: crate::prelude::Protocol> {
+    inner: &'d mut QueryResult<'a, 'b, 'c, T>,
+}
+
+impl<T: crate::prelude::Protocol> Iterator for ResultSets<'_, '_, '_, '_, T> {
+    type Item = Result<CollectedSet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let columns = self.inner.state.columns().cloned();
+        let summary = ExecResult::new(
+            self.inner.state.ok_packet(),
+            self.inner
+                .conn
+                .has_capability(CapabilityFlags::CLIENT_FOUND_ROWS),
+        );
+        let set = self.inner.iter()?;
+        let rows = match set.collect::<Result<Vec<_>>>() {
+            Ok(rows) => rows,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(Ok(CollectedSet {
+            columns: columns.unwrap_or_else(|| Vec::new().into()),
+            rows,
+            summary,
+        }))
+    }
+}
+
+/// Result set read into memory (see [`QueryResult::iter_sets`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectedSet {
+    columns: Arc<[Column]>,
+    rows: Vec<Row>,
+    summary: ExecResult,
+}
+
+impl CollectedSet {
+    /// Returns columns of this result set (empty if it's not a row set).
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Returns rows of this result set.
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// Returns rows of this result set.
+    pub fn into_rows(self) -> Vec<Row> {
+        self.rows
+    }
+
+    /// Returns the number of affected rows (for a result set without rows).
+    pub fn affected_rows(&self) -> u64 {
+        self.summary.affected_rows()
+    }
+
+    /// Returns the last insert id (for a result set without rows).
+    pub fn last_insert_id(&self) -> Option<u64> {
+        self.summary.last_insert_id()
+    }
+
+    /// Returns the warnings count (for a result set without rows).
+    pub fn warnings(&self) -> u16 {
+        self.summary.warnings()
+    }
+
+    /// Returns the parsed info string (for a result set without rows).
+    pub fn info(&self) -> Option<QueryInfo> {
+        self.summary.info()
+    }
+}
+
+impl IntoIterator for CollectedSet {
+    type Item = Row;
+    type IntoIter = std::vec::IntoIter<Row>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+/// Result set discarded by [`Conn::drain_results`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSummary {
+    columns: Option<Arc<[Column]>>,
+    rows: u64,
+    summary: ExecResult,
+}
+
+impl ResultSummary {
+    pub(crate) fn new(columns: Option<Arc<[Column]>>, rows: u64, summary: ExecResult) -> Self {
+        Self {
+            columns,
+            rows,
+            summary,
+        }
+    }
+
+    /// Returns columns of this result set (empty if it's not a row set).
+    ///
+    /// Returns `None` if the set was partially read, so its columns are unknown.
+    pub fn columns(&self) -> Option<&[Column]> {
+        self.columns.as_deref()
+    }
+
+    /// Returns the number of discarded rows.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Returns the number of affected rows (for a result set without rows).
+    pub fn affected_rows(&self) -> u64 {
+        self.summary.affected_rows()
+    }
+
+    /// Returns the last insert id (for a result set without rows).
+    pub fn last_insert_id(&self) -> Option<u64> {
+        self.summary.last_insert_id()
+    }
+
+    /// Returns the warnings count.
+    pub fn warnings(&self) -> u16 {
+        self.summary.warnings()
+    }
+
+    /// Returns the parsed info string (for a result set without rows).
+    pub fn info(&self) -> Option<QueryInfo> {
+        self.summary.info()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct SetColumns<'a> {
     inner: Option<&'a Arc<[Column]>>,
@@ -399,3 +1029,68 @@ impl<'a> SetColumns<'a> {
             .unwrap_or(&[][..])
     }
 }
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{constants::ColumnType, row::new_row};
+
+    use super::{text_cell, FloatDecoding, Utf8Decoding};
+    use crate::{Column, DriverError, Error, Value};
+
+    #[test]
+    fn should_decode_floats_as_decimals() {
+        let columns = [ColumnType::MYSQL_TYPE_FLOAT, ColumnType::MYSQL_TYPE_DOUBLE]
+            .into_iter()
+            .map(Column::new)
+            .collect::<Vec<_>>();
+        let values = vec![Value::Float(1.1), Value::Double(f64::from(1.1_f32))];
+        let mut row = new_row(values.clone(), columns.into());
+
+        FloatDecoding::Exact.apply(&mut row);
+        assert_eq!(row.clone().unwrap(), values);
+
+        FloatDecoding::Decimal.apply(&mut row);
+        assert_eq!(row[0], Value::from("1.1"));
+        assert_eq!(row.get::<f64, _>(0), Some(1.1));
+        assert_eq!(row.get::<f32, _>(0), Some(1.1));
+        assert_eq!(row.get::<f64, _>(1), Some(1.100000023841858));
+    }
+
+    #[test]
+    fn should_decode_invalid_utf8() {
+        let columns = vec![
+            Column::new(ColumnType::MYSQL_TYPE_VAR_STRING)
+                .with_name(b"text")
+                .with_character_set(45),
+            Column::new(ColumnType::MYSQL_TYPE_BLOB).with_character_set(63),
+        ];
+        let values = vec![Value::from(&b"a\xffb"[..]), Value::from(&b"\xff"[..])];
+        let row = new_row(values.clone(), columns.into());
+
+        let mut passthrough = row.clone();
+        Utf8Decoding::Passthrough.apply(&mut passthrough).unwrap();
+        assert_eq!(passthrough.unwrap(), values);
+
+        let mut lossy = row.clone();
+        Utf8Decoding::Lossy.apply(&mut lossy).unwrap();
+        assert_eq!(lossy.get::<String, _>(0).as_deref(), Some("a\u{fffd}b"));
+        assert_eq!(lossy[1], values[1]);
+
+        let mut strict = row;
+        let err = Utf8Decoding::Strict.apply(&mut strict).unwrap_err();
+        assert!(
+            matches!(err, Error::DriverError(DriverError::InvalidUtf8(ref column)) if column == "text")
+        );
+    }
+
+    #[test]
+    fn should_extract_text_cell() {
+        let row = [3, b'f', b'o', b'o', 0xfb, 0, 2, b'4', b'2'];
+        assert_eq!(text_cell(&row, 0).unwrap(), Some(&b"foo"[..]));
+        assert_eq!(text_cell(&row, 1).unwrap(), None);
+        assert_eq!(text_cell(&row, 2).unwrap(), Some(&b""[..]));
+        assert_eq!(text_cell(&row, 3).unwrap(), Some(&b"42"[..]));
+        assert!(text_cell(&row, 4).is_err());
+        assert!(text_cell(&row[..2], 0).is_err());
+    }
+}
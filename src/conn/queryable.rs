@@ -7,14 +7,18 @@
 // modified, or distributed except according to those terms.
 
 use mysql_common::row::convert::FromRowError;
+use serde::de::DeserializeOwned;
 
 use std::{borrow::Cow, result::Result as StdResult};
 
 use crate::{
-    conn::query_result::{Binary, Text},
-    from_row, from_row_opt,
-    prelude::FromRow,
-    Params, QueryResult, Result, Statement,
+    conn::{
+        hints::{Hinted, OptimizerHints},
+        query_result::{Binary, ExecResult, Text},
+    },
+    prelude::{DeserializeRow, FromRow},
+    row::{from_row, from_row_opt},
+    CachePolicy, Params, QueryResult, Result, Statement, StreamedParam, Value,
 };
 
 /// Something, that eventually is a `Statement` in the context of a `T: Queryable`.
@@ -28,6 +32,34 @@ pub trait Queryable {
     /// Performs text query.
     fn query_iter<Q: AsRef<str>>(&mut self, query: Q) -> Result<QueryResult<'_, '_, '_, Text>>;
 
+    /// Performs text query with the given query attributes.
+    ///
+    /// Attributes are name-value pairs, that the server exposes to the query
+    /// (via `mysql_query_attribute_string()`) and to plugins, so it's a way to attach
+    /// e.g. trace ids to individual queries. Attributes require `CLIENT_QUERY_ATTRIBUTES`
+    /// (MySql 8.0.23+) and are ignored if the server doesn't support them.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let pool = Pool::new(get_opts())?;
+    /// # let mut conn = pool.get_conn()?;
+    /// let attrs = [("trace_id", Value::from("4bf92f3577b34da6"))];
+    /// conn.query_iter_with_attrs("SELECT 1", &attrs)?;
+    /// # });
+    /// ```
+    ///
+    /// Defaults to [`Queryable::query_iter`], i.e. attributes are ignored.
+    fn query_iter_with_attrs<Q: AsRef<str>>(
+        &mut self,
+        query: Q,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Text>> {
+        let _ = attrs;
+        self.query_iter(query)
+    }
+
     /// Performs text query and collects the first result set.
     fn query<T, Q>(&mut self, query: Q) -> Result<Vec<T>>
     where
@@ -47,15 +79,14 @@ pub trait Queryable {
     }
 
     /// Performs text query and returns the first row of the first result set.
+    ///
+    /// Other rows are discarded, unless `OptsBuilder::strict_first` is set.
     fn query_first<T, Q>(&mut self, query: Q) -> Result<Option<T>>
     where
         Q: AsRef<str>,
         T: FromRow,
     {
-        self.query_iter(query)?
-            .next()
-            .map(|row| row.map(from_row))
-            .transpose()
+        Ok(self.query_iter(query)?.first_row()?.map(from_row))
     }
 
     /// Same as [`Queryable::query_first`] but useful when you not sure what your schema is.
@@ -64,10 +95,7 @@ pub trait Queryable {
         Q: AsRef<str>,
         T: FromRow,
     {
-        self.query_iter(query)?
-            .next()
-            .map(|row| row.map(from_row_opt))
-            .transpose()
+        Ok(self.query_iter(query)?.first_row()?.map(from_row_opt))
     }
 
     /// Performs text query and maps each row of the first result set.
@@ -108,6 +136,19 @@ pub trait Queryable {
             .try_fold(init, |acc, row: Result<T>| row.map(|row| f(acc, row)))
     }
 
+    /// Performs text query and folds the first result set over raw bytes of the given column.
+    ///
+    /// Unlike [`Queryable::query_fold`] it won't decode rows, so `f` receives the cell bytes
+    /// as they were sent by the server (`None` stands for `NULL`). It's useful to compute
+    /// checksums or sizes over huge columns (see [`QueryResult::fold_bytes`]).
+    fn query_fold_bytes<F, Q, U>(&mut self, query: Q, column: usize, init: U, f: F) -> Result<U>
+    where
+        Q: AsRef<str>,
+        F: FnMut(U, Option<&[u8]>) -> U,
+    {
+        self.query_iter(query)?.fold_bytes(column, init, f)
+    }
+
     /// Same as [`Queryable::query_fold`] but useful when you not sure what your schema is.
     fn query_fold_opt<T, F, Q, U>(&mut self, query: Q, init: U, mut f: F) -> Result<U>
     where
@@ -122,6 +163,36 @@ pub trait Queryable {
             })
     }
 
+    /// Performs text query and deserializes each row of the first result set into a serde type
+    /// (see [`DeserializeRow::deserialize`]).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # use serde_derive::Deserialize;
+    /// #[derive(Debug, PartialEq, Deserialize)]
+    /// struct User {
+    ///     id: u32,
+    ///     name: String,
+    ///     email: Option<String>,
+    /// }
+    ///
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let users: Vec<User> = conn.query_as("SELECT 1 AS id, 'foo' AS name, NULL AS email")?;
+    /// assert_eq!(users, [User { id: 1, name: "foo".into(), email: None }]);
+    /// # });
+    /// ```
+    fn query_as<T, Q>(&mut self, query: Q) -> Result<Vec<T>>
+    where
+        Q: AsRef<str>,
+        T: DeserializeOwned,
+    {
+        self.query_iter(query)?
+            .map(|row| row.and_then(|row| row.deserialize()))
+            .collect()
+    }
+
     /// Performs text query and drops the query result.
     fn query_drop<Q>(&mut self, query: Q) -> Result<()>
     where
@@ -130,9 +201,31 @@ pub trait Queryable {
         self.query_iter(query).map(drop)
     }
 
+    /// Performs text query and returns the summary of the last result set
+    /// (see [`QueryResult::exec_result`]).
+    fn query_result<Q>(&mut self, query: Q) -> Result<ExecResult>
+    where
+        Q: AsRef<str>,
+    {
+        self.query_iter(query)?.exec_result()
+    }
+
     /// Prepares the given `query` as a prepared statement.
     fn prep<Q: AsRef<str>>(&mut self, query: Q) -> Result<crate::Statement>;
 
+    /// Prepares the given `query` using the given statement cache policy
+    /// (see [`CachePolicy::apply`]).
+    ///
+    /// Defaults to [`Queryable::prep`], i.e. the policy is ignored.
+    fn prep_with_cache_policy<Q: AsRef<str>>(
+        &mut self,
+        query: Q,
+        policy: CachePolicy,
+    ) -> Result<Statement> {
+        let _ = policy;
+        self.prep(query)
+    }
+
     /// This function will close the given statement on the server side.
     fn close(&mut self, stmt: Statement) -> Result<()>;
 
@@ -142,6 +235,55 @@ pub trait Queryable {
         S: AsStatement,
         P: Into<Params>;
 
+    /// Executes the given `stmt` with the given `params` and query attributes
+    /// (see [`Queryable::query_iter_with_attrs`]).
+    ///
+    /// Defaults to [`Queryable::exec_iter`], i.e. attributes are ignored.
+    fn exec_iter_with_attrs<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        let _ = attrs;
+        self.exec_iter(stmt, params)
+    }
+
+    /// Executes the given `stmt` with the given positional `params`, some of which could be
+    /// streamed from readers (see [`StreamedParam`]).
+    ///
+    /// Readers and `Value::Bytes` parameters are sent in chunks using `COM_STMT_SEND_LONG_DATA`,
+    /// so huge values (e.g. multi-gigabyte `LONGBLOB`s) are never materialized in memory.
+    /// Note that the total size of a value is still limited by the server
+    /// (see `max_long_data_size` in older MySql versions).
+    ///
+    /// Defaults to [`Queryable::exec_iter`] with readers read into memory.
+    fn exec_iter_streamed<S>(
+        &mut self,
+        stmt: S,
+        params: Vec<StreamedParam<'_>>,
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+    {
+        let mut values = Vec::with_capacity(params.len());
+        for param in params {
+            match param {
+                StreamedParam::Value(value) => values.push(value),
+                StreamedParam::Reader(mut reader) => {
+                    let mut bytes = Vec::new();
+                    reader.read_to_end(&mut bytes)?;
+                    values.push(Value::Bytes(bytes));
+                }
+            }
+        }
+        self.exec_iter(stmt, Params::Positional(values))
+    }
+
     /// Prepares the given statement, and executes it with each item in the given params iterator.
     fn exec_batch<S, P, I>(&mut self, stmt: S, params: I) -> Result<()>
     where
@@ -179,16 +321,15 @@ pub trait Queryable {
     }
 
     /// Executes the given `stmt` and returns the first row of the first result set.
+    ///
+    /// Other rows are discarded, unless `OptsBuilder::strict_first` is set.
     fn exec_first<T, S, P>(&mut self, stmt: S, params: P) -> Result<Option<T>>
     where
         S: AsStatement,
         P: Into<Params>,
         T: FromRow,
     {
-        self.exec_iter(stmt, params)?
-            .next()
-            .map(|row| row.map(crate::from_row))
-            .transpose()
+        Ok(self.exec_iter(stmt, params)?.first_row()?.map(from_row))
     }
 
     /// Same as [`Queryable::exec_first`] but useful when you not sure what your schema is.
@@ -202,10 +343,7 @@ pub trait Queryable {
         P: Into<Params>,
         T: FromRow,
     {
-        self.exec_iter(stmt, params)?
-            .next()
-            .map(|row| row.map(from_row_opt))
-            .transpose()
+        Ok(self.exec_iter(stmt, params)?.first_row()?.map(from_row_opt))
     }
 
     /// Executes the given `stmt` and maps each row of the first result set.
@@ -260,6 +398,19 @@ pub trait Queryable {
         result.try_fold(init, |init, row| row.map(|row| f(init, from_row_opt(row))))
     }
 
+    /// Executes the given `stmt` and deserializes each row of the first result set
+    /// into a serde type (see [`Queryable::query_as`]).
+    fn exec_as<T, S, P>(&mut self, stmt: S, params: P) -> Result<Vec<T>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+        T: DeserializeOwned,
+    {
+        self.exec_iter(stmt, params)?
+            .map(|row| row.and_then(|row| row.deserialize()))
+            .collect()
+    }
+
     /// Executes the given `stmt` and drops the result.
     fn exec_drop<S, P>(&mut self, stmt: S, params: P) -> Result<()>
     where
@@ -268,4 +419,51 @@ pub trait Queryable {
     {
         self.exec_iter(stmt, params).map(drop)
     }
+
+    /// Returns a queryable that attaches the given optimizer hints to queries
+    /// (see [`OptimizerHints`]).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # use std::time::Duration;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let hints = OptimizerHints::new().with_max_execution_time(Duration::from_secs(5));
+    /// let count: Option<u64> = conn
+    ///     .with_hints(hints)
+    ///     .exec_first("SELECT COUNT(*) FROM information_schema.tables", ())?;
+    /// # assert!(count.is_some());
+    /// # });
+    /// ```
+    fn with_hints(&mut self, hints: OptimizerHints) -> Hinted<'_, Self>
+    where
+        Self: Sized,
+    {
+        Hinted::new(self, hints)
+    }
+
+    /// Executes the given `stmt` and returns the summary of the last result set
+    /// (see [`QueryResult::exec_result`]).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop(
+    ///     "CREATE TEMPORARY TABLE mysql.tbl (id INT AUTO_INCREMENT PRIMARY KEY, a INT)",
+    /// )?;
+    /// let result = conn.exec_result("INSERT INTO mysql.tbl (a) VALUES (?)", (42,))?;
+    /// assert_eq!(result.affected_rows(), 1);
+    /// assert_eq!(result.last_insert_id(), Some(1));
+    /// # });
+    /// ```
+    fn exec_result<S, P>(&mut self, stmt: S, params: P) -> Result<ExecResult>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        self.exec_iter(stmt, params)?.exec_result()
+    }
 }
@@ -0,0 +1,235 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::HashMap;
+
+use crate::{Params, Value};
+
+/// Value that replaces redacted values.
+pub const REDACTED: &[u8] = b"<redacted>";
+
+/// Kind of a [`Value`] (see [`RedactionPolicy::with_kind`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    Bytes,
+    Int,
+    UInt,
+    Float,
+    Double,
+    Date,
+    Time,
+}
+
+impl ValueKind {
+    /// Returns the kind of the given value (`None` for `NULL`).
+    pub fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::NULL => None,
+            Value::Bytes(_) => Some(ValueKind::Bytes),
+            Value::Int(_) => Some(ValueKind::Int),
+            Value::UInt(_) => Some(ValueKind::UInt),
+            Value::Float(_) => Some(ValueKind::Float),
+            Value::Double(_) => Some(ValueKind::Double),
+            Value::Date(..) => Some(ValueKind::Date),
+            Value::Time(..) => Some(ValueKind::Time),
+        }
+    }
+}
+
+/// Defines values that must never appear in the output of the driver's observability
+/// features, such as [`crate::SlowQuery::params`] (see [`crate::OptsBuilder::redaction_policy`]).
+///
+/// A value is redacted (i.e. replaced with [`REDACTED`]) if its name matches one
+/// of the name patterns or if its kind is listed. Names are only known for named parameters,
+/// so positional parameters are only redacted by kind. `NULL` is never redacted.
+///
+/// Note that the text of a query is reported as is, so values that should be redacted
+/// must be passed as parameters.
+///
+/// ```
+/// # use mysql::{RedactionPolicy, ValueKind, Value};
+/// let policy = RedactionPolicy::new()
+///     .with_name_pattern("*password*")
+///     .with_name_pattern("ssn")
+///     .with_kind(ValueKind::Date);
+///
+/// assert_eq!(policy.redact(Some("user_password"), &Value::from("secret")), Value::from("<redacted>"));
+/// assert_eq!(policy.redact(Some("login"), &Value::from("root")), Value::from("root"));
+/// assert_eq!(policy.redact(None, &Value::Date(2000, 1, 1, 0, 0, 0, 0)), Value::from("<redacted>"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RedactionPolicy {
+    name_patterns: Vec<String>,
+    kinds: Vec<ValueKind>,
+}
+
+impl RedactionPolicy {
+    /// Creates a policy that redacts nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a case-insensitive name pattern, where `*` matches any sequence of characters.
+    pub fn with_name_pattern<T: Into<String>>(mut self, pattern: T) -> Self {
+        self.name_patterns.push(pattern.into().to_lowercase());
+        self
+    }
+
+    /// Redacts every value of the given kind.
+    pub fn with_kind(mut self, kind: ValueKind) -> Self {
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+        self
+    }
+
+    /// Returns `true` if this policy redacts nothing.
+    pub fn is_empty(&self) -> bool {
+        self.name_patterns.is_empty() && self.kinds.is_empty()
+    }
+
+    /// Returns `true` if the value with the given name (if known) must be redacted.
+    pub fn should_redact(&self, name: Option<&str>, value: &Value) -> bool {
+        let kind = match ValueKind::of(value) {
+            Some(kind) => kind,
+            None => return false,
+        };
+        if self.kinds.contains(&kind) {
+            return true;
+        }
+        match name {
+            Some(name) => {
+                let name = name.to_lowercase();
+                self.name_patterns
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, &name))
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the value or [`REDACTED`], if the value must be redacted.
+    pub fn redact(&self, name: Option<&str>, value: &Value) -> Value {
+        if self.should_redact(name, value) {
+            Value::Bytes(REDACTED.to_vec())
+        } else {
+            value.clone()
+        }
+    }
+
+    /// Redacts statement parameters.
+    ///
+    /// `named_params` are names of positional parameters, if the statement has named parameters.
+    pub fn redact_params(&self, params: &Params, named_params: Option<&[Vec<u8>]>) -> Params {
+        if self.is_empty() {
+            return params.clone();
+        }
+        match params {
+            Params::Empty => Params::Empty,
+            Params::Named(values) => Params::Named(
+                values
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = self.redact(Some(&String::from_utf8_lossy(name)), value);
+                        (name.clone(), value)
+                    })
+                    .collect::<HashMap<_, _>>(),
+            ),
+            Params::Positional(values) => Params::Positional(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        let name = named_params
+                            .and_then(|names| names.get(i))
+                            .map(|name| String::from_utf8_lossy(name));
+                        self.redact(name.as_deref(), value)
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Matches the name against the pattern, where `*` matches any sequence of characters.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let (last, middle) = match parts.split_last() {
+        Some(split) => split,
+        // there is no `*`
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{matches_pattern, RedactionPolicy, ValueKind};
+    use crate::{params, Params, Value};
+
+    #[test]
+    fn should_match_name_patterns() {
+        assert!(matches_pattern("password", "password"));
+        assert!(!matches_pattern("password", "password2"));
+        assert!(matches_pattern("*password*", "user_password_hash"));
+        assert!(matches_pattern("*_token", "api_token"));
+        assert!(!matches_pattern("*_token", "token"));
+        assert!(matches_pattern("card_*_number", "card_1_number"));
+        assert!(!matches_pattern("card_*_number", "card_number"));
+        assert!(matches_pattern("*", ""));
+    }
+
+    #[test]
+    fn should_redact_params() {
+        let policy = RedactionPolicy::new()
+            .with_name_pattern("*Password*")
+            .with_kind(ValueKind::Double);
+
+        let params = policy.redact_params(
+            &params! { "user" => "root", "PASSWORD" => "secret", "token" => Value::NULL },
+            None,
+        );
+        assert_eq!(
+            params,
+            params! { "user" => "root", "PASSWORD" => "<redacted>", "token" => Value::NULL }
+        );
+
+        let named_params = vec![b"id".to_vec(), b"new_password".to_vec()];
+        let params = policy.redact_params(
+            &Params::Positional(vec![
+                Value::Int(1),
+                Value::from("secret"),
+                Value::Double(1.5),
+            ]),
+            Some(&named_params),
+        );
+        assert_eq!(
+            params,
+            Params::Positional(vec![
+                Value::Int(1),
+                Value::from("<redacted>"),
+                Value::from("<redacted>"),
+            ])
+        );
+
+        let params = Params::Positional(vec![Value::from("secret")]);
+        assert_eq!(RedactionPolicy::new().redact_params(&params, None), params);
+    }
+}
@@ -0,0 +1,425 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::constants::CapabilityFlags;
+
+use std::{fmt, str::FromStr};
+
+use crate::Error;
+
+/// Server implementation detected from the handshake (see [`crate::Conn::server_flavor`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServerFlavor {
+    MySql,
+    MariaDb,
+    /// Percona Server (detected using its `<version>-<build>` version format).
+    Percona,
+    TiDB,
+    /// Vitess (`vtgate`).
+    Vitess,
+}
+
+impl ServerFlavor {
+    /// Detects the flavor from the server version string of the initial handshake.
+    pub(crate) fn detect(server_version: &[u8]) -> Self {
+        let version = String::from_utf8_lossy(server_version);
+        if version.contains("MariaDB") {
+            ServerFlavor::MariaDb
+        } else if version.contains("TiDB") {
+            ServerFlavor::TiDB
+        } else if version.contains("Vitess") {
+            ServerFlavor::Vitess
+        } else {
+            // Percona Server appends the build number, e.g. `8.0.35-27` or `5.7.44-48-log`,
+            // while MySQL appends a suffix such as `-log`, `-debug` or `-0ubuntu0.22.04.1`
+            let suffix = version
+                .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+                .strip_prefix('-');
+            let build = suffix.map(|suffix| suffix.split('-').next().unwrap_or_default());
+            match build {
+                Some(build)
+                    if !build.is_empty()
+                        && build.chars().all(|c| c.is_ascii_digit() || c == '.') =>
+                {
+                    ServerFlavor::Percona
+                }
+                _ => ServerFlavor::MySql,
+            }
+        }
+    }
+
+    /// Returns `true` for servers that speak the MariaDB dialect of the protocol.
+    pub fn is_mariadb(self) -> bool {
+        self == ServerFlavor::MariaDb
+    }
+}
+
+impl fmt::Display for ServerFlavor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerFlavor::MySql => write!(f, "MySQL"),
+            ServerFlavor::MariaDb => write!(f, "MariaDB"),
+            ServerFlavor::Percona => write!(f, "Percona Server"),
+            ServerFlavor::TiDB => write!(f, "TiDB"),
+            ServerFlavor::Vitess => write!(f, "Vitess"),
+        }
+    }
+}
+
+/// SQL feature that is only available in some server versions (see [`crate::Conn::supports`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    /// Common table expressions (`WITH`), including `WITH RECURSIVE`.
+    CommonTableExpressions,
+    /// Window functions (`OVER (...)`).
+    WindowFunctions,
+    /// `JSON_TABLE` table function.
+    JsonTable,
+    /// Invisible columns (`INVISIBLE` column attribute).
+    InvisibleColumns,
+}
+
+impl Feature {
+    /// Returns `true` if the feature is available in the given version of the given server.
+    ///
+    /// The version is ignored for TiDB, which is assumed to be 5.1 or later
+    /// (it reports the version of MySQL it's compatible with).
+    pub fn is_supported_by(self, flavor: ServerFlavor, version: (u16, u16, u16)) -> bool {
+        let min_version = match (flavor, self) {
+            (ServerFlavor::TiDB, Feature::CommonTableExpressions | Feature::WindowFunctions) => {
+                return true
+            }
+            (ServerFlavor::TiDB, Feature::JsonTable | Feature::InvisibleColumns) => return false,
+            (ServerFlavor::MariaDb, Feature::CommonTableExpressions) => (10, 2, 1),
+            (ServerFlavor::MariaDb, Feature::WindowFunctions) => (10, 2, 0),
+            (ServerFlavor::MariaDb, Feature::JsonTable) => (10, 6, 0),
+            (ServerFlavor::MariaDb, Feature::InvisibleColumns) => (10, 3, 3),
+            (_, Feature::CommonTableExpressions) => (8, 0, 1),
+            (_, Feature::WindowFunctions) => (8, 0, 2),
+            (_, Feature::JsonTable) => (8, 0, 4),
+            (_, Feature::InvisibleColumns) => (8, 0, 23),
+        };
+        version >= min_version
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Feature::CommonTableExpressions => write!(f, "common table expressions"),
+            Feature::WindowFunctions => write!(f, "window functions"),
+            Feature::JsonTable => write!(f, "JSON_TABLE"),
+            Feature::InvisibleColumns => write!(f, "invisible columns"),
+        }
+    }
+}
+
+/// Checks the version against a requirement such as `>=8.0.18` or `>=10.2, <10.6`
+/// (comparisons are separated by commas, missing version components are zeros).
+///
+/// Returns `None` if the requirement can't be parsed.
+pub(crate) fn matches_version(requirement: &str, version: (u16, u16, u16)) -> Option<bool> {
+    let mut matches = true;
+    for comparison in requirement.split(',') {
+        let comparison = comparison.trim();
+        let (op, rest) = ["<=", ">=", "==", "<", ">", "="]
+            .iter()
+            .find_map(|op| comparison.strip_prefix(op).map(|rest| (*op, rest)))?;
+        let mut parts = rest.trim().split('.');
+        let mut next = || parts.next().map(str::parse::<u16>).transpose().ok();
+        let required = (next()??, next()?.unwrap_or(0), next()?.unwrap_or(0));
+        if parts.next().is_some() {
+            return None;
+        }
+        matches &= match op {
+            "<=" => version <= required,
+            ">=" => version >= required,
+            "<" => version < required,
+            ">" => version > required,
+            _ => version == required,
+        };
+    }
+    Some(matches)
+}
+
+/// Compatibility profile for MySQL-compatible servers (see [`crate::OptsBuilder::compat_mode`]).
+///
+/// The driver can't reliably tell what a server supports from the handshake alone,
+/// so a profile disables features known to misbehave on the given server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CompatMode {
+    /// MySQL or MariaDB semantics.
+    #[default]
+    Standard,
+    /// TiDB semantics:
+    ///
+    /// * `CLIENT_SESSION_TRACK` is never requested, since TiDB only partially implements
+    ///   session state tracking;
+    /// * TiKV write conflicts (`9007`) and retryable transaction errors (`8002`, `8022`)
+    ///   are retried by [`crate::Conn::with_transaction`], same as deadlocks;
+    /// * stored procedures are not supported.
+    TiDB,
+    /// Vitess (`vtgate`) semantics:
+    ///
+    /// * `CLIENT_SESSION_TRACK` is never requested, since `vtgate` doesn't track
+    ///   the session state of the underlying `mysqld`;
+    /// * `prefer_socket` is ignored, because `@@socket` is the socket of a tablet's `mysqld`,
+    ///   and connecting to it directly would bypass `vtgate`;
+    /// * stored procedures are only supported without `OUT` and `INOUT` parameters.
+    Vitess,
+}
+
+impl CompatMode {
+    /// Returns `false` if `OUT` and `INOUT` parameters of stored procedures aren't supported.
+    pub fn supports_out_params(self) -> bool {
+        self == CompatMode::Standard
+    }
+
+    /// Capabilities that must not be requested.
+    pub(crate) fn unsupported_capabilities(self) -> CapabilityFlags {
+        match self {
+            CompatMode::Standard => CapabilityFlags::empty(),
+            CompatMode::TiDB | CompatMode::Vitess => CapabilityFlags::CLIENT_SESSION_TRACK,
+        }
+    }
+
+    /// Returns `false` if the socket reported by the server must not be used
+    /// (see [`crate::Opts::get_prefer_socket`]).
+    pub(crate) fn allows_socket_improvement(self) -> bool {
+        self != CompatMode::Vitess
+    }
+
+    /// Returns `true` if the transaction that failed with `err` could be retried.
+    pub(crate) fn is_retryable_tx_error(self, err: &Error) -> bool {
+        /// `ErrTxnRetryable`
+        const ER_TIDB_TXN_RETRYABLE: u16 = 8002;
+        /// `ErrWriteConflictInTiDB`
+        const ER_TIDB_WRITE_CONFLICT: u16 = 8022;
+        /// `ErrWriteConflict`
+        const ER_TIKV_WRITE_CONFLICT: u16 = 9007;
+
+        match (self, err) {
+            _ if err.is_retryable_tx_error() => true,
+            (CompatMode::TiDB, Error::MySqlError(err)) => {
+                err.code == ER_TIDB_TXN_RETRYABLE
+                    || err.code == ER_TIDB_WRITE_CONFLICT
+                    || err.code == ER_TIKV_WRITE_CONFLICT
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parses `standard`, `tidb` or `vitess`.
+impl FromStr for CompatMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(CompatMode::Standard),
+            "tidb" => Ok(CompatMode::TiDB),
+            "vitess" => Ok(CompatMode::Vitess),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Extended capabilities advertised by a MariaDB server (`MARIADB_CLIENT_*` flags).
+///
+/// MariaDB sends them in the last four reserved bytes of the initial handshake
+/// (see [`crate::Conn::mariadb_capabilities`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MariadbCapabilities(u32);
+
+impl MariadbCapabilities {
+    /// Client supports progress indicator.
+    pub const PROGRESS: Self = MariadbCapabilities(1 << 0);
+    /// Permit `COM_MULTI` protocol.
+    pub const COM_MULTI: Self = MariadbCapabilities(1 << 1);
+    /// Permit bulk insert (array binding of `COM_STMT_BULK_EXECUTE`).
+    pub const STMT_BULK_OPERATIONS: Self = MariadbCapabilities(1 << 2);
+    /// Add extended metadata information.
+    pub const EXTENDED_TYPE_INFO: Self = MariadbCapabilities(1 << 3);
+    /// Permit skipping metadata.
+    pub const CACHE_METADATA: Self = MariadbCapabilities(1 << 4);
+    /// Permit per-row results of `COM_STMT_BULK_EXECUTE` (MariaDB 11.5+).
+    pub const BULK_UNIT_RESULTS: Self = MariadbCapabilities(1 << 6);
+
+    /// Capabilities requested by the driver if the server supports them.
+    pub(crate) const REQUESTED: Self =
+        MariadbCapabilities(Self::STMT_BULK_OPERATIONS.0 | Self::BULK_UNIT_RESULTS.0);
+
+    /// Creates capabilities from raw bits.
+    pub const fn from_bits(bits: u32) -> Self {
+        MariadbCapabilities(bits)
+    }
+
+    /// Returns raw bits.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if all the given flags are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns flags set in both `self` and `other`.
+    pub const fn intersection(self, other: Self) -> Self {
+        MariadbCapabilities(self.0 & other.0)
+    }
+
+    /// Returns `true` if no flags are set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Parses extended capabilities from the raw initial handshake packet.
+    ///
+    /// These are only sent by MariaDB servers, which signal it by clearing the
+    /// `CLIENT_LONG_PASSWORD` (a.k.a. `CLIENT_MYSQL`) capability flag.
+    pub(crate) fn parse(handshake: &[u8]) -> Self {
+        const CLIENT_MYSQL: u8 = 1;
+        // protocol version and nul-terminated server version
+        let version_end = match handshake.iter().skip(1).position(|x| *x == 0) {
+            Some(position) => position + 2,
+            None => return Self::default(),
+        };
+        // connection id (4), scramble (8), filler (1), lower capabilities (2), collation (1),
+        // status (2), upper capabilities (2), auth plugin data length (1), reserved (6)
+        let capabilities_offset = version_end + 4 + 8 + 1;
+        let offset = version_end + 27;
+        match (
+            handshake.get(capabilities_offset),
+            handshake.get(offset..offset + 4),
+        ) {
+            (Some(capabilities), Some(bytes)) if capabilities & CLIENT_MYSQL == 0 => {
+                MariadbCapabilities(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            }
+            _ => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{matches_version, CompatMode, Feature, MariadbCapabilities, ServerFlavor};
+    use crate::{Error, MySqlError};
+
+    #[test]
+    fn should_detect_server_flavor() {
+        for (version, flavor) in [
+            ("8.0.36", ServerFlavor::MySql),
+            ("5.7.44-log", ServerFlavor::MySql),
+            ("8.0.36-0ubuntu0.22.04.1", ServerFlavor::MySql),
+            ("8.0.35-27", ServerFlavor::Percona),
+            ("5.7.44-48-log", ServerFlavor::Percona),
+            ("8.0.35-27.1", ServerFlavor::Percona),
+            ("5.5.5-10.4.6-MariaDB-log", ServerFlavor::MariaDb),
+            (
+                "11.2.2-MariaDB-1:11.2.2+maria~ubu2204",
+                ServerFlavor::MariaDb,
+            ),
+            ("5.7.25-TiDB-v7.5.0", ServerFlavor::TiDB),
+            ("8.0.30-Vitess", ServerFlavor::Vitess),
+        ] {
+            assert_eq!(
+                ServerFlavor::detect(version.as_bytes()),
+                flavor,
+                "{}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn should_parse_mariadb_capabilities() {
+        fn handshake(version: &str, lower_capabilities: u16, extended: u32) -> Vec<u8> {
+            let mut packet = vec![10];
+            packet.extend_from_slice(version.as_bytes());
+            packet.push(0);
+            packet.extend_from_slice(&42_u32.to_le_bytes());
+            packet.extend_from_slice(&[1; 8]);
+            packet.push(0);
+            packet.extend_from_slice(&lower_capabilities.to_le_bytes());
+            packet.push(45);
+            packet.extend_from_slice(&2_u16.to_le_bytes());
+            packet.extend_from_slice(&0x81ff_u16.to_le_bytes());
+            packet.push(21);
+            packet.extend_from_slice(&[0; 6]);
+            packet.extend_from_slice(&extended.to_le_bytes());
+            packet.extend_from_slice(&[2; 13]);
+            packet
+        }
+
+        let capabilities =
+            MariadbCapabilities::parse(&handshake("11.2.2-MariaDB", 0xf7fe, 0b11101));
+        assert!(capabilities.contains(MariadbCapabilities::STMT_BULK_OPERATIONS));
+        assert!(capabilities.contains(MariadbCapabilities::CACHE_METADATA));
+        assert!(!capabilities.contains(MariadbCapabilities::COM_MULTI));
+
+        // `CLIENT_MYSQL` is set, so reserved bytes are ignored
+        let capabilities = MariadbCapabilities::parse(&handshake("8.0.36", 0xffff, 0b11101));
+        assert!(capabilities.is_empty());
+
+        assert!(MariadbCapabilities::parse(b"\x0a8.0.36").is_empty());
+    }
+
+    #[test]
+    fn should_map_tidb_errors() {
+        let err = |code| {
+            Error::MySqlError(MySqlError {
+                state: "HY000".into(),
+                message: String::new(),
+                code,
+            })
+        };
+
+        assert!(CompatMode::Standard.is_retryable_tx_error(&err(1213)));
+        assert!(!CompatMode::Standard.is_retryable_tx_error(&err(9007)));
+        assert!(CompatMode::TiDB.is_retryable_tx_error(&err(9007)));
+        assert!(CompatMode::TiDB.is_retryable_tx_error(&err(1205)));
+        assert!(!CompatMode::Vitess.is_retryable_tx_error(&err(9007)));
+        assert!(!CompatMode::TiDB.is_retryable_tx_error(&err(1062)));
+    }
+
+    #[test]
+    fn should_check_feature_support() {
+        use Feature::*;
+
+        assert!(!CommonTableExpressions.is_supported_by(ServerFlavor::MySql, (5, 7, 44)));
+        assert!(CommonTableExpressions.is_supported_by(ServerFlavor::Percona, (8, 0, 35)));
+        assert!(CommonTableExpressions.is_supported_by(ServerFlavor::MariaDb, (10, 2, 1)));
+        assert!(!JsonTable.is_supported_by(ServerFlavor::MariaDb, (10, 5, 20)));
+        assert!(JsonTable.is_supported_by(ServerFlavor::MySql, (8, 0, 4)));
+        assert!(!InvisibleColumns.is_supported_by(ServerFlavor::MySql, (8, 0, 22)));
+        assert!(InvisibleColumns.is_supported_by(ServerFlavor::MariaDb, (10, 3, 3)));
+        assert!(WindowFunctions.is_supported_by(ServerFlavor::TiDB, (5, 7, 25)));
+        assert!(!JsonTable.is_supported_by(ServerFlavor::TiDB, (8, 0, 11)));
+    }
+
+    #[test]
+    fn should_match_version_requirements() {
+        let version = (8, 0, 18);
+        assert_eq!(matches_version(">=8.0.18", version), Some(true));
+        assert_eq!(matches_version(">8.0.18", version), Some(false));
+        assert_eq!(matches_version("<= 8.0.18", version), Some(true));
+        assert_eq!(matches_version("<8", version), Some(false));
+        assert_eq!(matches_version("=8.0.18", version), Some(true));
+        assert_eq!(matches_version("==8.0", version), Some(false));
+        assert_eq!(matches_version(">=8.0, <8.1", version), Some(true));
+        assert_eq!(matches_version(">=5.7, <8", version), Some(false));
+
+        assert_eq!(matches_version("8.0.18", version), None);
+        assert_eq!(matches_version(">=", version), None);
+        assert_eq!(matches_version(">=8.0.x", version), None);
+        assert_eq!(matches_version(">=8.0.18.1", version), None);
+        assert_eq!(matches_version(">=8.0,", version), None);
+    }
+}
@@ -0,0 +1,402 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    fmt,
+    hash::Hasher,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{conn::interpolate::visit_code, Params, Row, Statement};
+
+/// Statements, that could be explained.
+const EXPLAINABLE: &[&[u8]] = &[
+    b"SELECT", b"INSERT", b"UPDATE", b"DELETE", b"REPLACE", b"TABLE", b"WITH",
+];
+
+/// Whether to run `EXPLAIN` for a slow query (see [`crate::OptsBuilder::explain_slow_queries`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ExplainMode {
+    /// Never run `EXPLAIN`.
+    #[default]
+    Off,
+    /// Run `EXPLAIN` for every `n`-th slow query of a connection (`0` is the same as `Off`).
+    Sampled(u32),
+    /// Run `EXPLAIN` for every slow query.
+    Always,
+}
+
+impl ExplainMode {
+    /// Returns `true` if the `n`-th (starting from `1`) slow query should be explained.
+    pub(crate) fn should_explain(self, n: u64) -> bool {
+        match self {
+            ExplainMode::Off => false,
+            ExplainMode::Sampled(rate) => n.checked_rem(u64::from(rate)) == Some(0),
+            ExplainMode::Always => true,
+        }
+    }
+}
+
+/// Parses `off`, `always` or `sampled:<n>`.
+impl FromStr for ExplainMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(ExplainMode::Off),
+            "always" => Ok(ExplainMode::Always),
+            _ => s
+                .strip_prefix("sampled:")
+                .and_then(|rate| rate.parse().ok())
+                .map(ExplainMode::Sampled)
+                .ok_or(()),
+        }
+    }
+}
+
+/// A query that exceeded the slow query threshold
+/// (see [`crate::OptsBuilder::slow_query_threshold`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowQuery {
+    pub(crate) query: String,
+    pub(crate) normalized_query: String,
+    pub(crate) digest: String,
+    pub(crate) params: Option<Params>,
+    pub(crate) params_count: u16,
+    pub(crate) elapsed: Duration,
+    pub(crate) rows_examined: Option<u64>,
+    pub(crate) plan: Option<Vec<Row>>,
+}
+
+impl SlowQuery {
+    /// Text of the query.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Text of the query with literals replaced by `?`, comments removed, whitespace collapsed
+    /// and lists of values collapsed to `...`, e.g. `SELECT * FROM t WHERE id IN(...)`.
+    ///
+    /// Queries, that differ only in literal values, have the same normalized text.
+    pub fn normalized_query(&self) -> &str {
+        &self.normalized_query
+    }
+
+    /// Hash of [`SlowQuery::normalized_query`] (16 hex digits), that could be used
+    /// to group slow queries.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    /// Number of parameters of the statement (`0` for a text query).
+    pub fn params_count(&self) -> u16 {
+        self.params_count
+    }
+
+    /// Parameters of the statement (`None` for a text query).
+    ///
+    /// Values are redacted according to [`crate::OptsBuilder::redaction_policy`].
+    pub fn params(&self) -> Option<&Params> {
+        self.params.as_ref()
+    }
+
+    /// Time elapsed from sending the query to reading the end of its result.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Number of rows examined by the server to execute this query.
+    ///
+    /// It's read from `performance_schema.events_statements_history`, so it's `None`
+    /// if the performance schema (or this consumer) is disabled or not accessible.
+    pub fn rows_examined(&self) -> Option<u64> {
+        self.rows_examined
+    }
+
+    /// Output of `EXPLAIN` for this query.
+    ///
+    /// `None` if `EXPLAIN` is turned off for this query (see [`ExplainMode`])
+    /// or if the query can't be explained.
+    pub fn plan(&self) -> Option<&[Row]> {
+        self.plan.as_deref()
+    }
+}
+
+/// Callback that receives slow queries (see [`crate::OptsBuilder::slow_query_handler`]).
+///
+/// ```rust
+/// # use mysql::SlowQueryHandler;
+/// let handler = SlowQueryHandler::new(|slow_query| {
+///     eprintln!(
+///         "[{}] {} took {:?}",
+///         slow_query.digest(),
+///         slow_query.normalized_query(),
+///         slow_query.elapsed()
+///     );
+/// });
+/// ```
+#[derive(Clone)]
+pub struct SlowQueryHandler(pub(crate) Arc<dyn Fn(&SlowQuery) + Send + Sync>);
+
+impl SlowQueryHandler {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&SlowQuery) + Send + Sync + 'static,
+    {
+        SlowQueryHandler(Arc::new(f))
+    }
+}
+
+impl PartialEq for SlowQueryHandler {
+    fn eq(&self, other: &SlowQueryHandler) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SlowQueryHandler {}
+
+impl fmt::Debug for SlowQueryHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "SlowQueryHandler(...)")
+    }
+}
+
+/// Normalizes the query text (see [`SlowQuery::normalized_query`]).
+pub(crate) fn normalize_query(query: &str) -> String {
+    let mut tokens: Vec<&str> = Vec::new();
+    let mut rest = query;
+    while let Some(first) = rest.chars().next() {
+        let len = match first {
+            _ if first.is_whitespace() => {
+                rest = rest.trim_start();
+                continue;
+            }
+            '#' => rest.find('\n').unwrap_or(rest.len()),
+            '-' if rest.starts_with("-- ") || rest == "--" => rest.find('\n').unwrap_or(rest.len()),
+            '/' if rest.starts_with("/*") => rest[2..].find("*/").map_or(rest.len(), |end| end + 4),
+            '\'' | '"' | '`' => {
+                let len = quoted_len(rest, first);
+                if first != '`' {
+                    tokens.push("?");
+                } else {
+                    tokens.push(&rest[..len]);
+                }
+                rest = &rest[len..];
+                continue;
+            }
+            '0'..='9' => {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+                    .unwrap_or(rest.len());
+                tokens.push("?");
+                rest = &rest[len..];
+                continue;
+            }
+            _ if is_word_char(first) => {
+                let len = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+                tokens.push(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+            '<' | '>' | '=' | '!' | ':' | '|' | '&' => {
+                let len = rest
+                    .find(|c| !matches!(c, '<' | '>' | '=' | '!' | ':' | '|' | '&'))
+                    .unwrap_or(rest.len());
+                tokens.push(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+            _ => {
+                tokens.push(&rest[..first.len_utf8()]);
+                rest = &rest[first.len_utf8()..];
+                continue;
+            }
+        };
+        // a comment
+        rest = &rest[len..];
+    }
+
+    let mut normalized = String::with_capacity(query.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let mut token = tokens[i];
+        // collapse lists of values, e.g. `?, ?, ?`
+        let mut end = i;
+        while token == "?" && tokens.get(end + 1) == Some(&",") && tokens.get(end + 2) == Some(&"?")
+        {
+            end += 2;
+        }
+        if end > i {
+            token = "...";
+        }
+        let glued = match (normalized.chars().last(), token) {
+            (None, _) | (Some('(' | '.'), _) | (_, "," | ")" | "." | ";") => true,
+            (Some(prev), "(") => is_word_char(prev) || prev == '`',
+            _ => false,
+        };
+        if !glued {
+            normalized.push(' ');
+        }
+        normalized.push_str(token);
+        i = end + 1;
+    }
+    normalized
+}
+
+/// Returns the hex digest of the normalized query.
+/// Returns `true` if the given query is a single statement, that could be explained.
+///
+/// `EXPLAIN` is prepended to the slow query text, so any other statement
+/// of a multi-statement query would be executed again.
+pub(crate) fn is_explainable(query: &str, no_backslash_escape: bool) -> bool {
+    let query = query.as_bytes();
+    let mut start = None;
+    let mut terminated = false;
+    let mut single = true;
+    visit_code(query, no_backslash_escape, |i| match query[i] {
+        x if x.is_ascii_whitespace() => (),
+        _ if terminated => single = false,
+        b';' => terminated = true,
+        _ => {
+            start.get_or_insert(i);
+        }
+    });
+
+    match start {
+        Some(start) if single => {
+            let keyword = query[start..]
+                .split(|x| !x.is_ascii_alphabetic())
+                .next()
+                .unwrap_or_default();
+            EXPLAINABLE.iter().any(|x| x.eq_ignore_ascii_case(keyword))
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn query_digest(normalized_query: &str) -> String {
+    let mut hasher = twox_hash::XxHash64::with_seed(0);
+    hasher.write(normalized_query.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '@'
+}
+
+/// Returns the length of a quoted string or identifier starting at the beginning of `s`.
+fn quoted_len(s: &str, quote: char) -> usize {
+    let mut chars = s.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && quote != '`' {
+            chars.next();
+        } else if c == quote {
+            // a doubled quote is an escaped one
+            if chars.peek().map(|&(_, next)| next) == Some(quote) {
+                chars.next();
+            } else {
+                return i + 1;
+            }
+        }
+    }
+    s.len()
+}
+
+/// Query which is being tracked for the slow query threshold or latency statistics.
+#[derive(Debug)]
+pub(crate) struct PendingQuery {
+    pub(crate) started: Instant,
+    pub(crate) kind: PendingQueryKind,
+}
+
+#[derive(Debug)]
+pub(crate) enum PendingQueryKind {
+    Text(String),
+    /// Parameters are only kept for the slow query handler.
+    Stmt(Statement, Option<Params>),
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_explainable, normalize_query, query_digest, ExplainMode};
+
+    #[test]
+    fn should_normalize_queries() {
+        assert_eq!(
+            normalize_query("select  *\n FROM `t 1` WHERE id IN (1, 2,3) AND name = 'it''s' -- x"),
+            "select * FROM `t 1` WHERE id IN(...) AND name = ?"
+        );
+        assert_eq!(
+            normalize_query("SELECT ? + SLEEP(0.1) /* comment */, \"a\\\"b\", t1.c2 >= -1e5 # x"),
+            "SELECT ? + SLEEP(?), ?, t1.c2 >= - ?"
+        );
+        assert_eq!(
+            normalize_query("INSERT INTO t VALUES (1, 'a'), (2, 'b')"),
+            "INSERT INTO t VALUES(...), (...)"
+        );
+        assert_eq!(
+            query_digest(&normalize_query("SELECT 1")),
+            query_digest(&normalize_query("SELECT  2"))
+        );
+        assert_ne!(query_digest("SELECT ?"), query_digest("SELECT ?, ?"));
+    }
+
+    #[test]
+    fn should_explain_single_statements_only() {
+        for query in [
+            "SELECT 1",
+            "  /* comment */ select * FROM t;  -- trailing comment",
+            "WITH x AS (SELECT 1) SELECT * FROM x",
+            "UPDATE t SET a = ';DELETE FROM t'",
+            "DELETE FROM `t;DROP TABLE t`",
+            "INSERT INTO t VALUES (1) # ; DELETE FROM t",
+            "REPLACE INTO t VALUES (1)",
+            "TABLE t",
+        ] {
+            assert!(is_explainable(query, false), "{}", query);
+        }
+        for query in [
+            "",
+            ";",
+            "SELECT 1; DELETE FROM t",
+            "SELECT 1;;",
+            "CREATE TABLE t (a INT)",
+            "SET @a = 1",
+            "CALL p()",
+            "SELECTED",
+            "/* SELECT */ SHOW TABLES",
+        ] {
+            assert!(!is_explainable(query, false), "{}", query);
+        }
+        // a backslash doesn't escape the quote
+        assert!(!is_explainable("SELECT '\\'; DELETE FROM t", true));
+        assert!(is_explainable("SELECT '\\'; DELETE FROM t'", false));
+    }
+
+    #[test]
+    fn should_parse_explain_mode() {
+        assert_eq!("off".parse(), Ok(ExplainMode::Off));
+        assert_eq!("always".parse(), Ok(ExplainMode::Always));
+        assert_eq!("sampled:10".parse(), Ok(ExplainMode::Sampled(10)));
+        assert!("sampled".parse::<ExplainMode>().is_err());
+        assert!("sampled:x".parse::<ExplainMode>().is_err());
+    }
+
+    #[test]
+    fn should_sample_explained_queries() {
+        assert!(!ExplainMode::Off.should_explain(1));
+        assert!(!ExplainMode::Sampled(0).should_explain(1));
+        assert!(ExplainMode::Always.should_explain(1));
+        let explained = (1..=10)
+            .filter(|n| ExplainMode::Sampled(3).should_explain(*n))
+            .collect::<Vec<_>>();
+        assert_eq!(explained, vec![3, 6, 9]);
+    }
+}
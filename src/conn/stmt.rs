@@ -8,9 +8,9 @@
 
 use mysql_common::{io::ParseBuf, packets::StmtPacket, proto::MyDeserialize};
 
-use std::{borrow::Cow, io, sync::Arc};
+use std::{borrow::Cow, io, sync::Arc, time::Duration};
 
-use crate::{prelude::*, Column, Result};
+use crate::{prelude::*, Column, Conn, DriverError, Params, Result, Value};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct InnerStmt {
@@ -18,6 +18,8 @@ pub struct InnerStmt {
     params: Option<Vec<Column>>,
     stmt_packet: StmtPacket,
     connection_id: u32,
+    query: Arc<Vec<u8>>,
+    schema: Option<Arc<str>>,
 }
 
 impl<'de> MyDeserialize<'de> for InnerStmt {
@@ -32,6 +34,8 @@ impl<'de> MyDeserialize<'de> for InnerStmt {
             params: None,
             stmt_packet,
             connection_id,
+            query: Default::default(),
+            schema: None,
         })
     }
 }
@@ -42,6 +46,16 @@ impl InnerStmt {
         self
     }
 
+    pub fn with_query(mut self, query: Arc<Vec<u8>>) -> Self {
+        self.query = query;
+        self
+    }
+
+    pub fn with_schema(mut self, schema: Option<Arc<str>>) -> Self {
+        self.schema = schema;
+        self
+    }
+
     pub fn with_columns(mut self, columns: Option<Vec<Column>>) -> Self {
         self.columns = columns;
         self
@@ -59,6 +73,16 @@ impl InnerStmt {
         self.stmt_packet.statement_id()
     }
 
+    /// Query this statement was prepared from (with named parameters replaced by `?`).
+    pub fn query(&self) -> &Arc<Vec<u8>> {
+        &self.query
+    }
+
+    /// Schema this statement is pinned to (see [`crate::Conn::prep_in`]).
+    pub fn schema(&self) -> Option<&Arc<str>> {
+        self.schema.as_ref()
+    }
+
     pub const fn connection_id(&self) -> u32 {
         self.connection_id
     }
@@ -76,6 +100,7 @@ impl InnerStmt {
 pub struct Statement {
     pub(crate) inner: Arc<InnerStmt>,
     pub(crate) named_params: Option<Vec<Vec<u8>>>,
+    pub(crate) missing_as_null: bool,
 }
 
 impl Statement {
@@ -83,6 +108,28 @@ impl Statement {
         Self {
             inner,
             named_params,
+            missing_as_null: false,
+        }
+    }
+
+    /// Converts the given parameters to positional parameters of this statement.
+    ///
+    /// Named parameters, that are absent from the map, are bound as `NULL`
+    /// if the statement is wrapped into [`MissingAsNull`].
+    pub(crate) fn positional_params(&self, params: Params) -> Result<Params> {
+        match params {
+            Params::Named(mut map) => match self.named_params {
+                Some(ref named_params) => {
+                    if self.missing_as_null {
+                        for name in named_params {
+                            map.entry(name.clone()).or_insert(Value::NULL);
+                        }
+                    }
+                    Ok(Params::Named(map).into_positional(named_params)?)
+                }
+                None => Err(DriverError::NamedParamsForPositionalQuery.into()),
+            },
+            params => Ok(params),
         }
     }
 
@@ -102,6 +149,12 @@ impl Statement {
         self.inner.connection_id()
     }
 
+    /// Returns the schema this statement is pinned to, if it was prepared
+    /// using [`crate::Conn::prep_in`].
+    pub fn schema(&self) -> Option<&str> {
+        self.inner.schema().map(|schema| &**schema)
+    }
+
     pub fn num_params(&self) -> u16 {
         self.inner.num_params()
     }
@@ -109,6 +162,32 @@ impl Statement {
     pub fn num_columns(&self) -> u16 {
         self.inner.num_columns()
     }
+
+    /// Prepares this statement again, so that [`Statement::columns`] and [`Statement::params`]
+    /// reflect schema changes (e.g. `ALTER TABLE`) made after it was prepared.
+    ///
+    /// The server re-prepares statements transparently, but the metadata is only sent
+    /// by `COM_STMT_PREPARE`. The old statement is closed, if it belongs to the given
+    /// connection, so its other copies are re-prepared on the next execution.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT)")?;
+    /// let mut stmt = conn.prep("SELECT * FROM mysql.tbl")?;
+    /// assert_eq!(stmt.num_columns(), 1);
+    ///
+    /// conn.query_drop("ALTER TABLE mysql.tbl ADD COLUMN name TEXT")?;
+    /// stmt.refresh(&mut conn)?;
+    /// assert_eq!(stmt.columns()[1].name_str(), "name");
+    /// # });
+    /// ```
+    pub fn refresh(&mut self, conn: &mut Conn) -> Result<()> {
+        *self = conn.refresh_stmt(self)?;
+        Ok(())
+    }
 }
 
 impl AsStatement for Statement {
@@ -129,3 +208,106 @@ impl<T: AsRef<str>> AsStatement for T {
         Ok(Cow::Owned(statement))
     }
 }
+
+/// Statement cache behavior for a single `exec_*` call (see [`CachePolicy::apply`]).
+///
+/// Useful for one-off dynamically generated queries, that would otherwise evict
+/// frequently used statements from the LRU statement cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CachePolicy {
+    /// Statement is cached as usual.
+    #[default]
+    Default,
+    /// Statement isn't cached, so it doesn't evict cached statements.
+    ///
+    /// Uncached statement is closed as soon as another uncached statement is prepared
+    /// (or the connection is dropped). Already cached statement is reused.
+    NoCache,
+    /// Statement is cached, but it's closed once the given TTL is elapsed.
+    ///
+    /// Statements with an elapsed TTL are closed on the next prepare.
+    Ttl(Duration),
+}
+
+impl CachePolicy {
+    /// Wraps the given query, so that it's prepared using this policy.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let pool = Pool::new(get_opts())?;
+    /// # let mut conn = pool.get_conn()?;
+    /// let query = format!("SELECT {}", vec!["?"; 5].join(", "));
+    /// let row: Option<Row> = conn.exec_first(CachePolicy::NoCache.apply(query), (1, 2, 3, 4, 5))?;
+    /// # assert!(row.is_some());
+    /// # });
+    /// ```
+    pub fn apply<T: AsRef<str>>(self, query: T) -> WithCachePolicy<T> {
+        WithCachePolicy {
+            query,
+            policy: self,
+        }
+    }
+}
+
+/// Query that is prepared using the given [`CachePolicy`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WithCachePolicy<T> {
+    query: T,
+    policy: CachePolicy,
+}
+
+impl<T: AsRef<str>> WithCachePolicy<T> {
+    /// Returns the wrapped query.
+    pub fn query(&self) -> &str {
+        self.query.as_ref()
+    }
+
+    /// Returns the statement cache policy.
+    pub fn policy(&self) -> CachePolicy {
+        self.policy
+    }
+}
+
+impl<T: AsRef<str>> AsStatement for WithCachePolicy<T> {
+    fn as_statement<Q: Queryable>(&self, queryable: &mut Q) -> Result<Cow<'static, Statement>> {
+        let statement = queryable.prep_with_cache_policy(self.query(), self.policy)?;
+        Ok(Cow::Owned(statement))
+    }
+}
+
+/// Statement, that binds named parameters absent from the given parameters as `NULL`.
+///
+/// By default an absent named parameter is an error
+/// (see [`DriverError::MissingNamedParameter`]), so this wrapper is an opt-in for a single
+/// `exec_*` call, e.g. for sparse updates. It wraps queries as well as prepared statements.
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// # use mysql::*;
+/// # use mysql::prelude::*;
+/// # let mut conn = Conn::new(get_opts())?;
+/// let stmt = conn.prep("SELECT :id, :name, :email")?;
+///
+/// let row: Option<(u32, Option<String>, Option<String>)> =
+///     conn.exec_first(MissingAsNull(&stmt), params! { "id" => 1, "name" => "foo" })?;
+/// assert_eq!(row, Some((1, Some("foo".into()), None)));
+///
+/// let result = conn.exec_drop(&stmt, params! { "id" => 1, "name" => "foo" });
+/// assert!(matches!(
+///     result,
+///     Err(Error::DriverError(DriverError::MissingNamedParameter(_)))
+/// ));
+/// # });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MissingAsNull<S>(pub S);
+
+impl<S: AsStatement> AsStatement for MissingAsNull<S> {
+    fn as_statement<Q: Queryable>(&self, queryable: &mut Q) -> Result<Cow<'_, Statement>> {
+        let mut statement = self.0.as_statement(queryable)?.into_owned();
+        statement.missing_as_null = true;
+        Ok(Cow::Owned(statement))
+    }
+}
@@ -10,10 +10,11 @@ use lru::LruCache;
 use twox_hash::XxHash;
 
 use std::{
-    borrow::Borrow,
+    borrow::{Borrow, Cow},
     collections::HashMap,
     hash::{BuildHasherDefault, Hash},
     sync::Arc,
+    time::Instant,
 };
 
 use crate::conn::stmt::InnerStmt;
@@ -33,9 +34,82 @@ impl PartialEq<[u8]> for QueryString {
     }
 }
 
+#[derive(Debug)]
 pub struct Entry {
     pub stmt: Arc<InnerStmt>,
     pub query: QueryString,
+    /// Statements cached with a TTL are closed once it's elapsed.
+    pub expires_at: Option<Instant>,
+    /// Number of times the statement was taken from the cache.
+    pub hits: u64,
+}
+
+impl Entry {
+    fn new(stmt: Arc<InnerStmt>, query: QueryString, expires_at: Option<Instant>) -> Self {
+        Entry {
+            stmt,
+            query,
+            expires_at,
+            hits: 0,
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+}
+
+/// Statement cached by a connection (see [`crate::Conn::cached_statements`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedStmt {
+    id: u32,
+    query: Arc<Vec<u8>>,
+    schema: Option<Arc<str>>,
+    hits: u64,
+    expires_at: Option<Instant>,
+}
+
+impl CachedStmt {
+    fn new(entry: &Entry) -> Self {
+        CachedStmt {
+            id: entry.stmt.id(),
+            query: entry.query.0.clone(),
+            schema: entry.stmt.schema().cloned(),
+            hits: entry.hits,
+            expires_at: entry.expires_at,
+        }
+    }
+
+    /// Returns the server-side identifier of the statement.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the query of the statement (with named parameters replaced by `?`).
+    pub fn query(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.query)
+    }
+
+    /// Returns the query of the statement as bytes (see [`crate::Conn::evict_stmt`]).
+    pub fn query_bytes(&self) -> &[u8] {
+        &self.query
+    }
+
+    /// Returns the schema the statement is pinned to (see [`crate::Conn::prep_in`]).
+    pub fn schema(&self) -> Option<&str> {
+        self.schema.as_deref()
+    }
+
+    /// Returns the number of times the statement was reused from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the time the statement expires at, if it was cached with a TTL
+    /// (see [`crate::CachePolicy::Ttl`]).
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires_at
+    }
 }
 
 #[derive(Debug)]
@@ -43,6 +117,12 @@ pub struct StmtCache {
     cap: usize,
     cache: LruCache<u32, Entry>,
     query_map: HashMap<QueryString, u32, BuildHasherDefault<XxHash>>,
+    /// Statements pinned to a schema (see `Conn::prep_in`).
+    pinned_map: HashMap<Arc<str>, HashMap<QueryString, u32, BuildHasherDefault<XxHash>>>,
+    /// Number of cached entries with a TTL.
+    expiring: usize,
+    /// The most recent statement prepared with `CachePolicy::NoCache`.
+    transient: Option<Entry>,
 }
 
 impl StmtCache {
@@ -51,6 +131,9 @@ impl StmtCache {
             cap,
             cache: LruCache::unbounded(),
             query_map: Default::default(),
+            pinned_map: Default::default(),
+            expiring: 0,
+            transient: None,
         }
     }
 
@@ -71,25 +154,66 @@ impl StmtCache {
         T: ?Sized,
     {
         let id = self.query_map.get(query).cloned();
-        match id {
-            Some(id) => self.cache.get(&id),
-            None => None,
-        }
+        let entry = match id {
+            Some(id) => self.cache.get_mut(&id),
+            None => self
+                .transient
+                .as_mut()
+                .filter(|entry| entry.stmt.schema().is_none() && entry.query == *query),
+        };
+        entry.map(|entry| {
+            entry.hits += 1;
+            &*entry
+        })
+    }
+
+    pub fn by_query_in(&mut self, schema: &str, query: &[u8]) -> Option<&Entry> {
+        let id = self
+            .pinned_map
+            .get(schema)
+            .and_then(|queries| queries.get(query))
+            .cloned();
+        let entry = match id {
+            Some(id) => self.cache.get_mut(&id),
+            None => self.transient.as_mut().filter(|entry| {
+                entry.stmt.schema().map(|x| &**x) == Some(schema) && entry.query == *query
+            }),
+        };
+        entry.map(|entry| {
+            entry.hits += 1;
+            &*entry
+        })
     }
 
-    pub fn put(&mut self, query: Arc<Vec<u8>>, stmt: Arc<InnerStmt>) -> Option<Arc<InnerStmt>> {
+    pub fn put(
+        &mut self,
+        query: Arc<Vec<u8>>,
+        stmt: Arc<InnerStmt>,
+        expires_at: Option<Instant>,
+    ) -> Option<Arc<InnerStmt>> {
         if self.cap == 0 {
             return None;
         }
 
         let query = QueryString(query);
 
-        self.query_map.insert(query.clone(), stmt.id());
-        self.cache.put(stmt.id(), Entry { stmt, query });
+        match stmt.schema() {
+            Some(schema) => self
+                .pinned_map
+                .entry(schema.clone())
+                .or_default()
+                .insert(query.clone(), stmt.id()),
+            None => self.query_map.insert(query.clone(), stmt.id()),
+        };
+        if expires_at.is_some() {
+            self.expiring += 1;
+        }
+        let entry = Entry::new(stmt, query, expires_at);
+        self.cache.put(entry.stmt.id(), entry);
 
         if self.cache.len() > self.cap {
             if let Some((_, entry)) = self.cache.pop_lru() {
-                self.query_map.remove(&**entry.query.0.as_ref());
+                self.forget(&entry);
                 return Some(entry.stmt);
             }
         }
@@ -97,14 +221,128 @@ impl StmtCache {
         None
     }
 
+    /// Keeps the given statement without caching it, so it doesn't evict cached statements.
+    ///
+    /// Returns the previous transient statement, that should be closed.
+    pub fn put_transient(
+        &mut self,
+        query: Arc<Vec<u8>>,
+        stmt: Arc<InnerStmt>,
+    ) -> Option<Arc<InnerStmt>> {
+        let entry = Entry::new(stmt, QueryString(query), None);
+        self.transient.replace(entry).map(|previous| previous.stmt)
+    }
+
+    pub fn take_transient(&mut self) -> Option<Entry> {
+        self.transient.take()
+    }
+
+    /// Removes entries with an elapsed TTL and returns their statements, that should be closed.
+    pub fn take_expired(&mut self, now: Instant) -> Vec<Arc<InnerStmt>> {
+        if self.expiring == 0 {
+            return Vec::new();
+        }
+        let expired = self
+            .cache
+            .iter()
+            .filter(|(_, entry)| entry.is_expired(now))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let mut stmts = Vec::with_capacity(expired.len());
+        for id in expired {
+            if let Some(entry) = self.cache.pop(&id) {
+                self.forget(&entry);
+                stmts.push(entry.stmt);
+            }
+        }
+        stmts
+    }
+
+    /// Returns the maximum number of cached statements.
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    /// Changes the maximum number of cached statements.
+    ///
+    /// Returns statements of evicted entries (the least recently used first),
+    /// that should be closed.
+    pub fn set_cap(&mut self, cap: usize) -> Vec<Arc<InnerStmt>> {
+        self.cap = cap;
+        let mut evicted = Vec::new();
+        while self.cache.len() > self.cap {
+            match self.cache.pop_lru() {
+                Some((_, entry)) => {
+                    self.forget(&entry);
+                    evicted.push(entry.stmt);
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    /// Removes entries of the given query (regardless of the schema they are pinned to)
+    /// and returns their statements, that should be closed.
+    pub fn take_by_query(&mut self, query: &[u8]) -> Vec<Arc<InnerStmt>> {
+        let ids = self
+            .cache
+            .iter()
+            .filter(|(_, entry)| entry.query == *query)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let mut stmts = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(entry) = self.cache.pop(&id) {
+                self.forget(&entry);
+                stmts.push(entry.stmt);
+            }
+        }
+        stmts
+    }
+
+    /// Describes cached entries, the most recently used first.
+    pub fn describe(&self) -> Vec<CachedStmt> {
+        self.cache
+            .iter()
+            .map(|(_, entry)| CachedStmt::new(entry))
+            .collect()
+    }
+
     pub fn clear(&mut self) {
         self.query_map.clear();
+        self.pinned_map.clear();
         self.cache.clear();
+        self.expiring = 0;
+        self.transient = None;
     }
 
     pub fn remove(&mut self, id: u32) {
         if let Some(entry) = self.cache.pop(&id) {
-            self.query_map.remove::<[u8]>(entry.query.borrow());
+            self.forget(&entry);
+        } else if matches!(self.transient, Some(ref entry) if entry.stmt.id() == id) {
+            self.transient = None;
+        }
+    }
+
+    /// Removes the query of an evicted entry.
+    fn forget(&mut self, entry: &Entry) {
+        if entry.expires_at.is_some() {
+            self.expiring -= 1;
+        }
+        let query: &[u8] = entry.query.borrow();
+        match entry.stmt.schema() {
+            Some(schema) => {
+                if let Some(queries) = self.pinned_map.get_mut(schema) {
+                    queries.remove(query);
+                    if queries.is_empty() {
+                        self.pinned_map.remove(schema);
+                    }
+                }
+            }
+            None => {
+                self.query_map.remove(query);
+            }
         }
     }
 
@@ -114,6 +352,9 @@ impl StmtCache {
     }
 
     pub fn into_iter(mut self) -> impl Iterator<Item = (u32, Entry)> {
-        std::iter::from_fn(move || self.cache.pop_lru())
+        let transient = self.transient.take().map(|entry| (entry.stmt.id(), entry));
+        transient
+            .into_iter()
+            .chain(std::iter::from_fn(move || self.cache.pop_lru()))
     }
 }
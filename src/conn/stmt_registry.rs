@@ -0,0 +1,85 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// Queries prepared by any connection of a pool (see `PoolOpts::with_stmt_registry`).
+///
+/// Holds at most `cap` most recently prepared queries.
+#[derive(Debug, Clone)]
+pub(crate) struct StmtRegistry {
+    cap: usize,
+    queries: Arc<Mutex<VecDeque<Arc<Vec<u8>>>>>,
+}
+
+impl StmtRegistry {
+    pub(crate) fn new(cap: usize) -> Self {
+        StmtRegistry {
+            cap,
+            queries: Default::default(),
+        }
+    }
+
+    /// Registers a query that was just prepared.
+    pub(crate) fn register(&self, query: &Arc<Vec<u8>>) {
+        let mut queries = self.queries.lock().unwrap();
+        if let Some(position) = queries.iter().position(|known| known == query) {
+            queries.remove(position);
+        }
+        queries.push_back(query.clone());
+        while queries.len() > self.cap {
+            queries.pop_front();
+        }
+    }
+
+    /// Removes a query that could not be prepared (e.g. because a table was dropped).
+    pub(crate) fn unregister(&self, query: &[u8]) {
+        self.queries
+            .lock()
+            .unwrap()
+            .retain(|known| known.as_slice() != query);
+    }
+
+    /// Returns registered queries, the least recently prepared first.
+    pub(crate) fn queries(&self) -> Vec<Arc<Vec<u8>>> {
+        self.queries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::StmtRegistry;
+
+    #[test]
+    fn should_keep_most_recent_queries() {
+        let registry = StmtRegistry::new(2);
+        let query = |q: &str| Arc::new(q.as_bytes().to_vec());
+
+        registry.register(&query("SELECT 1"));
+        registry.register(&query("SELECT 2"));
+        registry.register(&query("SELECT 1"));
+        assert_eq!(
+            registry.queries(),
+            vec![query("SELECT 2"), query("SELECT 1")]
+        );
+
+        registry.register(&query("SELECT 3"));
+        assert_eq!(
+            registry.queries(),
+            vec![query("SELECT 1"), query("SELECT 3")]
+        );
+
+        registry.unregister(b"SELECT 1");
+        assert_eq!(registry.queries(), vec![query("SELECT 3")]);
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Spans and events of the `tracing` feature.
+//!
+//! Everything here is a no-op without the feature, so callers don't need `cfg` attributes.
+
+use std::fmt;
+
+#[cfg(feature = "tracing")]
+use std::{
+    hash::Hasher,
+    time::{Duration, Instant},
+};
+
+use crate::Opts;
+
+/// Span of a driver operation.
+///
+/// Fields common to all spans are `connection_id`, `duration_us` and `error`.
+/// Statement spans also have `digest` (a hash of the statement text) and `rows`.
+#[derive(Debug, Default)]
+pub(crate) struct TraceSpan {
+    #[cfg(feature = "tracing")]
+    inner: Option<(tracing::Span, Instant, u64)>,
+}
+
+impl TraceSpan {
+    #[cfg(feature = "tracing")]
+    fn new(span: tracing::Span) -> Self {
+        TraceSpan {
+            inner: Some((span, Instant::now(), 0)),
+        }
+    }
+
+    /// Span of establishing a connection (including the init commands).
+    pub(crate) fn connect(opts: &Opts) -> Self {
+        #[cfg(feature = "tracing")]
+        return Self::new(tracing::info_span!(
+            target: "mysql::conn",
+            "connect",
+            host = %opts.get_ip_or_hostname(),
+            port = opts.get_tcp_port(),
+            connection_id = tracing::field::Empty,
+            duration_us = tracing::field::Empty,
+            error = tracing::field::Empty,
+        ));
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = opts;
+            TraceSpan::default()
+        }
+    }
+
+    /// Span of the handshake (including authentication).
+    pub(crate) fn handshake() -> Self {
+        #[cfg(feature = "tracing")]
+        return Self::new(tracing::debug_span!(
+            target: "mysql::conn",
+            "handshake",
+            connection_id = tracing::field::Empty,
+            duration_us = tracing::field::Empty,
+            error = tracing::field::Empty,
+        ));
+        #[cfg(not(feature = "tracing"))]
+        TraceSpan::default()
+    }
+
+    /// Span of `COM_STMT_PREPARE`.
+    pub(crate) fn prepare(connection_id: u32, query: &[u8]) -> Self {
+        #[cfg(feature = "tracing")]
+        return Self::new(tracing::debug_span!(
+            target: "mysql::conn",
+            "prepare",
+            connection_id,
+            digest = %digest(query),
+            duration_us = tracing::field::Empty,
+            error = tracing::field::Empty,
+        ));
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (connection_id, query);
+            TraceSpan::default()
+        }
+    }
+
+    /// Span of a text query (up to the end of its result).
+    pub(crate) fn query(connection_id: u32, query: &str) -> Self {
+        #[cfg(feature = "tracing")]
+        return Self::new(tracing::info_span!(
+            target: "mysql::conn",
+            "query",
+            connection_id,
+            digest = %digest(query.as_bytes()),
+            rows = tracing::field::Empty,
+            duration_us = tracing::field::Empty,
+            error = tracing::field::Empty,
+        ));
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (connection_id, query);
+            TraceSpan::default()
+        }
+    }
+
+    /// Span of a statement execution (up to the end of its result).
+    pub(crate) fn execute(connection_id: u32, stmt_id: u32, query: &[u8]) -> Self {
+        #[cfg(feature = "tracing")]
+        return Self::new(tracing::info_span!(
+            target: "mysql::conn",
+            "execute",
+            connection_id,
+            stmt_id,
+            digest = %digest(query),
+            rows = tracing::field::Empty,
+            duration_us = tracing::field::Empty,
+            error = tracing::field::Empty,
+        ));
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (connection_id, stmt_id, query);
+            TraceSpan::default()
+        }
+    }
+
+    /// Span of a `COM_STMT_FETCH` (up to the end of the fetched batch).
+    pub(crate) fn fetch(connection_id: u32, stmt_id: u32, fetch_size: u32) -> Self {
+        #[cfg(feature = "tracing")]
+        return Self::new(tracing::debug_span!(
+            target: "mysql::conn",
+            "fetch",
+            connection_id,
+            stmt_id,
+            fetch_size,
+            rows = tracing::field::Empty,
+            duration_us = tracing::field::Empty,
+            error = tracing::field::Empty,
+        ));
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (connection_id, stmt_id, fetch_size);
+            TraceSpan::default()
+        }
+    }
+
+    /// Runs `f` within this span, so that nested spans and events are its children.
+    pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "tracing")]
+        if let Some((ref span, _, _)) = self.inner {
+            return span.in_scope(f);
+        }
+        f()
+    }
+
+    pub(crate) fn record_connection_id(&self, connection_id: u32) {
+        #[cfg(feature = "tracing")]
+        if let Some((ref span, _, _)) = self.inner {
+            span.record("connection_id", connection_id);
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = connection_id;
+    }
+
+    pub(crate) fn add_row(&mut self) {
+        #[cfg(feature = "tracing")]
+        if let Some((_, _, ref mut rows)) = self.inner {
+            *rows += 1;
+        }
+    }
+
+    /// Records the number of rows, the duration and the error (if any) and closes the span.
+    pub(crate) fn finish<T, E: fmt::Display>(self, result: &Result<T, E>) {
+        self.finish_with(result.as_ref().err().map(|err| err as &dyn fmt::Display));
+    }
+
+    pub(crate) fn finish_with(self, error: Option<&dyn fmt::Display>) {
+        #[cfg(feature = "tracing")]
+        if let Some((span, started, rows)) = self.inner {
+            span.record("rows", rows);
+            span.record("duration_us", duration_us(started.elapsed()));
+            if let Some(error) = error {
+                span.record("error", tracing::field::display(error));
+            }
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = error;
+    }
+}
+
+/// Emits an event about an operation, that is retried after the given error.
+pub(crate) fn retry(connection_id: u32, error: &dyn fmt::Display) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(target: "mysql::conn", connection_id, %error, "retrying");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (connection_id, error);
+}
+
+/// Emits an event about the lost connection replaced with a new one.
+pub(crate) fn reconnect(old_connection_id: u32, connection_id: u32) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        target: "mysql::conn",
+        old_connection_id,
+        connection_id,
+        "reconnected"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (old_connection_id, connection_id);
+}
+
+#[cfg(feature = "tracing")]
+fn duration_us(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Hash of the statement text, that identifies the statement in spans.
+#[cfg(feature = "tracing")]
+pub(crate) fn digest(query: &[u8]) -> String {
+    let mut hasher = twox_hash::XxHash64::with_seed(0);
+    hasher.write(query);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use super::digest;
+
+    #[test]
+    fn should_compute_stable_digests() {
+        assert_eq!(digest(b"SELECT 1"), digest(b"SELECT 1"));
+        assert_ne!(digest(b"SELECT 1"), digest(b"SELECT 2"));
+        assert_eq!(digest(b"").len(), 16);
+    }
+}
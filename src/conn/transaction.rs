@@ -8,23 +8,42 @@
 
 use mysql_common::packets::OkPacket;
 
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, fmt, io, time::Duration};
 
 use crate::{
     conn::{
         query_result::{Binary, Text},
         ConnMut,
     },
+    consts::CursorType,
     prelude::*,
-    LocalInfileHandler, Params, QueryResult, Result, Statement,
+    CachePolicy, Cursor, ExecResult, LocalInfileHandler, Params, QueryInfo, QueryResult, Result,
+    ResultSummary, Statement, StreamedParam, Value, Warning,
 };
 
+/// Default delay before the first retry of a transaction (see [`TxOpts::set_retry_backoff`]).
+pub const DEFAULT_TX_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
 /// MySql transaction options.
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct TxOpts {
     with_consistent_snapshot: bool,
     isolation_level: Option<IsolationLevel>,
     access_mode: Option<AccessMode>,
+    max_retries: usize,
+    retry_backoff: Duration,
+}
+
+impl Default for TxOpts {
+    fn default() -> Self {
+        Self {
+            with_consistent_snapshot: false,
+            isolation_level: None,
+            access_mode: None,
+            max_retries: 0,
+            retry_backoff: DEFAULT_TX_RETRY_BACKOFF,
+        }
+    }
 }
 
 impl TxOpts {
@@ -43,6 +62,16 @@ impl TxOpts {
         self.isolation_level
     }
 
+    /// Returns the maximum number of retries performed by `with_transaction`.
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// Returns the delay before the first retry performed by `with_transaction`.
+    pub fn retry_backoff(&self) -> Duration {
+        self.retry_backoff
+    }
+
     /// Turns on/off the `WITH CONSISTENT SNAPSHOT` tx characteristic (defaults to `false`).
     pub fn set_with_consistent_snapshot(mut self, val: bool) -> Self {
         self.with_consistent_snapshot = val;
@@ -60,6 +89,22 @@ impl TxOpts {
         self.isolation_level = level;
         self
     }
+
+    /// Defines how many times `with_transaction` will retry a transaction that failed with
+    /// `ER_LOCK_DEADLOCK` or `ER_LOCK_WAIT_TIMEOUT` (defaults to `0`, i.e. no retries).
+    pub fn set_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Defines the delay before the first retry performed by `with_transaction`
+    /// (defaults to [`DEFAULT_TX_RETRY_BACKOFF`]).
+    ///
+    /// The delay is doubled for every subsequent retry.
+    pub fn set_retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
 }
 
 /// MySql transaction access mode.
@@ -97,6 +142,8 @@ pub struct Transaction<'a> {
     committed: bool,
     rolled_back: bool,
     restore_local_infile_handler: Option<LocalInfileHandler>,
+    /// Nesting level of this transaction (`0` for a top-level transaction).
+    depth: usize,
 }
 
 impl Transaction<'_> {
@@ -107,24 +154,167 @@ impl Transaction<'_> {
             committed: false,
             rolled_back: false,
             restore_local_infile_handler: handler,
+            depth: 0,
         }
     }
 
+    /// Name of the savepoint that backs a nested transaction.
+    fn nested_savepoint_name(&self) -> String {
+        format!("mysql_nested_tx_{}", self.depth)
+    }
+
+    /// Starts a nested transaction.
+    ///
+    /// Nested transaction is backed by a savepoint, i.e. its `commit` will release the savepoint
+    /// and its `rollback` (or `Drop`) will roll back to the savepoint, leaving the outer
+    /// transaction active.
+    pub fn start_transaction(&mut self) -> Result<Transaction<'_>> {
+        let mut nested = Transaction::new(ConnMut::Mut(&mut self.conn));
+        nested.depth = self.depth + 1;
+        let name = nested.nested_savepoint_name();
+        // a savepoint that fails to be created mustn't be rolled back to
+        nested.rolled_back = true;
+        nested.savepoint(&name)?;
+        nested.rolled_back = false;
+        Ok(nested)
+    }
+
     /// Will consume and commit transaction.
+    ///
+    /// For a nested transaction it'll release the corresponding savepoint.
     pub fn commit(mut self) -> Result<()> {
-        self.conn.query_drop("COMMIT")?;
+        if self.depth > 0 {
+            let name = self.nested_savepoint_name();
+            self.release(&name)?;
+        } else {
+            self.conn.query_drop("COMMIT")?;
+        }
         self.committed = true;
         Ok(())
     }
 
+    /// Will consume the transaction, execute the given query and commit the transaction.
+    ///
+    /// The query and the `COMMIT` are sent in a single network write, so it saves a round trip
+    /// (both responses are read afterwards). For a nested transaction the corresponding savepoint
+    /// is released instead of `COMMIT`.
+    ///
+    /// # Note
+    ///
+    /// `COMMIT` is sent before the query result is known, so the transaction will be committed
+    /// even if the query fails (the query error is returned in this case). Use it only if it's
+    /// acceptable for the changes made before the last query to be committed.
+    ///
+    /// Queries that may issue a local infile request (i.e. `LOAD DATA LOCAL` and `LOAD XML LOCAL`)
+    /// are not pipelined: the query response is read before `COMMIT` is sent, so `COMMIT` won't
+    /// be sent (and the transaction will be rolled back) if such a query fails.
+    ///
+    /// Result set of the query, if any, is dropped.
+    pub fn query_drop_and_commit<Q: AsRef<str>>(mut self, query: Q) -> Result<()> {
+        let query = query.as_ref().as_bytes();
+        let commit = self.commit_query();
+        let (result, commit_result) = self.conn.pipeline_with_commit::<Text, _>(
+            |conn| conn.write_query(query, &[]),
+            &commit,
+            !may_request_local_infile(query),
+        );
+        self.finish_pipelined(result, commit_result)
+    }
+
+    /// Will consume the transaction, execute the given statement and commit the transaction.
+    ///
+    /// This is the same as [`Transaction::query_drop_and_commit`] but for a statement
+    /// (all the notes apply). Prepared statements can't issue a local infile request,
+    /// so the statement is always pipelined with `COMMIT`.
+    pub fn exec_drop_and_commit<S, P>(mut self, stmt: S, params: P) -> Result<()>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        let statement = stmt.as_statement(&mut *self.conn)?;
+        let commit = self.commit_query();
+        let (result, commit_result) = self.conn.pipeline_with_commit::<Binary, _>(
+            |conn| {
+                conn.write_execute_request(
+                    &statement,
+                    &params.into(),
+                    CursorType::CURSOR_TYPE_NO_CURSOR,
+                    &[],
+                )
+            },
+            &commit,
+            true,
+        );
+        self.finish_pipelined(result, commit_result)
+    }
+
+    /// Query that commits this transaction.
+    fn commit_query(&self) -> String {
+        if self.depth > 0 {
+            format!(
+                "RELEASE SAVEPOINT {}",
+                quote_savepoint_name(&self.nested_savepoint_name())
+            )
+        } else {
+            "COMMIT".into()
+        }
+    }
+
+    fn finish_pipelined(
+        &mut self,
+        result: Result<()>,
+        commit_result: Option<Result<()>>,
+    ) -> Result<()> {
+        // `Drop` will roll back the transaction if `COMMIT` wasn't sent or has failed
+        self.committed = matches!(commit_result, Some(Ok(())));
+        result?;
+        commit_result.unwrap_or(Ok(()))
+    }
+
     /// Will consume and rollback transaction. You also can rely on `Drop` implementation but it
     /// will swallow errors.
+    ///
+    /// For a nested transaction it'll roll back to the corresponding savepoint.
     pub fn rollback(mut self) -> Result<()> {
-        self.conn.query_drop("ROLLBACK")?;
+        self.rollback_inner()?;
         self.rolled_back = true;
         Ok(())
     }
 
+    fn rollback_inner(&mut self) -> Result<()> {
+        if self.depth > 0 {
+            let name = self.nested_savepoint_name();
+            self.rollback_to(&name)?;
+            self.release(&name)
+        } else {
+            self.conn.query_drop("ROLLBACK")
+        }
+    }
+
+    /// Sets a named transaction savepoint (`SAVEPOINT name`).
+    ///
+    /// Existing savepoint with the same name will be replaced.
+    pub fn savepoint(&mut self, name: &str) -> Result<()> {
+        self.conn
+            .query_drop(format!("SAVEPOINT {}", quote_savepoint_name(name)))
+    }
+
+    /// Rolls back the transaction to the named savepoint (`ROLLBACK TO SAVEPOINT name`).
+    ///
+    /// The savepoint itself is not released.
+    pub fn rollback_to(&mut self, name: &str) -> Result<()> {
+        self.conn.query_drop(format!(
+            "ROLLBACK TO SAVEPOINT {}",
+            quote_savepoint_name(name)
+        ))
+    }
+
+    /// Releases the named savepoint (`RELEASE SAVEPOINT name`).
+    pub fn release(&mut self, name: &str) -> Result<()> {
+        self.conn
+            .query_drop(format!("RELEASE SAVEPOINT {}", quote_savepoint_name(name)))
+    }
+
     /// A way to override local infile handler for this transaction.
     /// Destructor of transaction will restore original handler.
     pub fn set_local_infile_handler(&mut self, handler: Option<LocalInfileHandler>) {
@@ -145,6 +335,12 @@ impl Transaction<'_> {
             .and_then(OkPacket::last_insert_id)
     }
 
+    /// Returns the last insert id reported since the session started
+    /// (see [`crate::Conn::session_last_insert_id`]).
+    pub fn session_last_insert_id(&self) -> Option<u64> {
+        self.conn.session_last_insert_id()
+    }
+
     /// Returns the warnings count, reported by the server.
     pub fn warnings(&self) -> u16 {
         self.conn.warnings()
@@ -167,6 +363,45 @@ impl Transaction<'_> {
     pub fn info_str(&self) -> Cow<str> {
         self.conn.info_str()
     }
+
+    /// Parsed [`Transaction::info_str`] (see [`QueryInfo`]).
+    pub fn info(&self) -> Option<QueryInfo> {
+        self.conn.info()
+    }
+
+    /// Runs `SHOW WARNINGS` (see [`crate::Conn::show_warnings`]).
+    pub fn show_warnings(&mut self) -> Result<Vec<Warning>> {
+        self.conn.show_warnings()
+    }
+
+    /// Discards unread result sets (see [`crate::Conn::drain_results`]).
+    pub fn drain_results(&mut self) -> Result<Vec<ResultSummary>> {
+        self.conn.drain_results()
+    }
+
+    /// Runs the query with the given local file data (see [`crate::Conn::query_with_infile`]).
+    pub fn query_with_infile<Q, R>(&mut self, query: Q, reader: R) -> Result<ExecResult>
+    where
+        Q: AsRef<str>,
+        R: io::Read,
+    {
+        self.conn.query_with_infile(query, reader)
+    }
+
+    /// Executes the statement using a server-side cursor
+    /// (see [`crate::Conn::exec_iter_cursored`]).
+    pub fn exec_iter_cursored<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+        fetch_size: u32,
+    ) -> Result<Cursor<'_, '_, '_>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        self.conn.exec_iter_cursored(stmt, params, fetch_size)
+    }
 }
 
 impl<'a> Queryable for Transaction<'a> {
@@ -174,10 +409,26 @@ impl<'a> Queryable for Transaction<'a> {
         self.conn.query_iter(query)
     }
 
+    fn query_iter_with_attrs<T: AsRef<str>>(
+        &mut self,
+        query: T,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Text>> {
+        self.conn.query_iter_with_attrs(query, attrs)
+    }
+
     fn prep<T: AsRef<str>>(&mut self, query: T) -> Result<Statement> {
         self.conn.prep(query)
     }
 
+    fn prep_with_cache_policy<T: AsRef<str>>(
+        &mut self,
+        query: T,
+        policy: CachePolicy,
+    ) -> Result<Statement> {
+        self.conn.prep_with_cache_policy(query, policy)
+    }
+
     fn close(&mut self, stmt: Statement) -> Result<()> {
         self.conn.close(stmt)
     }
@@ -189,13 +440,61 @@ impl<'a> Queryable for Transaction<'a> {
     {
         self.conn.exec_iter(stmt, params)
     }
+
+    fn exec_batch<S, P, I>(&mut self, stmt: S, params: I) -> Result<()>
+    where
+        Self: Sized,
+        S: AsStatement,
+        P: Into<Params>,
+        I: IntoIterator<Item = P>,
+    {
+        self.conn.exec_batch(stmt, params)
+    }
+
+    fn exec_iter_with_attrs<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+        attrs: &[(&str, Value)],
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        self.conn.exec_iter_with_attrs(stmt, params, attrs)
+    }
+
+    fn exec_iter_streamed<S>(
+        &mut self,
+        stmt: S,
+        params: Vec<StreamedParam<'_>>,
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+    {
+        self.conn.exec_iter_streamed(stmt, params)
+    }
+}
+
+fn quote_savepoint_name(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Returns `true` if the server may respond to the query with a local infile request.
+///
+/// It's conservative (any query mentioning `LOAD` matches) because a false positive
+/// only costs a round trip.
+fn may_request_local_infile(query: &[u8]) -> bool {
+    query
+        .windows(4)
+        .any(|word| word.eq_ignore_ascii_case(b"LOAD"))
 }
 
 impl<'a> Drop for Transaction<'a> {
     /// Will rollback transaction.
     fn drop(&mut self) {
         if !self.committed && !self.rolled_back {
-            let _ = self.conn.query_drop("ROLLBACK");
+            let _ = self.rollback_inner();
         }
         self.conn.0.local_infile_handler = self.restore_local_infile_handler.take();
     }
@@ -0,0 +1,179 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{fmt::Write as _, str::FromStr};
+
+/// Environment variable, that enables the wire log if it's not enabled by connection options.
+pub const WIRE_LOG_ENV: &str = "MYSQL_WIRE_LOG";
+
+/// Maximum number of payload bytes dumped per packet.
+const MAX_DUMP_LEN: usize = 4096;
+
+/// Verbosity of the wire log (see [`crate::OptsBuilder::wire_log`]).
+///
+/// Every packet is logged through the `log` crate with the `mysql::wire` target
+/// and the `Trace` level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WireLog {
+    /// Packets are not logged.
+    #[default]
+    Off,
+    /// Direction, sequence id, the first byte (i.e. the command of a client packet
+    /// or the type of a server packet) and the length of every packet.
+    Headers,
+    /// Same as `Headers` plus a hexdump of the payload (up to 4KiB). Payloads of packets,
+    /// that carry credentials (i.e. written during authentication), are not dumped.
+    Hexdump,
+}
+
+impl WireLog {
+    /// Reads the level from the `MYSQL_WIRE_LOG` environment variable
+    /// (`Off` if it's not set or invalid).
+    pub fn from_env() -> Self {
+        std::env::var(WIRE_LOG_ENV)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for WireLog {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(WireLog::Off),
+            "headers" => Ok(WireLog::Headers),
+            "hexdump" => Ok(WireLog::Hexdump),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Direction of a logged packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Sent,
+    Received,
+}
+
+/// Per-connection state of the wire log.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WireLogger {
+    level: WireLog,
+    /// Sequence id of the next packet as counted by the driver
+    /// (of uncompressed packets if compression is enabled).
+    seq_id: u8,
+}
+
+impl WireLogger {
+    pub(crate) fn new(level: WireLog) -> Self {
+        Self { level, seq_id: 0 }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.level != WireLog::Off
+    }
+
+    pub(crate) fn reset_seq_id(&mut self) {
+        self.seq_id = 0;
+    }
+
+    /// Logs a packet with the given payload (`len` is the full length of the payload).
+    pub(crate) fn packet(
+        &mut self,
+        connection_id: u32,
+        direction: Direction,
+        payload: &[u8],
+        len: usize,
+        redact: bool,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let seq_id = self.seq_id;
+        // a payload of 2^24-1 bytes or more is split into chunks, each having its own id
+        self.seq_id = seq_id.wrapping_add((len / 0xFF_FF_FF + 1) as u8);
+
+        let mut line = format!(
+            "connection {} {} seq={} len={}",
+            connection_id,
+            match direction {
+                Direction::Sent => "->",
+                Direction::Received => "<-",
+            },
+            seq_id,
+            len
+        );
+        if let Some(first) = payload.first() {
+            write!(line, " first=0x{:02x}", first).expect("infallible");
+        }
+        if self.level == WireLog::Hexdump {
+            if redact {
+                line.push_str(" <redacted>");
+            } else {
+                hexdump(payload, len, &mut line);
+            }
+        }
+        log::trace!(target: "mysql::wire", "{}", line);
+    }
+}
+
+/// Appends lines of `offset  hex bytes  |ascii|` to `out`.
+fn hexdump(payload: &[u8], len: usize, out: &mut String) {
+    let dumped = &payload[..payload.len().min(MAX_DUMP_LEN)];
+    for (i, chunk) in dumped.chunks(16).enumerate() {
+        write!(out, "\n{:08x} ", i * 16).expect("infallible");
+        for index in 0..16 {
+            match chunk.get(index) {
+                Some(byte) => write!(out, " {:02x}", byte).expect("infallible"),
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str("  |");
+        out.extend(chunk.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        }));
+        out.push('|');
+    }
+    if len > dumped.len() {
+        write!(out, "\n... {} more bytes", len - dumped.len()).expect("infallible");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hexdump, WireLog};
+
+    #[test]
+    fn should_parse_wire_log() {
+        assert_eq!("off".parse(), Ok(WireLog::Off));
+        assert_eq!("headers".parse(), Ok(WireLog::Headers));
+        assert_eq!("hexdump".parse(), Ok(WireLog::Hexdump));
+        assert_eq!("all".parse::<WireLog>(), Err(()));
+    }
+
+    #[test]
+    fn should_dump_payload() {
+        let mut out = String::new();
+        hexdump(b"\x03SELECT 1 + 1, 'foo'", 20, &mut out);
+        assert_eq!(
+            out,
+            "\n00000000  03 53 45 4c 45 43 54 20 31 20 2b 20 31 2c 20 27  |.SELECT 1 + 1, '|\
+             \n00000010  66 6f 6f 27                                      |foo'|"
+        );
+
+        let mut out = String::new();
+        hexdump(&[0; 5000], 5000, &mut out);
+        assert!(out.ends_with("\n... 904 more bytes"));
+    }
+}
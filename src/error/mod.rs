@@ -80,6 +80,85 @@ impl Error {
         }
     }
 
+    /// Returns `true` if this error means that the transaction was rolled back
+    /// (or has to be rolled back) because of a lock conflict, so it's safe to retry.
+    pub(crate) fn is_retryable_tx_error(&self) -> bool {
+        match self {
+            Error::MySqlError(err) => {
+                err.code == ServerError::ER_LOCK_DEADLOCK as u16
+                    || err.code == ServerError::ER_LOCK_WAIT_TIMEOUT as u16
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this error means that a prepared statement is stale, i.e. it was
+    /// invalidated by a DDL (`ER_NEED_REPREPARE`) or it's unknown to the server
+    /// (`ER_UNKNOWN_STMT_HANDLER`), so it has to be prepared again.
+    pub(crate) fn is_stale_stmt_error(&self) -> bool {
+        match self {
+            Error::MySqlError(err) => {
+                err.code == ServerError::ER_NEED_REPREPARE as u16
+                    || err.code == ServerError::ER_UNKNOWN_STMT_HANDLER as u16
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this error means that a socket read or write timed out.
+    pub(crate) fn is_io_timeout(&self) -> bool {
+        let err = match self {
+            Error::IoError(err) | Error::CodecError(PacketCodecError::Io(err)) => err,
+            _ => return false,
+        };
+        matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        )
+    }
+
+    /// Returns `true` if this error means that the connection is gone, i.e. the socket
+    /// was closed or the server terminated the session (see `OptsBuilder::auto_reconnect`).
+    pub(crate) fn is_connection_lost(&self) -> bool {
+        /// `ER_CONNECTION_KILLED` (MariaDB)
+        const ER_CONNECTION_KILLED: u16 = 1927;
+        /// `ER_CLIENT_INTERACTION_TIMEOUT` (MySQL 8.0.24+)
+        const ER_CLIENT_INTERACTION_TIMEOUT: u16 = 4031;
+
+        let err = match self {
+            Error::IoError(err) | Error::CodecError(PacketCodecError::Io(err)) => err,
+            Error::MySqlError(err) => {
+                return self.is_server_shutdown()
+                    || err.code == ER_CONNECTION_KILLED
+                    || err.code == ER_CLIENT_INTERACTION_TIMEOUT;
+            }
+            _ => return false,
+        };
+        matches!(
+            err.kind(),
+            io::ErrorKind::BrokenPipe
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::UnexpectedEof
+        )
+    }
+
+    /// Returns `true` if this error means that the server is shutting down
+    /// (`ER_SERVER_SHUTDOWN` or `ER_NORMAL_SHUTDOWN`), e.g. during a planned maintenance.
+    ///
+    /// Connections that received such an error are not returned to a pool, and the pool
+    /// drains its other connections to the same server (see [`crate::Pool::drain`]).
+    pub fn is_server_shutdown(&self) -> bool {
+        match self {
+            Error::MySqlError(err) => {
+                err.code == ServerError::ER_SERVER_SHUTDOWN as u16
+                    || err.code == ServerError::ER_NORMAL_SHUTDOWN as u16
+            }
+            _ => false,
+        }
+    }
+
     #[doc(hidden)]
     pub fn server_disconnected() -> Self {
         Error::IoError(io::Error::new(
@@ -196,6 +275,7 @@ impl fmt::Debug for Error {
 #[derive(Eq, PartialEq, Clone)]
 pub enum DriverError {
     ConnectTimeout,
+    HandshakeTimeout,
     // (address, description)
     CouldNotConnect(Option<(String, String, io::ErrorKind)>),
     UnsupportedProtocol(u8),
@@ -207,6 +287,12 @@ pub enum DriverError {
     InvalidPoolConstraints,
     SetupError,
     TlsNotSupported,
+    /// TLS option isn't supported by the TLS backend (see [`crate::SslOpts`]).
+    UnsupportedTlsOption(&'static str),
+    /// FIPS mode is required but the TLS backend doesn't operate in FIPS mode.
+    FipsModeNotEnabled,
+    /// (provider name)
+    CouldNotLoadTlsProvider(String),
     CouldNotParseVersion,
     ReadOnlyTransNotSupported,
     PoisonedPoolMutex,
@@ -217,6 +303,51 @@ pub enum DriverError {
     UnknownAuthPlugin(String),
     OldMysqlPasswordDisabled,
     CleartextPluginDisabled,
+    InvalidGeometry,
+    /// SRID of a geometry isn't a 32-bit unsigned integer.
+    InvalidSrid(String),
+    SridMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// Variants of an enum aren't values of an `ENUM` or `SET` column
+    /// (see [`crate::column_types::MysqlEnum::check_column`]).
+    EnumColumnMismatch {
+        column: String,
+        missing: Vec<String>,
+    },
+    /// (index, number of columns)
+    ColumnIndexOutOfRange(usize, usize),
+    /// Result of a `*_first` method has more than one row (see `OptsBuilder::strict_first`).
+    TooManyRows,
+    /// Connection was inherited by a forked process in the middle of a transaction
+    /// (it was replaced with a new connection, so the transaction is lost).
+    ForkedInTransaction,
+    /// `OUT` and `INOUT` parameters of stored procedures aren't supported by the server
+    /// (see [`crate::CompatMode::supports_out_params`]).
+    OutParamsNotSupported,
+    /// Statement given to [`crate::Conn::exec_batch_bulk`] isn't an `INSERT` statement
+    /// with a single row tuple.
+    InvalidBulkStatement,
+    /// Server requested a local file that isn't in the allow-list of the handler
+    /// (see [`crate::LocalInfileHandler::with_allowed_files`]).
+    LocalInfileNotAllowed(String),
+    /// Version requirement given to [`crate::Conn::when_version`] can't be parsed.
+    InvalidVersionRequirement(String),
+    /// Row can't be deserialized (see [`crate::prelude::DeserializeRow`]).
+    CouldNotDeserializeRow(String),
+    /// CSV record can't be read or converted to parameters (see `CsvConverters`, requires the `csv` feature).
+    InvalidCsvRecord(String),
+    /// Parameter can't be inlined into a query (see [`crate::Conn::interpolate`]).
+    CouldNotInterpolate(String),
+    /// Value of the column isn't a valid UTF-8 string (see [`crate::Utf8Decoding::Strict`]).
+    InvalidUtf8(String),
+    /// Row can't be passed to `JSON_TABLE` (see [`crate::JsonTable::param`]).
+    InvalidJsonTableRow(String),
+    /// Query is vetoed by the query interceptor (see [`crate::Interception::Veto`]).
+    QueryVetoed(String),
+    /// MySQL option file can't be parsed (see [`crate::Opts::from_mysql_config`]).
+    InvalidOptionFile(String),
 }
 
 impl error::Error for DriverError {
@@ -229,6 +360,7 @@ impl fmt::Display for DriverError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             DriverError::ConnectTimeout => write!(f, "Could not connect: connection timeout"),
+            DriverError::HandshakeTimeout => write!(f, "Could not connect: handshake timeout"),
             DriverError::CouldNotConnect(None) => {
                 write!(f, "Could not connect: address not specified")
             }
@@ -254,6 +386,20 @@ impl fmt::Display for DriverError {
                 "Client requires secure connection but server \
                  does not have this capability"
             ),
+            DriverError::UnsupportedTlsOption(option) => {
+                write!(
+                    f,
+                    "TLS option `{}' is not supported by the TLS backend",
+                    option
+                )
+            }
+            DriverError::FipsModeNotEnabled => write!(
+                f,
+                "FIPS mode is required but the TLS backend does not operate in FIPS mode"
+            ),
+            DriverError::CouldNotLoadTlsProvider(ref name) => {
+                write!(f, "Could not load OpenSSL provider `{}'", name)
+            }
             DriverError::CouldNotParseVersion => write!(f, "Could not parse MySQL version"),
             DriverError::ReadOnlyTransNotSupported => write!(
                 f,
@@ -283,6 +429,73 @@ impl fmt::Display for DriverError {
             DriverError::CleartextPluginDisabled => {
                 write!(f, "mysql_clear_password must be enabled on the client side")
             }
+            DriverError::InvalidGeometry => write!(f, "Invalid WKB representation of a geometry"),
+            DriverError::InvalidSrid(ref srid) => {
+                write!(f, "Geometry SRID {} is out of the range of SRIDs", srid)
+            }
+            DriverError::SridMismatch { expected, actual } => write!(
+                f,
+                "Geometry SRID {} does not match the expected SRID {}",
+                actual, expected
+            ),
+            DriverError::EnumColumnMismatch {
+                ref column,
+                ref missing,
+            } => write!(
+                f,
+                "Column `{}` does not define values: {}",
+                column,
+                missing.join(", ")
+            ),
+            DriverError::ColumnIndexOutOfRange(index, count) => write!(
+                f,
+                "Column index {} is out of range for a result set with {} columns",
+                index, count
+            ),
+            DriverError::TooManyRows => write!(f, "Expected at most one row but got more"),
+            DriverError::InvalidBulkStatement => write!(
+                f,
+                "Bulk insert requires an INSERT statement with a single row tuple"
+            ),
+            DriverError::OutParamsNotSupported => write!(
+                f,
+                "OUT and INOUT parameters of stored procedures are not supported by the server"
+            ),
+            DriverError::ForkedInTransaction => write!(
+                f,
+                "Connection was inherited by a forked process in the middle of a transaction"
+            ),
+            DriverError::LocalInfileNotAllowed(ref file_name) => write!(
+                f,
+                "Server requested local file `{}` which is not allowed",
+                file_name
+            ),
+            DriverError::InvalidVersionRequirement(ref requirement) => {
+                write!(f, "Invalid version requirement `{}`", requirement)
+            }
+            DriverError::CouldNotDeserializeRow(ref err) => {
+                write!(f, "Could not deserialize row: {}", err)
+            }
+            DriverError::InvalidCsvRecord(ref err) => write!(f, "Invalid CSV record: {}", err),
+            DriverError::CouldNotInterpolate(ref value) => {
+                write!(f, "Value `{}' can't be inlined into a query", value)
+            }
+            DriverError::InvalidUtf8(ref column) => {
+                write!(
+                    f,
+                    "Value of column `{}' is not a valid UTF-8 string",
+                    column
+                )
+            }
+            DriverError::InvalidJsonTableRow(ref err) => {
+                write!(f, "Invalid JSON_TABLE row: {}", err)
+            }
+            DriverError::QueryVetoed(ref reason) => {
+                write!(f, "Query is vetoed by the interceptor: {}", reason)
+            }
+            DriverError::InvalidOptionFile(ref err) => {
+                write!(f, "Invalid option file: {}", err)
+            }
         }
     }
 }
@@ -306,6 +519,8 @@ pub enum UrlError {
         min: usize,
         max: usize,
     },
+    /// The URL scheme selects a socket transport, but the socket isn't given (scheme).
+    SocketRequired(String),
     BadUrl,
 }
 
@@ -340,6 +555,12 @@ impl fmt::Display for UrlError {
                     min, max
                 )
             }
+            UrlError::SocketRequired(ref scheme) => write!(
+                f,
+                "URL scheme `{}' requires a socket given in place of the host \
+                 or via the `socket' parameter",
+                scheme
+            ),
             UrlError::BadUrl => write!(f, "Invalid or incomplete connection URL"),
         }
     }
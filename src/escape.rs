@@ -0,0 +1,236 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Escaping of identifiers and literals for dynamically built queries.
+//!
+//! Prefer parameters of prepared statements (or [`crate::Conn::query_interpolated`]) for values.
+//! These utilities are meant for the parts of a query, that can't be parameters,
+//! e.g. table names.
+//!
+//! ```rust
+//! # mysql::doctest_wrapper!(__result, {
+//! # use mysql::*;
+//! # use mysql::prelude::*;
+//! use mysql::escape::{escape_like_pattern, escape_string, quote_identifier};
+//!
+//! let mut conn = Conn::new(get_opts())?;
+//! let mode = conn.escape_mode()?;
+//! let table = quote_identifier("my`table", mode);
+//! conn.query_drop(format!("CREATE TEMPORARY TABLE {} (name TEXT)", table))?;
+//! conn.query_drop(format!(
+//!     "INSERT INTO {} VALUES ({}), ('100 apples')",
+//!     table,
+//!     escape_string("100% 'organic'", mode),
+//! ))?;
+//!
+//! let pattern = format!("{}%", escape_like_pattern("100%"));
+//! let names: Vec<String> = conn.query(format!(
+//!     "SELECT name FROM {} WHERE name LIKE {} {}",
+//!     table,
+//!     escape_string(&pattern, mode),
+//!     mode.like_escape_clause(),
+//! ))?;
+//! assert_eq!(names, ["100% 'organic'"]);
+//! # });
+//! ```
+
+use crate::{prelude::*, Conn, Result};
+
+/// SQL modes of a session, that affect escaping (see [`crate::Conn::escape_mode`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EscapeMode {
+    no_backslash_escapes: bool,
+    ansi_quotes: bool,
+}
+
+impl EscapeMode {
+    /// Creates the mode of the default `sql_mode`, i.e. backslash escapes are enabled
+    /// and `"` quotes strings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the value of the `sql_mode` variable, e.g. `ANSI_QUOTES,NO_BACKSLASH_ESCAPES`.
+    pub fn from_sql_mode(sql_mode: &str) -> Self {
+        let mut mode = Self::default();
+        for name in sql_mode.split(',').map(str::trim) {
+            if name.eq_ignore_ascii_case("NO_BACKSLASH_ESCAPES") {
+                mode.no_backslash_escapes = true;
+            } else if name.eq_ignore_ascii_case("ANSI_QUOTES") || name.eq_ignore_ascii_case("ANSI")
+            {
+                mode.ansi_quotes = true;
+            }
+        }
+        mode
+    }
+
+    /// Sets whether the `NO_BACKSLASH_ESCAPES` SQL mode is enabled, i.e. backslash
+    /// is an ordinary character within string literals.
+    pub fn with_no_backslash_escapes(mut self, no_backslash_escapes: bool) -> Self {
+        self.no_backslash_escapes = no_backslash_escapes;
+        self
+    }
+
+    /// Sets whether the `ANSI_QUOTES` SQL mode is enabled, i.e. `"` quotes identifiers
+    /// rather than strings.
+    pub fn with_ansi_quotes(mut self, ansi_quotes: bool) -> Self {
+        self.ansi_quotes = ansi_quotes;
+        self
+    }
+
+    /// Returns `true` if the `NO_BACKSLASH_ESCAPES` SQL mode is enabled.
+    pub fn no_backslash_escapes(&self) -> bool {
+        self.no_backslash_escapes
+    }
+
+    /// Returns `true` if the `ANSI_QUOTES` SQL mode is enabled.
+    pub fn ansi_quotes(&self) -> bool {
+        self.ansi_quotes
+    }
+
+    /// Returns the `ESCAPE` clause, that makes `\` the escape character of a `LIKE` pattern
+    /// (see [`escape_like_pattern`]).
+    ///
+    /// Backslash is the default escape character, unless `NO_BACKSLASH_ESCAPES` is enabled,
+    /// so the clause is required in that case. It's valid in both cases though.
+    pub fn like_escape_clause(&self) -> &'static str {
+        if self.no_backslash_escapes {
+            r"ESCAPE '\'"
+        } else {
+            r"ESCAPE '\\'"
+        }
+    }
+}
+
+/// Quotes the given identifier (e.g. a table or a column name).
+///
+/// Identifiers are quoted using `"` if `ANSI_QUOTES` is enabled, or using backticks otherwise.
+/// Note that a qualified name should be quoted part by part, e.g. `` `db`.`table` ``.
+///
+/// ```
+/// # use mysql::escape::{quote_identifier, EscapeMode};
+/// assert_eq!(quote_identifier("my`table", EscapeMode::new()), "`my``table`");
+/// let ansi = EscapeMode::new().with_ansi_quotes(true);
+/// assert_eq!(quote_identifier(r#"my"table"#, ansi), r#""my""table""#);
+/// ```
+pub fn quote_identifier(name: &str, mode: EscapeMode) -> String {
+    let quote = if mode.ansi_quotes { '"' } else { '`' };
+    let mut out = String::with_capacity(name.len() + 2);
+    out.push(quote);
+    for c in name.chars() {
+        if c == quote {
+            out.push(quote);
+        }
+        out.push(c);
+    }
+    out.push(quote);
+    out
+}
+
+/// Quotes and escapes the given string literal.
+///
+/// The literal is always quoted using `'`, because `"` quotes identifiers if `ANSI_QUOTES`
+/// is enabled. Special characters are escaped using backslash, unless `NO_BACKSLASH_ESCAPES`
+/// is enabled, in which case quotes are doubled.
+///
+/// ```
+/// # use mysql::escape::{escape_string, EscapeMode};
+/// assert_eq!(escape_string("it's\n", EscapeMode::new()), r"'it\'s\n'");
+/// let mode = EscapeMode::new().with_no_backslash_escapes(true);
+/// assert_eq!(escape_string("it's\n", mode), "'it''s\n'");
+/// ```
+pub fn escape_string(value: &str, mode: EscapeMode) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('\'');
+    for c in value.chars() {
+        match c {
+            '\'' if mode.no_backslash_escapes => out.push_str("''"),
+            _ if mode.no_backslash_escapes => out.push(c),
+            '\0' => out.push_str(r"\0"),
+            '\n' => out.push_str(r"\n"),
+            '\r' => out.push_str(r"\r"),
+            '\x1a' => out.push_str(r"\Z"),
+            '\\' | '\'' | '"' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Escapes wildcards (`%` and `_`) and backslashes of the given string, so that it matches
+/// literally within a `LIKE` pattern.
+///
+/// The result is a pattern rather than a literal, so it should be passed as a parameter
+/// or escaped using [`escape_string`]. Backslash is used as the escape character,
+/// see [`EscapeMode::like_escape_clause`].
+///
+/// ```
+/// # use mysql::escape::escape_like_pattern;
+/// assert_eq!(format!("{}%", escape_like_pattern(r"50%_off\")), r"50\%\_off\\%");
+/// ```
+pub fn escape_like_pattern(pattern: &str) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    for c in pattern.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+impl Conn {
+    /// Queries SQL modes of the session, that affect escaping (see [`crate::escape`]).
+    pub fn escape_mode(&mut self) -> Result<EscapeMode> {
+        let sql_mode: Option<String> = self.query_first("SELECT @@SESSION.sql_mode")?;
+        let mode = EscapeMode::from_sql_mode(sql_mode.as_deref().unwrap_or_default());
+        Ok(mode
+            .with_no_backslash_escapes(mode.no_backslash_escapes() || self.no_backslash_escape()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape_like_pattern, escape_string, quote_identifier, EscapeMode};
+
+    #[test]
+    fn should_parse_sql_mode() {
+        assert_eq!(EscapeMode::from_sql_mode(""), EscapeMode::new());
+        let mode = EscapeMode::from_sql_mode("STRICT_TRANS_TABLES,NO_BACKSLASH_ESCAPES");
+        assert!(mode.no_backslash_escapes() && !mode.ansi_quotes());
+        let mode = EscapeMode::from_sql_mode("REAL_AS_FLOAT,PIPES_AS_CONCAT,ANSI_QUOTES");
+        assert!(!mode.no_backslash_escapes() && mode.ansi_quotes());
+        assert!(EscapeMode::from_sql_mode("ansi").ansi_quotes());
+    }
+
+    #[test]
+    fn should_escape_identifiers_and_literals() {
+        let default = EscapeMode::new();
+        let ansi = EscapeMode::new()
+            .with_ansi_quotes(true)
+            .with_no_backslash_escapes(true);
+
+        assert_eq!(quote_identifier("t", default), "`t`");
+        assert_eq!(quote_identifier("a\"b`c", default), "`a\"b``c`");
+        assert_eq!(quote_identifier("a\"b`c", ansi), "\"a\"\"b`c\"");
+
+        assert_eq!(
+            escape_string("a\0b\r\x1a\\\"'", default),
+            r#"'a\0b\r\Z\\\"\''"#
+        );
+        assert_eq!(escape_string("a\0\\\"'", ansi), "'a\0\\\"'''");
+
+        assert_eq!(escape_like_pattern("a_b%c"), r"a\_b\%c");
+        assert_eq!(default.like_escape_clause(), r"ESCAPE '\\'");
+        assert_eq!(ansi.like_escape_clause(), r"ESCAPE '\'");
+    }
+}
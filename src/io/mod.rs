@@ -22,22 +22,31 @@ use std::{
     time::Duration,
 };
 
-use crate::error::{
-    DriverError::{ConnectTimeout, CouldNotConnect},
-    Error::DriverError,
-    Result as MyResult,
+use crate::{
+    error::{
+        DriverError::{ConnectTimeout, CouldNotConnect},
+        Error::DriverError,
+        Result as MyResult,
+    },
+    ConnectEvent,
 };
 
 mod tcp;
 mod tls;
+#[cfg(feature = "zstd")]
+mod zstd_stream;
 
 #[derive(Debug, Read, Write)]
+#[allow(clippy::enum_variant_names)]
 pub enum Stream {
     #[cfg(unix)]
     SocketStream(BufStream<unix::net::UnixStream>),
     #[cfg(windows)]
     SocketStream(BufStream<np::PipeClient>),
     TcpStream(TcpStream),
+    /// zstd-compressed stream (see [`zstd_stream::ZstdStream`]).
+    #[cfg(feature = "zstd")]
+    Zstd(Box<zstd_stream::ZstdStream>),
 }
 
 impl Stream {
@@ -46,9 +55,12 @@ impl Stream {
         socket: &str,
         read_timeout: Option<Duration>,
         write_timeout: Option<Duration>,
+        log: &mut Vec<ConnectEvent>,
     ) -> MyResult<Stream> {
         match unix::net::UnixStream::connect(socket) {
             Ok(stream) => {
+                log::debug!(target: "mysql::io", "connected to {}", socket);
+                log.push(ConnectEvent::Connected(socket.into()));
                 stream.set_read_timeout(read_timeout)?;
                 stream.set_write_timeout(write_timeout)?;
                 Ok(Stream::SocketStream(BufStream::new(stream)))
@@ -56,6 +68,8 @@ impl Stream {
             Err(e) => {
                 let addr = socket.to_string();
                 let desc = e.to_string();
+                log::debug!(target: "mysql::io", "unable to connect to {}: {}", addr, desc);
+                log.push(ConnectEvent::ConnectFailed(addr.clone(), desc.clone()));
                 Err(DriverError(CouldNotConnect(Some((addr, desc, e.kind())))))
             }
         }
@@ -66,16 +80,21 @@ impl Stream {
         socket: &str,
         read_timeout: Option<Duration>,
         write_timeout: Option<Duration>,
+        log: &mut Vec<ConnectEvent>,
     ) -> MyResult<Stream> {
         let full_name = format!(r"\\.\pipe\{}", socket);
         match np::PipeClient::connect(full_name.clone()) {
             Ok(mut stream) => {
+                log::debug!(target: "mysql::io", "connected to {}", full_name);
+                log.push(ConnectEvent::Connected(full_name));
                 stream.set_read_timeout(read_timeout);
                 stream.set_write_timeout(write_timeout);
                 Ok(Stream::SocketStream(BufStream::new(stream)))
             }
             Err(e) => {
                 let desc = format!("{}", e);
+                log::debug!(target: "mysql::io", "unable to connect to {}: {}", full_name, desc);
+                log.push(ConnectEvent::ConnectFailed(full_name.clone(), desc.clone()));
                 Err(DriverError(CouldNotConnect(Some((
                     full_name,
                     desc,
@@ -105,6 +124,7 @@ impl Stream {
         nodelay: bool,
         tcp_connect_timeout: Option<Duration>,
         bind_address: Option<SocketAddr>,
+        log: &mut Vec<ConnectEvent>,
     ) -> MyResult<Stream> {
         let mut builder = tcp::MyTcpBuilder::new((ip_or_hostname, port));
         builder
@@ -121,7 +141,7 @@ impl Stream {
         #[cfg(target_os = "linux")]
         builder.user_timeout(tcp_user_timeout);
         builder
-            .connect()
+            .connect(log)
             .map(|stream| Stream::TcpStream(TcpStream::Insecure(BufStream::new(stream))))
             .map_err(|err| {
                 if err.kind() == io::ErrorKind::TimedOut {
@@ -135,11 +155,55 @@ impl Stream {
     }
 
     pub fn is_insecure(&self) -> bool {
-        matches!(self, Stream::TcpStream(TcpStream::Insecure(_)))
+        match self {
+            #[cfg(feature = "zstd")]
+            Stream::Zstd(stream) => stream.get_ref().is_insecure(),
+            _ => matches!(self, Stream::TcpStream(TcpStream::Insecure(_))),
+        }
     }
 
     pub fn is_socket(&self) -> bool {
-        matches!(self, Stream::SocketStream(_))
+        match self {
+            #[cfg(feature = "zstd")]
+            Stream::Zstd(stream) => stream.get_ref().is_socket(),
+            _ => matches!(self, Stream::SocketStream(_)),
+        }
+    }
+
+    /// Wraps this stream so that the compressed protocol payloads are zstd-compressed
+    /// with the given `level`.
+    #[cfg(feature = "zstd")]
+    pub fn into_zstd(self, level: u8) -> Stream {
+        Stream::Zstd(Box::new(zstd_stream::ZstdStream::new(self, level)))
+    }
+
+    /// Updates read and write timeouts of the underlying socket.
+    pub fn set_timeouts(
+        &mut self,
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Stream::SocketStream(stream) => {
+                stream.get_ref().set_read_timeout(read_timeout)?;
+                stream.get_ref().set_write_timeout(write_timeout)?;
+            }
+            #[cfg(windows)]
+            Stream::SocketStream(stream) => {
+                stream.get_mut().set_read_timeout(read_timeout);
+                stream.get_mut().set_write_timeout(write_timeout);
+            }
+            Stream::TcpStream(stream) => {
+                stream.get_ref().set_read_timeout(read_timeout)?;
+                stream.get_ref().set_write_timeout(write_timeout)?;
+            }
+            #[cfg(feature = "zstd")]
+            Stream::Zstd(stream) => {
+                stream.get_mut().set_timeouts(read_timeout, write_timeout)?;
+            }
+        }
+        Ok(())
     }
 
     #[cfg(all(not(feature = "native-tls"), not(feature = "rustls")))]
@@ -157,6 +221,8 @@ impl AsRawFd for Stream {
         match self {
             Stream::SocketStream(stream) => stream.get_ref().as_raw_fd(),
             Stream::TcpStream(stream) => stream.as_raw_fd(),
+            #[cfg(feature = "zstd")]
+            Stream::Zstd(stream) => stream.get_ref().as_raw_fd(),
         }
     }
 }
@@ -170,6 +236,19 @@ pub enum TcpStream {
     Insecure(BufStream<net::TcpStream>),
 }
 
+impl TcpStream {
+    /// Returns a reference to the underlying TCP socket.
+    fn get_ref(&self) -> &net::TcpStream {
+        match self {
+            #[cfg(feature = "native-tls")]
+            TcpStream::Secure(stream) => stream.get_ref().get_ref(),
+            #[cfg(feature = "rustls")]
+            TcpStream::Secure(stream) => stream.get_ref().get_ref(),
+            TcpStream::Insecure(stream) => stream.get_ref(),
+        }
+    }
+}
+
 #[cfg(unix)]
 impl AsRawFd for TcpStream {
     fn as_raw_fd(&self) -> RawFd {
@@ -14,6 +14,8 @@ use std::{
     time::Duration,
 };
 
+use crate::ConnectEvent;
+
 pub struct MyTcpBuilder<T> {
     address: T,
     bind_address: Option<SocketAddr>,
@@ -103,7 +105,8 @@ impl<T: ToSocketAddrs> MyTcpBuilder<T> {
         }
     }
 
-    pub fn connect(self) -> io::Result<TcpStream> {
+    /// Connects to the first available address (steps are recorded to the `log`).
+    pub fn connect(self, log: &mut Vec<ConnectEvent>) -> io::Result<TcpStream> {
         let MyTcpBuilder {
             address,
             bind_address,
@@ -126,57 +129,49 @@ impl<T: ToSocketAddrs> MyTcpBuilder<T> {
         };
         let err = io::Error::new(io::ErrorKind::Other, err_msg);
 
-        let addrs = address.to_socket_addrs()?.collect::<Vec<_>>();
-
-        let socket = if let Some(bind_address) = bind_address {
-            let fold_fun = |prev, sock_addr: &SocketAddr| match prev {
-                Ok(socket) => Ok(socket),
-                Err(_) => {
-                    let domain = Domain::for_address(*sock_addr);
-                    let socket = Socket::new(domain, Type::STREAM, None)?;
-                    socket.bind(&bind_address.into())?;
-                    if let Some(connect_timeout) = connect_timeout {
-                        socket.connect_timeout(&SockAddr::from(*sock_addr), connect_timeout)?;
-                    } else {
-                        socket.connect(&SockAddr::from(*sock_addr))?;
-                    }
-                    Ok(socket)
-                }
-            };
-
-            if bind_address.is_ipv4() {
-                // client wants to bind to ipv4, so let's look for ipv4 addresses first
-                addrs
-                    .iter()
-                    .filter(|x| x.is_ipv4())
-                    .fold(Err(err), fold_fun)
-                    .or_else(|e| addrs.iter().filter(|x| x.is_ipv6()).fold(Err(e), fold_fun))
+        let mut addrs = address.to_socket_addrs()?.collect::<Vec<_>>();
+        log::trace!(target: "mysql::io", "resolved {:?}", addrs);
+        log.push(ConnectEvent::Resolved(addrs.clone()));
+
+        if let Some(bind_address) = bind_address {
+            // client wants to bind to a specific address family, so let's look for
+            // addresses of this family first
+            addrs.sort_by_key(|addr| addr.is_ipv4() != bind_address.is_ipv4());
+        }
+
+        let connect = |sock_addr: SocketAddr| -> io::Result<Socket> {
+            let domain = Domain::for_address(sock_addr);
+            let socket = Socket::new(domain, Type::STREAM, None)?;
+            if let Some(bind_address) = bind_address {
+                socket.bind(&bind_address.into())?;
+            }
+            if let Some(connect_timeout) = connect_timeout {
+                socket.connect_timeout(&SockAddr::from(sock_addr), connect_timeout)?;
             } else {
-                // client wants to bind to ipv6, so let's look for ipv6 addresses first
-                addrs
-                    .iter()
-                    .filter(|x| x.is_ipv6())
-                    .fold(Err(err), fold_fun)
-                    .or_else(|e| addrs.iter().filter(|x| x.is_ipv4()).fold(Err(e), fold_fun))
+                socket.connect(&SockAddr::from(sock_addr))?;
             }
-        } else {
-            // no bind address
-            addrs
-                .into_iter()
-                .fold(Err(err), |prev, sock_addr| match prev {
-                    Ok(socket) => Ok(socket),
-                    Err(_) => {
-                        let domain = Domain::for_address(sock_addr);
-                        let socket = Socket::new(domain, Type::STREAM, None)?;
-                        if let Some(connect_timeout) = connect_timeout {
-                            socket.connect_timeout(&sock_addr.into(), connect_timeout)?;
-                        } else {
-                            socket.connect(&sock_addr.into())?;
-                        }
-                        Ok(socket)
-                    }
-                })
-        }?;
+            Ok(socket)
+        };
+
+        let mut result = Err(err);
+        for sock_addr in addrs {
+            result = connect(sock_addr);
+            match result {
+                Ok(_) => {
+                    log::debug!(target: "mysql::io", "connected to {}", sock_addr);
+                    log.push(ConnectEvent::Connected(sock_addr.to_string()));
+                    break;
+                }
+                Err(ref err) => {
+                    log::debug!(target: "mysql::io", "unable to connect to {}: {}", sock_addr, err);
+                    log.push(ConnectEvent::ConnectFailed(
+                        sock_addr.to_string(),
+                        err.to_string(),
+                    ))
+                }
+            }
+        }
+        let socket = result?;
 
         socket.set_read_timeout(read_timeout)?;
         socket.set_write_timeout(write_timeout)?;
@@ -10,9 +10,65 @@ use native_tls::{Certificate, TlsConnector};
 
 use crate::{
     io::{Stream, TcpStream},
-    Result, SslOpts,
+    DriverError, Result, SslOpts,
 };
 
+/// Controls of the OpenSSL library behind `native-tls`.
+mod openssl {
+    use crate::{DriverError, Result};
+
+    #[cfg(ossl300)]
+    pub(super) fn load_provider(name: &str) -> Result<()> {
+        use std::{collections::HashSet, ffi::CString, ptr, sync::Mutex};
+
+        use once_cell::sync::Lazy;
+
+        /// Providers loaded so far (they are never unloaded).
+        static LOADED: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+        let mut loaded = LOADED.lock().unwrap();
+        if loaded.contains(name) {
+            return Ok(());
+        }
+
+        let c_name =
+            CString::new(name).map_err(|_| DriverError::CouldNotLoadTlsProvider(name.into()))?;
+        openssl_sys::init();
+        // keep fallback providers (i.e. `default`) available
+        let provider =
+            unsafe { openssl_sys::OSSL_PROVIDER_try_load(ptr::null_mut(), c_name.as_ptr(), 1) };
+        if provider.is_null() {
+            unsafe { openssl_sys::ERR_clear_error() };
+            return Err(DriverError::CouldNotLoadTlsProvider(name.into()).into());
+        }
+        loaded.insert(name.into());
+        Ok(())
+    }
+
+    #[cfg(not(ossl300))]
+    pub(super) fn load_provider(_name: &str) -> Result<()> {
+        Err(DriverError::UnsupportedTlsOption("tls_providers").into())
+    }
+
+    #[cfg(openssl_tls)]
+    pub(super) fn fips_enabled() -> bool {
+        openssl_sys::init();
+        #[cfg(ossl300)]
+        unsafe {
+            openssl_sys::EVP_default_properties_is_fips_enabled(std::ptr::null_mut()) != 0
+        }
+        #[cfg(not(ossl300))]
+        unsafe {
+            openssl_sys::FIPS_mode() != 0
+        }
+    }
+
+    #[cfg(not(openssl_tls))]
+    pub(super) fn fips_enabled() -> bool {
+        false
+    }
+}
+
 impl Stream {
     pub fn make_secure(self, host: url::Host, ssl_opts: SslOpts) -> Result<Stream> {
         if self.is_socket() {
@@ -26,6 +82,16 @@ impl Stream {
             url::Host::Ipv6(ip) => ip.to_string(),
         };
 
+        if ssl_opts.keylog_path().is_some() {
+            return Err(DriverError::UnsupportedTlsOption("keylog_path").into());
+        }
+        for provider in ssl_opts.tls_providers() {
+            openssl::load_provider(provider)?;
+        }
+        if ssl_opts.require_fips() && !openssl::fips_enabled() {
+            return Err(DriverError::FipsModeNotEnabled.into());
+        }
+
         let mut builder = TlsConnector::builder();
         if let Some(root_cert_path) = ssl_opts.root_cert_path() {
             let mut root_cert_data = vec![];
@@ -69,3 +135,22 @@ impl Stream {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    #[cfg(ossl300)]
+    #[test]
+    fn should_load_openssl_providers() {
+        use super::openssl::load_provider;
+        use crate::{DriverError, Error};
+
+        load_provider("null").unwrap();
+        load_provider("null").unwrap();
+        match load_provider("no-such-provider") {
+            Err(Error::DriverError(DriverError::CouldNotLoadTlsProvider(name))) => {
+                assert_eq!(name, "no-such-provider")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -2,21 +2,22 @@
 
 use std::{
     convert::TryInto,
-    fs::File,
-    io::{self, Read},
-    sync::Arc,
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    sync::{Arc, Mutex},
 };
 
 use bufstream::BufStream;
 use rustls::{
     client::{ServerCertVerifier, WebPkiVerifier},
-    Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore,
+    Certificate, ClientConfig, KeyLog, OwnedTrustAnchor, RootCertStore,
 };
 use rustls_pemfile::certs;
 
 use crate::{
     io::{Stream, TcpStream},
-    Result, SslOpts,
+    DriverError, Result, SslOpts,
 };
 
 impl Stream {
@@ -32,6 +33,14 @@ impl Stream {
             url::Host::Ipv6(ip) => ip.to_string(),
         };
 
+        if !ssl_opts.tls_providers().is_empty() {
+            return Err(DriverError::UnsupportedTlsOption("tls_providers").into());
+        }
+        if ssl_opts.require_fips() {
+            // rustls can't report FIPS mode
+            return Err(DriverError::FipsModeNotEnabled.into());
+        }
+
         let mut root_store = RootCertStore::empty();
         root_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
             OwnedTrustAnchor::from_subject_spki_name_constraints(
@@ -71,6 +80,14 @@ impl Stream {
             config_builder.with_no_client_auth()
         };
 
+        if let Some(keylog_path) = ssl_opts.keylog_path() {
+            let file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(keylog_path)?;
+            config.key_log = Arc::new(KeyLogFile(Mutex::new(file)));
+        }
+
         let server_name = domain
             .as_str()
             .try_into()
@@ -105,6 +122,24 @@ impl Stream {
     }
 }
 
+/// Writes TLS secrets to a file in the NSS key log format.
+struct KeyLogFile(Mutex<File>);
+
+impl KeyLog for KeyLogFile {
+    fn log(&self, label: &str, client_random: &[u8], secret: &[u8]) {
+        let mut line = String::from(label);
+        for bytes in [client_random, secret] {
+            line.push(' ');
+            for byte in bytes {
+                let _ = write!(line, "{:02x}", byte);
+            }
+        }
+        line.push('\n');
+        // logging is best-effort
+        let _ = self.0.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
 struct DangerousVerifier {
     accept_invalid_certs: bool,
     skip_domain_validation: bool,
@@ -0,0 +1,241 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    cmp::min,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use super::Stream;
+
+/// Length of the compressed packet header.
+const HEADER_LEN: usize = 7;
+
+/// Payloads shorter than this are sent uncompressed (same as `MIN_COMPRESS_LENGTH` in `net_serv.cc`).
+const MIN_COMPRESS_LENGTH: usize = 50;
+
+/// Stream that zstd-compresses the compressed protocol.
+///
+/// The compressed protocol framing is the same for zlib and zstd, but the packet codec
+/// (provided by `mysql_common`) implements zlib only. So the codec runs with
+/// `Compression::none()`, i.e. it emits and expects compressed packets with uncompressed
+/// payloads, and this stream transcodes them:
+///
+/// * outgoing payloads are compressed using zstd (unless they are too short
+///   or incompressible);
+/// * incoming zstd payloads are decompressed before they reach the codec.
+pub struct ZstdStream {
+    inner: Stream,
+    level: i32,
+    /// Outgoing bytes that don't form a complete compressed packet yet.
+    out_buf: Vec<u8>,
+    /// Incoming bytes that don't form a complete compressed packet yet.
+    in_buf: Vec<u8>,
+    /// Decompressed incoming packet that isn't consumed by the codec yet.
+    plain: Vec<u8>,
+    plain_pos: usize,
+}
+
+impl ZstdStream {
+    pub fn new(inner: Stream, level: u8) -> Self {
+        Self {
+            inner,
+            level: level as i32,
+            out_buf: Vec::new(),
+            in_buf: Vec::new(),
+            plain: Vec::new(),
+            plain_pos: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &Stream {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut Stream {
+        &mut self.inner
+    }
+
+    /// Moves the first compressed packet of `in_buf` (if it's complete) to `plain`
+    /// decompressing its payload.
+    fn decode_packet(&mut self) -> io::Result<bool> {
+        let len = match complete_packet(&self.in_buf) {
+            Some(len) => len,
+            None => return Ok(false),
+        };
+
+        self.plain.clear();
+        self.plain_pos = 0;
+        let uncompressed_len = read_u24(&self.in_buf[4..]);
+        if uncompressed_len == 0 {
+            self.plain.extend_from_slice(&self.in_buf[..len]);
+        } else {
+            let payload = zstd::bulk::decompress(&self.in_buf[HEADER_LEN..len], uncompressed_len)?;
+            if payload.len() != uncompressed_len {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "bad compressed packet length",
+                ));
+            }
+            write_header(&mut self.plain, payload.len(), self.in_buf[3], 0);
+            self.plain.extend_from_slice(&payload);
+        }
+        self.in_buf.drain(..len);
+
+        Ok(true)
+    }
+}
+
+impl Read for ZstdStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.plain_pos == self.plain.len() {
+            if !self.decode_packet()? {
+                let mut chunk = [0_u8; 16 * 1024];
+                match self.inner.read(&mut chunk)? {
+                    0 if self.in_buf.is_empty() => return Ok(0),
+                    0 => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "incomplete compressed packet",
+                        ))
+                    }
+                    n => self.in_buf.extend_from_slice(&chunk[..n]),
+                }
+            }
+        }
+
+        let n = min(buf.len(), self.plain.len() - self.plain_pos);
+        buf[..n].copy_from_slice(&self.plain[self.plain_pos..self.plain_pos + n]);
+        self.plain_pos += n;
+        Ok(n)
+    }
+}
+
+impl Write for ZstdStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out_buf.extend_from_slice(buf);
+
+        let mut encoded = Vec::with_capacity(self.out_buf.len());
+        let mut pos = 0;
+        while let Some(len) = complete_packet(&self.out_buf[pos..]) {
+            let packet = &self.out_buf[pos..pos + len];
+            let payload = &packet[HEADER_LEN..];
+            let compressed = if read_u24(&packet[4..]) == 0 && payload.len() >= MIN_COMPRESS_LENGTH
+            {
+                Some(zstd::bulk::compress(payload, self.level)?)
+            } else {
+                None
+            };
+            match compressed {
+                Some(compressed) if compressed.len() < payload.len() => {
+                    write_header(&mut encoded, compressed.len(), packet[3], payload.len());
+                    encoded.extend_from_slice(&compressed);
+                }
+                _ => encoded.extend_from_slice(packet),
+            }
+            pos += len;
+        }
+        self.out_buf.drain(..pos);
+
+        self.inner.write_all(&encoded)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl fmt::Debug for ZstdStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZstdStream")
+            .field("inner", &self.inner)
+            .field("level", &self.level)
+            .finish()
+    }
+}
+
+/// Returns the length of the first compressed packet of `buf` if `buf` contains all of it.
+fn complete_packet(buf: &[u8]) -> Option<usize> {
+    if buf.len() < HEADER_LEN {
+        return None;
+    }
+    let len = HEADER_LEN + read_u24(buf);
+    (buf.len() >= len).then_some(len)
+}
+
+fn read_u24(buf: &[u8]) -> usize {
+    buf[0] as usize | (buf[1] as usize) << 8 | (buf[2] as usize) << 16
+}
+
+fn write_header(dst: &mut Vec<u8>, len: usize, seq_id: u8, uncompressed_len: usize) {
+    dst.extend_from_slice(&(len as u32).to_le_bytes()[..3]);
+    dst.push(seq_id);
+    dst.extend_from_slice(&(uncompressed_len as u32).to_le_bytes()[..3]);
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use bufstream::BufStream;
+    use bytes::BytesMut;
+    use mysql_common::proto::codec::{Compression, PacketCodec};
+
+    use std::{
+        io::{Read, Write},
+        net::Shutdown,
+        os::unix::net::UnixStream,
+    };
+
+    use super::ZstdStream;
+    use crate::io::Stream;
+
+    #[test]
+    fn should_roundtrip_packets() {
+        let packets = [vec![0x03, b'x'], vec![b'a'; 100_000]];
+
+        // outgoing packets
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut codec = PacketCodec::default();
+        codec.compress(Compression::none());
+        let mut stream = ZstdStream::new(Stream::SocketStream(BufStream::new(client)), 3);
+        for packet in &packets {
+            let mut out = BytesMut::new();
+            codec.encode(&mut &packet[..], &mut out).unwrap();
+            // the stream must cope with partial writes
+            let (head, tail) = out.split_at(out.len() / 2);
+            stream.write_all(head).unwrap();
+            stream.write_all(tail).unwrap();
+        }
+        stream.flush().unwrap();
+        drop(stream);
+
+        let mut wire = Vec::new();
+        server.read_to_end(&mut wire).unwrap();
+        // the short packet is sent as is and the long one is compressed
+        assert_eq!(&wire[..13], &[6, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0x03, b'x']);
+        assert!(wire.len() < 1000);
+
+        // incoming packets
+        let (client, mut server) = UnixStream::pair().unwrap();
+        server.write_all(&wire).unwrap();
+        server.shutdown(Shutdown::Write).unwrap();
+        let mut codec = PacketCodec::default();
+        codec.compress(Compression::none());
+        let mut stream = ZstdStream::new(Stream::SocketStream(BufStream::new(client)), 3);
+        let mut input = Vec::new();
+        stream.read_to_end(&mut input).unwrap();
+        let mut input = BytesMut::from(&input[..]);
+        for packet in &packets {
+            let mut decoded = Vec::new();
+            assert!(codec.decode(&mut input, &mut decoded).unwrap());
+            assert_eq!(&decoded, packet);
+        }
+        assert!(input.is_empty());
+    }
+}
@@ -127,6 +127,38 @@
 //!     *   **buffer-pool** (enabled by default) – enables buffer pooling
 //!         (see the [Buffer Pool](#buffer-pool) section)
 //!     *   **derive** (enabled by default) – reexports derive macros under `prelude`
+//!         (see [Deriving `FromRow`](#deriving-fromrow) and [Deriving `ToParams`](#deriving-toparams))
+//!     *   **testing** (disabled by default) – allows to inject time and connect functions
+//!         into the pool and provides result set assertions for integration tests
+//!         (see the `testing` module)
+//!     *   **alloc-stats** (disabled by default) – accounts memory allocated per query
+//!         (see `Conn::alloc_stats`)
+//!     *   **testkit** (disabled by default) – starts throwaway MySql and MariaDb servers
+//!         in docker containers for integration tests (see the `testkit` module, unix only)
+//!     *   **bigdecimal** (enabled by default) – conversions between `DECIMAL` values
+//!         and `bigdecimal::BigDecimal` (see the [Decimals](#decimals) section)
+//!     *   **rust_decimal** (enabled by default) – conversions between `DECIMAL` values
+//!         and `rust_decimal::Decimal` (see the [Decimals](#decimals) section)
+//!     *   **csv** (disabled by default) – allows to insert records of the `csv` crate
+//!         (see `CsvConverters`, `Conn::exec_batch_bulk_csv` and `LoadDataBuilder::load_csv`)
+//!     *   **profiling** (disabled by default) – marks reading, decoding and conversion
+//!         of packets and rows as scopes of the `profiling` crate, i.e. they show up
+//!         in flamegraphs of the profiler enabled via one of `profiling/profile-with-*` features
+//!         (`mysql::read_packet`, `mysql::read_compressed_packet`, `mysql::decode_column`,
+//!         `mysql::decode_row` and `mysql::convert_row`)
+//!     *   **tracing** (disabled by default) – emits spans of the `tracing` crate (target `mysql::conn`)
+//!         for `connect`, `handshake`, `prepare`, `query`, `execute` and `fetch` with `connection_id`,
+//!         `digest` (a hash of the statement text), `rows` and `duration_us` fields,
+//!         as well as `retrying` and `reconnected` events
+//!     *   **metrics** (disabled by default) – publishes driver metrics via the `metrics` crate,
+//!         i.e. to the recorder installed by the application (e.g. a Prometheus exporter):
+//!         `mysql_queries_total` (by `type`: `query`, `execute` or `prepare`),
+//!         `mysql_errors_total` (server errors by `code`), `mysql_bytes_sent_total`,
+//!         `mysql_bytes_received_total` (packet payloads), `mysql_pool_checkouts_total`
+//!         (by `result`: `ok` or `timeout`) and `mysql_connect_duration_seconds` histogram
+//!         (by `result`: `ok` or `error`)
+//!     *   **zstd** (disabled by default) – enables zstd protocol compression
+//!         (see `Compression::Zstd`)
 //!
 //! * external features enabled by default:
 //!
@@ -136,8 +168,6 @@
 //!
 //!     * for the `mysql_common` crate (please consult `mysql_common` crate documentation for available features):
 //!
-//!         *   **mysql_common/bigdecimal03** – the `bigdecimal03` is enabled by default
-//!         *   **mysql_common/rust_decimal** – the `rust_decimal` is enabled by default
 //!         *   **mysql_common/time03** – the `time03` is enabled by default
 //!         *   **mysql_common/uuid** – the `uuid` is enabled by default
 //!         *   **mysql_common/frunk** – the `frunk` is enabled by default
@@ -147,10 +177,10 @@
 //! ```toml
 //! [dependencies]
 //! # Lets say that we want to use the `rustls-tls` feature:
-//! mysql = { version = "*", default-features = false, features = ["minimal", "rustls-tls"] }
+//! mysql = { version = "*", default-features = false, features = ["minimal", "rustls-tls", "rust_decimal"] }
 //! # Previous line disables default mysql features,
 //! # so now we need to choose desired mysql_common features:
-//! mysql_common = { version = "*", default-features = false, features = ["bigdecimal03", "time03", "uuid"]}
+//! mysql_common = { version = "*", default-features = false, features = ["time03", "uuid"]}
 //! ```
 //!
 //! ## API Documentation
@@ -167,8 +197,9 @@
 //! #### URL-based connection string
 //!
 //! Note, that you can use URL-based connection string as a source of an `Opts` instance.
-//! URL schema must be `mysql`. Host, port and credentials, as well as query parameters,
-//! should be given in accordance with the RFC 3986.
+//! URL schema must be `mysql` (or `mysql+unix`/`mysql+pipe` to connect via the unix socket or
+//! the named pipe given in place of the host, see `Opts::from_url`). Host, port and credentials,
+//! as well as query parameters, should be given in accordance with the RFC 3986.
 //!
 //! Examples:
 //!
@@ -191,6 +222,10 @@
 //! *   `port: u16` – MySql server port;
 //! *   `pool_min: usize` – see [`PoolConstraints::min`];
 //! *   `pool_max: usize` – see [`PoolConstraints::max`];
+//! *   `pool_warm_up: usize` – see [`PoolOpts::with_warm_up`];
+//! *   `checkout_timeout_ms: u64` – see [`PoolOpts::with_checkout_timeout`];
+//! *   `queue_strategy: fifo | lifo` – see [`PoolOpts::with_queue_strategy`];
+//! *   `stmt_registry: true | false` – see [`PoolOpts::with_stmt_registry`];
 //! *   `prefer_socket: true | false` - see [`Opts::get_prefer_socket`];
 //! *   `tcp_keepalive_time_ms: u32` - defines the value (in milliseconds)
 //!     of the `tcp_keepalive_time` field in the `Opts` structure;
@@ -206,13 +241,22 @@
 //! *   `enable_cleartext_plugin` – see [`Opts::get_enable_cleartext_plugin`];
 //! *   `secure_auth` – see [`Opts::get_secure_auth`];
 //! *   `reset_connection` – see [`PoolOpts::reset_connection`];
+//! *   `reset_policy: none | fast | full` – see [`PoolOpts::with_reset_policy`];
 //! *   `check_health` – see [`PoolOpts::check_health`];
+//! *   `validation_mode` – see [`PoolOpts::with_validation_mode`];
+//! *   `validation_query` – see [`PoolOpts::with_validation_query`];
+//! *   `max_lifetime_ms` – see [`PoolOpts::with_max_lifetime`];
+//! *   `idle_timeout_ms` – see [`PoolOpts::with_idle_timeout`];
+//! *   `auto_reconnect` – see [`OptsBuilder::auto_reconnect`];
+//! *   `compat_mode: standard | tidb | vitess` – see [`OptsBuilder::compat_mode`];
 //! *   `compress` - defines the value of the same field in the `Opts` structure.
 //!     Supported value are:
 //!     *  `true` - enables compression with the default compression level;
 //!     *  `fast` - enables compression with "fast" compression level;
 //!     *  `best` - enables compression with "best" compression level;
 //!     *  `1`..`9` - enables compression with the given compression level.
+//!     *  `zstd`, `zstd:1`..`zstd:22` - enables zstd compression with the default (`3`)
+//!        or the given level (requires the `zstd` feature).
 //! *   `socket` - socket path on UNIX, or pipe name on Windows.
 //!
 //! ### `OptsBuilder`
@@ -405,6 +449,69 @@
 //! # });
 //! ```
 //!
+//! #### Decimals
+//!
+//! `DECIMAL` values are transferred as strings, so converting them to `f64` silently loses
+//! precision. The **rust_decimal** and **bigdecimal** features (enabled by default) implement
+//! exact conversions between `DECIMAL` values and `rust_decimal::Decimal`
+//! or `bigdecimal::BigDecimal`, in both directions. The crates are reexported
+//! as `mysql::rust_decimal` and `mysql::bigdecimal`, so that their versions match.
+//!
+//! ```
+//! # mysql::doctest_wrapper!(__result, {
+//! use mysql::*;
+//! use mysql::prelude::*;
+//! use mysql::{bigdecimal::BigDecimal, rust_decimal::Decimal};
+//! use std::str::FromStr;
+//!
+//! let mut conn = Conn::new(get_opts())?;
+//! let price = Decimal::from_str("0.10").unwrap();
+//! let total: Option<Decimal> = conn.exec_first("SELECT CAST(? AS DECIMAL(20, 2)) * 3", (price,))?;
+//! assert_eq!(total, Some(Decimal::from_str("0.30").unwrap()));
+//!
+//! let big: Option<BigDecimal> = conn.query_first("SELECT 12345678901234567890.123456789")?;
+//! assert_eq!(big.unwrap().to_string(), "12345678901234567890.123456789");
+//! # });
+//! ```
+//!
+//! #### `BIT`, `ENUM`, `SET` and `VECTOR`
+//!
+//! The [`column_types`] module implements conversions for values of these columns:
+//!
+//! *   `column_types::Bits` – value of a `BIT(n)` column as `u64` (and `bitvec::BitVec`,
+//!     the crate is reexported as `mysql::bitvec`);
+//! *   `#[derive(MysqlEnum)]` (requires the `derive` feature) – maps an enum with unit variants
+//!     to values of an `ENUM` column (supports `#[mysql(rename_all = "...")]` on the enum
+//!     and `#[mysql(rename = "...")]` on variants);
+//! *   `column_types::Set<T>` – members of a `SET` column, where `T` is a `MysqlEnum`;
+//! *   `column_types::Vector` – value of a `VECTOR(n)` column (MySql 9.0+) as `Vec<f32>`
+//!     (use `Vector::to_text` with `STRING_TO_VECTOR(?)`, if binary values aren't accepted).
+//!
+//! Use `MysqlEnum::check_column` to make sure that all variants are values of a column
+//! (see `Conn::columns`).
+//!
+//! ```
+//! # mysql::doctest_wrapper!(__result, {
+//! use mysql::*;
+//! use mysql::prelude::*;
+//! use mysql::column_types::{Bits, Set};
+//!
+//! #[derive(Debug, PartialEq, MysqlEnum)]
+//! #[mysql(rename_all = "lowercase")]
+//! enum Perm {
+//!     Read,
+//!     Write,
+//! }
+//!
+//! let mut conn = Conn::new(get_opts())?;
+//! let (bits, perm, perms): (Bits, Perm, Set<Perm>) =
+//!     conn.query_first("SELECT b'101', 'write', 'read,write'")?.unwrap();
+//! assert_eq!(bits.value(), 5);
+//! assert_eq!(perm, Perm::Write);
+//! assert_eq!(perms.len(), 2);
+//! # });
+//! ```
+//!
 //! ### `Row`
 //!
 //! Internally `Row` is a vector of `Value`s, that also allows indexing by a column name/offset,
@@ -478,6 +585,65 @@
 //! # });
 //! ```
 //!
+//! #### Deriving `FromRow`
+//!
+//! `#[derive(FromRow)]` (requires the `derive` feature) maps columns to fields of a struct
+//! by name, so the conversion doesn't depend on the order of columns. Supported attributes:
+//!
+//! *   `#[mysql(rename = "name")]` – field is mapped to the column with the given name;
+//! *   `#[mysql(rename_all = "...")]` (on the struct) – field names are converted
+//!     to column names using the given convention, e.g. `camelCase` or `SCREAMING_SNAKE_CASE`;
+//! *   `#[mysql(json)]` – column contains a JSON document that is deserialized into the field;
+//! *   `#[mysql(default)]` – field is set to `Default::default()` if the column
+//!     is missing from the result set;
+//! *   `#[mysql(default = "path")]` – field is set to the result of the given function
+//!     (e.g. `fn default_limit() -> u32`) if the column is missing from the result set;
+//! *   `#[mysql(default)]` (on the struct) – every field is `default` unless it has its own
+//!     default, so that the struct keeps working if columns are dropped or not selected yet;
+//! *   `#[mysql(skip)]` – field isn't mapped to a column and is set to `Default::default()`;
+//! *   `#[mysql(table_name = "name")]` (on the struct) – defines the `TABLE_NAME` constant
+//!     (a `<FIELD>_FIELD` constant with the column name is defined for every field);
+//! *   `#[mysql(crate_name = "path")]` (on the struct) – path of this crate if it's renamed.
+//!
+//! Conversion fails if a column is missing (unless it's `default`) or can't be converted
+//! to the type of the field. Columns that aren't mapped to fields are ignored.
+//!
+//! ```
+//! # mysql::doctest_wrapper!(__result, {
+//! use mysql::*;
+//! use mysql::prelude::*;
+//!
+//! #[derive(Debug, PartialEq, FromRow)]
+//! struct Payment {
+//!     id: u32,
+//!     #[mysql(rename = "customer")]
+//!     customer_id: u32,
+//!     #[mysql(json)]
+//!     tags: Vec<String>,
+//!     #[mysql(default)]
+//!     note: Option<String>,
+//! }
+//!
+//! let mut conn = Conn::new(get_opts())?;
+//! let payment: Option<Payment> = conn.exec_first(
+//!     "SELECT ? AS tags, 'ignored' AS extra, 2 AS customer, 1 AS id",
+//!     (r#"["a", "b"]"#,),
+//! )?;
+//! assert_eq!(
+//!     payment,
+//!     Some(Payment {
+//!         id: 1,
+//!         customer_id: 2,
+//!         tags: vec!["a".into(), "b".into()],
+//!         note: None,
+//!     })
+//! );
+//!
+//! let ids = conn.query_map("SELECT 3 AS id, 4 AS customer, '[]' AS tags", |p: Payment| p.id)?;
+//! assert_eq!(ids, [3]);
+//! # });
+//! ```
+//!
 //! ### `Params`
 //!
 //! Represents parameters of a prepared statement, but this type won't appear directly in your code
@@ -618,6 +784,14 @@
 //! # });
 //! ```
 //!
+//! Queries, that can't be prepared (e.g. some DDL or `SET` statements), could still take
+//! parameters using `Conn::query_interpolated`, that safely escapes and inlines them into
+//! the query on the client side.
+//!
+//! Identifiers and literals of dynamically built queries (e.g. table names) could be
+//! escaped using the `mysql::escape` module, that honors `ANSI_QUOTES` and
+//! `NO_BACKSLASH_ESCAPES` SQL modes of the session (see `Conn::escape_mode`).
+//!
 //! ### The `TextQuery` trait.
 //!
 //! The `TextQuery` trait covers the set of `Queryable::query*` methods from the perspective
@@ -718,6 +892,37 @@
 //!
 //! Statement cache is completely disabled if `stmt_cache_size` is zero.
 //!
+//! Cache behavior could be overridden for a single `exec_*` call using [`CachePolicy`],
+//! e.g. so that one-off dynamically generated queries don't evict frequently used statements:
+//!
+//! *   [`CachePolicy::NoCache`] – statement isn't cached and it's closed as soon as another
+//!     uncached statement is prepared;
+//! *   [`CachePolicy::Ttl`] – statement is cached, but it's closed once the TTL is elapsed.
+//!
+//! ```rust
+//! # mysql::doctest_wrapper!(__result, {
+//! # use mysql::*;
+//! # use mysql::prelude::*;
+//! # let pool = Pool::new(get_opts())?;
+//! # let mut conn = pool.get_conn()?;
+//! # use std::time::Duration;
+//! let ids = [1, 2, 3];
+//! let query = format!("SELECT {}", vec!["?"; ids.len()].join(", "));
+//! conn.exec_drop(CachePolicy::NoCache.apply(&query), ids.to_vec())?;
+//!
+//! let ttl = CachePolicy::Ttl(Duration::from_secs(60));
+//! conn.exec_drop(ttl.apply("DO ?"), (42,))?;
+//! # });
+//! ```
+//!
+//! If the server reports that a statement is no longer valid (`ER_NEED_REPREPARE`, e.g. after
+//! a DDL on an underlying table, or `ER_UNKNOWN_STMT_HANDLER`), then the statement
+//! is prepared again and the execution is retried once (this doesn't apply to cursors).
+//!
+//! The cache could be inspected using `Conn::cached_statements` (queries, statement ids and hit
+//! counts) and managed at runtime using `Conn::evict_stmt`, `Conn::clear_stmt_cache` and
+//! `Conn::set_stmt_cache_size`.
+//!
 //! **Caveats:**
 //!
 //! *   disabled statement cache means, that you have to close statements yourself using
@@ -745,6 +950,9 @@
 //!
 //! **Note:** Positional and named parameters can't be mixed within the single statement.
 //!
+//! Absent named parameter is an error unless the statement is wrapped into [`MissingAsNull`],
+//! that binds absent named parameters as `NULL` (e.g. for sparse updates).
+//!
 //! Examples:
 //!
 //! ```rust
@@ -767,6 +975,46 @@
 //! # });
 //! ```
 //!
+//! #### Deriving `ToParams`
+//!
+//! `#[derive(ToParams)]` (requires the `derive` feature) implements `Into<Params>` for a struct
+//! and a reference to it, so that fields become named parameters. It supports the `rename`,
+//! `rename_all`, `json` and `skip` attributes of [`FromRow`](#deriving-fromrow). Parameters
+//! that aren't used by the statement are ignored.
+//!
+//! ```rust
+//! # mysql::doctest_wrapper!(__result, {
+//! # use mysql::*;
+//! # use mysql::prelude::*;
+//! #[derive(ToParams)]
+//! struct Payment {
+//!     customer_id: u32,
+//!     amount: u32,
+//!     #[mysql(rename = "name")]
+//!     account_name: Option<String>,
+//! }
+//!
+//! let payments = vec![
+//!     Payment { customer_id: 1, amount: 2, account_name: None },
+//!     Payment { customer_id: 3, amount: 4, account_name: Some("foo".into()) },
+//! ];
+//!
+//! let mut conn = Conn::new(get_opts())?;
+//! conn.query_drop(
+//!     "CREATE TEMPORARY TABLE mysql.payments (customer_id INT, amount INT, name TEXT)",
+//! )?;
+//! conn.exec_batch(
+//!     "INSERT INTO mysql.payments VALUES (:customer_id, :amount, :name)",
+//!     payments.iter(),
+//! )?;
+//! let name: Option<String> = conn.exec_first(
+//!     "SELECT name FROM mysql.payments WHERE customer_id = :customer_id",
+//!     &payments[1],
+//! )?;
+//! assert_eq!(name.as_deref(), Some("foo"));
+//! # });
+//! ```
+//!
 //! ### Buffer pool
 //!
 //! Crate uses the global lock-free buffer pool for the purpose of IO and data serialization/deserialization,
@@ -853,10 +1101,27 @@
 //! *   `{query|exec}_first` - to get the first `T: FromRow`, if any;
 //! *   `{query|exec}_map` - to map each `T: FromRow` to some `U`;
 //! *   `{query|exec}_fold` - to fold the set of `T: FromRow` to a single value;
+//! *   `{query|exec}_as` - to deserialize each row into a serde type (see `DeserializeRow`);
 //! *   `{query|exec}_drop` - to immediately drop the result.
 //!
 //! The trait also defines the `exec_batch` function, which is a helper for batch statement
-//! execution.
+//! execution. On MariaDB 10.2+ it sends all rows at once using `COM_STMT_BULK_EXECUTE`
+//! (see `Conn::exec_batch_ids` to get generated ids of rows).
+//!
+//! ## Logging
+//!
+//! The driver logs its internals using the [`log`](https://docs.rs/log) crate, so any logger
+//! implementation could be used to collect them. Records are emitted under the following targets,
+//! so that verbosity could be raised for a single subsystem (e.g. `RUST_LOG=mysql::pool=debug`
+//! for `env_logger`):
+//!
+//! *   `mysql::conn` – connection lifecycle (established, reset, lost and reconnected, closed)
+//!     and statements closed due to the statement cache eviction or expiration;
+//! *   `mysql::pool` – pooled connections being created, reaped, validated, discarded and drained;
+//! *   `mysql::io` – resolved addresses, connection attempts and TLS negotiation;
+//! *   `mysql::wire` – every packet sent or received (`Trace` level), if enabled
+//!     via `OptsBuilder::wire_log` or the `MYSQL_WIRE_LOG` environment variable
+//!     (`headers` or `hexdump`).
 //!
 //! ## SSL Support
 //!
@@ -872,6 +1137,8 @@
 //!     *   it will fail if you'll try to connect to the server by its IP address, hostname is required;
 //!     *   it, most likely, won't work on windows, at least with default server certs, generated by the
 //!         MySql installer.
+//!     *   client identity is a pair of PEM (or DER) files – a certificate chain and a private key
+//!         (see `ClientIdentity`), which is handy when cross-compiling (e.g. to musl targets).
 //!
 //! [crate docs]: https://docs.rs/mysql
 //! [mysql_common docs]: https://docs.rs/mysql_common
@@ -889,13 +1156,34 @@ pub extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
 
+/// Marks the rest of the enclosing block as a scope of the `profiling` crate
+/// (a no-op without the `profiling` feature).
+macro_rules! profile_scope {
+    ($name:literal) => {
+        #[cfg(feature = "profiling")]
+        profiling::scope!($name);
+    };
+}
+
 mod buffer_pool;
+mod column;
+pub mod column_types;
 mod conn;
 pub mod error;
+pub mod escape;
 mod io;
+#[cfg(any(test, feature = "testing"))]
+mod result_diff;
+mod row;
+pub mod schema;
+pub mod spatial;
+#[cfg(all(feature = "testkit", unix))]
+pub mod testkit;
 
 #[cfg(feature = "derive")]
 extern crate mysql_common;
+// makes `::mysql` paths generated by derive macros usable within this crate
+extern crate self as mysql;
 
 #[doc(inline)]
 pub use crate::myc::constants as consts;
@@ -916,59 +1204,146 @@ pub mod binlog {
 pub use crate::conn::opts::ClientIdentity;
 
 #[doc(inline)]
-pub use crate::myc::packets::{session_state_change, SessionStateInfo};
+pub use crate::myc::packets::{session_state_change, AuthPlugin, SessionStateInfo};
 
+#[doc(inline)]
+pub use crate::conn::admin::{AdminConn, CancelHandle, ProcessInfo};
+#[cfg(feature = "alloc-stats")]
+#[doc(inline)]
+pub use crate::conn::alloc_stats::AllocStats;
+#[doc(inline)]
+pub use crate::conn::call::{CallArg, CallResult};
+#[doc(inline)]
+pub use crate::conn::chunked::ChunkReport;
+#[doc(inline)]
+pub use crate::conn::compression::Compression;
+#[doc(inline)]
+pub use crate::conn::connect_log::{ConnectError, ConnectEvent};
+#[cfg(feature = "csv")]
+#[doc(inline)]
+pub use crate::conn::csv_records::{CsvConverters, CsvRecord};
+#[doc(inline)]
+pub use crate::conn::export::{CsvOpts, ExportOpts, TableChecksum};
+#[doc(inline)]
+pub use crate::conn::health::{HealthReport, ReplicaStatus};
+#[doc(inline)]
+pub use crate::conn::hints::{Hinted, OptimizerHints};
+#[doc(inline)]
+pub use crate::conn::info::{QueryInfo, UpsertOutcome, Warning, WarningLevel};
+#[doc(inline)]
+pub use crate::conn::interceptor::{
+    InterceptedQuery, Interception, QueryInterceptor, QueryOutcome,
+};
+#[doc(inline)]
+pub use crate::conn::json_table::JsonTable;
+#[doc(inline)]
+pub use crate::conn::latency::LatencyStats;
+#[doc(inline)]
+pub use crate::conn::load_data::{LoadDataBuilder, OnDuplicate};
 #[doc(inline)]
 pub use crate::conn::local_infile::{LocalInfile, LocalInfileHandler};
 #[doc(inline)]
+pub use crate::conn::long_data::StreamedParam;
+#[doc(inline)]
 pub use crate::conn::opts::SslOpts;
 #[doc(inline)]
 pub use crate::conn::opts::{
-    pool_opts::{PoolConstraints, PoolOpts},
+    pool_opts::{
+        InitHandler, PoolConstraints, PoolOpts, QueueStrategy, ResetPolicy, ValidationMode,
+    },
     ChangeUserOpts, Opts, OptsBuilder, DEFAULT_STMT_CACHE_SIZE,
 };
 #[doc(inline)]
+pub use crate::conn::param_report::ParamEncoding;
+#[doc(inline)]
 pub use crate::conn::pool::{Pool, PooledConn};
 #[doc(inline)]
 pub use crate::conn::query::QueryWithParams;
 #[doc(inline)]
-pub use crate::conn::query_result::{Binary, QueryResult, ResultSet, SetColumns, Text};
+pub use crate::conn::query_result::{
+    Binary, CollectedSet, ExecResult, FloatDecoding, QueryResult, RawRow, ResultSet, ResultSets,
+    ResultSummary, SetColumns, Text, Utf8Decoding,
+};
+#[doc(inline)]
+pub use crate::conn::redaction::{RedactionPolicy, ValueKind, REDACTED};
+#[doc(inline)]
+pub use crate::conn::server_flavor::{CompatMode, Feature, MariadbCapabilities, ServerFlavor};
 #[doc(inline)]
-pub use crate::conn::stmt::Statement;
+pub use crate::conn::slow_query::{ExplainMode, SlowQuery, SlowQueryHandler};
 #[doc(inline)]
-pub use crate::conn::transaction::{AccessMode, IsolationLevel, Transaction, TxOpts};
+pub use crate::conn::stmt::{CachePolicy, MissingAsNull, Statement, WithCachePolicy};
 #[doc(inline)]
-pub use crate::conn::{binlog_stream::BinlogStream, Conn};
+pub use crate::conn::stmt_cache::CachedStmt;
+#[doc(inline)]
+pub use crate::conn::transaction::{
+    AccessMode, IsolationLevel, Transaction, TxOpts, DEFAULT_TX_RETRY_BACKOFF,
+};
+#[doc(inline)]
+pub use crate::conn::wire_log::WireLog;
+#[doc(inline)]
+pub use crate::conn::{binlog_stream::BinlogStream, cursor::Cursor, Conn};
 #[doc(inline)]
 pub use crate::error::{DriverError, Error, MySqlError, Result, ServerError, UrlError};
+#[cfg(feature = "bigdecimal")]
+#[doc(inline)]
+pub use crate::myc::bigdecimal;
 #[doc(inline)]
 pub use crate::myc::packets::Column;
 #[doc(inline)]
 pub use crate::myc::params::Params;
 #[doc(inline)]
-pub use crate::myc::proto::codec::Compression;
-#[doc(inline)]
 pub use crate::myc::row::convert::{from_row, from_row_opt, FromRowError};
 #[doc(inline)]
 pub use crate::myc::row::Row;
+#[cfg(feature = "rust_decimal")]
+#[doc(inline)]
+pub use crate::myc::rust_decimal;
 #[doc(inline)]
 pub use crate::myc::value::convert::{from_value, from_value_opt, FromValueError};
 #[doc(inline)]
 pub use crate::myc::value::json::{Deserialized, Serialized};
 #[doc(inline)]
 pub use crate::myc::value::Value;
+#[doc(inline)]
+pub use bitvec;
+
+/// Injectable time and connect functions of [`Pool`] and result set assertions
+/// (requires the `testing` feature).
+///
+/// It's meant for downstream crates that build on top of the pool and need to test
+/// timeouts, connection reaping and connect failures deterministically
+/// (see [`Pool::new_with_hooks`]), and for integration tests, that verify results
+/// of complex queries (see [`testing::assert_result_eq`]).
+#[cfg(feature = "testing")]
+pub mod testing {
+    #[doc(inline)]
+    pub use crate::conn::pool::hooks::{
+        Clock, Connector, DefaultConnector, ManualClock, PoolHooks, SystemClock,
+    };
+    #[doc(inline)]
+    pub use crate::result_diff::{assert_result_eq, result_diff, ResultTable};
+}
 
 pub mod prelude {
+    #[doc(inline)]
+    pub use crate::column::ColumnMetadata;
+    #[doc(inline)]
+    pub use crate::column_types::MysqlEnum;
     #[doc(inline)]
     pub use crate::conn::query::{BatchQuery, BinQuery, TextQuery, WithParams};
     #[doc(inline)]
     pub use crate::conn::queryable::{AsStatement, Queryable};
     #[doc(inline)]
-    pub use crate::myc::prelude::FromRow;
-    #[doc(inline)]
     pub use crate::myc::prelude::{FromValue, ToValue};
     #[doc(inline)]
+    pub use crate::myc::row::convert::FromRow;
+    #[doc(inline)]
     pub use crate::myc::row::ColumnIndex;
+    #[doc(inline)]
+    pub use crate::row::{DeserializeRow, JsonRow};
+    #[cfg(feature = "derive")]
+    #[doc(inline)]
+    pub use mysql_derive::{FromRow, MysqlEnum, ToParams};
 
     /// Trait for protocol markers [`crate::Binary`] and [`crate::Text`].
     pub trait Protocol: crate::conn::query_result::Protocol {}
@@ -1070,3 +1445,163 @@ mod test_misc {
 
     def_get_opts!();
 }
+
+#[cfg(all(test, feature = "derive"))]
+mod derive_test {
+    use mysql_common::{constants::ColumnType, row::new_row};
+
+    use crate::{from_row_opt, prelude::*, Column, Params, Value};
+
+    #[derive(Debug, PartialEq, FromRow)]
+    #[mysql(rename_all = "camelCase", table_name = "payments")]
+    struct Payment {
+        payment_id: u32,
+        #[mysql(rename = "customer")]
+        customer_id: Option<u32>,
+        #[mysql(json)]
+        tags: Vec<String>,
+        #[mysql(default)]
+        note: String,
+        #[mysql(skip)]
+        cached: Option<u64>,
+    }
+
+    fn row(columns: &[&str], values: Vec<Value>) -> crate::Row {
+        let columns = columns
+            .iter()
+            .map(|name| Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_name(name.as_bytes()))
+            .collect::<Vec<_>>();
+        new_row(values, columns.into())
+    }
+
+    #[test]
+    fn should_derive_from_row() {
+        assert_eq!(Payment::TABLE_NAME, "payments");
+        assert_eq!(Payment::PAYMENT_ID_FIELD, "paymentId");
+        assert_eq!(Payment::CUSTOMER_ID_FIELD, "customer");
+
+        let payment = from_row_opt::<Payment>(row(
+            &["tags", "extra", "customer", "paymentId", "note"],
+            vec![
+                Value::from(r#"["a"]"#),
+                Value::from("ignored"),
+                Value::NULL,
+                Value::Int(1),
+                Value::from("foo"),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(
+            payment,
+            Payment {
+                payment_id: 1,
+                customer_id: None,
+                tags: vec!["a".into()],
+                note: "foo".into(),
+                cached: None,
+            }
+        );
+
+        // missing column with a default
+        let payment: Payment = from_row_opt(row(
+            &["paymentId", "customer", "tags"],
+            vec![Value::Int(2), Value::Int(3), Value::from("[]")],
+        ))
+        .unwrap();
+        assert_eq!(payment.note, "");
+        assert_eq!(payment.customer_id, Some(3));
+    }
+
+    #[test]
+    fn should_keep_row_intact_if_derived_conversion_fails() {
+        let values = vec![Value::UInt(1), Value::UInt(2), Value::from("not json")];
+        let err = from_row_opt::<Payment>(row(&["paymentId", "customer", "tags"], values.clone()))
+            .unwrap_err();
+        assert_eq!(err.0.unwrap(), values);
+
+        // missing column without a default
+        let values = vec![Value::UInt(1), Value::from("[]")];
+        let err = from_row_opt::<Payment>(row(&["paymentId", "tags"], values.clone())).unwrap_err();
+        assert_eq!(err.0.unwrap(), values);
+    }
+
+    #[derive(Debug, PartialEq, FromRow)]
+    #[mysql(default)]
+    struct Settings {
+        id: u32,
+        theme: Option<String>,
+        #[mysql(default = "default_page_size")]
+        page_size: u32,
+    }
+
+    fn default_page_size() -> u32 {
+        20
+    }
+
+    #[test]
+    fn should_default_missing_columns() {
+        let settings: Settings = from_row_opt(row(&["theme"], vec![Value::from("dark")])).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                id: 0,
+                theme: Some("dark".into()),
+                page_size: 20,
+            }
+        );
+
+        let settings: Settings = from_row_opt(row(
+            &["page_size", "id"],
+            vec![Value::Int(50), Value::Int(1)],
+        ))
+        .unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                id: 1,
+                theme: None,
+                page_size: 50,
+            }
+        );
+
+        // present columns still have to be convertible
+        let values = vec![Value::from("dark"), Value::from("many")];
+        let err =
+            from_row_opt::<Settings>(row(&["theme", "page_size"], values.clone())).unwrap_err();
+        assert_eq!(err.0.unwrap(), values);
+    }
+
+    #[derive(ToParams)]
+    #[mysql(rename_all = "camelCase")]
+    struct NewPayment<'a> {
+        payment_id: u32,
+        #[mysql(rename = "customer")]
+        customer_id: Option<u32>,
+        #[mysql(json)]
+        tags: Vec<&'a str>,
+        #[mysql(skip)]
+        #[allow(dead_code)]
+        cached: Option<u64>,
+    }
+
+    #[test]
+    fn should_derive_to_params() {
+        let payment = NewPayment {
+            payment_id: 1,
+            customer_id: None,
+            tags: vec!["a"],
+            cached: Some(2),
+        };
+        let expected = Params::Named(
+            vec![
+                (b"paymentId".to_vec(), Value::UInt(1)),
+                (b"customer".to_vec(), Value::NULL),
+                (b"tags".to_vec(), Value::from(r#"["a"]"#)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(Params::from(&payment), expected);
+        assert_eq!(Params::from(payment), expected);
+    }
+}
@@ -0,0 +1,439 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt::Write as _;
+
+use crate::{consts::ColumnType, CollectedSet, Params, Row, Value};
+
+/// Result set compared by [`assert_result_eq`].
+///
+/// It's either built from rows read from the server (`Vec<Row>`, `&[Row]` or a [`CollectedSet`])
+/// or written as a table literal:
+///
+/// ```rust
+/// # use mysql::testing::ResultTable;
+/// let expected = ResultTable::new(["id", "name"]).with_rows(vec![(1, "foo"), (2, "bar")]);
+/// assert_eq!(expected.rows().len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultTable {
+    /// `None` if unknown (i.e. built from an empty list of rows).
+    columns: Option<Vec<(String, Option<ColumnType>)>>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl ResultTable {
+    /// Creates an empty table literal with the given columns (of unknown types).
+    pub fn new<I>(columns: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            columns: Some(
+                columns
+                    .into_iter()
+                    .map(|name| (name.into(), None))
+                    .collect(),
+            ),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Appends the given rows.
+    ///
+    /// Rows are either positional parameters in the order of columns
+    /// or named parameters keyed by column names (missing ones are `NULL`).
+    pub fn with_rows<I>(mut self, rows: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Params>,
+    {
+        for row in rows {
+            let values = match row.into() {
+                Params::Empty => Vec::new(),
+                Params::Positional(values) => values,
+                Params::Named(mut map) => self
+                    .columns
+                    .iter()
+                    .flatten()
+                    .map(|(name, _)| map.remove(name.as_bytes()).unwrap_or(Value::NULL))
+                    .collect(),
+            };
+            self.rows.push(values);
+        }
+        self
+    }
+
+    /// Returns column names (`None` if unknown).
+    pub fn columns(&self) -> Option<Vec<&str>> {
+        self.columns
+            .as_ref()
+            .map(|columns| columns.iter().map(|(name, _)| &**name).collect())
+    }
+
+    /// Returns rows.
+    pub fn rows(&self) -> &[Vec<Value>] {
+        &self.rows
+    }
+
+    fn column_name(&self, index: usize) -> Option<&str> {
+        self.columns
+            .as_ref()
+            .and_then(|columns| columns.get(index))
+            .map(|(name, _)| &**name)
+    }
+}
+
+impl From<&[Row]> for ResultTable {
+    fn from(rows: &[Row]) -> Self {
+        Self {
+            columns: rows.first().map(|row| {
+                row.columns_ref()
+                    .iter()
+                    .map(|column| (column.name_str().into_owned(), Some(column.column_type())))
+                    .collect()
+            }),
+            rows: rows
+                .iter()
+                .map(|row| {
+                    (0..row.len())
+                        .map(|index| row.as_ref(index).cloned().unwrap_or(Value::NULL))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<Vec<Row>> for ResultTable {
+    fn from(rows: Vec<Row>) -> Self {
+        Self::from(&*rows)
+    }
+}
+
+impl From<&CollectedSet> for ResultTable {
+    fn from(set: &CollectedSet) -> Self {
+        let mut table = Self::from(set.rows());
+        table.columns = Some(
+            set.columns()
+                .iter()
+                .map(|column| (column.name_str().into_owned(), Some(column.column_type())))
+                .collect(),
+        );
+        table
+    }
+}
+
+impl From<CollectedSet> for ResultTable {
+    fn from(set: CollectedSet) -> Self {
+        Self::from(&set)
+    }
+}
+
+/// Compares two result sets and returns a readable description of their differences
+/// (`None` if they are equal).
+///
+/// Columns are compared by names and by types (unless a type is unknown, as in a table
+/// literal). Values are compared regardless of the protocol, i.e. a value read using the text
+/// protocol (bytes) is equal to the same value read using the binary protocol or written
+/// in a table literal (e.g. `'42'` is equal to `42`).
+pub fn result_diff<L, R>(left: L, right: R) -> Option<String>
+where
+    L: Into<ResultTable>,
+    R: Into<ResultTable>,
+{
+    let (left, right) = (left.into(), right.into());
+    let mut out = String::new();
+
+    if let (Some(left_columns), Some(right_columns)) = (&left.columns, &right.columns) {
+        if left_columns.len() != right_columns.len() {
+            writeln!(
+                out,
+                "column count: {} != {} (columns: {} != {})",
+                left_columns.len(),
+                right_columns.len(),
+                column_list(left_columns),
+                column_list(right_columns)
+            )
+            .expect("infallible");
+        } else {
+            for (index, (left_column, right_column)) in
+                left_columns.iter().zip(right_columns).enumerate()
+            {
+                if left_column.0 != right_column.0 {
+                    writeln!(
+                        out,
+                        "column {}: `{}` != `{}`",
+                        index, left_column.0, right_column.0
+                    )
+                    .expect("infallible");
+                }
+                if let (Some(left_type), Some(right_type)) = (left_column.1, right_column.1) {
+                    if left_type != right_type {
+                        writeln!(
+                            out,
+                            "column {} (`{}`): type {:?} != {:?}",
+                            index, left_column.0, left_type, right_type
+                        )
+                        .expect("infallible");
+                    }
+                }
+            }
+        }
+    }
+
+    if left.rows.len() != right.rows.len() {
+        writeln!(
+            out,
+            "row count: {} != {}",
+            left.rows.len(),
+            right.rows.len()
+        )
+        .expect("infallible");
+    }
+    for (index, (left_row, right_row)) in left.rows.iter().zip(&right.rows).enumerate() {
+        if left_row.len() != right_row.len() {
+            writeln!(
+                out,
+                "row {}: {} != {}",
+                index,
+                row_literal(left_row),
+                row_literal(right_row)
+            )
+            .expect("infallible");
+            continue;
+        }
+        for (column, (left_value, right_value)) in left_row.iter().zip(right_row).enumerate() {
+            if !values_eq(left_value, right_value) {
+                write!(out, "row {}, column {}", index, column).expect("infallible");
+                if let Some(name) = left.column_name(column).or(right.column_name(column)) {
+                    write!(out, " (`{}`)", name).expect("infallible");
+                }
+                let (left_sql, right_sql) = (left_value.as_sql(false), right_value.as_sql(false));
+                if left_sql == right_sql {
+                    // e.g. `1` and `1.0`
+                    writeln!(out, ": {:?} != {:?}", left_value, right_value)
+                } else {
+                    writeln!(out, ": {} != {}", left_sql, right_sql)
+                }
+                .expect("infallible");
+            }
+        }
+    }
+    for (index, row) in left.rows.iter().enumerate().skip(right.rows.len()) {
+        writeln!(out, "row {}: only on the left: {}", index, row_literal(row)).expect("infallible");
+    }
+    for (index, row) in right.rows.iter().enumerate().skip(left.rows.len()) {
+        writeln!(
+            out,
+            "row {}: only on the right: {}",
+            index,
+            row_literal(row)
+        )
+        .expect("infallible");
+    }
+
+    (!out.is_empty()).then_some(out)
+}
+
+/// Asserts that two result sets are equal (see [`result_diff`]).
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// # use mysql::*;
+/// # use mysql::prelude::*;
+/// use mysql::testing::{assert_result_eq, ResultTable};
+/// # let mut conn = Conn::new(get_opts())?;
+///
+/// let rows: Vec<Row> = conn.query("SELECT 1 AS id, 'foo' AS name UNION SELECT 2, 'bar'")?;
+/// assert_result_eq(
+///     rows,
+///     ResultTable::new(["id", "name"]).with_rows(vec![(1, "foo"), (2, "bar")]),
+/// );
+/// # });
+/// ```
+///
+/// # Panics
+///
+/// Panics with the description of differences if result sets are not equal, e.g.:
+///
+/// ```text
+/// result sets are not equal (left != right):
+/// column 1 (`name`): type MYSQL_TYPE_VAR_STRING != MYSQL_TYPE_BLOB
+/// row count: 2 != 3
+/// row 1, column 1 (`name`): 'bar' != 'baz'
+/// row 2: only on the right: (3, 'qux')
+/// ```
+#[track_caller]
+pub fn assert_result_eq<L, R>(left: L, right: R)
+where
+    L: Into<ResultTable>,
+    R: Into<ResultTable>,
+{
+    if let Some(diff) = result_diff(left, right) {
+        panic!("result sets are not equal (left != right):\n{}", diff);
+    }
+}
+
+fn column_list(columns: &[(String, Option<ColumnType>)]) -> String {
+    let names = columns
+        .iter()
+        .map(|(name, _)| format!("`{}`", name))
+        .collect::<Vec<_>>();
+    format!("({})", names.join(", "))
+}
+
+fn row_literal(row: &[Value]) -> String {
+    let values = row
+        .iter()
+        .map(|value| value.as_sql(false))
+        .collect::<Vec<_>>();
+    format!("({})", values.join(", "))
+}
+
+/// Compares values regardless of the protocol they were read with.
+fn values_eq(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Int(x), Value::UInt(y)) | (Value::UInt(y), Value::Int(x)) => {
+            u64::try_from(*x).ok() == Some(*y)
+        }
+        (Value::Float(x), Value::Double(y)) | (Value::Double(y), Value::Float(x)) => {
+            *x == *y as f32
+        }
+        (Value::Bytes(_), Value::Bytes(_)) => left == right,
+        (Value::Bytes(bytes), other) | (other, Value::Bytes(bytes)) => {
+            match std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|text| parse_as(text, other))
+            {
+                Some(parsed) => values_eq(&parsed, other),
+                None => false,
+            }
+        }
+        _ => left == right,
+    }
+}
+
+/// Parses the text representation of a value into the variant of `like`.
+fn parse_as(text: &str, like: &Value) -> Option<Value> {
+    match like {
+        Value::NULL | Value::Bytes(_) => None,
+        Value::Int(_) => text.parse().ok().map(Value::Int),
+        Value::UInt(_) => text.parse().ok().map(Value::UInt),
+        Value::Float(_) => text.parse().ok().map(Value::Float),
+        Value::Double(_) => text.parse().ok().map(Value::Double),
+        Value::Date(..) => {
+            let (date, time) = text.split_once(' ').unwrap_or((text, "00:00:00"));
+            let mut date = date.splitn(3, '-').map(str::parse::<u32>);
+            let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+            let (negative, hours, minutes, seconds, micros) = parse_time(time)?;
+            if negative || hours > 23 {
+                return None;
+            }
+            Some(Value::Date(
+                u16::try_from(year).ok()?,
+                u8::try_from(month).ok()?,
+                u8::try_from(day).ok()?,
+                hours as u8,
+                minutes,
+                seconds,
+                micros,
+            ))
+        }
+        Value::Time(..) => {
+            let (negative, hours, minutes, seconds, micros) = parse_time(text)?;
+            Some(Value::Time(
+                negative,
+                hours / 24,
+                (hours % 24) as u8,
+                minutes,
+                seconds,
+                micros,
+            ))
+        }
+    }
+}
+
+/// Parses `[-]HH:MM:SS[.ffffff]`.
+fn parse_time(text: &str) -> Option<(bool, u32, u8, u8, u32)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (hms, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let mut hms = hms.splitn(3, ':');
+    let hours = hms.next()?.parse().ok()?;
+    let minutes = hms.next()?.parse().ok()?;
+    let seconds = hms.next()?.parse().ok()?;
+    if fraction.len() > 6 || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let micros = format!("{:0<6}", fraction).parse().ok()?;
+    Some((negative, hours, minutes, seconds, micros))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{result_diff, ResultTable};
+    use crate::{params, Value};
+
+    #[test]
+    fn should_compare_values_regardless_of_protocol() {
+        let text = ResultTable::new(["a", "b", "c", "d", "e"]).with_rows(vec![vec![
+            Value::from("42"),
+            Value::from("0.5"),
+            Value::from("2020-01-02 03:04:05.5"),
+            Value::from("-27:00:01"),
+            Value::from("2020-01-02"),
+        ]]);
+        let binary = ResultTable::new(["a", "b", "c", "d", "e"]).with_rows(vec![vec![
+            Value::UInt(42),
+            Value::Float(0.5),
+            Value::Date(2020, 1, 2, 3, 4, 5, 500_000),
+            Value::Time(true, 1, 3, 0, 1, 0),
+            Value::Date(2020, 1, 2, 0, 0, 0, 0),
+        ]]);
+        assert_eq!(result_diff(text.clone(), binary.clone()), None);
+        assert_eq!(result_diff(binary, text), None);
+        assert_eq!(
+            result_diff(
+                ResultTable::new(["a"]).with_rows(vec![(Value::Int(1),)]),
+                ResultTable::new(["a"]).with_rows(vec![(Value::Double(1.0),)]),
+            )
+            .as_deref(),
+            Some("row 0, column 0 (`a`): Int(1) != Double(1.0)\n")
+        );
+    }
+
+    #[test]
+    fn should_describe_differences() {
+        let left = ResultTable::new(["id", "name"]).with_rows(vec![(1, "foo"), (2, "bar")]);
+        assert_eq!(left.columns(), Some(vec!["id", "name"]));
+        assert_eq!(left.rows()[1], [Value::Int(2), Value::from("bar")]);
+        let right = ResultTable::new(["id", "title"]).with_rows(vec![
+            params! { "id" => 1, "title" => "foo" },
+            params! { "id" => 2, "title" => "baz" },
+            params! { "id" => 3 },
+        ]);
+        assert_eq!(
+            result_diff(left.clone(), right).unwrap(),
+            "column 1: `name` != `title`\n\
+             row count: 2 != 3\n\
+             row 1, column 1 (`name`): 'bar' != 'baz'\n\
+             row 2: only on the right: (3, NULL)\n"
+        );
+        assert_eq!(
+            result_diff(left, ResultTable::new(["id"])).unwrap(),
+            "column count: 2 != 1 (columns: (`id`, `name`) != (`id`))\n\
+             row count: 2 != 0\n\
+             row 0: only on the left: (1, 'foo')\n\
+             row 1: only on the left: (2, 'bar')\n"
+        );
+    }
+}
@@ -0,0 +1,482 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Serde and JSON support for rows.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use mysql_common::constants::{ColumnFlags, ColumnType};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Number};
+
+use mysql_common::row::convert::{self, FromRow, FromRowError};
+
+use crate::{Column, DriverError, Result, Row, Value};
+
+/// Character set number of binary strings.
+const BINARY_CHARSET: u16 = 63;
+
+/// Same as [`crate::from_row`] but marked as the `mysql::convert_row` profiling scope.
+pub(crate) fn from_row<T: FromRow>(row: Row) -> T {
+    profile_scope!("mysql::convert_row");
+    convert::from_row(row)
+}
+
+/// Same as [`crate::from_row_opt`] but marked as the `mysql::convert_row` profiling scope.
+pub(crate) fn from_row_opt<T: FromRow>(row: Row) -> std::result::Result<T, FromRowError> {
+    profile_scope!("mysql::convert_row");
+    convert::from_row_opt(row)
+}
+
+/// Deserializes rows into serde types (see [`crate::prelude::Queryable::query_as`]).
+pub trait DeserializeRow {
+    /// Deserializes the row into `T`, where columns are mapped to fields by name.
+    ///
+    /// The row is deserialized as a map of column names to values, where:
+    ///
+    /// *   integer and floating-point columns are numbers (`inf` and `nan` are `null`);
+    /// *   `DECIMAL`, date and time columns are strings (so they could be parsed losslessly);
+    /// *   `JSON` columns are JSON documents, i.e. they could be deserialized into nested types;
+    /// *   binary strings are sequences of bytes (e.g. `Vec<u8>`), other strings are strings;
+    /// *   `NULL` is `null` (e.g. `Option::None`).
+    ///
+    /// Columns missing from the result set are missing from the map, so `#[serde(default)]`
+    /// (on a field or on the whole struct) could be used to tolerate schema changes.
+    ///
+    /// ```
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # use serde_derive::Deserialize;
+    /// #[derive(Debug, PartialEq, Deserialize)]
+    /// struct Tag {
+    ///     name: String,
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq, Deserialize)]
+    /// struct Post {
+    ///     id: u64,
+    ///     tags: Vec<Tag>,
+    /// }
+    ///
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let row: Row = conn
+    ///     .query_first(r#"SELECT 1 AS id, CAST('[{"name": "rust"}]' AS JSON) AS tags"#)?
+    ///     .unwrap();
+    /// let post: Post = row.deserialize()?;
+    /// assert_eq!(post.tags, [Tag { name: "rust".into() }]);
+    /// # });
+    /// ```
+    fn deserialize<T: DeserializeOwned>(&self) -> Result<T>;
+}
+
+/// Converts rows to JSON objects, e.g. for tools that don't know the schema at compile time.
+pub trait JsonRow {
+    /// Converts the row to a JSON object keyed by column names, where:
+    ///
+    /// *   integer and floating-point columns are numbers (`inf` and `nan` are `null`);
+    /// *   `DECIMAL` columns are strings (so they could be parsed losslessly);
+    /// *   `DATE`, `DATETIME` and `TIMESTAMP` columns are ISO 8601 strings, e.g. `2020-01-02`
+    ///     and `2020-01-02T03:04:05.000006` (`TIME` columns are `[-]HH:MM:SS[.ffffff]`,
+    ///     where hours may exceed `23`);
+    /// *   `JSON` columns are JSON documents;
+    /// *   binary strings are base64-encoded strings, other strings are strings;
+    /// *   `NULL` is `null`.
+    ///
+    /// Values that were taken out of the row are omitted, and the last column wins
+    /// if columns have the same name.
+    ///
+    /// ```
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let row: Row = conn
+    ///     .query_first("SELECT 1 AS id, x'00ff' AS hash, DATE('2020-01-02') AS day")?
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     serde_json::Value::Object(row.as_json()),
+    ///     serde_json::json!({ "id": 1, "hash": "AP8=", "day": "2020-01-02" }),
+    /// );
+    /// # });
+    /// ```
+    fn as_json(&self) -> Map<String, serde_json::Value>;
+}
+
+impl JsonRow for Row {
+    fn as_json(&self) -> Map<String, serde_json::Value> {
+        self.columns_ref()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, column)| {
+                let value = self.as_ref(i)?;
+                Some((
+                    column.name_str().into_owned(),
+                    to_json_document(value, column),
+                ))
+            })
+            .collect()
+    }
+}
+
+impl DeserializeRow for Row {
+    fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        let columns = self.columns_ref();
+        let mut map = Map::with_capacity(columns.len());
+        for (i, column) in columns.iter().enumerate() {
+            let value = match self.as_ref(i) {
+                Some(value) => to_json(value, column),
+                // the value was taken out of the row
+                None => continue,
+            };
+            map.insert(column.name_str().into_owned(), value);
+        }
+        serde_json::from_value(serde_json::Value::Object(map))
+            .map_err(|err| DriverError::CouldNotDeserializeRow(err.to_string()).into())
+    }
+}
+
+/// Converts a value of the given column to JSON (see [`DeserializeRow::deserialize`]).
+fn to_json(value: &Value, column: &Column) -> serde_json::Value {
+    let bytes = match value {
+        Value::Bytes(bytes) => bytes,
+        // binary protocol dates and times are represented as they are in the text protocol
+        Value::Date(..) | Value::Time(..) => {
+            let sql = value.as_sql(false);
+            return sql.trim_matches('\'').into();
+        }
+        other => return scalar_to_json(other),
+    };
+    if let Some(value) = typed_bytes_to_json(bytes, column) {
+        value
+    } else if is_binary(column) {
+        bytes.clone().into()
+    } else {
+        String::from_utf8_lossy(bytes).into()
+    }
+}
+
+/// Converts a value of the given column to JSON (see [`JsonRow::as_json`]).
+fn to_json_document(value: &Value, column: &Column) -> serde_json::Value {
+    let bytes = match value {
+        Value::Bytes(bytes) => bytes,
+        Value::Date(year, month, day, hour, minute, second, micros) => {
+            let date = format!("{:04}-{:02}-{:02}", year, month, day);
+            return if column.column_type() == ColumnType::MYSQL_TYPE_DATE {
+                date.into()
+            } else {
+                let time = iso_time(u32::from(*hour), *minute, *second, *micros);
+                format!("{}T{}", date, time).into()
+            };
+        }
+        Value::Time(negative, days, hours, minutes, seconds, micros) => {
+            let hours = days * 24 + u32::from(*hours);
+            let sign = if *negative { "-" } else { "" };
+            return format!("{}{}", sign, iso_time(hours, *minutes, *seconds, *micros)).into();
+        }
+        other => return scalar_to_json(other),
+    };
+    if let Some(value) = typed_bytes_to_json(bytes, column) {
+        return value;
+    }
+    match column.column_type() {
+        ColumnType::MYSQL_TYPE_DATETIME
+        | ColumnType::MYSQL_TYPE_DATETIME2
+        | ColumnType::MYSQL_TYPE_TIMESTAMP
+        | ColumnType::MYSQL_TYPE_TIMESTAMP2 => {
+            String::from_utf8_lossy(bytes).replacen(' ', "T", 1).into()
+        }
+        _ if is_binary(column) => BASE64.encode(bytes).into(),
+        _ => String::from_utf8_lossy(bytes).into(),
+    }
+}
+
+/// Converts values that are not strings to JSON.
+fn scalar_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Int(x) => (*x).into(),
+        Value::UInt(x) => (*x).into(),
+        Value::Float(x) => float(f64::from(*x)),
+        Value::Double(x) => float(*x),
+        _ => serde_json::Value::Null,
+    }
+}
+
+/// Converts text protocol values of numeric and `JSON` columns.
+///
+/// Returns `None` if the value should be treated as a string.
+fn typed_bytes_to_json(bytes: &[u8], column: &Column) -> Option<serde_json::Value> {
+    match column.column_type() {
+        ColumnType::MYSQL_TYPE_TINY
+        | ColumnType::MYSQL_TYPE_SHORT
+        | ColumnType::MYSQL_TYPE_INT24
+        | ColumnType::MYSQL_TYPE_LONG
+        | ColumnType::MYSQL_TYPE_LONGLONG
+        | ColumnType::MYSQL_TYPE_YEAR => {
+            let text = std::str::from_utf8(bytes).ok()?;
+            let number = text
+                .parse::<i64>()
+                .map(Number::from)
+                .or_else(|_| text.parse::<u64>().map(Number::from))
+                .ok()?;
+            Some(serde_json::Value::Number(number))
+        }
+        ColumnType::MYSQL_TYPE_FLOAT | ColumnType::MYSQL_TYPE_DOUBLE => {
+            match std::str::from_utf8(bytes).map(str::parse::<f64>) {
+                Ok(Ok(x)) => Some(float(x)),
+                _ => Some(serde_json::Value::Null),
+            }
+        }
+        ColumnType::MYSQL_TYPE_JSON => serde_json::from_slice(bytes).ok(),
+        _ => None,
+    }
+}
+
+/// Returns `true` if values of the column are binary strings.
+fn is_binary(column: &Column) -> bool {
+    column.character_set() == BINARY_CHARSET
+        && !column.flags().contains(ColumnFlags::NUM_FLAG)
+        && matches!(
+            column.column_type(),
+            ColumnType::MYSQL_TYPE_BIT
+                | ColumnType::MYSQL_TYPE_STRING
+                | ColumnType::MYSQL_TYPE_VAR_STRING
+                | ColumnType::MYSQL_TYPE_VARCHAR
+                | ColumnType::MYSQL_TYPE_TINY_BLOB
+                | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+                | ColumnType::MYSQL_TYPE_LONG_BLOB
+                | ColumnType::MYSQL_TYPE_BLOB
+                | ColumnType::MYSQL_TYPE_GEOMETRY
+        )
+}
+
+/// Formats time as `HH:MM:SS[.ffffff]`.
+fn iso_time(hours: u32, minutes: u8, seconds: u8, micros: u32) -> String {
+    if micros == 0 {
+        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}:{:02}.{:06}", hours, minutes, seconds, micros)
+    }
+}
+
+/// Converts a floating-point number to JSON (`inf` and `nan` are `null`).
+fn float(x: f64) -> serde_json::Value {
+    Number::from_f64(x)
+        .map(serde_json::Value::Number)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        constants::{ColumnFlags, ColumnType},
+        row::new_row,
+    };
+
+    use super::{DeserializeRow, JsonRow};
+    use crate::{Column, DriverError, Error, Row, Value};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Meta {
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Post {
+        id: u64,
+        score: f64,
+        title: String,
+        price: String,
+        created: String,
+        hash: Vec<u8>,
+        meta: Meta,
+        note: Option<String>,
+    }
+
+    fn row(columns: Vec<(&str, Column)>, values: Vec<Value>) -> Row {
+        let columns = columns
+            .into_iter()
+            .map(|(name, column)| column.with_name(name.as_bytes()))
+            .collect::<Vec<_>>();
+        new_row(values, columns.into())
+    }
+
+    fn post_columns() -> Vec<(&'static str, Column)> {
+        let num = ColumnFlags::NUM_FLAG;
+        vec![
+            (
+                "id",
+                Column::new(ColumnType::MYSQL_TYPE_LONGLONG).with_flags(num),
+            ),
+            (
+                "score",
+                Column::new(ColumnType::MYSQL_TYPE_DOUBLE).with_flags(num),
+            ),
+            (
+                "title",
+                Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_character_set(255),
+            ),
+            (
+                "price",
+                Column::new(ColumnType::MYSQL_TYPE_NEWDECIMAL).with_flags(num),
+            ),
+            (
+                "created",
+                Column::new(ColumnType::MYSQL_TYPE_DATETIME).with_character_set(63),
+            ),
+            (
+                "hash",
+                Column::new(ColumnType::MYSQL_TYPE_BLOB).with_character_set(63),
+            ),
+            (
+                "meta",
+                Column::new(ColumnType::MYSQL_TYPE_JSON).with_character_set(63),
+            ),
+            (
+                "note",
+                Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_character_set(255),
+            ),
+        ]
+    }
+
+    #[test]
+    fn should_deserialize_text_and_binary_rows() {
+        let expected = Post {
+            id: 1,
+            score: 0.5,
+            title: "foo".into(),
+            price: "9.99".into(),
+            created: "2020-01-02 03:04:05".into(),
+            hash: vec![0, 0xff],
+            meta: Meta {
+                tags: vec!["a".into()],
+            },
+            note: None,
+        };
+
+        let text = row(
+            post_columns(),
+            vec![
+                Value::from("1"),
+                Value::from("0.5"),
+                Value::from("foo"),
+                Value::from("9.99"),
+                Value::from("2020-01-02 03:04:05"),
+                Value::Bytes(vec![0, 0xff]),
+                Value::from(r#"{"tags": ["a"]}"#),
+                Value::NULL,
+            ],
+        );
+        assert_eq!(text.deserialize::<Post>().unwrap(), expected);
+
+        let binary = row(
+            post_columns(),
+            vec![
+                Value::UInt(1),
+                Value::Double(0.5),
+                Value::from("foo"),
+                Value::from("9.99"),
+                Value::Date(2020, 1, 2, 3, 4, 5, 0),
+                Value::Bytes(vec![0, 0xff]),
+                Value::from(r#"{"tags": ["a"]}"#),
+                Value::NULL,
+            ],
+        );
+        assert_eq!(binary.deserialize::<Post>().unwrap(), expected);
+    }
+
+    #[test]
+    fn should_report_deserialization_errors() {
+        let mut values = vec![Value::from("x"); 8];
+        values[6] = Value::from(r#"{"tags": []}"#);
+        let err = row(post_columns(), values)
+            .deserialize::<Post>()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DriverError(DriverError::CouldNotDeserializeRow(_))
+        ));
+
+        let err = row(vec![], vec![]).deserialize::<Post>().unwrap_err();
+        assert!(err.to_string().contains("missing field `id`"));
+    }
+
+    #[test]
+    fn should_convert_rows_to_json() {
+        let expected = serde_json::json!({
+            "id": 1,
+            "score": 0.5,
+            "title": "foo",
+            "price": "9.99",
+            "created": "2020-01-02T03:04:05",
+            "hash": "AP8=",
+            "meta": { "tags": ["a"] },
+            "note": null,
+        });
+
+        let text = row(
+            post_columns(),
+            vec![
+                Value::from("1"),
+                Value::from("0.5"),
+                Value::from("foo"),
+                Value::from("9.99"),
+                Value::from("2020-01-02 03:04:05"),
+                Value::Bytes(vec![0, 0xff]),
+                Value::from(r#"{"tags": ["a"]}"#),
+                Value::NULL,
+            ],
+        );
+        assert_eq!(serde_json::Value::Object(text.as_json()), expected);
+
+        let mut binary = row(
+            post_columns(),
+            vec![
+                Value::Int(1),
+                Value::Float(0.5),
+                Value::from("foo"),
+                Value::from("9.99"),
+                Value::Date(2020, 1, 2, 3, 4, 5, 0),
+                Value::Bytes(vec![0, 0xff]),
+                Value::from(r#"{"tags": ["a"]}"#),
+                Value::NULL,
+            ],
+        );
+        assert_eq!(serde_json::Value::Object(binary.as_json()), expected);
+
+        // taken values are omitted
+        binary.take::<Value, _>("hash");
+        assert!(!binary.as_json().contains_key("hash"));
+
+        let date = Column::new(ColumnType::MYSQL_TYPE_DATE).with_character_set(63);
+        let time = Column::new(ColumnType::MYSQL_TYPE_TIME).with_character_set(63);
+        let double = Column::new(ColumnType::MYSQL_TYPE_DOUBLE).with_flags(ColumnFlags::NUM_FLAG);
+        let temporal = row(
+            vec![
+                ("date", date),
+                ("time", time.clone()),
+                ("short", time),
+                ("nan", double),
+            ],
+            vec![
+                Value::Date(2020, 1, 2, 0, 0, 0, 0),
+                Value::Time(true, 1, 2, 3, 4, 5),
+                Value::Time(false, 0, 2, 3, 4, 0),
+                Value::Double(f64::NAN),
+            ],
+        );
+        assert_eq!(
+            serde_json::Value::Object(temporal.as_json()),
+            serde_json::json!({
+                "date": "2020-01-02",
+                "time": "-26:03:04.000005",
+                "short": "02:03:04",
+                "nan": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,1171 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Schema introspection based on `information_schema`.
+//!
+//! See [`Conn::tables`], [`Conn::columns`], [`Conn::primary_key`], [`Conn::indexes`],
+//! [`Conn::check_constraints`] and [`Conn::partitioning`].
+
+use crate::{
+    escape::{quote_identifier, EscapeMode},
+    prelude::*,
+    Conn, Result, Row,
+};
+
+/// How a generated column is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GenerationKind {
+    /// Values are computed when rows are read (`VIRTUAL`).
+    Virtual,
+    /// Values are computed when rows are written and stored (`STORED`, `PERSISTENT` in MariaDB).
+    Stored,
+}
+
+/// Generated column definition (`GENERATED ALWAYS AS (expr)`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Generated {
+    kind: GenerationKind,
+    expression: String,
+}
+
+impl Generated {
+    /// Returns how the column is stored.
+    pub fn kind(&self) -> GenerationKind {
+        self.kind
+    }
+
+    /// Returns the expression as reported by the server.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+}
+
+/// Default value of a column.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ColumnDefault {
+    /// Literal value, e.g. `0` or `abc` for `DEFAULT 'abc'` (unquoted).
+    Literal(String),
+    /// Default expression, e.g. `CURRENT_TIMESTAMP` or `(uuid())`.
+    Expression(String),
+}
+
+/// Column of a table (see [`Conn::columns`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableColumn {
+    name: String,
+    position: u32,
+    data_type: String,
+    column_type: String,
+    nullable: bool,
+    default: Option<ColumnDefault>,
+    comment: String,
+    generated: Option<Generated>,
+}
+
+impl TableColumn {
+    /// Name of the column.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 1-based position of the column in the table.
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Data type without attributes, e.g. `varchar`.
+    pub fn data_type(&self) -> &str {
+        &self.data_type
+    }
+
+    /// Full column type, e.g. `varchar(255)` or `int unsigned`.
+    pub fn column_type(&self) -> &str {
+        &self.column_type
+    }
+
+    /// Returns values of an `ENUM` or `SET` column in the order of definition
+    /// (`None` for other columns).
+    pub fn values(&self) -> Option<Vec<String>> {
+        parse_values(&self.column_type)
+    }
+
+    /// Returns `true` if the column accepts `NULL`.
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    /// Returns the default value, if any.
+    ///
+    /// `DEFAULT NULL` is reported as `None`.
+    pub fn default(&self) -> Option<&ColumnDefault> {
+        self.default.as_ref()
+    }
+
+    /// Comment of the column (empty if not defined).
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// Returns the definition of a generated column.
+    pub fn generated(&self) -> Option<&Generated> {
+        self.generated.as_ref()
+    }
+
+    /// Returns `false` for generated columns, i.e. for columns that can't be written to.
+    pub fn is_writable(&self) -> bool {
+        self.generated.is_none()
+    }
+}
+
+/// Row of `information_schema.COLUMNS` selected by [`Conn::columns`].
+type ColumnRow = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+);
+
+/// Parses values of `enum('a','b')` or `set('a','b')` column type
+/// (quotes within values are doubled).
+fn parse_values(column_type: &str) -> Option<Vec<String>> {
+    let lower = column_type.to_ascii_lowercase();
+    let list = if lower.starts_with("enum(") {
+        &column_type[5..]
+    } else if lower.starts_with("set(") {
+        &column_type[4..]
+    } else {
+        return None;
+    };
+    let mut values = Vec::new();
+    let mut chars = list.chars().peekable();
+    loop {
+        match chars.next()? {
+            '\'' => (),
+            ')' if values.is_empty() => return Some(values),
+            _ => return None,
+        }
+        let mut value = String::new();
+        loop {
+            match chars.next()? {
+                '\'' if chars.peek() == Some(&'\'') => {
+                    chars.next();
+                    value.push('\'');
+                }
+                '\'' => break,
+                c => value.push(c),
+            }
+        }
+        values.push(value);
+        match chars.next()? {
+            ',' => (),
+            ')' => return Some(values),
+            _ => return None,
+        }
+    }
+}
+
+/// Parses the `EXTRA` column of `information_schema.COLUMNS`.
+fn generation_kind(extra: &str) -> Option<GenerationKind> {
+    let extra = extra.to_ascii_uppercase();
+    if extra.contains("VIRTUAL GENERATED") {
+        Some(GenerationKind::Virtual)
+    } else if extra.contains("STORED GENERATED") || extra.contains("PERSISTENT GENERATED") {
+        Some(GenerationKind::Stored)
+    } else {
+        None
+    }
+}
+
+/// Parses the `COLUMN_DEFAULT` column of `information_schema.COLUMNS`.
+///
+/// MySql reports literals as is and marks expressions using `DEFAULT_GENERATED` in `EXTRA`
+/// (but string literals are not quoted, so `DEFAULT 'CURRENT_TIMESTAMP'` is ambiguous),
+/// whereas MariaDb quotes string literals and reports `DEFAULT NULL` as `NULL`.
+fn parse_default(value: Option<String>, extra: &str, mariadb: bool) -> Option<ColumnDefault> {
+    let value = value?;
+    if !mariadb {
+        // servers prior to 8.0.13 only allow `CURRENT_TIMESTAMP` and don't mark it
+        let expression = extra.to_ascii_uppercase().contains("DEFAULT_GENERATED")
+            || value.to_ascii_uppercase().starts_with("CURRENT_TIMESTAMP");
+        return if expression {
+            Some(ColumnDefault::Expression(value))
+        } else {
+            Some(ColumnDefault::Literal(value))
+        };
+    }
+
+    if value == "NULL" {
+        None
+    } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        let literal = value[1..value.len() - 1].replace("''", "'");
+        Some(ColumnDefault::Literal(literal))
+    } else if value.parse::<f64>().is_ok() {
+        Some(ColumnDefault::Literal(value))
+    } else {
+        Some(ColumnDefault::Expression(value))
+    }
+}
+
+/// `CHECK` constraint of a table (see [`Conn::check_constraints`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CheckConstraint {
+    name: String,
+    clause: String,
+    enforced: bool,
+}
+
+impl CheckConstraint {
+    /// Name of the constraint.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Checked expression as reported by the server.
+    pub fn clause(&self) -> &str {
+        &self.clause
+    }
+
+    /// Returns `false` for `NOT ENFORCED` constraints (always `true` on MariaDb).
+    pub fn is_enforced(&self) -> bool {
+        self.enforced
+    }
+}
+
+/// Type of a table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TableType {
+    /// Regular table (`BASE TABLE`).
+    BaseTable,
+    /// View (`VIEW`).
+    View,
+    /// Table of a system schema, e.g. of `information_schema` (`SYSTEM VIEW`).
+    SystemView,
+    /// Other types as reported by the server, e.g. `SEQUENCE` on MariaDb.
+    Other(String),
+}
+
+impl From<String> for TableType {
+    fn from(table_type: String) -> Self {
+        match table_type.as_str() {
+            "BASE TABLE" => TableType::BaseTable,
+            "VIEW" => TableType::View,
+            "SYSTEM VIEW" => TableType::SystemView,
+            _ => TableType::Other(table_type),
+        }
+    }
+}
+
+/// Table of a database (see [`Conn::tables`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Table {
+    name: String,
+    table_type: TableType,
+    engine: Option<String>,
+    comment: String,
+}
+
+impl Table {
+    /// Name of the table.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Type of the table.
+    pub fn table_type(&self) -> &TableType {
+        &self.table_type
+    }
+
+    /// Storage engine, e.g. `InnoDB` (`None` for views).
+    pub fn engine(&self) -> Option<&str> {
+        self.engine.as_deref()
+    }
+
+    /// Comment of the table (empty if not defined).
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+}
+
+/// Key part of an index (see [`Index::columns`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IndexColumn {
+    name: Option<String>,
+    sub_part: Option<u32>,
+    descending: bool,
+}
+
+impl IndexColumn {
+    /// Name of the column (`None` for functional key parts).
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Number of indexed characters or bytes, if only a prefix of the column is indexed.
+    pub fn sub_part(&self) -> Option<u32> {
+        self.sub_part
+    }
+
+    /// Returns `true` if the column is indexed in descending order.
+    pub fn is_descending(&self) -> bool {
+        self.descending
+    }
+}
+
+/// Index of a table (see [`Conn::indexes`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Index {
+    name: String,
+    unique: bool,
+    index_type: String,
+    columns: Vec<IndexColumn>,
+}
+
+impl Index {
+    /// Name of the index (`PRIMARY` for the primary key).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `true` for the primary key.
+    pub fn is_primary(&self) -> bool {
+        self.name == "PRIMARY"
+    }
+
+    /// Returns `true` for unique indexes (including the primary key).
+    pub fn is_unique(&self) -> bool {
+        self.unique
+    }
+
+    /// Index method, e.g. `BTREE`, `HASH`, `FULLTEXT` or `SPATIAL`.
+    pub fn index_type(&self) -> &str {
+        &self.index_type
+    }
+
+    /// Key parts in the order of definition.
+    pub fn columns(&self) -> &[IndexColumn] {
+        &self.columns
+    }
+}
+
+/// Partitioning method of a table (see [`Partitioning::method`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PartitionMethod {
+    /// `RANGE`.
+    Range,
+    /// `RANGE COLUMNS`.
+    RangeColumns,
+    /// `LIST`.
+    List,
+    /// `LIST COLUMNS`.
+    ListColumns,
+    /// `HASH`.
+    Hash,
+    /// `LINEAR HASH`.
+    LinearHash,
+    /// `KEY`.
+    Key,
+    /// `LINEAR KEY`.
+    LinearKey,
+    /// Other methods as reported by the server, e.g. `SYSTEM_TIME` on MariaDb.
+    Other(String),
+}
+
+impl From<String> for PartitionMethod {
+    fn from(method: String) -> Self {
+        match method.as_str() {
+            "RANGE" => PartitionMethod::Range,
+            "RANGE COLUMNS" => PartitionMethod::RangeColumns,
+            "LIST" => PartitionMethod::List,
+            "LIST COLUMNS" => PartitionMethod::ListColumns,
+            "HASH" => PartitionMethod::Hash,
+            "LINEAR HASH" => PartitionMethod::LinearHash,
+            "KEY" => PartitionMethod::Key,
+            "LINEAR KEY" => PartitionMethod::LinearKey,
+            _ => PartitionMethod::Other(method),
+        }
+    }
+}
+
+/// Partition of a table (see [`Partitioning::partitions`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Partition {
+    name: String,
+    position: u32,
+    description: Option<String>,
+    rows: u64,
+    comment: String,
+    subpartitions: Vec<String>,
+}
+
+impl Partition {
+    /// Name of the partition.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Position of the partition (starting from 1).
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Bound of a `RANGE` partition (e.g. `2024` or `MAXVALUE`) or comma-separated values
+    /// of a `LIST` partition (`None` for other methods).
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Estimated number of rows of the partition (including its subpartitions).
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Comment of the partition (empty if not defined).
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// Names of subpartitions ordered by position (empty if the table isn't subpartitioned).
+    pub fn subpartitions(&self) -> &[String] {
+        &self.subpartitions
+    }
+
+    /// Returns the table reference that selects rows of this partition only,
+    /// e.g. `` `db`.`events` PARTITION (`p2024`) ``.
+    ///
+    /// It's meant for maintenance queries, e.g. to archive rows of a partition
+    /// before it's dropped (see [`PartitionOp`]):
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # use mysql::schema::PartitionOp;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// # conn.query_drop("DROP TABLE IF EXISTS mysql.events")?;
+    /// conn.query_drop(
+    ///     "CREATE TABLE mysql.events (id INT, year INT) PARTITION BY RANGE (year) (\
+    ///      PARTITION p2023 VALUES LESS THAN (2024), PARTITION p2024 VALUES LESS THAN (2025))",
+    /// )?;
+    /// conn.query_drop("INSERT INTO mysql.events VALUES (1, 2023), (2, 2024)")?;
+    ///
+    /// let mode = conn.escape_mode()?;
+    /// let partitioning = conn.partitioning("mysql", "events")?.unwrap();
+    /// let oldest = &partitioning.partitions()[0];
+    /// let ids: Vec<u32> = conn.query(format!(
+    ///     "SELECT id FROM {}",
+    ///     oldest.target("mysql", "events", mode)
+    /// ))?;
+    /// assert_eq!(ids, [1]);
+    /// conn.query_drop(PartitionOp::Drop.sql("mysql", "events", &[oldest.name()], mode))?;
+    /// # conn.query_drop("DROP TABLE mysql.events")?;
+    /// # });
+    /// ```
+    pub fn target(&self, db: &str, table: &str, mode: EscapeMode) -> String {
+        format!(
+            "{}.{} PARTITION ({})",
+            quote_identifier(db, mode),
+            quote_identifier(table, mode),
+            quote_identifier(&self.name, mode)
+        )
+    }
+}
+
+/// Partitioning scheme of a table (see [`Conn::partitioning`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Partitioning {
+    method: PartitionMethod,
+    expression: Option<String>,
+    subpartition_method: Option<PartitionMethod>,
+    subpartition_expression: Option<String>,
+    partitions: Vec<Partition>,
+}
+
+impl Partitioning {
+    /// Partitioning method.
+    pub fn method(&self) -> &PartitionMethod {
+        &self.method
+    }
+
+    /// Partitioning expression or the list of columns, e.g. `` year(`created_at`) ``
+    /// (`None` for `KEY` partitioning by the primary key).
+    pub fn expression(&self) -> Option<&str> {
+        self.expression.as_deref()
+    }
+
+    /// Subpartitioning method (`None` if the table isn't subpartitioned).
+    pub fn subpartition_method(&self) -> Option<&PartitionMethod> {
+        self.subpartition_method.as_ref()
+    }
+
+    /// Subpartitioning expression (`None` if the table isn't subpartitioned).
+    pub fn subpartition_expression(&self) -> Option<&str> {
+        self.subpartition_expression.as_deref()
+    }
+
+    /// Partitions ordered by position.
+    pub fn partitions(&self) -> &[Partition] {
+        &self.partitions
+    }
+}
+
+/// Maintenance operation on partitions of a table (see [`PartitionOp::sql`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PartitionOp {
+    /// `ANALYZE PARTITION`.
+    Analyze,
+    /// `CHECK PARTITION`.
+    Check,
+    /// `OPTIMIZE PARTITION`.
+    Optimize,
+    /// `REBUILD PARTITION`.
+    Rebuild,
+    /// `REPAIR PARTITION`.
+    Repair,
+    /// `TRUNCATE PARTITION` (removes rows of partitions).
+    Truncate,
+    /// `DROP PARTITION` (removes partitions along with their rows).
+    Drop,
+}
+
+impl PartitionOp {
+    /// Returns the `ALTER TABLE` statement, that performs this operation on the given
+    /// partitions, e.g. `` ALTER TABLE `db`.`events` DROP PARTITION `p2023`, `p2024` ``.
+    ///
+    /// See [`Partition::target`] for an example.
+    pub fn sql(self, db: &str, table: &str, partitions: &[&str], mode: EscapeMode) -> String {
+        let op = match self {
+            PartitionOp::Analyze => "ANALYZE",
+            PartitionOp::Check => "CHECK",
+            PartitionOp::Optimize => "OPTIMIZE",
+            PartitionOp::Rebuild => "REBUILD",
+            PartitionOp::Repair => "REPAIR",
+            PartitionOp::Truncate => "TRUNCATE",
+            PartitionOp::Drop => "DROP",
+        };
+        let partitions = partitions
+            .iter()
+            .map(|partition| quote_identifier(partition, mode))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "ALTER TABLE {}.{} {} PARTITION {}",
+            quote_identifier(db, mode),
+            quote_identifier(table, mode),
+            op,
+            partitions
+        )
+    }
+}
+
+/// Row of `information_schema.PARTITIONS` selected by [`Conn::partitioning`].
+type PartitionRow = (
+    Option<String>,
+    Option<String>,
+    Option<u32>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<u64>,
+    String,
+);
+
+/// Groups subpartitions (ordered by position) into partitions.
+///
+/// Returns `None` if the table isn't partitioned, i.e. its only row has no partition name.
+fn group_partitions<I: IntoIterator<Item = PartitionRow>>(rows: I) -> Option<Partitioning> {
+    let mut partitioning: Option<Partitioning> = None;
+    for row in rows {
+        let (name, subpartition, position, method, sub_method, expr, sub_expr, desc, rows, comment) =
+            row;
+        let (name, method) = match (name, method) {
+            (Some(name), Some(method)) => (name, method),
+            _ => return None,
+        };
+        let partitioning = partitioning.get_or_insert_with(|| Partitioning {
+            method: method.into(),
+            expression: expr,
+            subpartition_method: sub_method.map(Into::into),
+            subpartition_expression: sub_expr,
+            partitions: Vec::new(),
+        });
+        match partitioning.partitions.last_mut() {
+            Some(partition) if partition.name == name => {
+                partition.rows += rows.unwrap_or_default();
+                partition.subpartitions.extend(subpartition);
+            }
+            _ => partitioning.partitions.push(Partition {
+                name,
+                position: position.unwrap_or_default(),
+                description: desc,
+                rows: rows.unwrap_or_default(),
+                comment,
+                subpartitions: subpartition.into_iter().collect(),
+            }),
+        }
+    }
+    partitioning
+}
+
+/// Row of `information_schema.STATISTICS` selected by [`Conn::indexes`].
+type IndexRow = (
+    String,
+    u32,
+    String,
+    Option<String>,
+    Option<u32>,
+    Option<String>,
+);
+
+/// Groups key parts (ordered by index name and position) into indexes.
+fn group_indexes<I: IntoIterator<Item = IndexRow>>(rows: I) -> Vec<Index> {
+    let mut indexes: Vec<Index> = Vec::new();
+    for (name, non_unique, index_type, column, sub_part, collation) in rows {
+        let column = IndexColumn {
+            name: column,
+            sub_part,
+            descending: collation.as_deref() == Some("D"),
+        };
+        match indexes.last_mut() {
+            Some(index) if index.name == name => index.columns.push(column),
+            _ => indexes.push(Index {
+                name,
+                unique: non_unique == 0,
+                index_type,
+                columns: vec![column],
+            }),
+        }
+    }
+    indexes
+}
+
+/// Estimates the number of rows returned by the outermost query block using `EXPLAIN` output,
+/// given as `(id, rows, filtered)` tuples.
+///
+/// Tables of a query block are joined using nested loops, so the estimate is the product
+/// of the number of rows examined for each table multiplied by the percentage of rows
+/// that pass the condition (`filtered` is missing on MariaDb).
+fn explain_estimate<I>(rows: I) -> Option<u64>
+where
+    I: IntoIterator<Item = (Option<u64>, Option<u64>, Option<f64>)>,
+{
+    let mut rows = rows.into_iter().peekable();
+    let outermost = rows.peek()?.0;
+    let mut estimate = None;
+    for (id, examined, filtered) in rows {
+        if id != outermost {
+            continue;
+        }
+        if let Some(examined) = examined {
+            let filtered = filtered.unwrap_or(100.0).clamp(0.0, 100.0) / 100.0;
+            estimate = Some(estimate.unwrap_or(1.0) * examined as f64 * filtered);
+        }
+    }
+    estimate.map(|x: f64| x.round() as u64)
+}
+
+impl Conn {
+    /// Returns the estimated number of rows of the given table in the current database
+    /// (`TABLE_ROWS` of `information_schema.TABLES`), or `None` if there is no such table
+    /// or the estimate isn't available (e.g. for views).
+    ///
+    /// It doesn't scan the table, so it's cheap, but the estimate is based on statistics,
+    /// i.e. it could be far from the actual number of rows for InnoDB tables (use `ANALYZE TABLE`
+    /// to update the statistics). Note that MySql 8.0 caches statistics
+    /// (see `information_schema_stats_expiry`).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.select_db("mysql");
+    /// let estimate = conn.estimate_rows("user")?;
+    /// assert!(estimate.is_some());
+    /// # });
+    /// ```
+    pub fn estimate_rows(&mut self, table: &str) -> Result<Option<u64>> {
+        let estimate: Option<Option<u64>> = self.exec_first(
+            "SELECT TABLE_ROWS FROM information_schema.TABLES \
+             WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+            (table,),
+        )?;
+        Ok(estimate.flatten())
+    }
+
+    /// Returns the estimated number of rows returned by the given query, or `None`
+    /// if the estimate isn't available.
+    ///
+    /// The estimate is computed from the `EXPLAIN` output of the query, so the query isn't
+    /// executed. It's based on statistics and it doesn't account for `LIMIT`, `GROUP BY`
+    /// or subqueries, so it's only meant for sizing work, e.g. for pagination or batching.
+    pub fn estimate_rows_for(&mut self, query: &str) -> Result<Option<u64>> {
+        let mut explained = Vec::new();
+        for mut row in self.query::<Row, _>(format!("EXPLAIN {}", query))? {
+            let id = row.take_opt::<Option<u64>, _>("id").transpose()?.flatten();
+            let rows = row
+                .take_opt::<Option<u64>, _>("rows")
+                .transpose()?
+                .flatten();
+            let filtered = row
+                .take_opt::<Option<f64>, _>("filtered")
+                .transpose()?
+                .flatten();
+            explained.push((id, rows, filtered));
+        }
+        Ok(explain_estimate(explained))
+    }
+
+    /// Returns columns of the given table ordered by position.
+    ///
+    /// Returns an empty list if there is no such table. Note that temporary tables
+    /// are not visible in `information_schema`.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop(
+    ///     "CREATE TABLE mysql.items (price INT, qty INT, total INT AS (price * qty))",
+    /// )?;
+    /// let writable = conn
+    ///     .columns("mysql", "items")?
+    ///     .into_iter()
+    ///     .filter(|column| column.is_writable())
+    ///     .map(|column| column.name().to_owned())
+    ///     .collect::<Vec<_>>();
+    /// # conn.query_drop("DROP TABLE mysql.items")?;
+    /// assert_eq!(writable, ["price", "qty"]);
+    /// # });
+    /// ```
+    pub fn columns(&mut self, db: &str, table: &str) -> Result<Vec<TableColumn>> {
+        let mariadb = self.server_flavor().is_mariadb();
+        self.exec_map(
+            "SELECT COLUMN_NAME, ORDINAL_POSITION, DATA_TYPE, COLUMN_TYPE, IS_NULLABLE, EXTRA, \
+             GENERATION_EXPRESSION, COLUMN_DEFAULT, COLUMN_COMMENT \
+             FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? \
+             ORDER BY ORDINAL_POSITION",
+            (db, table),
+            |row: ColumnRow| {
+                let (
+                    name,
+                    position,
+                    data_type,
+                    column_type,
+                    nullable,
+                    extra,
+                    expr,
+                    default,
+                    comment,
+                ) = row;
+                TableColumn {
+                    name,
+                    position,
+                    data_type,
+                    column_type,
+                    nullable: nullable == "YES",
+                    default: parse_default(default, &extra, mariadb),
+                    comment,
+                    generated: generation_kind(&extra).map(|kind| Generated {
+                        kind,
+                        expression: expr.unwrap_or_default(),
+                    }),
+                }
+            },
+        )
+    }
+
+    /// Returns tables and views of the given database ordered by name.
+    ///
+    /// Note that temporary tables are not visible in `information_schema`.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # use mysql::schema::TableType;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let tables = conn.tables("mysql")?;
+    /// let db = tables.iter().find(|table| table.name() == "db").unwrap();
+    /// assert_eq!(db.table_type(), &TableType::BaseTable);
+    /// # });
+    /// ```
+    pub fn tables(&mut self, db: &str) -> Result<Vec<Table>> {
+        self.exec_map(
+            "SELECT TABLE_NAME, TABLE_TYPE, ENGINE, TABLE_COMMENT \
+             FROM information_schema.TABLES WHERE TABLE_SCHEMA = ? \
+             ORDER BY TABLE_NAME",
+            (db,),
+            |(name, table_type, engine, comment): (String, String, Option<String>, String)| Table {
+                name,
+                table_type: table_type.into(),
+                engine,
+                comment,
+            },
+        )
+    }
+
+    /// Returns columns of the primary key of the given table in the order of definition.
+    ///
+    /// Returns an empty list if there is no such table or it has no primary key.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TABLE mysql.pk (a INT, b INT, c INT, PRIMARY KEY (b, a))")?;
+    /// let primary_key = conn.primary_key("mysql", "pk")?;
+    /// # conn.query_drop("DROP TABLE mysql.pk")?;
+    /// assert_eq!(primary_key, ["b", "a"]);
+    /// # });
+    /// ```
+    pub fn primary_key(&mut self, db: &str, table: &str) -> Result<Vec<String>> {
+        self.exec(
+            "SELECT COLUMN_NAME FROM information_schema.STATISTICS \
+             WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? AND INDEX_NAME = 'PRIMARY' \
+             ORDER BY SEQ_IN_INDEX",
+            (db, table),
+        )
+    }
+
+    /// Returns indexes of the given table, the primary key first, then ordered by name.
+    ///
+    /// Returns an empty list if there is no such table.
+    pub fn indexes(&mut self, db: &str, table: &str) -> Result<Vec<Index>> {
+        let rows = self.exec(
+            "SELECT INDEX_NAME, NON_UNIQUE, INDEX_TYPE, COLUMN_NAME, SUB_PART, COLLATION \
+             FROM information_schema.STATISTICS WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? \
+             ORDER BY INDEX_NAME != 'PRIMARY', INDEX_NAME, SEQ_IN_INDEX",
+            (db, table),
+        )?;
+        Ok(group_indexes(rows))
+    }
+
+    /// Returns `CHECK` constraints of the given table ordered by name.
+    ///
+    /// Requires MySql 8.0.16 or MariaDb 10.2.
+    pub fn check_constraints(&mut self, db: &str, table: &str) -> Result<Vec<CheckConstraint>> {
+        let query = if self.server_flavor().is_mariadb() {
+            "SELECT CONSTRAINT_NAME, CHECK_CLAUSE, 'YES' \
+             FROM information_schema.CHECK_CONSTRAINTS \
+             WHERE CONSTRAINT_SCHEMA = ? AND TABLE_NAME = ? \
+             ORDER BY CONSTRAINT_NAME"
+        } else {
+            "SELECT cc.CONSTRAINT_NAME, cc.CHECK_CLAUSE, tc.ENFORCED \
+             FROM information_schema.TABLE_CONSTRAINTS tc \
+             JOIN information_schema.CHECK_CONSTRAINTS cc \
+             ON cc.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA \
+             AND cc.CONSTRAINT_NAME = tc.CONSTRAINT_NAME \
+             WHERE tc.TABLE_SCHEMA = ? AND tc.TABLE_NAME = ? AND tc.CONSTRAINT_TYPE = 'CHECK' \
+             ORDER BY cc.CONSTRAINT_NAME"
+        };
+        self.exec_map(
+            query,
+            (db, table),
+            |(name, clause, enforced): (String, String, String)| CheckConstraint {
+                name,
+                clause,
+                enforced: enforced == "YES",
+            },
+        )
+    }
+
+    /// Returns the partitioning scheme and partitions of the given table.
+    ///
+    /// Returns `None` if there is no such table or it isn't partitioned. See [`Partition::target`]
+    /// and [`PartitionOp`] for helpers to build maintenance queries, e.g. to rotate partitions.
+    pub fn partitioning(&mut self, db: &str, table: &str) -> Result<Option<Partitioning>> {
+        let rows = self.exec(
+            "SELECT PARTITION_NAME, SUBPARTITION_NAME, PARTITION_ORDINAL_POSITION, \
+             PARTITION_METHOD, SUBPARTITION_METHOD, PARTITION_EXPRESSION, \
+             SUBPARTITION_EXPRESSION, PARTITION_DESCRIPTION, TABLE_ROWS, PARTITION_COMMENT \
+             FROM information_schema.PARTITIONS WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? \
+             ORDER BY PARTITION_ORDINAL_POSITION, SUBPARTITION_ORDINAL_POSITION",
+            (db, table),
+        )?;
+        Ok(group_partitions(rows))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        explain_estimate, generation_kind, group_indexes, group_partitions, parse_default,
+        parse_values, ColumnDefault, GenerationKind, PartitionMethod, PartitionOp, TableType,
+    };
+    use crate::escape::EscapeMode;
+
+    #[test]
+    fn should_group_index_columns() {
+        let indexes = group_indexes(vec![
+            (
+                "PRIMARY".into(),
+                0,
+                "BTREE".into(),
+                Some("id".into()),
+                None,
+                Some("A".into()),
+            ),
+            (
+                "idx".into(),
+                1,
+                "BTREE".into(),
+                Some("a".into()),
+                Some(10),
+                Some("A".into()),
+            ),
+            (
+                "idx".into(),
+                1,
+                "BTREE".into(),
+                None,
+                None,
+                Some("D".into()),
+            ),
+            (
+                "txt".into(),
+                1,
+                "FULLTEXT".into(),
+                Some("b".into()),
+                None,
+                None,
+            ),
+        ]);
+        assert_eq!(indexes.len(), 3);
+        assert!(indexes[0].is_primary() && indexes[0].is_unique());
+        assert_eq!(indexes[1].name(), "idx");
+        assert!(!indexes[1].is_primary() && !indexes[1].is_unique());
+        assert_eq!(indexes[1].columns().len(), 2);
+        assert_eq!(indexes[1].columns()[0].name(), Some("a"));
+        assert_eq!(indexes[1].columns()[0].sub_part(), Some(10));
+        assert!(!indexes[1].columns()[0].is_descending());
+        assert_eq!(indexes[1].columns()[1].name(), None);
+        assert!(indexes[1].columns()[1].is_descending());
+        assert_eq!(indexes[2].index_type(), "FULLTEXT");
+        assert!(group_indexes(vec![]).is_empty());
+    }
+
+    #[test]
+    fn should_group_partitions() {
+        let row = |name: &str, sub: Option<&str>, position, desc: Option<&str>, rows| {
+            (
+                Some(name.to_owned()),
+                sub.map(str::to_owned),
+                Some(position),
+                Some("RANGE".to_owned()),
+                sub.map(|_| "HASH".to_owned()),
+                Some("`year`".to_owned()),
+                sub.map(|_| "`id`".to_owned()),
+                desc.map(str::to_owned),
+                Some(rows),
+                String::new(),
+            )
+        };
+
+        let partitioning = group_partitions(vec![
+            row("p2023", Some("p2023sp0"), 1, Some("2024"), 10),
+            row("p2023", Some("p2023sp1"), 1, Some("2024"), 5),
+            row("pmax", Some("pmaxsp0"), 2, Some("MAXVALUE"), 1),
+            row("pmax", Some("pmaxsp1"), 2, Some("MAXVALUE"), 0),
+        ])
+        .unwrap();
+        assert_eq!(partitioning.method(), &PartitionMethod::Range);
+        assert_eq!(partitioning.expression(), Some("`year`"));
+        assert_eq!(
+            partitioning.subpartition_method(),
+            Some(&PartitionMethod::Hash)
+        );
+        assert_eq!(partitioning.subpartition_expression(), Some("`id`"));
+        let partitions = partitioning.partitions();
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(partitions[0].name(), "p2023");
+        assert_eq!(partitions[0].rows(), 15);
+        assert_eq!(partitions[0].subpartitions(), ["p2023sp0", "p2023sp1"]);
+        assert_eq!(partitions[1].position(), 2);
+        assert_eq!(partitions[1].description(), Some("MAXVALUE"));
+
+        let partitioning = group_partitions(vec![row("p0", None, 1, None, 3)]).unwrap();
+        assert_eq!(partitioning.subpartition_method(), None);
+        assert!(partitioning.partitions()[0].subpartitions().is_empty());
+
+        // not partitioned
+        let plain = (
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(3),
+            String::new(),
+        );
+        assert_eq!(group_partitions(vec![plain]), None);
+        assert_eq!(group_partitions(vec![]), None);
+    }
+
+    #[test]
+    fn should_build_partition_sql() {
+        let partitioning = group_partitions(vec![(
+            Some("p`0".into()),
+            None,
+            Some(1),
+            Some("LINEAR KEY".into()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            String::new(),
+        )])
+        .unwrap();
+        assert_eq!(partitioning.method(), &PartitionMethod::LinearKey);
+        assert_eq!(
+            partitioning.partitions()[0].target("db", "events", EscapeMode::new()),
+            "`db`.`events` PARTITION (`p``0`)"
+        );
+        assert_eq!(
+            PartitionOp::Drop.sql("db", "events", &["p0", "p1"], EscapeMode::new()),
+            "ALTER TABLE `db`.`events` DROP PARTITION `p0`, `p1`"
+        );
+        let ansi = EscapeMode::new().with_ansi_quotes(true);
+        assert_eq!(
+            PartitionOp::Truncate.sql("db", "events", &["p0"], ansi),
+            r#"ALTER TABLE "db"."events" TRUNCATE PARTITION "p0""#
+        );
+        assert_eq!(
+            PartitionMethod::from("SYSTEM_TIME".to_owned()),
+            PartitionMethod::Other("SYSTEM_TIME".into())
+        );
+    }
+
+    #[test]
+    fn should_parse_table_types() {
+        assert_eq!(
+            TableType::from("BASE TABLE".to_owned()),
+            TableType::BaseTable
+        );
+        assert_eq!(TableType::from("VIEW".to_owned()), TableType::View);
+        assert_eq!(
+            TableType::from("SEQUENCE".to_owned()),
+            TableType::Other("SEQUENCE".into())
+        );
+    }
+
+    #[test]
+    fn should_parse_generation_kind() {
+        assert_eq!(
+            generation_kind("VIRTUAL GENERATED"),
+            Some(GenerationKind::Virtual)
+        );
+        assert_eq!(
+            generation_kind("STORED GENERATED"),
+            Some(GenerationKind::Stored)
+        );
+        assert_eq!(
+            generation_kind("PERSISTENT GENERATED"),
+            Some(GenerationKind::Stored)
+        );
+        assert_eq!(generation_kind("DEFAULT_GENERATED"), None);
+        assert_eq!(generation_kind("auto_increment"), None);
+        assert_eq!(generation_kind(""), None);
+    }
+
+    #[test]
+    fn should_parse_enum_values() {
+        assert_eq!(
+            parse_values("enum('a','it''s','x,y')"),
+            Some(vec!["a".into(), "it's".into(), "x,y".into()])
+        );
+        assert_eq!(
+            parse_values("SET('read','write')"),
+            Some(vec!["read".into(), "write".into()])
+        );
+        assert_eq!(parse_values("set('')"), Some(vec!["".into()]));
+        assert_eq!(parse_values("varchar(10)"), None);
+        assert_eq!(parse_values("enum('a'"), None);
+    }
+
+    #[test]
+    fn should_parse_column_defaults() {
+        let literal = |x: &str| Some(ColumnDefault::Literal(x.into()));
+        let expression = |x: &str| Some(ColumnDefault::Expression(x.into()));
+
+        assert_eq!(parse_default(None, "", false), None);
+        assert_eq!(
+            parse_default(Some("NULL".into()), "", false),
+            literal("NULL")
+        );
+        assert_eq!(parse_default(Some("0".into()), "", false), literal("0"));
+        assert_eq!(
+            parse_default(Some("CURRENT_TIMESTAMP(3)".into()), "", false),
+            expression("CURRENT_TIMESTAMP(3)")
+        );
+        assert_eq!(
+            parse_default(Some("CURRENT_TIMESTAMP".into()), "DEFAULT_GENERATED", false),
+            expression("CURRENT_TIMESTAMP")
+        );
+
+        assert_eq!(parse_default(None, "", true), None);
+        assert_eq!(parse_default(Some("NULL".into()), "", true), None);
+        assert_eq!(
+            parse_default(Some("'NULL'".into()), "", true),
+            literal("NULL")
+        );
+        assert_eq!(
+            parse_default(Some("'it''s'".into()), "", true),
+            literal("it's")
+        );
+        assert_eq!(
+            parse_default(Some("-1.5".into()), "", true),
+            literal("-1.5")
+        );
+        assert_eq!(
+            parse_default(Some("current_timestamp()".into()), "", true),
+            expression("current_timestamp()")
+        );
+    }
+
+    #[test]
+    fn should_estimate_rows_from_explain_output() {
+        // single table
+        assert_eq!(
+            explain_estimate(vec![(Some(1), Some(1000), Some(10.0))]),
+            Some(100)
+        );
+        // join
+        assert_eq!(
+            explain_estimate(vec![
+                (Some(1), Some(100), Some(50.0)),
+                (Some(1), Some(4), Some(100.0)),
+            ]),
+            Some(200)
+        );
+        // subqueries and union results are not accounted for, `filtered` is missing on MariaDb
+        assert_eq!(
+            explain_estimate(vec![
+                (Some(1), Some(30), None),
+                (Some(2), Some(1000), Some(1.0)),
+                (None, None, Some(100.0)),
+            ]),
+            Some(30)
+        );
+        // no tables used
+        assert_eq!(explain_estimate(vec![(Some(1), None, None)]), None);
+        assert_eq!(explain_estimate(vec![]), None);
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Spatial values.
+
+use mysql_common::value::convert::{FromValue, FromValueError};
+
+use std::{convert::TryFrom, fmt};
+
+use crate::{DriverError, Result, Value};
+
+/// Spatial value with its SRID (spatial reference system identifier).
+///
+/// MySql stores geometries using the internal format, i.e. 4-byte little-endian SRID followed
+/// by the [WKB][1] representation of a geometry. This type gives access to both parts and
+/// converts to and from such values, so it could be used to read spatial columns
+/// and to bind spatial parameters.
+///
+/// SRID `0` means that the SRID is not specified (the default SRID of a Cartesian plane).
+///
+/// ```
+/// # use mysql::spatial::Geometry;
+/// # use mysql::{from_value, Value};
+/// // POINT(1 2) with SRID 4326
+/// let wkb = [
+///     1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0, 0, 0, 0, 0, 0, 64,
+/// ];
+/// let point = Geometry::from_wkb(wkb.to_vec()).unwrap().with_default_srid(4326);
+/// assert_eq!(point.srid(), 4326);
+///
+/// let value = Value::from(point.clone());
+/// assert_eq!(from_value::<Geometry>(value), point);
+/// ```
+///
+/// [1]: https://dev.mysql.com/doc/refman/8.0/en/gis-data-formats.html#gis-wkb-format
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Geometry {
+    srid: u32,
+    wkb: Vec<u8>,
+}
+
+impl Geometry {
+    /// Creates a new geometry with the given SRID.
+    ///
+    /// Returns an error if `wkb` is not a valid WKB representation of a geometry
+    /// or if `srid` isn't a 32-bit unsigned integer (e.g. a negative SRID),
+    /// i.e. values are validated in the same way as when they are read.
+    pub fn new<S>(srid: S, wkb: Vec<u8>) -> Result<Self>
+    where
+        S: TryInto<u32> + fmt::Display + Copy,
+    {
+        let srid = match srid.try_into() {
+            Ok(srid) => srid,
+            Err(_) => return Err(DriverError::InvalidSrid(srid.to_string()).into()),
+        };
+        if !is_valid_wkb(&wkb) {
+            return Err(DriverError::InvalidGeometry.into());
+        }
+        Ok(Self { srid, wkb })
+    }
+
+    /// Creates a new geometry with unspecified SRID (i.e. `0`).
+    ///
+    /// Returns an error if `wkb` is not a valid WKB representation of a geometry.
+    pub fn from_wkb(wkb: Vec<u8>) -> Result<Self> {
+        Self::new(0, wkb)
+    }
+
+    /// Returns the SRID of this geometry.
+    pub fn srid(&self) -> u32 {
+        self.srid
+    }
+
+    /// Returns the WKB representation of this geometry.
+    pub fn wkb(&self) -> &[u8] {
+        &self.wkb
+    }
+
+    /// Returns the WKB representation of this geometry.
+    pub fn into_wkb(self) -> Vec<u8> {
+        self.wkb
+    }
+
+    /// Overrides the SRID of this geometry.
+    pub fn with_srid(mut self, srid: u32) -> Self {
+        self.srid = srid;
+        self
+    }
+
+    /// Sets the SRID of this geometry, if it is not specified (i.e. `0`).
+    pub fn with_default_srid(mut self, srid: u32) -> Self {
+        if self.srid == 0 {
+            self.srid = srid;
+        }
+        self
+    }
+
+    /// Makes sure that this geometry has the expected SRID.
+    ///
+    /// MySql 8 will refuse to store a geometry into a column with mismatching SRID restriction,
+    /// so this is a way to catch the mismatch before sending the value to the server.
+    pub fn check_srid(&self, expected: u32) -> Result<()> {
+        if self.srid != expected {
+            return Err(DriverError::SridMismatch {
+                expected,
+                actual: self.srid,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Checks the WKB header (byte order and geometry type).
+fn is_valid_wkb(wkb: &[u8]) -> bool {
+    let geometry_type = match wkb {
+        [0, a, b, c, d, ..] => u32::from_be_bytes([*a, *b, *c, *d]),
+        [1, a, b, c, d, ..] => u32::from_le_bytes([*a, *b, *c, *d]),
+        _ => return false,
+    };
+    // Point, LineString, Polygon, MultiPoint, MultiLineString, MultiPolygon, GeometryCollection
+    (1..=7).contains(&geometry_type)
+}
+
+impl TryFrom<Value> for Geometry {
+    type Error = FromValueError;
+
+    fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+        match value {
+            Value::Bytes(bytes) if bytes.len() > 4 && is_valid_wkb(&bytes[4..]) => {
+                let srid = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                Ok(Geometry {
+                    srid,
+                    wkb: bytes[4..].to_vec(),
+                })
+            }
+            value => Err(FromValueError(value)),
+        }
+    }
+}
+
+impl FromValue for Geometry {
+    type Intermediate = Geometry;
+}
+
+impl From<Geometry> for Value {
+    fn from(geometry: Geometry) -> Value {
+        let mut bytes = Vec::with_capacity(geometry.wkb.len() + 4);
+        bytes.extend_from_slice(&geometry.srid.to_le_bytes());
+        bytes.extend_from_slice(&geometry.wkb);
+        Value::Bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use super::Geometry;
+    use crate::{from_value_opt, DriverError, Error, Value};
+
+    const POINT: &[u8] = &[
+        1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0, 0, 0, 0, 0, 0, 64,
+    ];
+
+    #[test]
+    fn should_convert_geometry_to_and_from_value() {
+        let mut bytes = 4326_u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(POINT);
+
+        let geometry: Geometry = from_value_opt(Value::Bytes(bytes.clone())).unwrap();
+        assert_eq!(geometry.srid(), 4326);
+        assert_eq!(geometry.wkb(), POINT);
+        assert_eq!(Value::from(geometry), Value::Bytes(bytes));
+
+        assert!(from_value_opt::<Geometry>(Value::Bytes(POINT[..4].to_vec())).is_err());
+        assert!(from_value_opt::<Geometry>(Value::Int(1)).is_err());
+    }
+
+    #[test]
+    fn should_validate_geometry() {
+        assert!(Geometry::from_wkb(vec![2, 1, 0, 0, 0]).is_err());
+        assert!(Geometry::from_wkb(vec![1, 8, 0, 0, 0]).is_err());
+
+        let geometry = Geometry::from_wkb(POINT.to_vec()).unwrap();
+        assert_eq!(geometry.srid(), 0);
+        assert!(geometry.check_srid(4326).is_err());
+
+        let geometry = geometry.with_default_srid(4326);
+        assert!(geometry.check_srid(4326).is_ok());
+        assert_eq!(geometry.with_default_srid(3857).srid(), 4326);
+
+        // SRID must fit into the 4-byte header of the value
+        let geometry = Geometry::new(u32::MAX, POINT.to_vec()).unwrap();
+        assert_eq!(
+            Geometry::try_from(Value::from(geometry.clone())),
+            Ok(geometry)
+        );
+        for err in [
+            Geometry::new(-1, POINT.to_vec()).unwrap_err(),
+            Geometry::new(u64::from(u32::MAX) + 1, POINT.to_vec()).unwrap_err(),
+        ] {
+            assert!(matches!(
+                err,
+                Error::DriverError(DriverError::InvalidSrid(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn should_read_and_write_spatial_values() {
+        use crate::{prelude::*, test_misc::get_opts, Conn};
+
+        let mut conn = Conn::new(get_opts()).unwrap();
+        let geometry: Geometry = conn
+            .query_first("SELECT ST_GeomFromText('POINT(1 2)', 4326)")
+            .unwrap()
+            .unwrap();
+        assert_eq!(geometry.srid(), 4326);
+
+        let srid: u32 = conn
+            .exec_first("SELECT ST_SRID(?)", (geometry.clone(),))
+            .unwrap()
+            .unwrap();
+        assert_eq!(srid, 4326);
+    }
+}
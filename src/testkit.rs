@@ -0,0 +1,394 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Throwaway MySql and MariaDb servers for integration tests (requires the `testkit` feature).
+//!
+//! Servers are started in docker containers using the docker socket (`DOCKER_HOST`
+//! if it's a `unix://` address, `/var/run/docker.sock` otherwise), so tests could run
+//! against several server versions:
+//!
+//! ```rust,no_run
+//! use mysql::prelude::*;
+//! use mysql::testkit::{TestServer, TestServerOpts};
+//!
+//! # fn main() -> mysql::Result<()> {
+//! for opts in [
+//!     TestServerOpts::mysql("5.7"),
+//!     TestServerOpts::mysql("8.0"),
+//!     TestServerOpts::mariadb("11.4"),
+//! ] {
+//!     let server = TestServer::start(opts)?;
+//!     let pool = server.pool()?;
+//!     let version: Option<String> = pool.get_conn()?.query_first("SELECT VERSION()")?;
+//!     println!("{}: {:?}", server.image(), version);
+//! } // containers are removed here
+//! # Ok(())
+//! # }
+//! ```
+
+use serde_json::{json, Value as Json};
+
+use std::{
+    io::{self, Read as _, Write as _},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{Conn, Error, Opts, OptsBuilder, Pool, Result};
+
+/// Password of the `root` user of test servers.
+pub const ROOT_PASSWORD: &str = "password";
+
+/// Default time to wait for a server to accept connections.
+pub const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Label of containers started by [`TestServer`].
+const CONTAINER_LABEL: &str = "rust-mysql-simple.testkit";
+
+/// Options of a [`TestServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestServerOpts {
+    repository: String,
+    tag: String,
+    db_name: String,
+    startup_timeout: Duration,
+    docker_socket: Option<PathBuf>,
+}
+
+impl TestServerOpts {
+    fn new(repository: &str, tag: &str) -> Self {
+        Self {
+            repository: repository.into(),
+            tag: tag.into(),
+            db_name: "test".into(),
+            startup_timeout: DEFAULT_STARTUP_TIMEOUT,
+            docker_socket: None,
+        }
+    }
+
+    /// Official MySql image with the given tag, e.g. `5.7` or `8.0`.
+    pub fn mysql(tag: &str) -> Self {
+        Self::new("mysql", tag)
+    }
+
+    /// Official MariaDb image with the given tag, e.g. `10.6` or `11.4`.
+    pub fn mariadb(tag: &str) -> Self {
+        Self::new("mariadb", tag)
+    }
+
+    /// Database created on startup and selected by connections (defaults to `test`).
+    pub fn with_db_name<T: Into<String>>(mut self, db_name: T) -> Self {
+        self.db_name = db_name.into();
+        self
+    }
+
+    /// Time to wait for the server to accept connections (defaults to
+    /// [`DEFAULT_STARTUP_TIMEOUT`]), including the time it takes to pull the image.
+    pub fn with_startup_timeout(mut self, startup_timeout: Duration) -> Self {
+        self.startup_timeout = startup_timeout;
+        self
+    }
+
+    /// Path to the docker socket (see the [module docs](self) for the default).
+    pub fn with_docker_socket<T: Into<PathBuf>>(mut self, docker_socket: T) -> Self {
+        self.docker_socket = Some(docker_socket.into());
+        self
+    }
+
+    /// Returns the image name, e.g. `mysql:8.0`.
+    pub fn image(&self) -> String {
+        format!("{}:{}", self.repository, self.tag)
+    }
+}
+
+/// Server running in a docker container, which is removed on drop.
+#[derive(Debug)]
+pub struct TestServer {
+    docker: Docker,
+    container_id: String,
+    image: String,
+    opts: Opts,
+}
+
+impl TestServer {
+    /// Starts a container (pulling the image if necessary) and waits until the server
+    /// accepts connections.
+    pub fn start(opts: TestServerOpts) -> Result<Self> {
+        let deadline = Instant::now() + opts.startup_timeout;
+        let docker = Docker {
+            socket: opts.docker_socket.clone().unwrap_or_else(default_socket),
+        };
+        let image = opts.image();
+        let create = json!({
+            "Image": image,
+            "Env": [
+                format!("MYSQL_ROOT_PASSWORD={}", ROOT_PASSWORD),
+                format!("MARIADB_ROOT_PASSWORD={}", ROOT_PASSWORD),
+                format!("MYSQL_DATABASE={}", opts.db_name),
+            ],
+            "Labels": { CONTAINER_LABEL: "" },
+            "ExposedPorts": { "3306/tcp": {} },
+            "HostConfig": {
+                "PortBindings": { "3306/tcp": [{ "HostIp": "127.0.0.1", "HostPort": "" }] },
+            },
+        });
+
+        let container = match docker.call("POST", "/containers/create", Some(&create)) {
+            Err(DockerError::Status(404, _)) => {
+                let path = format!(
+                    "/images/create?fromImage={}&tag={}",
+                    opts.repository, opts.tag
+                );
+                docker.call("POST", &path, None)?;
+                docker.call("POST", "/containers/create", Some(&create))?
+            }
+            result => result?,
+        };
+        let container_id = container["Id"]
+            .as_str()
+            .ok_or_else(|| testkit_error("container id is missing"))?
+            .to_owned();
+
+        // the container is removed on drop if anything fails from now on
+        let mut server = TestServer {
+            docker,
+            container_id,
+            image,
+            opts: Opts::default(),
+        };
+        let path = format!("/containers/{}/start", server.container_id);
+        server.docker.call("POST", &path, None)?;
+
+        let path = format!("/containers/{}/json", server.container_id);
+        let info = server.docker.call("GET", &path, None)?;
+        let port = info["NetworkSettings"]["Ports"]["3306/tcp"][0]["HostPort"]
+            .as_str()
+            .and_then(|port| port.parse().ok())
+            .ok_or_else(|| testkit_error("port 3306 is not published"))?;
+
+        server.opts = OptsBuilder::new()
+            .ip_or_hostname(Some("127.0.0.1"))
+            .tcp_port(port)
+            .user(Some("root"))
+            .pass(Some(ROOT_PASSWORD))
+            .db_name(Some(opts.db_name))
+            .prefer_socket(false)
+            .into();
+
+        // images run a temporary server without networking during initialization,
+        // so the first successful connection is to the actual server
+        loop {
+            match Conn::new(server.opts.clone()) {
+                Ok(_) => return Ok(server),
+                Err(err) if Instant::now() >= deadline => return Err(err),
+                Err(_) => thread::sleep(Duration::from_millis(500)),
+            }
+        }
+    }
+
+    /// Returns options to connect to the server as `root`.
+    pub fn opts(&self) -> &Opts {
+        &self.opts
+    }
+
+    /// Creates a pool of connections to the server.
+    pub fn pool(&self) -> Result<Pool> {
+        Pool::new(self.opts.clone())
+    }
+
+    /// Returns the image of the server, e.g. `mysql:8.0`.
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    /// Returns the id of the container.
+    pub fn container_id(&self) -> &str {
+        &self.container_id
+    }
+
+    /// Removes the container, reporting errors unlike drop.
+    pub fn stop(mut self) -> Result<()> {
+        let container_id = std::mem::take(&mut self.container_id);
+        self.docker.remove(&container_id).map_err(Into::into)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if !self.container_id.is_empty() {
+            let _ = self.docker.remove(&self.container_id);
+        }
+    }
+}
+
+fn default_socket() -> PathBuf {
+    std::env::var("DOCKER_HOST")
+        .ok()
+        .and_then(|host| host.strip_prefix("unix://").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("/var/run/docker.sock"))
+}
+
+fn testkit_error(message: &str) -> Error {
+    io::Error::new(io::ErrorKind::Other, format!("testkit: {}", message)).into()
+}
+
+#[derive(Debug)]
+enum DockerError {
+    Io(io::Error),
+    /// (status code, message)
+    Status(u16, String),
+}
+
+impl From<io::Error> for DockerError {
+    fn from(err: io::Error) -> Self {
+        DockerError::Io(err)
+    }
+}
+
+impl From<DockerError> for Error {
+    fn from(err: DockerError) -> Self {
+        match err {
+            DockerError::Io(err) => err.into(),
+            DockerError::Status(status, message) => {
+                testkit_error(&format!("docker responded with {}: {}", status, message))
+            }
+        }
+    }
+}
+
+/// Minimal client of the docker engine API.
+#[derive(Debug)]
+struct Docker {
+    socket: PathBuf,
+}
+
+impl Docker {
+    /// Sends a request and parses the response body (the last JSON document for streams).
+    fn call(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Json>,
+    ) -> std::result::Result<Json, DockerError> {
+        let body = body.map(Json::to_string).unwrap_or_default();
+        let mut stream = UnixStream::connect(&self.socket)?;
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: docker\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        let (status, body) = parse_response(&response)?;
+
+        // streaming endpoints (e.g. image pull) report errors in the stream
+        let mut last = Json::Null;
+        for document in serde_json::Deserializer::from_slice(&body).into_iter::<Json>() {
+            let document = document.map_err(io::Error::from)?;
+            if let Some(error) = document.get("error").and_then(Json::as_str) {
+                return Err(DockerError::Status(status, error.into()));
+            }
+            last = document;
+        }
+        if status >= 300 {
+            let message = last["message"].as_str().unwrap_or_default().into();
+            return Err(DockerError::Status(status, message));
+        }
+        Ok(last)
+    }
+
+    fn remove(&self, container_id: &str) -> std::result::Result<(), DockerError> {
+        let path = format!("/containers/{}?force=true&v=true", container_id);
+        self.call("DELETE", &path, None).map(drop)
+    }
+}
+
+/// Parses an HTTP/1.1 response into the status code and the body.
+fn parse_response(response: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP response");
+    let head_len = response
+        .windows(4)
+        .position(|x| x == b"\r\n\r\n")
+        .ok_or_else(invalid)?;
+    let head = std::str::from_utf8(&response[..head_len]).map_err(|_| invalid())?;
+    let body = &response[head_len + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(invalid)?;
+    let chunked = lines.any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    if !chunked {
+        return Ok((status, body.to_vec()));
+    }
+
+    let mut decoded = Vec::new();
+    let mut rest = body;
+    loop {
+        let line_len = rest
+            .windows(2)
+            .position(|x| x == b"\r\n")
+            .ok_or_else(invalid)?;
+        let size = std::str::from_utf8(&rest[..line_len]).map_err(|_| invalid())?;
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| invalid())?;
+        if size == 0 {
+            return Ok((status, decoded));
+        }
+        let chunk = rest
+            .get(line_len + 2..line_len + 2 + size)
+            .ok_or_else(invalid)?;
+        decoded.extend_from_slice(chunk);
+        rest = rest.get(line_len + 2 + size + 2..).ok_or_else(invalid)?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_response, TestServerOpts};
+
+    #[test]
+    fn should_parse_docker_responses() {
+        let response = b"HTTP/1.1 201 Created\r\nContent-Type: application/json\r\n\
+                         Content-Length: 13\r\n\r\n{\"Id\":\"abc\"}\n";
+        let (status, body) = parse_response(response).unwrap();
+        assert_eq!(status, 201);
+        assert_eq!(body, b"{\"Id\":\"abc\"}\n");
+
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         5\r\n{\"a\":\r\na;ext=1\r\n1}{\"b\":2}\n\r\n0\r\n\r\n";
+        let (status, body) = parse_response(response).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, b"{\"a\":1}{\"b\":2}\n");
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+        assert!(
+            parse_response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nab")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn should_build_test_server_opts() {
+        let opts = TestServerOpts::mariadb("11.4").with_db_name("it");
+        assert_eq!(opts.image(), "mariadb:11.4");
+        assert_eq!(opts.db_name, "it");
+        assert_eq!(TestServerOpts::mysql("5.7").image(), "mysql:5.7");
+    }
+}